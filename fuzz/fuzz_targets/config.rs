@@ -0,0 +1,38 @@
+// Fuzzes the config deserializers. Run with `cargo +nightly fuzz run config`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// The crate is a binary, so the modules under test are compiled in directly.
+#[allow(dead_code)]
+#[path = "../../src/buttons.rs"]
+mod buttons;
+#[allow(dead_code)]
+#[path = "../../src/geometry.rs"]
+mod geometry;
+#[allow(dead_code)]
+#[path = "../../src/keys.rs"]
+mod keys;
+#[allow(dead_code)]
+#[path = "../../src/config.rs"]
+mod config;
+
+use config::{Config, ConfigFile, MAX_CPS, MIN_CPS};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+
+    let (file, _) = ConfigFile::parse(text);
+    assert!(file.profiles.contains_key(&file.active_profile));
+    assert!(file.bindings.iter().all(|b| file.profiles.contains_key(&b.profile)));
+    for profile in file.profiles.values() {
+        assert!((MIN_CPS..=MAX_CPS).contains(&profile.target_cps));
+    }
+    // Whatever was loaded must survive being saved and loaded again.
+    let (reparsed, issues) = ConfigFile::parse(&file.to_toml());
+    assert!(issues.is_empty());
+    assert_eq!(reparsed, file);
+
+    let (legacy, _) = Config::parse_legacy(text);
+    assert!((MIN_CPS..=MAX_CPS).contains(&legacy.target_cps));
+});
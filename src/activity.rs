@@ -0,0 +1,301 @@
+// Real user input, as opposed to the input the clicker injects, and the idle
+// stop built on it. The listener reports every event it believes is real:
+// cursor moves the engine or a macro made are recognized by their position,
+// and button and key presses by being matched, in order, against the ones
+// sent, since rdev doesn't pass on the injected flag Windows puts on them.
+// The echoes of one button or key come back in the order they were sent, so
+// a release seen while our press is still due is the user's. Text the typer
+// enters isn't sent key by key, so keys are only the user's while it is off.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+
+use crate::backend::InputBackend;
+use crate::config::KeyInjection;
+use crate::geometry::Point;
+use crate::log::Log;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// How far a reported cursor position may be from an injected one to count as it.
+const MOVE_TOLERANCE: f64 = 1.0;
+// How long after the last one sent the listener may report the clicker's own
+// presses; ones it never reports are forgotten then.
+const ECHO_WINDOW: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Button(MouseButton),
+    Key(rdev::Key),
+}
+
+/// A press (`down`) or release sent and not yet reported back.
+struct Unseen {
+    input: Input,
+    down: bool,
+    sent: Instant,
+}
+
+/// When real input was last seen.
+pub struct Activity {
+    last_input: Mutex<Instant>,
+    // Last real key press other than the clicker's own hotkeys.
+    last_typed: Mutex<Option<Instant>>,
+    // Where the engine or a macro last moved the cursor.
+    injected_cursor: Mutex<Option<Point>>,
+    // Oldest first.
+    unseen: Mutex<VecDeque<Unseen>>,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Activity {
+            last_input: Mutex::new(Instant::now()),
+            last_typed: Mutex::new(None),
+            injected_cursor: Mutex::new(None),
+            unseen: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl Activity {
+    pub fn input(&self, now: Instant) {
+        *self.last_input.lock().unwrap() = now;
+    }
+
+    pub fn last_input(&self) -> Instant {
+        *self.last_input.lock().unwrap()
+    }
+
+    pub fn typed(&self, now: Instant) {
+        *self.last_typed.lock().unwrap() = Some(now);
+    }
+
+    /// Whether the user typed within the last `window`.
+    pub fn typing(&self, window: Duration) -> bool {
+        self.last_typed.lock().unwrap().is_some_and(|typed| typed.elapsed() < window)
+    }
+
+    /// Called right before the cursor is moved to `to`.
+    pub fn moving_to(&self, to: Point) {
+        *self.injected_cursor.lock().unwrap() = Some(to);
+    }
+
+    /// Whether a cursor move to `at` is the last injected one.
+    pub fn injected_move(&self, (x, y): Point) -> bool {
+        self.injected_cursor
+            .lock()
+            .unwrap()
+            .is_some_and(|(ix, iy)| (x - ix).abs() <= MOVE_TOLERANCE && (y - iy).abs() <= MOVE_TOLERANCE)
+    }
+
+    /// Called right before a button is pressed or released.
+    pub fn sending_button(&self, button: MouseButton, down: bool) {
+        self.sending(Input::Button(button), down);
+    }
+
+    /// Called right before a key is pressed or released.
+    pub fn sending_key(&self, key: rdev::Key, down: bool) {
+        self.sending(Input::Key(key), down);
+    }
+
+    /// Whether a button press or release the listener saw is one that was
+    /// sent, which then isn't waited for any more.
+    pub fn injected_button(&self, button: MouseButton, down: bool) -> bool {
+        self.injected(Input::Button(button), down)
+    }
+
+    /// The same for a key.
+    pub fn injected_key(&self, key: rdev::Key, down: bool) -> bool {
+        self.injected(Input::Key(key), down)
+    }
+
+    fn sending(&self, input: Input, down: bool) {
+        self.unseen.lock().unwrap().push_back(Unseen { input, down, sent: Instant::now() });
+    }
+
+    fn injected(&self, input: Input, down: bool) -> bool {
+        let mut unseen = self.unseen.lock().unwrap();
+        while unseen.front().is_some_and(|unseen| unseen.sent.elapsed() >= ECHO_WINDOW) {
+            unseen.pop_front();
+        }
+        // Only the oldest one of this button or key can be coming back next.
+        match unseen.iter().position(|unseen| unseen.input == input) {
+            Some(index) if unseen[index].down == down => {
+                unseen.remove(index);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Backend that tells `Activity` about the input it sends.
+pub struct Reporting<B> {
+    pub inner: B,
+    pub activity: Arc<Activity>,
+}
+
+impl<B: InputBackend> InputBackend for Reporting<B> {
+    fn button_down(&mut self, button: MouseButton) {
+        self.activity.sending_button(button, true);
+        self.inner.button_down(button);
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        self.activity.sending_button(button, false);
+        self.inner.button_up(button);
+    }
+
+    fn move_to(&mut self, point: Point) {
+        self.activity.moving_to(point);
+        self.inner.move_to(point);
+    }
+
+    fn key_down(&mut self, key: rdev::Key, injection: KeyInjection) {
+        self.activity.sending_key(key, true);
+        self.inner.key_down(key, injection);
+    }
+
+    fn key_up(&mut self, key: rdev::Key, injection: KeyInjection) {
+        self.activity.sending_key(key, false);
+        self.inner.key_up(key, injection);
+    }
+
+    fn click(&mut self, button: MouseButton) {
+        self.activity.sending_button(button, true);
+        self.activity.sending_button(button, false);
+        self.inner.click(button);
+    }
+
+    fn press(&mut self, key: rdev::Key, injection: KeyInjection) {
+        self.activity.sending_key(key, true);
+        self.activity.sending_key(key, false);
+        self.inner.press(key, injection);
+    }
+}
+
+/// Shared state the idle stop is allowed to touch.
+pub struct IdleHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub macro_playing: Arc<AtomicBool>,
+    pub activity: Arc<Activity>,
+    // Time without real input after which clicking stops; off if unset.
+    pub idle_stop: Arc<Mutex<Option<Duration>>>,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+/// Tracks when clicking started, which counts as input: starting it from
+/// a script after a long idle time shouldn't stop it right away.
+#[derive(Default)]
+struct IdleWatch {
+    active_since: Option<Instant>,
+}
+
+impl IdleWatch {
+    /// Whether to stop now, given the last real input.
+    fn update(&mut self, active: bool, last_input: Instant, now: Instant, limit: Duration) -> bool {
+        if !active {
+            self.active_since = None;
+            return false;
+        }
+        let since = *self.active_since.get_or_insert(now);
+        if now.duration_since(since.max(last_input)) < limit {
+            return false;
+        }
+        self.active_since = None;
+        true
+    }
+}
+
+/// Stops clicking and macros once there was no real input for the idle time.
+pub fn spawn_idle_stop(handles: IdleHandles) {
+    thread::spawn(move || {
+        let mut watch = IdleWatch::default();
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+            let Some(limit) = *handles.idle_stop.lock().unwrap() else {
+                watch = IdleWatch::default();
+                continue;
+            };
+            let active = handles.clicking.load(Ordering::SeqCst) || handles.macro_playing.load(Ordering::SeqCst);
+            if watch.update(active, handles.activity.last_input(), Instant::now(), limit) {
+                handles.clicking.store(false, Ordering::SeqCst);
+                handles.macro_playing.store(false, Ordering::SeqCst);
+                handles.log.push(format!("No input for {} min, clicking stopped", limit.as_secs() / 60));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_time_counts_from_the_later_of_input_and_start() {
+        let limit = Duration::from_secs(600);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut watch = IdleWatch::default();
+        // Input long before clicking started doesn't count against it.
+        assert!(!watch.update(true, start, at(1000), limit));
+        assert!(!watch.update(true, start, at(1599), limit));
+        assert!(watch.update(true, start, at(1600), limit));
+
+        assert!(!watch.update(true, at(1700), at(1700), limit));
+        assert!(!watch.update(true, at(2000), at(2500), limit));
+        assert!(watch.update(true, at(2000), at(2600), limit));
+        assert!(!watch.update(false, at(2000), at(9000), limit));
+    }
+
+    #[test]
+    fn engine_moves_are_recognized() {
+        let activity = Activity::default();
+        assert!(!activity.injected_move((10.0, 10.0)));
+        activity.moving_to((10.0, 10.0));
+        assert!(activity.injected_move((10.4, 9.0)));
+        assert!(!activity.injected_move((12.0, 10.0)));
+    }
+
+    #[test]
+    fn sent_button_presses_are_recognized_once() {
+        let activity = Activity::default();
+        activity.sending_button(MouseButton::Left, true);
+        activity.sending_button(MouseButton::Left, false);
+        activity.sending_button(MouseButton::Left, true);
+        assert!(!activity.injected_button(MouseButton::Right, true));
+        assert!(activity.injected_button(MouseButton::Left, true));
+        assert!(activity.injected_button(MouseButton::Left, false));
+        assert!(activity.injected_button(MouseButton::Left, true));
+        assert!(!activity.injected_button(MouseButton::Left, true));
+
+        activity.sending_key(rdev::Key::KeyW, true);
+        assert!(!activity.injected_key(rdev::Key::KeyW, false));
+        assert!(activity.injected_key(rdev::Key::KeyW, true));
+    }
+
+    #[test]
+    fn real_presses_between_sent_ones_are_the_users() {
+        let activity = Activity::default();
+        let left = MouseButton::Left;
+        // The user holds the button while the clicker clicks it.
+        activity.sending_button(left, true);
+        activity.sending_button(left, false);
+        assert!(activity.injected_button(left, true));
+        activity.sending_button(left, true);
+        activity.sending_button(left, false);
+        assert!(activity.injected_button(left, false));
+        // Their release arrives while our next press is due, so it is theirs.
+        assert!(!activity.injected_button(left, false));
+        assert!(activity.injected_button(left, true));
+        assert!(activity.injected_button(left, false));
+        // With nothing of ours left due, their next press is seen too.
+        assert!(!activity.injected_button(left, true));
+    }
+}
@@ -0,0 +1,131 @@
+// CPU affinity for the click engine: pinning its thread to one core keeps the
+// scheduler from moving it around on a busy machine, which shows up as jitter
+// in the time between rounds. Done with sched_setaffinity on Linux and
+// SetThreadAffinityMask on Windows; elsewhere pinning fails.
+
+/// Spread of the time between rounds in the last run with the engine
+/// running anywhere, and pinned to a core, in milliseconds.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct JitterReadout {
+    pub unpinned: Option<f64>,
+    pub pinned: Option<(usize, f64)>,
+}
+
+impl JitterReadout {
+    /// Stores the spread of a run on `core`, or on any core.
+    pub fn record(&mut self, core: Option<usize>, deviation: f64) {
+        match core {
+            Some(core) => self.pinned = Some((core, deviation)),
+            None => self.unpinned = Some(deviation),
+        }
+    }
+}
+
+pub use platform::{cores, pin};
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+    use std::mem;
+
+    /// Cores online, whatever the current thread is pinned to.
+    pub fn cores() -> usize {
+        (unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) }).max(1) as usize
+    }
+
+    /// Pins the calling thread to `core`, or lets it run on every core again.
+    pub fn pin(core: Option<usize>) -> Result<(), String> {
+        if let Some(core) = core.filter(|&core| core >= cores()) {
+            return Err(format!("no core {}", core));
+        }
+        unsafe {
+            let mut set: libc::cpu_set_t = mem::zeroed();
+            match core {
+                Some(core) => libc::CPU_SET(core, &mut set),
+                None => (0..cores()).for_each(|core| libc::CPU_SET(core, &mut set)),
+            }
+            if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::io;
+
+    type Handle = *mut c_void;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> Handle;
+        fn GetCurrentProcess() -> Handle;
+        fn SetThreadAffinityMask(thread: Handle, mask: usize) -> usize;
+        fn GetProcessAffinityMask(process: Handle, process_mask: *mut usize, system_mask: *mut usize) -> i32;
+    }
+
+    pub fn cores() -> usize {
+        std::thread::available_parallelism().map_or(1, |cores| cores.get())
+    }
+
+    /// Pins the calling thread to `core`, or lets it run wherever the process may.
+    pub fn pin(core: Option<usize>) -> Result<(), String> {
+        let mask = match core {
+            Some(core) if core < usize::BITS as usize => 1 << core,
+            Some(core) => return Err(format!("no core {}", core)),
+            None => {
+                let (mut process, mut system) = (0, 0);
+                if unsafe { GetProcessAffinityMask(GetCurrentProcess(), &mut process, &mut system) } == 0 {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                process
+            }
+        };
+        if unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub fn cores() -> usize {
+        std::thread::available_parallelism().map_or(1, |cores| cores.get())
+    }
+
+    pub fn pin(_core: Option<usize>) -> Result<(), String> {
+        Err("not supported on this system".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_recorded_by_where_they_ran() {
+        let mut readout = JitterReadout::default();
+        readout.record(None, 0.4);
+        readout.record(Some(2), 0.1);
+        readout.record(None, 0.3);
+        assert_eq!(readout, JitterReadout { unpinned: Some(0.3), pinned: Some((2, 0.1)) });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn pinning_moves_the_thread_and_back() {
+        let (pinned, on_one, unpinned) = std::thread::spawn(|| {
+            let core = unsafe { libc::sched_getcpu() }.max(0) as usize;
+            let pinned = pin(Some(core));
+            let on_one = std::thread::available_parallelism().map_or(0, |cores| cores.get());
+            (pinned, on_one, pin(None))
+        })
+        .join()
+        .unwrap();
+        assert_eq!((pinned, on_one, unpinned), (Ok(()), 1, Ok(())));
+    }
+}
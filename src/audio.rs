@@ -0,0 +1,280 @@
+// Click sound playback (built with the `audio` feature).
+
+use std::io::Cursor;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rodio::source::Buffered;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source};
+
+use crate::sounds::Theme;
+use crate::travel::Jitter;
+
+// Toggle confirmation tones in Hz: high for start, low for stop.
+const START_TONE: u32 = 880;
+const STOP_TONE: u32 = 440;
+const BEEP_LENGTH: Duration = Duration::from_millis(80);
+const BEEP_VOLUME: f32 = 0.25;
+// Countdown before a delayed start: rising tones for 3, 2 and 1 seconds
+// left, then a longer, higher "go".
+const COUNTDOWN_TONES: [u32; 3] = [523, 659, 784];
+const GO_TONE: u32 = 1047;
+const GO_LENGTH: Duration = Duration::from_millis(250);
+// Pitch of the CPS hotkey beep: an octave up for every tenfold rate.
+const CPS_BASE_TONE: f64 = 440.0;
+// How often the toggle beeper checks the clicking state.
+const TOGGLE_POLL: Duration = Duration::from_millis(10);
+// Click sounds waiting for the audio worker; more are dropped.
+const CLICK_QUEUE: usize = 8;
+// Click sounds playing at once; requests beyond that are dropped.
+const MAX_VOICES: usize = 16;
+// Playback speed range of varied click sounds: about a semitone either way.
+const PITCH_VARIATION: (f32, f32) = (0.94, 1.06);
+
+type Sample = Buffered<Decoder<Cursor<Vec<u8>>>>;
+
+pub struct Audio {
+    // Playback stops when the stream is dropped, so it lives as long as `Audio`.
+    _stream: OutputStream,
+    handle: Arc<OutputStreamHandle>,
+}
+
+impl Audio {
+    /// Opens the default output device.
+    pub fn init() -> Result<Audio, String> {
+        let (stream, handle) = OutputStream::try_default()
+            .map_err(|e| format!("Failed to get audio output stream: {}", e))?;
+        Ok(Audio { _stream: stream, handle: Arc::new(handle) })
+    }
+
+    /// Returns a callback that plays the theme's click sound while it is turned on,
+    /// at most `sound_rate` times a second. Sounds are played by one worker
+    /// thread; when clicks come faster than it keeps up with, the queued ones
+    /// play as one and the rest are dropped.
+    pub fn click_player(&self, handles: ClickSoundHandles) -> impl Fn() + Send + Sync + 'static {
+        let (requests, queue) = mpsc::sync_channel(CLICK_QUEUE);
+        let stream_handle = self.handle.clone();
+        thread::spawn(move || {
+            // Click sound of the theme it was decoded from, decoded again when the theme changes.
+            let mut decoded: Option<(Arc<Theme>, Option<Sample>)> = None;
+            let mut voices: Vec<rodio::Sink> = Vec::new();
+            let mut limiter = SoundLimiter::default();
+            let seed = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64);
+            let mut jitter = Jitter::new(seed);
+            while queue.recv().is_ok() {
+                coalesce(&queue);
+                voices.retain(|sink| !sink.empty());
+                if voices.len() >= MAX_VOICES || !limiter.allow(Instant::now(), *handles.sound_rate.lock().unwrap()) {
+                    continue;
+                }
+                let theme = handles.theme.lock().unwrap().clone();
+                if !decoded.as_ref().is_some_and(|(of, _)| Arc::ptr_eq(of, &theme)) {
+                    decoded = Some((theme.clone(), theme.click.as_deref().and_then(|bytes| decode(bytes, "click"))));
+                }
+                let Some((_, Some(sound))) = &decoded else { continue };
+                match rodio::Sink::try_new(&stream_handle) {
+                    Ok(sink) => {
+                        let (low, high) = PITCH_VARIATION;
+                        let speed = match handles.vary_pitch.load(Ordering::SeqCst) {
+                            true => jitter.range(f64::from(low), f64::from(high)) as f32,
+                            false => 1.0,
+                        };
+                        sink.append(sound.clone().speed(speed));
+                        voices.push(sink);
+                    }
+                    Err(e) => eprintln!("Failed to play click sound: {}", e),
+                }
+            }
+        });
+        move || {
+            // A full queue already holds a sound for this click.
+            if handles.play_sound.load(Ordering::SeqCst) {
+                let _ = requests.try_send(());
+            }
+        }
+    }
+
+    /// Beeps whenever clicking is toggled, however that happened (hotkey,
+    /// window or CLI), counts down a delayed start and sounds out the rate
+    /// set with the CPS hotkeys.
+    pub fn spawn_beeper(&self, handles: BeepHandles) {
+        let handle = self.handle.clone();
+        thread::spawn(move || {
+            let mut was_clicking = handles.clicking.load(Ordering::SeqCst);
+            let mut started = Instant::now();
+            let mut countdown = Vec::new();
+            loop {
+                thread::sleep(TOGGLE_POLL);
+                let clicking = handles.clicking.load(Ordering::SeqCst);
+                if clicking != was_clicking {
+                    if handles.toggle_beep.load(Ordering::SeqCst) {
+                        let theme = handles.theme.lock().unwrap().clone();
+                        let (sample, kind) = if clicking { (&theme.start, "start") } else { (&theme.stop, "stop") };
+                        match sample.as_deref().and_then(|bytes| decode(bytes, kind)) {
+                            Some(sound) => play(&handle, sound),
+                            None => play_tone(&handle, if clicking { START_TONE } else { STOP_TONE }, BEEP_LENGTH),
+                        }
+                    }
+                    // Stopping cancels what is left of the countdown.
+                    countdown.clear();
+                    if clicking && handles.countdown_beeps.load(Ordering::SeqCst) {
+                        countdown = countdown_beeps(*handles.start_delay.lock().unwrap());
+                    }
+                    started = Instant::now();
+                    was_clicking = clicking;
+                }
+                while countdown.first().is_some_and(|beep| started.elapsed() >= beep.0) {
+                    let (_, frequency, length) = countdown.remove(0);
+                    play_tone(&handle, frequency, length);
+                }
+                if handles.cps_nudged.swap(false, Ordering::SeqCst) && handles.cps_beep.load(Ordering::SeqCst) {
+                    play_tone(&handle, cps_tone(*handles.target_cps.lock().unwrap()), BEEP_LENGTH);
+                }
+            }
+        });
+    }
+}
+
+/// Shared state the click sound worker reads.
+pub struct ClickSoundHandles {
+    pub play_sound: Arc<AtomicBool>,
+    pub sound_rate: Arc<Mutex<u32>>,
+    pub vary_pitch: Arc<AtomicBool>,
+    pub theme: Arc<Mutex<Arc<Theme>>>,
+}
+
+/// Spaces click sounds at least a `1 / rate` second apart.
+#[derive(Default)]
+struct SoundLimiter {
+    last: Option<Instant>,
+}
+
+impl SoundLimiter {
+    /// Whether a sound may play at `now`, counting it if so.
+    fn allow(&mut self, now: Instant, rate: u32) -> bool {
+        let gap = Duration::from_secs(1) / rate.max(1);
+        if self.last.is_some_and(|last| now.duration_since(last) < gap) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// Takes the click sounds queued behind the one being played, which play as
+/// one. Returns how many there were.
+fn coalesce(queue: &Receiver<()>) -> usize {
+    queue.try_iter().count()
+}
+
+/// Shared state the beeper thread reads.
+pub struct BeepHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub toggle_beep: Arc<AtomicBool>,
+    pub start_delay: Arc<Mutex<Duration>>,
+    pub countdown_beeps: Arc<AtomicBool>,
+    pub target_cps: Arc<Mutex<f64>>,
+    pub cps_beep: Arc<AtomicBool>,
+    pub cps_nudged: Arc<AtomicBool>,
+    pub theme: Arc<Mutex<Arc<Theme>>>,
+}
+
+fn cps_tone(cps: f64) -> u32 {
+    (CPS_BASE_TONE * cps.max(1.0).log10().exp2()) as u32
+}
+
+/// When each countdown beep of a `delay` long wait plays, after starting,
+/// with its frequency and length. Shorter waits skip the first beeps.
+fn countdown_beeps(delay: Duration) -> Vec<(Duration, u32, Duration)> {
+    if delay.is_zero() {
+        return Vec::new();
+    }
+    let mut beeps: Vec<_> = COUNTDOWN_TONES
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &frequency)| {
+            let left = Duration::from_secs((COUNTDOWN_TONES.len() - i) as u64);
+            (left <= delay).then(|| (delay - left, frequency, BEEP_LENGTH))
+        })
+        .collect();
+    beeps.push((delay, GO_TONE, GO_LENGTH));
+    beeps
+}
+
+/// WAV data of a theme's `kind` sound, ready to play.
+fn decode(bytes: &[u8], kind: &str) -> Option<Sample> {
+    match Decoder::new(Cursor::new(bytes.to_vec())) {
+        Ok(decoder) => Some(decoder.buffered()),
+        Err(e) => {
+            eprintln!("Failed to decode the {} sound: {}", kind, e);
+            None
+        }
+    }
+}
+
+/// Starts `sound` without waiting for it to finish.
+fn play(handle: &OutputStreamHandle, sound: Sample) {
+    match rodio::Sink::try_new(handle) {
+        Ok(sink) => {
+            sink.append(sound);
+            sink.detach();
+        }
+        Err(e) => eprintln!("Failed to play sound: {}", e),
+    }
+}
+
+/// Starts a sine tone without waiting for it to finish.
+fn play_tone(handle: &OutputStreamHandle, frequency: u32, length: Duration) {
+    match rodio::Sink::try_new(handle) {
+        Ok(sink) => {
+            sink.append(rodio::source::SineWave::new(frequency).take_duration(length).amplify(BEEP_VOLUME));
+            sink.detach();
+        }
+        Err(e) => eprintln!("Failed to play tone: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_rises_to_go() {
+        let secs = Duration::from_secs;
+        assert_eq!(
+            countdown_beeps(secs(5)),
+            [(secs(2), 523, BEEP_LENGTH), (secs(3), 659, BEEP_LENGTH), (secs(4), 784, BEEP_LENGTH), (secs(5), GO_TONE, GO_LENGTH)]
+        );
+        let short = countdown_beeps(Duration::from_millis(1500));
+        assert_eq!(short.iter().map(|b| b.1).collect::<Vec<_>>(), [784, GO_TONE]);
+        assert!(countdown_beeps(Duration::ZERO).is_empty());
+    }
+
+    #[test]
+    fn click_sounds_are_capped_per_second() {
+        let start = Instant::now();
+        let mut limiter = SoundLimiter::default();
+        // 100 clicks over one second against a cap of 20.
+        let played = (0..100).filter(|&i| limiter.allow(start + Duration::from_millis(i * 10), 20)).count();
+        assert_eq!(played, 20);
+        assert!(limiter.allow(start + Duration::from_secs(2), 1));
+        assert!(!limiter.allow(start + Duration::from_millis(2999), 1));
+    }
+
+    #[test]
+    fn click_bursts_are_bounded_and_coalesced() {
+        let (requests, queue) = mpsc::sync_channel(CLICK_QUEUE);
+        let accepted = (0..CLICK_QUEUE * 2).filter(|_| requests.try_send(()).is_ok()).count();
+        assert_eq!(accepted, CLICK_QUEUE);
+        assert!(queue.recv().is_ok());
+        assert_eq!(coalesce(&queue), CLICK_QUEUE - 1);
+        assert_eq!(coalesce(&queue), 0);
+    }
+}
@@ -0,0 +1,212 @@
+// Keeping the machine awake: while clicking or a macro plays, the system is
+// kept from sleeping and the display from turning off, so a long unattended
+// run doesn't die with it. Done with SetThreadExecutionState on Windows and a
+// systemd-inhibit lock on Linux, which also resets the X screen saver timer.
+// Everything is released again once the run stops.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::log::Log;
+
+const POLL: Duration = Duration::from_millis(500);
+
+/// Shared state the awake keeper is allowed to touch.
+pub struct AwakeHandles {
+    pub keep_awake: Arc<AtomicBool>,
+    pub clicking: Arc<AtomicBool>,
+    pub macro_playing: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// The inhibition held while running, and whether taking it failed this run.
+struct Keeper<T> {
+    held: Option<T>,
+    failed: bool,
+}
+
+impl<T> Keeper<T> {
+    fn new() -> Self {
+        Keeper { held: None, failed: false }
+    }
+
+    /// Takes the inhibition while `running`, and drops it otherwise. A failure
+    /// is returned once and not retried until the next run.
+    fn update(&mut self, running: bool, acquire: impl FnOnce() -> Result<T, String>) -> Option<String> {
+        if !running {
+            self.held = None;
+            self.failed = false;
+            return None;
+        }
+        if self.held.is_some() || self.failed {
+            return None;
+        }
+        match acquire() {
+            Ok(held) => {
+                self.held = Some(held);
+                None
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(e)
+            }
+        }
+    }
+}
+
+/// Keeps the machine awake whenever clicking or a macro runs, until shutdown.
+pub fn spawn_keeper(handles: AwakeHandles) {
+    // Windows ties the execution state to the thread that set it, so it is
+    // taken and released here.
+    thread::spawn(move || {
+        let mut keeper = Keeper::new();
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            let running = handles.clicking.load(Ordering::SeqCst) || handles.macro_playing.load(Ordering::SeqCst);
+            if let Some(e) = keeper.update(running && handles.keep_awake.load(Ordering::SeqCst), platform::inhibit) {
+                handles.log.push(format!("Couldn't keep the computer awake: {}", e));
+            }
+            if let Some(held) = &mut keeper.held {
+                held.refresh();
+            }
+            thread::sleep(POLL);
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, ScreenSaver};
+    use x11rb::rust_connection::RustConnection;
+
+    // X blanks the screen after a few minutes at the least.
+    const SCREEN_SAVER_RESET: Duration = Duration::from_secs(30);
+
+    /// A systemd-inhibit lock, and the X server whose screen saver is held off.
+    pub struct Inhibitor {
+        lock: Child,
+        display: Option<RustConnection>,
+        reset_at: Option<Instant>,
+    }
+
+    pub fn inhibit() -> Result<Inhibitor, String> {
+        // The lock lives as long as the command under it; tail ends with this
+        // process, so a crash doesn't leave the machine awake for good.
+        let lock = Command::new("systemd-inhibit")
+            .args(["--what=sleep:idle", "--who=Rust Autoclicker", "--why=Clicking", "--mode=block"])
+            .args(["tail", "--pid", &std::process::id().to_string(), "-f", "/dev/null"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("systemd-inhibit couldn't run: {}", e))?;
+        let display = x11rb::connect(None).ok().map(|(conn, _)| conn);
+        Ok(Inhibitor { lock, display, reset_at: None })
+    }
+
+    impl Inhibitor {
+        /// Resets the screen saver timer every so often, as input would.
+        pub fn refresh(&mut self) {
+            if self.reset_at.is_some_and(|at| at.elapsed() < SCREEN_SAVER_RESET) {
+                return;
+            }
+            self.reset_at = Some(Instant::now());
+            if let Some(conn) = &self.display {
+                let _ = conn.force_screen_saver(ScreenSaver::RESET);
+                let _ = conn.flush();
+            }
+        }
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            let _ = self.lock.kill();
+            let _ = self.lock.wait();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    const ES_CONTINUOUS: u32 = 0x8000_0000;
+    const ES_SYSTEM_REQUIRED: u32 = 0x0000_0001;
+    const ES_DISPLAY_REQUIRED: u32 = 0x0000_0002;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetThreadExecutionState(flags: u32) -> u32;
+    }
+
+    /// The calling thread's execution state, held until dropped.
+    pub struct Inhibitor;
+
+    pub fn inhibit() -> Result<Inhibitor, String> {
+        if unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED) } == 0 {
+            return Err("SetThreadExecutionState failed".to_string());
+        }
+        Ok(Inhibitor)
+    }
+
+    impl Inhibitor {
+        pub fn refresh(&mut self) {}
+    }
+
+    impl Drop for Inhibitor {
+        fn drop(&mut self) {
+            unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub struct Inhibitor;
+
+    pub fn inhibit() -> Result<Inhibitor, String> {
+        Err("not supported on this system".to_string())
+    }
+
+    impl Inhibitor {
+        pub fn refresh(&mut self) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Held(Arc<AtomicBool>);
+
+    impl Drop for Held {
+        fn drop(&mut self) {
+            self.0.store(false, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn inhibition_is_held_for_the_run() {
+        let held = Arc::new(AtomicBool::new(false));
+        let take = || {
+            held.store(true, Ordering::SeqCst);
+            Ok(Held(held.clone()))
+        };
+        let mut keeper = Keeper::new();
+        assert_eq!(keeper.update(true, take), None);
+        assert!(held.load(Ordering::SeqCst));
+        assert_eq!(keeper.update(true, || panic!("taken twice")), None);
+        assert_eq!(keeper.update(false, || panic!("taken while stopped")), None);
+        assert!(!held.load(Ordering::SeqCst));
+
+        // A failure is logged once per run.
+        assert_eq!(keeper.update(true, || Err::<Held, _>("no logind".to_string())).as_deref(), Some("no logind"));
+        assert_eq!(keeper.update(true, || panic!("retried")), None);
+        keeper.update(false, || panic!("taken while stopped"));
+        assert!(keeper.update(true, || Err::<Held, _>("no logind".to_string())).is_some());
+    }
+}
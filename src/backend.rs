@@ -0,0 +1,355 @@
+// Input injection backends used by the click engine.
+
+use std::sync::{Arc, Mutex};
+
+use enigo::{Enigo, MouseButton, MouseControllable};
+use rdev::{EventType, Key};
+
+use crate::config::{BackendKind, KeyInjection};
+use crate::geometry::Point;
+use crate::layout::display_name;
+use crate::log::Log;
+
+/// Something that can emit mouse and keyboard input.
+pub trait InputBackend: Send {
+    fn button_down(&mut self, button: MouseButton);
+    fn button_up(&mut self, button: MouseButton);
+    fn move_to(&mut self, point: Point);
+    fn key_down(&mut self, key: Key, injection: KeyInjection);
+    fn key_up(&mut self, key: Key, injection: KeyInjection);
+
+    fn click(&mut self, button: MouseButton) {
+        self.button_down(button);
+        self.button_up(button);
+    }
+
+    fn press(&mut self, key: Key, injection: KeyInjection) {
+        self.key_down(key, injection);
+        self.key_up(key, injection);
+    }
+}
+
+impl InputBackend for Box<dyn InputBackend> {
+    fn button_down(&mut self, button: MouseButton) {
+        (**self).button_down(button);
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        (**self).button_up(button);
+    }
+
+    fn move_to(&mut self, point: Point) {
+        (**self).move_to(point);
+    }
+
+    fn key_down(&mut self, key: Key, injection: KeyInjection) {
+        (**self).key_down(key, injection);
+    }
+
+    fn key_up(&mut self, key: Key, injection: KeyInjection) {
+        (**self).key_up(key, injection);
+    }
+
+    fn click(&mut self, button: MouseButton) {
+        (**self).click(button);
+    }
+
+    fn press(&mut self, key: Key, injection: KeyInjection) {
+        (**self).press(key, injection);
+    }
+}
+
+/// Creates a real backend, or explains why it isn't available here.
+pub fn create(kind: BackendKind) -> Result<Box<dyn InputBackend>, String> {
+    match kind {
+        BackendKind::Enigo => Ok(Box::new(EnigoBackend::new())),
+        #[cfg(all(windows, feature = "interception"))]
+        BackendKind::Interception => {
+            crate::interception::InterceptionBackend::new().map(|b| Box::new(b) as Box<dyn InputBackend>)
+        }
+        #[cfg(not(all(windows, feature = "interception")))]
+        BackendKind::Interception => Err("needs a Windows build with the interception feature".to_string()),
+        #[cfg(target_os = "linux")]
+        BackendKind::Uinput => crate::uinput::UinputBackend::new().map(|b| Box::new(b) as Box<dyn InputBackend>),
+        #[cfg(not(target_os = "linux"))]
+        BackendKind::Uinput => Err("only available on Linux".to_string()),
+    }
+}
+
+/// What needs fixing before `kind` can be used, checked without creating it.
+pub fn setup_problem(kind: BackendKind) -> Option<String> {
+    match kind {
+        #[cfg(target_os = "linux")]
+        BackendKind::Uinput => crate::uinput::check_access().err(),
+        _ => None,
+    }
+}
+
+/// Forwards to the backend chosen in the settings, switching between clicks
+/// when the choice changes. Falls back to enigo if the choice can't be created.
+pub struct SelectedBackend {
+    choice: Arc<Mutex<BackendKind>>,
+    kind: BackendKind,
+    backend: Box<dyn InputBackend>,
+    log: Arc<Log>,
+}
+
+impl SelectedBackend {
+    pub fn new(choice: Arc<Mutex<BackendKind>>, log: Arc<Log>) -> Self {
+        let kind = *choice.lock().unwrap();
+        let backend = SelectedBackend::create_or_fallback(kind, &log);
+        SelectedBackend { choice, kind, backend, log }
+    }
+
+    fn create_or_fallback(kind: BackendKind, log: &Log) -> Box<dyn InputBackend> {
+        create(kind).unwrap_or_else(|e| {
+            log.push(format!("{} backend unavailable ({}), using enigo", kind.name(), e));
+            Box::new(EnigoBackend::new())
+        })
+    }
+
+    /// The backend to start the next action with. Only called before a
+    /// press, so a down/up pair always goes to the same backend.
+    fn current(&mut self) -> &mut dyn InputBackend {
+        let kind = *self.choice.lock().unwrap();
+        if kind != self.kind {
+            self.backend = SelectedBackend::create_or_fallback(kind, &self.log);
+            self.kind = kind;
+        }
+        self.backend.as_mut()
+    }
+}
+
+impl InputBackend for SelectedBackend {
+    fn button_down(&mut self, button: MouseButton) {
+        self.current().button_down(button);
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        self.backend.button_up(button);
+    }
+
+    fn move_to(&mut self, point: Point) {
+        self.current().move_to(point);
+    }
+
+    fn key_down(&mut self, key: Key, injection: KeyInjection) {
+        self.current().key_down(key, injection);
+    }
+
+    fn key_up(&mut self, key: Key, injection: KeyInjection) {
+        self.backend.key_up(key, injection);
+    }
+
+    fn click(&mut self, button: MouseButton) {
+        self.current().click(button);
+    }
+
+    fn press(&mut self, key: Key, injection: KeyInjection) {
+        self.current().press(key, injection);
+    }
+}
+
+/// Injects real input through enigo, and keys through rdev.
+pub struct EnigoBackend {
+    enigo: Enigo,
+}
+
+impl EnigoBackend {
+    pub fn new() -> Self {
+        EnigoBackend { enigo: Enigo::new() }
+    }
+}
+
+impl InputBackend for EnigoBackend {
+    fn button_down(&mut self, button: MouseButton) {
+        self.enigo.mouse_down(button);
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        self.enigo.mouse_up(button);
+    }
+
+    fn move_to(&mut self, (x, y): Point) {
+        self.enigo.mouse_move_to(x.round() as i32, y.round() as i32);
+    }
+
+    fn key_down(&mut self, key: Key, injection: KeyInjection) {
+        send_key(key, true, injection);
+    }
+
+    fn key_up(&mut self, key: Key, injection: KeyInjection) {
+        send_key(key, false, injection);
+    }
+}
+
+// X11 and macOS key events already carry layout-independent hardware codes,
+// so only Windows needs a separate scancode path.
+#[cfg(not(windows))]
+fn send_key(key: Key, down: bool, _injection: KeyInjection) {
+    simulate_key(key, down);
+}
+
+#[cfg(windows)]
+fn send_key(key: Key, down: bool, injection: KeyInjection) {
+    match crate::keys::scancode(&key) {
+        Some(code) if injection == KeyInjection::Scancode => windows_scancode::send(code, down),
+        _ => simulate_key(key, down),
+    }
+}
+
+fn simulate_key(key: Key, down: bool) {
+    let event = if down { EventType::KeyPress(key) } else { EventType::KeyRelease(key) };
+    if let Err(e) = rdev::simulate(&event) {
+        eprintln!("Failed to send {}: {:?}", display_name(&key), e);
+    }
+}
+
+#[cfg(windows)]
+mod windows_scancode {
+    const INPUT_KEYBOARD: u32 = 1;
+    const KEYEVENTF_EXTENDEDKEY: u32 = 0x0001;
+    const KEYEVENTF_KEYUP: u32 = 0x0002;
+    const KEYEVENTF_SCANCODE: u32 = 0x0008;
+
+    #[repr(C)]
+    struct KeybdInput {
+        vk: u16,
+        scan: u16,
+        flags: u32,
+        time: u32,
+        extra_info: usize,
+    }
+
+    // INPUT is a union sized by its largest member, MOUSEINPUT.
+    #[repr(C)]
+    struct Input {
+        kind: u32,
+        ki: KeybdInput,
+        _padding: [u8; 8],
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn SendInput(count: u32, inputs: *const Input, size: i32) -> u32;
+    }
+
+    pub fn send(code: u16, down: bool) {
+        let mut flags = KEYEVENTF_SCANCODE;
+        if code & 0xFF00 == 0xE000 {
+            flags |= KEYEVENTF_EXTENDEDKEY;
+        }
+        if !down {
+            flags |= KEYEVENTF_KEYUP;
+        }
+        let input = Input {
+            kind: INPUT_KEYBOARD,
+            ki: KeybdInput { vk: 0, scan: code & 0xFF, flags, time: 0, extra_info: 0 },
+            _padding: [0; 8],
+        };
+        unsafe {
+            SendInput(1, &input, std::mem::size_of::<Input>() as i32);
+        }
+    }
+}
+
+/// Emits nothing, logging each would-be click and where it goes instead.
+pub struct DryRunBackend {
+    log: Arc<Log>,
+    clicks: u64,
+    // Where the cursor would be, and whether it moved there since the last
+    // line: a move is shown on the click that follows it, or on its own.
+    at: Option<Point>,
+    moved: bool,
+}
+
+impl DryRunBackend {
+    pub fn new(log: Arc<Log>) -> Self {
+        DryRunBackend { log, clicks: 0, at: None, moved: false }
+    }
+
+    /// Logs the last move if nothing was sent there.
+    pub fn flush(&mut self) {
+        if let (true, Some((x, y))) = (std::mem::take(&mut self.moved), self.at) {
+            self.log.push(format!("dry run: move to ({:.0}, {:.0})", x, y));
+        }
+    }
+
+    // Logs `what` with where the cursor is.
+    fn log_at(&mut self, what: String) {
+        self.moved = false;
+        match self.at {
+            Some((x, y)) => self.log.push(format!("dry run: {} at ({:.0}, {:.0})", what, x, y)),
+            None => self.log.push(format!("dry run: {}", what)),
+        }
+    }
+}
+
+impl InputBackend for DryRunBackend {
+    fn button_down(&mut self, button: MouseButton) {
+        self.log_at(format!("hold {:?}", button));
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        self.log_at(format!("release {:?}", button));
+    }
+
+    fn move_to(&mut self, point: Point) {
+        self.flush();
+        self.at = Some(point);
+        self.moved = true;
+    }
+
+    fn key_down(&mut self, key: Key, _injection: KeyInjection) {
+        self.log.push(format!("dry run: hold {}", display_name(&key)));
+    }
+
+    fn key_up(&mut self, key: Key, _injection: KeyInjection) {
+        self.log.push(format!("dry run: release {}", display_name(&key)));
+    }
+
+    fn click(&mut self, button: MouseButton) {
+        self.clicks += 1;
+        self.log_at(format!("click #{} {:?}", self.clicks, button));
+    }
+
+    fn press(&mut self, key: Key, injection: KeyInjection) {
+        self.clicks += 1;
+        self.log.push(format!("dry run: press #{} {} ({})", self.clicks, display_name(&key), injection.name()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_runs_log_each_click_where_it_goes() {
+        let log = Arc::new(Log::new());
+        let mut backend = DryRunBackend::new(log.clone());
+        backend.click(MouseButton::Left);
+        backend.move_to((10.0, 20.0));
+        backend.click(MouseButton::Left);
+        backend.click(MouseButton::Right);
+        backend.move_to((5.0, 5.0));
+        backend.move_to((30.0, 40.0));
+        backend.button_down(MouseButton::Left);
+        backend.button_up(MouseButton::Left);
+        backend.move_to((0.0, 0.0));
+        backend.flush();
+        backend.flush();
+        let lines: Vec<String> = log.lines().iter().map(|line| line.split_once("] ").unwrap().1.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "dry run: click #1 Left",
+                "dry run: click #2 Left at (10, 20)",
+                "dry run: click #3 Right at (10, 20)",
+                "dry run: move to (5, 5)",
+                "dry run: hold Left at (30, 40)",
+                "dry run: release Left at (30, 40)",
+                "dry run: move to (0, 0)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,183 @@
+// Micro-benchmark of the input injection backends available on this machine.
+// The clicks are aimed at the test pad in our own window, after a countdown,
+// and stop as soon as the benchmark is cancelled or the pad goes off screen.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+
+use crate::activity::{Activity, Reporting};
+use crate::backend::{self, InputBackend};
+use crate::config::BackendKind;
+use crate::geometry::{Point, Rect};
+
+/// Clicks sent per backend.
+pub const BENCH_CLICKS: usize = 500;
+/// Wait between starting the benchmark and its first click, to cancel in.
+pub const BENCH_COUNTDOWN: Duration = Duration::from_secs(3);
+const POLL: Duration = Duration::from_millis(50);
+
+/// Shared state the benchmark is allowed to touch.
+pub struct BenchHandles {
+    // Set while the benchmark runs; Cancel and the abort key clear it.
+    pub running: Arc<AtomicBool>,
+    // The test pad the clicks land on, while it is on screen.
+    pub test_pad: Arc<Mutex<Option<Rect>>>,
+    pub cursor: Arc<Mutex<Option<Point>>>,
+    // Told about the clicks, so the listener doesn't take them for the user's.
+    pub activity: Arc<Activity>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub mean: Duration,
+    pub p99: Duration,
+    pub clicks_per_sec: f64,
+}
+
+/// Outcome for every backend, in `BackendKind::ALL` order.
+pub type BenchReport = Vec<(BackendKind, Result<BenchResult, String>)>;
+
+/// Times `clicks` left clicks on the test pad, returning each click's
+/// latency and the total. The cursor is put back on the pad whenever it is
+/// seen off it. None if the benchmark was cancelled or the pad went away.
+fn measure(backend: &mut dyn InputBackend, clicks: usize, handles: &BenchHandles) -> Option<(Vec<Duration>, Duration)> {
+    let start = Instant::now();
+    let mut latencies = Vec::with_capacity(clicks);
+    for _ in 0..clicks {
+        let pad = (*handles.test_pad.lock().unwrap()).filter(|_| handles.running.load(Ordering::SeqCst))?;
+        let mut cursor = handles.cursor.lock().unwrap();
+        if !cursor.is_some_and(|(x, y)| pad.contains(x, y)) {
+            let center = (pad.x + pad.width / 2.0, pad.y + pad.height / 2.0);
+            backend.move_to(center);
+            *cursor = Some(center);
+        }
+        drop(cursor);
+        let click = Instant::now();
+        backend.click(MouseButton::Left);
+        latencies.push(click.elapsed());
+    }
+    Some((latencies, start.elapsed()))
+}
+
+fn summarize(mut latencies: Vec<Duration>, total: Duration) -> BenchResult {
+    latencies.sort();
+    let count = latencies.len().max(1);
+    let mean = latencies.iter().sum::<Duration>() / count as u32;
+    let p99 = latencies.get((count * 99).div_ceil(100) - 1).copied().unwrap_or_default();
+    let clicks_per_sec = latencies.len() as f64 / total.as_secs_f64().max(f64::EPSILON);
+    BenchResult { mean, p99, clicks_per_sec }
+}
+
+/// Benchmarks every backend in turn once the countdown is over. None if it
+/// was cancelled or the test pad went off screen before the end.
+pub fn run(handles: &BenchHandles) -> Option<BenchReport> {
+    let start = Instant::now();
+    while start.elapsed() < BENCH_COUNTDOWN {
+        if !handles.running.load(Ordering::SeqCst) {
+            return None;
+        }
+        thread::sleep(POLL);
+    }
+    let mut report = BenchReport::new();
+    for kind in BackendKind::ALL {
+        let result = match backend::create(kind) {
+            Ok(backend) => {
+                let mut backend = Reporting { inner: backend, activity: handles.activity.clone() };
+                let (latencies, total) = measure(&mut backend, BENCH_CLICKS, handles)?;
+                Ok(summarize(latencies, total))
+            }
+            Err(e) => Err(e),
+        };
+        report.push((kind, result));
+    }
+    Some(report)
+}
+
+/// The backend with the highest throughput, if any could be measured.
+pub fn fastest(report: &BenchReport) -> Option<BackendKind> {
+    report
+        .iter()
+        .filter_map(|(kind, result)| result.as_ref().ok().map(|r| (*kind, r.clicks_per_sec)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(kind, _)| kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeyInjection;
+
+    /// Counts the presses sent and records where the cursor was moved.
+    #[derive(Default)]
+    struct CountingBackend(usize, Vec<Point>);
+
+    impl InputBackend for CountingBackend {
+        fn button_down(&mut self, _button: MouseButton) {
+            self.0 += 1;
+        }
+
+        fn button_up(&mut self, _button: MouseButton) {}
+
+        fn move_to(&mut self, point: Point) {
+            self.1.push(point);
+        }
+
+        fn key_down(&mut self, _key: rdev::Key, _injection: KeyInjection) {}
+
+        fn key_up(&mut self, _key: rdev::Key, _injection: KeyInjection) {}
+    }
+
+    fn handles(pad: Option<Rect>) -> BenchHandles {
+        BenchHandles {
+            running: Arc::new(AtomicBool::new(true)),
+            test_pad: Arc::new(Mutex::new(pad)),
+            cursor: Arc::new(Mutex::new(Some((0.0, 0.0)))),
+            activity: Arc::new(Activity::default()),
+        }
+    }
+
+    #[test]
+    fn measures_every_click_on_the_test_pad() {
+        let handles = handles(Some(Rect { x: 100.0, y: 200.0, width: 40.0, height: 20.0 }));
+        let mut backend = CountingBackend::default();
+        let (latencies, total) = measure(&mut backend, 100, &handles).unwrap();
+        assert_eq!(backend.0, 100);
+        assert_eq!(latencies.len(), 100);
+        assert!(latencies.iter().sum::<Duration>() <= total);
+        // Aimed once, since the cursor stayed on the pad.
+        assert_eq!(backend.1, [(120.0, 210.0)]);
+    }
+
+    #[test]
+    fn nothing_is_clicked_without_the_pad_or_once_cancelled() {
+        let mut backend = CountingBackend::default();
+        assert_eq!(measure(&mut backend, 100, &handles(None)), None);
+        let cancelled = handles(Some(Rect { x: 0.0, y: 0.0, width: 10.0, height: 10.0 }));
+        cancelled.running.store(false, Ordering::SeqCst);
+        assert_eq!(measure(&mut backend, 100, &cancelled), None);
+        assert_eq!(run(&cancelled), None);
+        assert_eq!(backend.0, 0);
+    }
+
+    #[test]
+    fn summary_statistics() {
+        let latencies: Vec<Duration> = (1..=100).rev().map(Duration::from_micros).collect();
+        let result = summarize(latencies, Duration::from_millis(20));
+        assert_eq!(result.mean, Duration::from_nanos(50_500));
+        assert_eq!(result.p99, Duration::from_micros(99));
+        assert_eq!(result.clicks_per_sec, 5000.0);
+    }
+
+    #[test]
+    fn fastest_ignores_failures() {
+        let result = |clicks_per_sec| BenchResult { mean: Duration::ZERO, p99: Duration::ZERO, clicks_per_sec };
+        let report: BenchReport = vec![(BackendKind::Enigo, Err("no display".to_string()))];
+        assert_eq!(fastest(&report), None);
+        let report: BenchReport = vec![(BackendKind::Enigo, Ok(result(10.0)))];
+        assert_eq!(fastest(&report), Some(BackendKind::Enigo));
+    }
+}
@@ -0,0 +1,27 @@
+// Mouse button names, as written in config files and macros. Free of the
+// input crates like keys.rs, so the config fuzz target can include it.
+
+/// A mouse button the clicker presses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl ClickButton {
+    pub const ALL: [ClickButton; 3] = [ClickButton::Left, ClickButton::Middle, ClickButton::Right];
+
+    /// Name used in config files and macros.
+    pub fn name(self) -> &'static str {
+        match self {
+            ClickButton::Left => "left",
+            ClickButton::Middle => "middle",
+            ClickButton::Right => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ClickButton> {
+        ClickButton::ALL.into_iter().find(|b| b.name() == name)
+    }
+}
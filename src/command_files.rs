@@ -0,0 +1,163 @@
+// Command files, for tools like Stream Deck that can open or write a file but
+// don't speak the control channel. In the `commands` folder of the config
+// directory:
+//
+//     start.cmd        dropping an empty file runs the command named after it,
+//     set-cps.cmd      with the file's first line, if any, as its argument ("50")
+//     commands.txt     every line appended to it runs as a command ("profile fast")
+//
+// Each dropped file is deleted once it ran. Commands are the ones of the
+// control channel; files and lines left over from before the watcher was on
+// are discarded rather than run.
+
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::config_dir;
+use crate::ipc::{self, ControlHandles};
+use crate::log::Log;
+
+const POLL: Duration = Duration::from_millis(250);
+const EXTENSION: &str = "cmd";
+const LINES_FILE: &str = "commands.txt";
+
+/// Shared state the command file watcher is allowed to touch.
+pub struct CommandFileHandles {
+    pub control: ControlHandles,
+    pub enabled: Arc<AtomicBool>,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+pub fn commands_dir() -> PathBuf {
+    config_dir().join("commands")
+}
+
+/// What has been run in a commands folder so far.
+struct Watcher {
+    dir: PathBuf,
+    // Bytes of the lines file already run; `None` until the first poll.
+    offset: Option<u64>,
+}
+
+impl Watcher {
+    fn new(dir: PathBuf) -> Watcher {
+        Watcher { dir, offset: None }
+    }
+
+    /// Command lines that arrived since the last poll, oldest first.
+    fn poll(&mut self) -> Vec<String> {
+        let first = self.offset.is_none();
+        let mut commands = Vec::new();
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        files.retain(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(EXTENSION)));
+        files.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+        for path in files {
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            // A file that can't be removed would run again on every poll.
+            if fs::remove_file(&path).is_err() || first {
+                continue;
+            }
+            let command = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let arg = text.lines().next().unwrap_or_default().trim();
+            commands.push(if arg.is_empty() { command.to_string() } else { format!("{} {}", command, arg) });
+        }
+        commands.extend(self.new_lines());
+        commands
+    }
+
+    /// Complete lines appended to the lines file since the last read.
+    fn new_lines(&mut self) -> Vec<String> {
+        let path = self.dir.join(LINES_FILE);
+        let Ok(mut file) = fs::File::open(&path) else {
+            self.offset = Some(0);
+            return Vec::new();
+        };
+        let len = file.metadata().map_or(0, |m| m.len());
+        let mut offset = self.offset.unwrap_or(len);
+        // Truncated or replaced: start over.
+        if len < offset {
+            offset = 0;
+        }
+        let mut text = String::new();
+        if file.seek(SeekFrom::Start(offset)).is_ok() {
+            let _ = file.read_to_string(&mut text);
+        }
+        // A line still being written is left for the next poll.
+        let complete = text.rfind('\n').map_or(0, |i| i + 1);
+        self.offset = Some(offset + complete as u64);
+        text[..complete].lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect()
+    }
+}
+
+/// Watches the commands folder on a background thread while it is enabled.
+pub fn spawn(handles: CommandFileHandles) {
+    let dir = commands_dir();
+    thread::spawn(move || {
+        let mut watcher = None;
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL);
+            if !handles.enabled.load(Ordering::SeqCst) {
+                watcher = None;
+                continue;
+            }
+            let watcher = watcher.get_or_insert_with(|| {
+                if let Err(e) = fs::create_dir_all(&dir) {
+                    handles.log.push(format!("Can't create {}: {}", dir.display(), e));
+                }
+                Watcher::new(dir.clone())
+            });
+            for line in watcher.poll() {
+                run(&line, &handles);
+            }
+        }
+    });
+}
+
+fn run(line: &str, handles: &CommandFileHandles) {
+    match ipc::handle_command(line, &handles.control) {
+        Ok(reply) => handles.log.push(format!("Command file: {} ({})", line, reply)),
+        Err(e) => handles.log.push(format!("Command file: {} failed: {}", line, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn dropped_files_and_appended_lines_run_once() {
+        let dir = std::env::temp_dir().join(format!("autoclicker-commands-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stop.cmd"), "").unwrap();
+        fs::write(dir.join(LINES_FILE), "start\n").unwrap();
+
+        // Whatever was there before the first poll is discarded.
+        let mut watcher = Watcher::new(dir.clone());
+        assert!(watcher.poll().is_empty());
+        assert!(!dir.join("stop.cmd").exists());
+
+        fs::write(dir.join("set-cps.cmd"), "50\r\n").unwrap();
+        let mut lines = fs::OpenOptions::new().append(true).open(dir.join(LINES_FILE)).unwrap();
+        write!(lines, "toggle\n\nprofile fast\nsta").unwrap();
+        assert_eq!(watcher.poll(), ["set-cps 50", "toggle", "profile fast"]);
+        assert!(watcher.poll().is_empty());
+        writeln!(lines, "rt").unwrap();
+        assert_eq!(watcher.poll(), ["start"]);
+
+        fs::write(dir.join(LINES_FILE), "stop\n").unwrap();
+        assert_eq!(watcher.poll(), ["stop"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,2412 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use toml_edit::{value, Array, ArrayOfTables, Document, Item, Table};
+
+use crate::buttons::ClickButton;
+use crate::geometry::{format_point, format_size, parse_point, parse_size, Point, Rect};
+use crate::keys::{key_to_string, string_to_rdev_key};
+
+pub const MIN_CPS: f64 = 0.1;
+pub const MAX_CPS: f64 = 10_000.0;
+// Rates above this are allowed, but few apps or games register them.
+pub const REALISTIC_CPS: f64 = 1000.0;
+// Highest setting of the global CPS guard; superspeed can go well past MAX_CPS.
+pub const MAX_CPS_LIMIT: f64 = 100_000.0;
+// Most quick-preset rate buttons.
+pub const MAX_CPS_PRESETS: usize = 12;
+// Largest change of the target CPS per press of a CPS hotkey.
+pub const MAX_CPS_STEP: f64 = 100.0;
+// Most click sounds a second the cap can be set to.
+pub const MAX_SOUND_RATE: u32 = 100;
+// Sound theme of new configs; the original click.wav.
+pub const DEFAULT_SOUND_THEME: &str = "classic";
+// Superspeed injection threads.
+pub const MAX_WORKERS: usize = 16;
+// Highest CPU core number the click engine can be pinned to.
+pub const MAX_CORE: usize = 1023;
+// Cursor travel speed in pixels per second.
+pub const MIN_TRAVEL_SPEED: f64 = 100.0;
+pub const MAX_TRAVEL_SPEED: f64 = 20000.0;
+// Longest wait between two nudges of the mouse jiggler.
+pub const MAX_JIGGLE_INTERVAL: Duration = Duration::from_secs(3600);
+// Longest wait between starting the clicker and the first click.
+pub const MAX_START_DELAY: Duration = Duration::from_secs(60);
+// Longest a click sequence waits for its pixel to change between points.
+pub const MAX_SEQUENCE_WAIT: Duration = Duration::from_secs(3600);
+// Most clicks a single chat command can trigger, whatever the config says.
+pub const MAX_CHAT_CLICKS: u32 = 1000;
+// Fastest a chat burst clicks, so the longest one is over in 20 s.
+pub const MAX_CHAT_CPS: f64 = 50.0;
+pub const MAX_TYPING_PAUSE: Duration = Duration::from_secs(60);
+pub const MAX_IDLE_STOP: Duration = Duration::from_secs(24 * 60 * 60);
+pub const MAX_CHAT_COOLDOWN: Duration = Duration::from_secs(3600);
+// Longest waits of the text typer between characters and between repeats.
+pub const MAX_TYPER_CHAR_DELAY: Duration = Duration::from_secs(10);
+pub const MAX_TYPER_REPEAT_DELAY: Duration = Duration::from_secs(3600);
+// Range of the longest wait between attempts to restart a failed global listener.
+pub const MIN_LISTENER_RETRY: Duration = Duration::from_secs(1);
+pub const MAX_LISTENER_RETRY: Duration = Duration::from_secs(3600);
+// Longest wait between the first key of a hotkey chord and the hotkey.
+pub const CHORD_WINDOW: Duration = Duration::from_secs(1);
+// Most clicks between two progress posts of the webhook.
+pub const MAX_WEBHOOK_EVERY: u64 = 1_000_000;
+// Range of the time between two reads of a text trigger's region.
+pub const MIN_TEXT_TRIGGER_EVERY: Duration = Duration::from_millis(250);
+pub const MAX_TEXT_TRIGGER_EVERY: Duration = Duration::from_secs(60);
+
+// Range of the UI scale, on top of the display's own scaling.
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 3.0;
+// Range and default of the body text size, in points; the other text
+// styles keep their size relative to it.
+pub const MIN_FONT_SIZE: f32 = 8.0;
+pub const MAX_FONT_SIZE: f32 = 32.0;
+pub const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Pre-TOML config file, read from the working directory.
+const LEGACY_CONFIG: &str = "config.txt";
+const LEGACY_PROFILE_DIR: &str = "profiles";
+
+// Settings of a single profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub hotkey: String,  // e.g. "Insert"
+    pub fast_mode: bool,
+    pub target_cps: f64,
+    pub left_click: bool,
+    pub middle_click: bool,
+    pub right_click: bool,
+    pub play_sound: bool,
+    // Most click sounds played a second, however fast clicking goes, and a
+    // slightly different pitch for each so fast clicking doesn't buzz.
+    pub sound_rate: u32,
+    pub vary_pitch: bool,
+    // Short beep whenever clicking is toggled, independent of play_sound.
+    pub toggle_beep: bool,
+    // Wait before the first click after starting, counted down with beeps.
+    pub start_delay: Duration,
+    pub countdown_beeps: bool,
+    pub activation: Activation,
+    // Key pressed just before the hotkey for it to count, as in "G then C"; off if unset.
+    pub chord_key: Option<rdev::Key>,
+    // Mouse button that clicks for as long as it is held down; off if unset.
+    pub turbo_button: Option<ClickButton>,
+    // Clicks only happen while the cursor is inside this rectangle.
+    pub region: Option<Rect>,
+    // Clicks never happen while the cursor is inside any of these.
+    pub exclusions: Vec<Rect>,
+    // Points clicked in turn; empty to click wherever the cursor is.
+    pub sequence: Vec<Point>,
+    // Offsets added to the click position in turn, spreading clicks over a few pixels.
+    pub offsets: Vec<Point>,
+    // Screen size the sequence was made on, when its points are screen positions.
+    pub sequence_resolution: Option<(u32, u32)>,
+    // Pixel the sequence waits on to change after each point, if any.
+    pub sequence_wait: Option<SequenceWait>,
+    // App whose window the sequence points are relative to, if not the screen.
+    pub relative_to: Option<String>,
+    // Injection threads used in superspeed mode.
+    pub workers: usize,
+    // Key auto-pressed each round instead of the mouse buttons.
+    pub press_key: Option<rdev::Key>,
+    pub key_injection: KeyInjection,
+    // Key held down for as long as clicking is active.
+    pub hold_key: Option<rdev::Key>,
+    // Curved cursor travel to fixed targets instead of teleporting.
+    pub human_travel: bool,
+    pub travel_speed: f64,
+    // Put the cursor back where it was after clicking a fixed target.
+    pub return_cursor: bool,
+    // Mouse jiggler instead of clicking: a one-pixel nudge every interval,
+    // moved back right away or alternating direction.
+    pub jiggle: bool,
+    pub jiggle_interval: Duration,
+    pub jiggle_back: bool,
+    // Keys that raise and lower the target CPS by `cps_step` while clicking.
+    pub cps_up_key: Option<rdev::Key>,
+    pub cps_down_key: Option<rdev::Key>,
+    pub cps_step: f64,
+    // Beep the new rate after a CPS hotkey, higher for faster.
+    pub cps_beep: bool,
+    // Stops a playing macro.
+    pub abort_key: rdev::Key,
+    // Runs the next step of a macro being debugged.
+    pub step_key: rdev::Key,
+}
+
+/// How the hotkey has to be pressed to toggle the clicker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activation {
+    Press,
+    Hold,
+    DoubleTap,
+}
+
+/// How auto-pressed keys are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyInjection {
+    // Virtual-key codes, translated by the OS keyboard layout.
+    VirtualKey,
+    // Hardware scancodes, for games that ignore virtual-key input.
+    Scancode,
+}
+
+/// Input injection backends the engine can use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Enigo,
+    Interception,
+    Uinput,
+}
+
+impl BackendKind {
+    pub const ALL: [BackendKind; 3] = [BackendKind::Enigo, BackendKind::Interception, BackendKind::Uinput];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            BackendKind::Enigo => "enigo",
+            BackendKind::Interception => "interception",
+            BackendKind::Uinput => "uinput",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BackendKind> {
+        BackendKind::ALL.into_iter().find(|b| b.name() == name)
+    }
+}
+
+/// Window colors: the OS dark/light preference, or a fixed choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeMode {
+    System,
+    Dark,
+    Light,
+}
+
+impl ThemeMode {
+    pub const ALL: [ThemeMode; 3] = [ThemeMode::System, ThemeMode::Dark, ThemeMode::Light];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeMode::System => "system",
+            ThemeMode::Dark => "dark",
+            ThemeMode::Light => "light",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ThemeMode> {
+        ThemeMode::ALL.into_iter().find(|t| t.name() == name)
+    }
+}
+
+/// What clicking does while the workstation is locked or the screensaver runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockPolicy {
+    Ignore,
+    // Stops clicking; it stays stopped after unlocking.
+    Pause,
+    // Stops clicking and starts it again after unlocking if it was running.
+    Resume,
+}
+
+impl LockPolicy {
+    pub const ALL: [LockPolicy; 3] = [LockPolicy::Ignore, LockPolicy::Pause, LockPolicy::Resume];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            LockPolicy::Ignore => "ignore",
+            LockPolicy::Pause => "pause",
+            LockPolicy::Resume => "resume",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LockPolicy> {
+        LockPolicy::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+/// Where the on-screen badge showing that clicking is active sits, if anywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndicatorPlace {
+    Off,
+    // Top right corner of the screen.
+    Corner,
+    // Just below and right of the cursor.
+    Cursor,
+}
+
+impl IndicatorPlace {
+    pub const ALL: [IndicatorPlace; 3] = [IndicatorPlace::Off, IndicatorPlace::Corner, IndicatorPlace::Cursor];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            IndicatorPlace::Off => "off",
+            IndicatorPlace::Corner => "corner",
+            IndicatorPlace::Cursor => "cursor",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IndicatorPlace> {
+        IndicatorPlace::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+/// What a text trigger or title rule does once its text shows up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAction {
+    Start,
+    Stop,
+}
+
+impl TextAction {
+    pub const ALL: [TextAction; 2] = [TextAction::Start, TextAction::Stop];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            TextAction::Start => "start",
+            TextAction::Stop => "stop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TextAction> {
+        TextAction::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+/// How a title rule compares the focused window's title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleMatch {
+    Contains,
+    Regex,
+}
+
+impl TitleMatch {
+    pub const ALL: [TitleMatch; 2] = [TitleMatch::Contains, TitleMatch::Regex];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            TitleMatch::Contains => "contains",
+            TitleMatch::Regex => "regex",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TitleMatch> {
+        TitleMatch::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+/// How hard superspeed pushes when the system can't take input any faster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuperspeedMode {
+    // Sends the next round the moment the last one is out, keeping a core busy.
+    MaxPerformance,
+    // Yields between rounds, and waits while input is queuing up.
+    Balanced,
+}
+
+impl SuperspeedMode {
+    pub const ALL: [SuperspeedMode; 2] = [SuperspeedMode::MaxPerformance, SuperspeedMode::Balanced];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            SuperspeedMode::MaxPerformance => "max_performance",
+            SuperspeedMode::Balanced => "balanced",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SuperspeedMode> {
+        SuperspeedMode::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+/// What clicking does while running on battery or with battery saver on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryPolicy {
+    Ignore,
+    // Stops clicking until plugged in again, then resumes it.
+    Pause,
+    // Caps every mode at the battery rate.
+    Throttle,
+}
+
+impl BatteryPolicy {
+    pub const ALL: [BatteryPolicy; 3] = [BatteryPolicy::Ignore, BatteryPolicy::Pause, BatteryPolicy::Throttle];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            BatteryPolicy::Ignore => "ignore",
+            BatteryPolicy::Pause => "pause",
+            BatteryPolicy::Throttle => "throttle",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BatteryPolicy> {
+        BatteryPolicy::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Weekday::Monday => "monday",
+            Weekday::Tuesday => "tuesday",
+            Weekday::Wednesday => "wednesday",
+            Weekday::Thursday => "thursday",
+            Weekday::Friday => "friday",
+            Weekday::Saturday => "saturday",
+            Weekday::Sunday => "sunday",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Weekday> {
+        Weekday::ALL.into_iter().find(|d| d.name() == name)
+    }
+}
+
+/// When "today" and "this week" start over in the click statistics, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rollover {
+    // Hour of the day, 0 to 23.
+    pub day_start: u32,
+    pub week_start: Weekday,
+}
+
+impl Default for Rollover {
+    fn default() -> Self {
+        Rollover { day_start: 0, week_start: Weekday::Monday }
+    }
+}
+
+/// Profile to switch to when an application gains focus.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppBinding {
+    pub app: String,  // process name, e.g. "javaw.exe" or "firefox"
+    pub profile: String,
+    // Stop clicking when the application loses focus again.
+    pub stop_on_blur: bool,
+}
+
+/// Starts or stops clicking when a window whose title matches gains focus,
+/// e.g. starting on "Roblox" and stopping on "Sign in".
+#[derive(Debug, Clone)]
+pub struct TitleRule {
+    pub title: String,  // text or regex, ignoring case
+    pub kind: TitleMatch,
+    pub action: TextAction,
+    // The title compiled, for regex rules that compile; see `compile`.
+    regex: Option<regex::Regex>,
+}
+
+impl PartialEq for TitleRule {
+    fn eq(&self, other: &TitleRule) -> bool {
+        (&self.title, self.kind, self.action) == (&other.title, other.kind, other.action)
+    }
+}
+
+/// Pixel a click sequence waits on between points, e.g. "100,200 5000": after
+/// each point it holds until the pixel changes, or for the timeout in ms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SequenceWait {
+    pub at: Point,
+    pub timeout: Duration,
+}
+
+impl SequenceWait {
+    pub fn parse(text: &str) -> Option<SequenceWait> {
+        let (at, timeout) = text.trim().split_once(' ')?;
+        let timeout = Duration::from_millis(timeout.trim().parse().ok()?);
+        (timeout <= MAX_SEQUENCE_WAIT).then_some(SequenceWait { at: parse_point(at)?, timeout })
+    }
+}
+
+impl fmt::Display for SequenceWait {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", format_point(self.at), self.timeout.as_millis())
+    }
+}
+
+/// Twitch chat command that triggers click bursts, e.g. "!click 10".
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatTrigger {
+    pub channel: String,  // channel name without '#', e.g. "somestreamer"
+    pub command: String,
+    // Clicks one message can ask for, and how long each viewer waits between bursts.
+    pub max_clicks: u32,
+    pub cooldown: Duration,
+    pub cps: f64,
+}
+
+/// Screen region read with OCR, starting or stopping clicking when it shows
+/// `text`, e.g. stopping once "Inventory full" appears.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextTrigger {
+    pub region: Rect,
+    // Matched anywhere in what is read, ignoring case and line breaks.
+    pub text: String,
+    pub action: TextAction,
+    pub every: Duration,
+}
+
+/// MQTT broker to take commands from and report the state to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttConfig {
+    pub broker: String,  // "host" or "host:port"
+    // Commands are read from "<topic>/set" and the state published to "<topic>/state".
+    pub topic: String,
+    // Login, empty for brokers that allow anonymous clients.
+    pub username: String,
+    pub password: String,
+}
+
+/// URL clicking starts, stops and progress are posted to as JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookConfig {
+    pub url: String, // plain http://
+    // Clicks between two progress posts while clicking; none if 0.
+    pub every: u64,
+}
+
+/// Shell commands run when clicking starts and stops.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hooks {
+    // Nothing runs unless this is set, whatever the commands.
+    pub enabled: bool,
+    pub on_start: String,
+    pub on_stop: String,
+}
+
+/// Text typed over and over while its hotkey has the typer on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextTyper {
+    pub text: String,
+    pub hotkey: rdev::Key,
+    pub char_delay: Duration,
+    pub repeat_delay: Duration,
+}
+
+/// Saved macro played when a global hotkey is pressed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroHotkey {
+    pub key: rdev::Key,
+    pub name: String,  // macro name, e.g. "farm_route"
+}
+
+/// Contents of config.toml: every profile plus the one in use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigFile {
+    pub active_profile: String,
+    pub profiles: BTreeMap<String, Config>,
+    // Input injection backend, shared by all profiles.
+    pub backend: BackendKind,
+    // Hard cap on clicks per second across every mode and worker; none if unset.
+    pub max_cps: Option<f64>,
+    pub superspeed: SuperspeedMode,
+    // CPU core the click engine thread is pinned to; wherever the system puts it if unset.
+    pub pin_core: Option<usize>,
+    // Opt-in usage metrics and where "Send" posts them.
+    pub telemetry: bool,
+    pub telemetry_endpoint: String,
+    pub theme: ThemeMode,
+    // Name of the sound theme: built in, or a folder of the sounds folder.
+    pub sound_theme: String,
+    // Size of the whole UI relative to the system's scaling.
+    pub ui_scale: f32,
+    // Size of body text in points, on top of the UI scale.
+    pub font_size: f32,
+    // Window position and size when it was last closed, in points at the
+    // system's scaling (not physical pixels); the system's choice if unset.
+    pub window: Option<Rect>,
+    pub on_lock: LockPolicy,
+    pub on_battery: BatteryPolicy,
+    // Clicks per second `BatteryPolicy::Throttle` allows.
+    pub battery_cps: f64,
+    // Target rates offered as one-click buttons next to the CPS slider.
+    pub cps_presets: Vec<f64>,
+    // Stop clicking after this long without real input, in whole minutes; off if unset.
+    pub idle_stop: Option<Duration>,
+    // Hold clicking while typing, until this long after the last key press; off if unset.
+    pub typing_pause: Option<Duration>,
+    // Loopback port of the stream overlay page; off if unset.
+    pub overlay_port: Option<u16>,
+    pub chat_trigger: Option<ChatTrigger>,
+    pub text_trigger: Option<TextTrigger>,
+    pub mqtt: Option<MqttConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub hooks: Hooks,
+    pub typer: Option<TextTyper>,
+    // Watch the commands folder for Stream Deck style command files.
+    pub command_files: bool,
+    // Check that the system receives every click sent, through the global listener.
+    pub verify_clicks: bool,
+    // Scale macros and click sequences made on a screen of another size to this one.
+    pub scale_recordings: bool,
+    // Keep the system from sleeping and the display on while clicking or a macro runs.
+    pub keep_awake: bool,
+    // Pauses clicking, the playing macro and the typer at once, and resumes them.
+    pub pause_key: Option<rdev::Key>,
+    // Click-through badge that lights up while clicking is active.
+    pub indicator: IndicatorPlace,
+    // Longest wait between attempts to restart the global listener after it failed.
+    pub listener_retry: Duration,
+    // Folder (in Dropbox, OneDrive...) profiles and click counts are synced through; off if unset.
+    pub sync_dir: Option<PathBuf>,
+    pub rollover: Rollover,
+    // Applications that are never clicked while focused, whatever turned clicking on.
+    pub blocklist: Vec<String>,
+    // Checked in order; the first matching binding wins.
+    pub bindings: Vec<AppBinding>,
+    // Checked in order when the focused window's title changes; the first match applies.
+    pub title_rules: Vec<TitleRule>,
+    pub macro_hotkeys: Vec<MacroHotkey>,
+}
+
+/// A problem found while reading a config file. The offending value is
+/// replaced by its default (or clamped) in the loaded config.
+#[derive(Debug)]
+pub enum ConfigIssue {
+    Malformed { at: String, text: String },
+    UnknownKey { at: String, key: String },
+    InvalidValue { at: String, key: String, value: String },
+    CpsOutOfRange { at: String, value: f64 },
+    UnknownHotkey { at: String, value: String },
+    MissingProfile { name: String },
+    UnboundProfile { app: String, profile: String },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigIssue::Malformed { at, text } => write!(f, "{}: could not parse \"{}\"", at, text),
+            ConfigIssue::UnknownKey { at, key } => write!(f, "{}: unknown setting \"{}\"", at, key),
+            ConfigIssue::InvalidValue { at, key, value } => {
+                write!(f, "{}: \"{}\" is not a valid value for {}, using default", at, value, key)
+            }
+            ConfigIssue::CpsOutOfRange { at, value } => write!(
+                f,
+                "{}: target CPS {} is outside {}-{}, clamped",
+                at, value, MIN_CPS, MAX_CPS
+            ),
+            ConfigIssue::UnknownHotkey { at, value } => {
+                write!(f, "{}: unrecognized hotkey \"{}\", using Insert", at, value)
+            }
+            ConfigIssue::MissingProfile { name } => {
+                write!(f, "active profile \"{}\" does not exist, using \"{}\"", name, DEFAULT_PROFILE)
+            }
+            ConfigIssue::UnboundProfile { app, profile } => {
+                write!(f, "binding for \"{}\": profile \"{}\" does not exist, ignored", app, profile)
+            }
+        }
+    }
+}
+
+impl ConfigIssue {
+    /// Names `file` in where the issue is, for issues from one of several files.
+    fn locate(&mut self, file: &Path) {
+        match self {
+            ConfigIssue::Malformed { at, .. }
+            | ConfigIssue::UnknownKey { at, .. }
+            | ConfigIssue::InvalidValue { at, .. }
+            | ConfigIssue::CpsOutOfRange { at, .. }
+            | ConfigIssue::UnknownHotkey { at, .. } => *at = format!("{} {}", file.display(), at),
+            ConfigIssue::MissingProfile { .. } | ConfigIssue::UnboundProfile { .. } => {}
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            hotkey: "Insert".to_string(),
+            fast_mode: true,
+            target_cps: 10.0,
+            left_click: true,
+            middle_click: false,
+            right_click: false,
+            play_sound: false,
+            sound_rate: 20,
+            vary_pitch: false,
+            toggle_beep: false,
+            start_delay: Duration::ZERO,
+            countdown_beeps: true,
+            activation: Activation::Press,
+            region: None,
+            exclusions: Vec::new(),
+            sequence: Vec::new(),
+            offsets: Vec::new(),
+            sequence_resolution: None,
+            sequence_wait: None,
+            relative_to: None,
+            workers: 1,
+            press_key: None,
+            key_injection: KeyInjection::VirtualKey,
+            hold_key: None,
+            chord_key: None,
+            turbo_button: None,
+            human_travel: false,
+            travel_speed: 2000.0,
+            return_cursor: false,
+            jiggle: false,
+            jiggle_interval: Duration::from_secs(60),
+            jiggle_back: true,
+            cps_up_key: None,
+            cps_down_key: None,
+            cps_step: 5.0,
+            cps_beep: false,
+            abort_key: rdev::Key::Escape,
+            step_key: rdev::Key::F8,
+        }
+    }
+}
+
+impl Activation {
+    pub const ALL: [Activation; 3] = [Activation::Press, Activation::Hold, Activation::DoubleTap];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Activation::Press => "press",
+            Activation::Hold => "hold",
+            Activation::DoubleTap => "double_tap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Activation> {
+        Activation::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+impl KeyInjection {
+    pub const ALL: [KeyInjection; 2] = [KeyInjection::VirtualKey, KeyInjection::Scancode];
+
+    /// Name used in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyInjection::VirtualKey => "virtual_key",
+            KeyInjection::Scancode => "scancode",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<KeyInjection> {
+        KeyInjection::ALL.into_iter().find(|k| k.name() == name)
+    }
+}
+
+impl Config {
+    /// Applies one `key = value` setting, recording an issue if it can't be used.
+    fn apply_setting(&mut self, key: &str, value: &str, at: &str, issues: &mut Vec<ConfigIssue>) {
+        let invalid = || ConfigIssue::InvalidValue {
+            at: at.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let flag = match key {
+            "fast_mode" => &mut self.fast_mode,
+            "left_click" => &mut self.left_click,
+            "middle_click" => &mut self.middle_click,
+            "right_click" => &mut self.right_click,
+            "play_sound" => &mut self.play_sound,
+            "vary_pitch" => &mut self.vary_pitch,
+            "sound_rate" => {
+                match value.parse::<u32>() {
+                    Ok(rate) if (1..=MAX_SOUND_RATE).contains(&rate) => self.sound_rate = rate,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "toggle_beep" => &mut self.toggle_beep,
+            "countdown_beeps" => &mut self.countdown_beeps,
+            "start_delay" => {
+                match value.parse::<u64>().map(Duration::from_millis) {
+                    Ok(delay) if delay <= MAX_START_DELAY => self.start_delay = delay,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "human_travel" => &mut self.human_travel,
+            "return_cursor" => &mut self.return_cursor,
+            "cps_beep" => &mut self.cps_beep,
+            "jiggle" => &mut self.jiggle,
+            "jiggle_back" => &mut self.jiggle_back,
+            "jiggle_interval" => {
+                match value.parse::<u64>().map(Duration::from_secs) {
+                    Ok(interval) if !interval.is_zero() && interval <= MAX_JIGGLE_INTERVAL => self.jiggle_interval = interval,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "cps_step" => {
+                match value.parse::<f64>() {
+                    Ok(step) if step > 0.0 && step <= MAX_CPS_STEP => self.cps_step = step,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "travel_speed" => {
+                match value.parse::<f64>() {
+                    Ok(speed) if (MIN_TRAVEL_SPEED..=MAX_TRAVEL_SPEED).contains(&speed) => self.travel_speed = speed,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "activation" => {
+                match Activation::from_name(value) {
+                    Some(activation) => self.activation = activation,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "turbo_button" => {
+                match ClickButton::from_name(value) {
+                    Some(button) => self.turbo_button = Some(button),
+                    None if value.trim().is_empty() => self.turbo_button = None,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "region" => {
+                match Rect::parse(value) {
+                    Some(rect) => self.region = Some(rect),
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "exclusions" => {
+                let zones: Option<Vec<Rect>> = value.split(';').filter(|z| !z.trim().is_empty()).map(Rect::parse).collect();
+                match zones {
+                    Some(zones) => self.exclusions = zones,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "sequence" | "offsets" => {
+                let points: Option<Vec<Point>> = value.split(';').filter(|p| !p.trim().is_empty()).map(parse_point).collect();
+                match points {
+                    Some(points) if key == "sequence" => self.sequence = points,
+                    Some(points) => self.offsets = points,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "sequence_resolution" => {
+                match parse_size(value) {
+                    Some(size) => self.sequence_resolution = Some(size),
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "sequence_wait" => {
+                match SequenceWait::parse(value) {
+                    Some(wait) => self.sequence_wait = Some(wait),
+                    None if value.trim().is_empty() => self.sequence_wait = None,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "relative_to" => {
+                self.relative_to = Some(value.trim().to_string()).filter(|app| !app.is_empty());
+                return;
+            }
+            "workers" => {
+                match value.parse::<usize>() {
+                    Ok(workers) if (1..=MAX_WORKERS).contains(&workers) => self.workers = workers,
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            "press_key" | "hold_key" | "chord_key" | "cps_up_key" | "cps_down_key" => {
+                let slot = match key {
+                    "press_key" => &mut self.press_key,
+                    "hold_key" => &mut self.hold_key,
+                    "chord_key" => &mut self.chord_key,
+                    "cps_up_key" => &mut self.cps_up_key,
+                    _ => &mut self.cps_down_key,
+                };
+                match string_to_rdev_key(value) {
+                    Some(key) => *slot = Some(key),
+                    None if value.trim().is_empty() => *slot = None,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "abort_key" | "step_key" => {
+                let slot = if key == "abort_key" { &mut self.abort_key } else { &mut self.step_key };
+                match string_to_rdev_key(value) {
+                    Some(key) => *slot = key,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "key_injection" => {
+                match KeyInjection::from_name(value) {
+                    Some(injection) => self.key_injection = injection,
+                    None => issues.push(invalid()),
+                }
+                return;
+            }
+            "hotkey" => {
+                if string_to_rdev_key(value).is_some() {
+                    self.hotkey = value.to_string();
+                } else {
+                    issues.push(ConfigIssue::UnknownHotkey { at: at.to_string(), value: value.to_string() });
+                }
+                return;
+            }
+            "target_cps" => {
+                match value.parse::<f64>() {
+                    Ok(cps) if (MIN_CPS..=MAX_CPS).contains(&cps) => self.target_cps = cps,
+                    Ok(cps) if cps.is_finite() => {
+                        self.target_cps = cps.clamp(MIN_CPS, MAX_CPS);
+                        issues.push(ConfigIssue::CpsOutOfRange { at: at.to_string(), value: cps });
+                    }
+                    _ => issues.push(invalid()),
+                }
+                return;
+            }
+            _ => {
+                issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                return;
+            }
+        };
+        match value.parse::<bool>() {
+            Ok(v) => *flag = v,
+            Err(_) => issues.push(invalid()),
+        }
+    }
+
+    /// Reads a legacy `key=value` config file; its issues name the file.
+    pub fn load_legacy(path: &Path) -> Option<(Config, Vec<ConfigIssue>)> {
+        let text = fs::read_to_string(path).ok()?;
+        let (config, mut issues) = Config::parse_legacy(&text);
+        issues.iter_mut().for_each(|issue| issue.locate(path));
+        Some((config, issues))
+    }
+
+    pub fn parse_legacy(text: &str) -> (Config, Vec<ConfigIssue>) {
+        let mut config = Config::default();
+        let mut issues = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let at = format!("line {}", index + 1);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') { continue; }
+            let parts: Vec<&str> = trimmed.splitn(2, '=').collect();
+            if parts.len() != 2 {
+                issues.push(ConfigIssue::Malformed { at, text: trimmed.to_string() });
+                continue;
+            }
+            config.apply_setting(parts[0].trim(), parts[1].trim(), &at, &mut issues);
+        }
+
+        (config, issues)
+    }
+
+    fn from_table(table: &Table, at: &str, issues: &mut Vec<ConfigIssue>) -> Config {
+        let mut config = Config::default();
+        for (key, item) in table.iter() {
+            match item.as_value() {
+                Some(v) => {
+                    let text = match v.as_str() {
+                        Some(s) => s.to_string(),
+                        None => v.to_string().trim().to_string(),
+                    };
+                    config.apply_setting(key, &text, at, issues);
+                }
+                None => issues.push(ConfigIssue::InvalidValue {
+                    at: at.to_string(),
+                    key: key.to_string(),
+                    value: item.to_string().trim().to_string(),
+                }),
+            }
+        }
+        config
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["hotkey"] = value(self.hotkey.as_str());
+        table["fast_mode"] = value(self.fast_mode);
+        table["target_cps"] = value(self.target_cps);
+        table["left_click"] = value(self.left_click);
+        table["middle_click"] = value(self.middle_click);
+        table["right_click"] = value(self.right_click);
+        table["play_sound"] = value(self.play_sound);
+        table["sound_rate"] = value(i64::from(self.sound_rate));
+        table["vary_pitch"] = value(self.vary_pitch);
+        table["toggle_beep"] = value(self.toggle_beep);
+        table["start_delay"] = value(self.start_delay.as_millis() as i64);
+        table["countdown_beeps"] = value(self.countdown_beeps);
+        table["activation"] = value(self.activation.name());
+        if let Some(button) = self.turbo_button {
+            table["turbo_button"] = value(button.name());
+        }
+        if let Some(region) = self.region {
+            table["region"] = value(region.to_string());
+        }
+        if !self.exclusions.is_empty() {
+            let zones: Vec<String> = self.exclusions.iter().map(Rect::to_string).collect();
+            table["exclusions"] = value(zones.join(";"));
+        }
+        table["workers"] = value(self.workers as i64);
+        if !self.sequence.is_empty() {
+            let points: Vec<String> = self.sequence.iter().copied().map(format_point).collect();
+            table["sequence"] = value(points.join(";"));
+        }
+        if !self.offsets.is_empty() {
+            let points: Vec<String> = self.offsets.iter().copied().map(format_point).collect();
+            table["offsets"] = value(points.join(";"));
+        }
+        if let Some(size) = self.sequence_resolution {
+            table["sequence_resolution"] = value(format_size(size));
+        }
+        if let Some(wait) = self.sequence_wait {
+            table["sequence_wait"] = value(wait.to_string());
+        }
+        if let Some(app) = &self.relative_to {
+            table["relative_to"] = value(app.as_str());
+        }
+        if let Some(key) = self.press_key {
+            table["press_key"] = value(key_to_string(&key));
+        }
+        table["key_injection"] = value(self.key_injection.name());
+        if let Some(key) = self.hold_key {
+            table["hold_key"] = value(key_to_string(&key));
+        }
+        if let Some(key) = self.chord_key {
+            table["chord_key"] = value(key_to_string(&key));
+        }
+        table["human_travel"] = value(self.human_travel);
+        table["travel_speed"] = value(self.travel_speed);
+        table["return_cursor"] = value(self.return_cursor);
+        table["jiggle"] = value(self.jiggle);
+        table["jiggle_interval"] = value(self.jiggle_interval.as_secs() as i64);
+        table["jiggle_back"] = value(self.jiggle_back);
+        if let Some(key) = self.cps_up_key {
+            table["cps_up_key"] = value(key_to_string(&key));
+        }
+        if let Some(key) = self.cps_down_key {
+            table["cps_down_key"] = value(key_to_string(&key));
+        }
+        table["cps_step"] = value(self.cps_step);
+        table["cps_beep"] = value(self.cps_beep);
+        table["abort_key"] = value(key_to_string(&self.abort_key));
+        table["step_key"] = value(key_to_string(&self.step_key));
+        table
+    }
+}
+
+/// Compares process names case-insensitively, with or without ".exe".
+pub fn same_app(app: &str, process: &str) -> bool {
+    fn normalize(name: &str) -> String {
+        let name = name.trim().to_lowercase();
+        name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+    }
+    !app.trim().is_empty() && normalize(app) == normalize(process)
+}
+
+impl AppBinding {
+    pub fn matches(&self, process: &str) -> bool {
+        same_app(&self.app, process)
+    }
+
+    fn from_table(table: &Table, at: &str, issues: &mut Vec<ConfigIssue>) -> Option<AppBinding> {
+        let app = table.get("app").and_then(Item::as_str).filter(|app| !app.trim().is_empty());
+        let profile = table.get("profile").and_then(Item::as_str);
+        let stop_on_blur = table.get("stop_on_blur").map_or(Some(false), Item::as_bool);
+        match (app, profile, stop_on_blur) {
+            (Some(app), Some(profile), Some(stop_on_blur)) => {
+                Some(AppBinding { app: app.to_string(), profile: profile.to_string(), stop_on_blur })
+            }
+            _ => {
+                issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+                None
+            }
+        }
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["app"] = value(self.app.as_str());
+        table["profile"] = value(self.profile.as_str());
+        if self.stop_on_blur {
+            table["stop_on_blur"] = value(true);
+        }
+        table
+    }
+}
+
+impl TitleRule {
+    pub fn new(title: &str) -> TitleRule {
+        TitleRule::with(title, TitleMatch::Contains, TextAction::Start)
+    }
+
+    fn with(title: &str, kind: TitleMatch, action: TextAction) -> TitleRule {
+        let mut rule = TitleRule { title: title.to_string(), kind, action, regex: None };
+        rule.compile();
+        rule
+    }
+
+    /// Compiles the title once, rather than on every poll of the focused
+    /// window; call it after changing the title or how it is matched.
+    pub fn compile(&mut self) {
+        self.regex = match self.kind {
+            TitleMatch::Contains => None,
+            TitleMatch::Regex => regex::RegexBuilder::new(&self.title).case_insensitive(true).build().ok(),
+        };
+    }
+
+    /// An empty title or a regex that doesn't compile matches nothing.
+    pub fn is_valid(&self) -> bool {
+        !self.title.trim().is_empty() && (self.kind == TitleMatch::Contains || self.regex.is_some())
+    }
+
+    pub fn matches(&self, title: &str) -> bool {
+        if self.title.trim().is_empty() {
+            return false;
+        }
+        match self.kind {
+            TitleMatch::Contains => title.to_lowercase().contains(&self.title.to_lowercase()),
+            TitleMatch::Regex => self.regex.as_ref().is_some_and(|regex| regex.is_match(title)),
+        }
+    }
+
+    fn from_table(table: &Table, at: &str, issues: &mut Vec<ConfigIssue>) -> Option<TitleRule> {
+        let title = table.get("title").and_then(Item::as_str);
+        let kind = table.get("match").map_or(Some(TitleMatch::Contains), |item| item.as_str().and_then(TitleMatch::from_name));
+        let action = table.get("action").and_then(Item::as_str).and_then(TextAction::from_name);
+        let rule = match (title, kind, action) {
+            (Some(title), Some(kind), Some(action)) => Some(TitleRule::with(title, kind, action)),
+            _ => None,
+        };
+        let rule = rule.filter(TitleRule::is_valid);
+        if rule.is_none() {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+        }
+        rule
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["title"] = value(self.title.as_str());
+        if self.kind != TitleMatch::Contains {
+            table["match"] = value(self.kind.name());
+        }
+        table["action"] = value(self.action.name());
+        table
+    }
+}
+
+impl MacroHotkey {
+    fn from_table(table: &Table, at: &str, issues: &mut Vec<ConfigIssue>) -> Option<MacroHotkey> {
+        let key = table.get("key").and_then(Item::as_str).and_then(string_to_rdev_key);
+        let name = table.get("macro").and_then(Item::as_str).filter(|name| !name.trim().is_empty());
+        match (key, name) {
+            (Some(key), Some(name)) => Some(MacroHotkey { key, name: name.to_string() }),
+            _ => {
+                issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+                None
+            }
+        }
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["key"] = value(key_to_string(&self.key));
+        table["macro"] = value(self.name.as_str());
+        table
+    }
+}
+
+impl TextTyper {
+    pub fn new(text: &str) -> TextTyper {
+        TextTyper {
+            text: text.to_string(),
+            hotkey: rdev::Key::F9,
+            char_delay: Duration::from_millis(30),
+            repeat_delay: Duration::from_secs(1),
+        }
+    }
+
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Option<TextTyper> {
+        let at = "typer";
+        let Some(text) = table.get("text").and_then(Item::as_str).filter(|text| !text.is_empty()) else {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+            return None;
+        };
+        let mut typer = TextTyper::new(text);
+        for (key, item) in table.iter() {
+            let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+            let millis = || text.parse::<u64>().ok().map(Duration::from_millis);
+            let valid = match key {
+                "text" => true,
+                "hotkey" => match item.as_str().and_then(string_to_rdev_key) {
+                    Some(hotkey) => {
+                        typer.hotkey = hotkey;
+                        true
+                    }
+                    None => false,
+                },
+                "char_delay" => match millis().filter(|&delay| delay <= MAX_TYPER_CHAR_DELAY) {
+                    Some(delay) => {
+                        typer.char_delay = delay;
+                        true
+                    }
+                    None => false,
+                },
+                "repeat_delay" => match millis().filter(|&delay| delay <= MAX_TYPER_REPEAT_DELAY) {
+                    Some(delay) => {
+                        typer.repeat_delay = delay;
+                        true
+                    }
+                    None => false,
+                },
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        Some(typer)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["text"] = value(self.text.as_str());
+        table["hotkey"] = value(key_to_string(&self.hotkey));
+        table["char_delay"] = value(self.char_delay.as_millis() as i64);
+        table["repeat_delay"] = value(self.repeat_delay.as_millis() as i64);
+        table
+    }
+}
+
+impl ChatTrigger {
+    pub fn new(channel: &str) -> ChatTrigger {
+        ChatTrigger {
+            channel: channel.to_string(),
+            command: "!click".to_string(),
+            max_clicks: 20,
+            cooldown: Duration::from_secs(30),
+            cps: 10.0,
+        }
+    }
+
+    /// Twitch channel names are 1-25 letters, digits and underscores.
+    pub fn valid_channel(channel: &str) -> bool {
+        (1..=25).contains(&channel.len()) && channel.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Option<ChatTrigger> {
+        let at = "twitch";
+        let channel = table.get("channel").and_then(Item::as_str).map(|c| c.trim().to_lowercase());
+        let Some(channel) = channel.filter(|c| ChatTrigger::valid_channel(c)) else {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+            return None;
+        };
+        let mut trigger = ChatTrigger::new(&channel);
+        for (key, item) in table.iter() {
+            let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+            let valid = match key {
+                "channel" => true,
+                "command" => match item.as_str().map(str::trim).filter(|c| !c.is_empty() && !c.contains(' ')) {
+                    Some(command) => {
+                        trigger.command = command.to_string();
+                        true
+                    }
+                    None => false,
+                },
+                "max_clicks" => match text.parse::<u32>() {
+                    Ok(n) if (1..=MAX_CHAT_CLICKS).contains(&n) => {
+                        trigger.max_clicks = n;
+                        true
+                    }
+                    _ => false,
+                },
+                "cooldown" => match text.parse::<u64>().map(Duration::from_secs) {
+                    Ok(cooldown) if cooldown <= MAX_CHAT_COOLDOWN => {
+                        trigger.cooldown = cooldown;
+                        true
+                    }
+                    _ => false,
+                },
+                "cps" => match text.parse::<f64>() {
+                    Ok(cps) if (MIN_CPS..=MAX_CHAT_CPS).contains(&cps) => {
+                        trigger.cps = cps;
+                        true
+                    }
+                    _ => false,
+                },
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        Some(trigger)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["channel"] = value(self.channel.as_str());
+        table["command"] = value(self.command.as_str());
+        table["max_clicks"] = value(i64::from(self.max_clicks));
+        table["cooldown"] = value(self.cooldown.as_secs() as i64);
+        table["cps"] = value(self.cps);
+        table
+    }
+}
+
+impl TextTrigger {
+    pub fn new(region: Rect, text: &str) -> TextTrigger {
+        TextTrigger { region, text: text.trim().to_string(), action: TextAction::Stop, every: Duration::from_secs(1) }
+    }
+
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Option<TextTrigger> {
+        let at = "text_trigger";
+        let region = table.get("region").and_then(Item::as_str).and_then(Rect::parse);
+        let text = table.get("text").and_then(Item::as_str).map(str::trim).filter(|text| !text.is_empty());
+        let (Some(region), Some(text)) = (region, text) else {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+            return None;
+        };
+        let mut trigger = TextTrigger::new(region, text);
+        for (key, item) in table.iter() {
+            let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+            let valid = match key {
+                "region" | "text" => true,
+                "action" => match item.as_str().and_then(TextAction::from_name) {
+                    Some(action) => {
+                        trigger.action = action;
+                        true
+                    }
+                    None => false,
+                },
+                "every" => match text.parse::<u64>().map(Duration::from_millis) {
+                    Ok(every) if (MIN_TEXT_TRIGGER_EVERY..=MAX_TEXT_TRIGGER_EVERY).contains(&every) => {
+                        trigger.every = every;
+                        true
+                    }
+                    _ => false,
+                },
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        Some(trigger)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["region"] = value(self.region.to_string());
+        table["text"] = value(self.text.as_str());
+        table["action"] = value(self.action.name());
+        table["every"] = value(self.every.as_millis() as i64);
+        table
+    }
+}
+
+impl MqttConfig {
+    pub fn new(broker: &str) -> MqttConfig {
+        MqttConfig { broker: broker.to_string(), topic: "autoclicker".to_string(), username: String::new(), password: String::new() }
+    }
+
+    pub fn valid_broker(broker: &str) -> bool {
+        !broker.is_empty() && !broker.contains(char::is_whitespace)
+    }
+
+    /// Topics are published to, so they can't hold wildcards.
+    pub fn valid_topic(topic: &str) -> bool {
+        !topic.is_empty() && !topic.contains(['+', '#']) && !topic.ends_with('/')
+    }
+
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Option<MqttConfig> {
+        let at = "mqtt";
+        let broker = table.get("broker").and_then(Item::as_str).map(str::trim);
+        let Some(broker) = broker.filter(|b| MqttConfig::valid_broker(b)) else {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+            return None;
+        };
+        let mut mqtt = MqttConfig::new(broker);
+        for (key, item) in table.iter() {
+            let valid = match (key, item.as_str()) {
+                ("broker", _) => true,
+                ("topic", Some(topic)) if MqttConfig::valid_topic(topic.trim()) => {
+                    mqtt.topic = topic.trim().to_string();
+                    true
+                }
+                ("username", Some(username)) => {
+                    mqtt.username = username.to_string();
+                    true
+                }
+                ("password", Some(password)) => {
+                    mqtt.password = password.to_string();
+                    true
+                }
+                ("topic" | "username" | "password", _) => false,
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        Some(mqtt)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["broker"] = value(self.broker.as_str());
+        table["topic"] = value(self.topic.as_str());
+        if !self.username.is_empty() {
+            table["username"] = value(self.username.as_str());
+        }
+        if !self.password.is_empty() {
+            table["password"] = value(self.password.as_str());
+        }
+        table
+    }
+}
+
+/// Host, port and path of an http:// URL.
+pub fn parse_endpoint(url: &str) -> Result<(String, u16, String), String> {
+    let rest = url.trim().strip_prefix("http://").ok_or("only http:// endpoints are supported")?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| format!("invalid port \"{}\"", port))?),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+impl WebhookConfig {
+    pub fn new(url: &str) -> WebhookConfig {
+        WebhookConfig { url: url.to_string(), every: 1000 }
+    }
+
+    pub fn valid_url(url: &str) -> bool {
+        parse_endpoint(url).is_ok() && !url.contains(char::is_whitespace)
+    }
+
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Option<WebhookConfig> {
+        let at = "webhook";
+        let url = table.get("url").and_then(Item::as_str).map(str::trim);
+        let Some(url) = url.filter(|url| WebhookConfig::valid_url(url)) else {
+            issues.push(ConfigIssue::Malformed { at: at.to_string(), text: table.to_string().trim().to_string() });
+            return None;
+        };
+        let mut webhook = WebhookConfig::new(url);
+        for (key, item) in table.iter() {
+            let valid = match (key, item.as_integer()) {
+                ("url", _) => true,
+                ("every", Some(every)) if (0..=MAX_WEBHOOK_EVERY as i64).contains(&every) => {
+                    webhook.every = every as u64;
+                    true
+                }
+                ("every", _) => false,
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        Some(webhook)
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["url"] = value(self.url.as_str());
+        table["every"] = value(self.every as i64);
+        table
+    }
+}
+
+impl Hooks {
+    fn from_table(table: &Table, issues: &mut Vec<ConfigIssue>) -> Hooks {
+        let at = "hooks";
+        let mut hooks = Hooks::default();
+        for (key, item) in table.iter() {
+            let valid = match (key, item.as_value()) {
+                ("enabled", Some(value)) if value.as_bool().is_some() => {
+                    hooks.enabled = value.as_bool().unwrap_or(false);
+                    true
+                }
+                ("on_start", Some(value)) if value.as_str().is_some() => {
+                    hooks.on_start = value.as_str().unwrap_or_default().to_string();
+                    true
+                }
+                ("on_stop", Some(value)) if value.as_str().is_some() => {
+                    hooks.on_stop = value.as_str().unwrap_or_default().to_string();
+                    true
+                }
+                ("enabled" | "on_start" | "on_stop", _) => false,
+                _ => {
+                    issues.push(ConfigIssue::UnknownKey { at: at.to_string(), key: key.to_string() });
+                    true
+                }
+            };
+            if !valid {
+                let text = item.as_value().map(|v| v.to_string().trim().to_string()).unwrap_or_default();
+                issues.push(ConfigIssue::InvalidValue { at: at.to_string(), key: key.to_string(), value: text });
+            }
+        }
+        hooks
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["enabled"] = value(self.enabled);
+        table["on_start"] = value(self.on_start.as_str());
+        table["on_stop"] = value(self.on_stop.as_str());
+        table
+    }
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), Config::default());
+        ConfigFile {
+            active_profile: DEFAULT_PROFILE.to_string(),
+            profiles,
+            backend: BackendKind::Enigo,
+            max_cps: None,
+            telemetry: false,
+            telemetry_endpoint: String::new(),
+            theme: ThemeMode::System,
+            sound_theme: DEFAULT_SOUND_THEME.to_string(),
+            ui_scale: 1.0,
+            font_size: DEFAULT_FONT_SIZE,
+            window: None,
+            on_lock: LockPolicy::Pause,
+            superspeed: SuperspeedMode::Balanced,
+            pin_core: None,
+            on_battery: BatteryPolicy::Ignore,
+            battery_cps: 10.0,
+            cps_presets: vec![10.0, 20.0, 50.0, 100.0],
+            idle_stop: None,
+            typing_pause: None,
+            overlay_port: None,
+            chat_trigger: None,
+            text_trigger: None,
+            typer: None,
+            command_files: false,
+            verify_clicks: false,
+            scale_recordings: false,
+            keep_awake: true,
+            pause_key: None,
+            indicator: IndicatorPlace::Off,
+            listener_retry: Duration::from_secs(60),
+            sync_dir: None,
+            rollover: Rollover::default(),
+            mqtt: None,
+            webhook: None,
+            hooks: Hooks::default(),
+            blocklist: Vec::new(),
+            bindings: Vec::new(),
+            title_rules: Vec::new(),
+            macro_hotkeys: Vec::new(),
+        }
+    }
+}
+
+// The CPS guard, if `item` is a number within range.
+fn parse_cps_limit(item: &Item) -> Option<f64> {
+    let cps = item.as_value()?.to_string().trim().parse::<f64>().ok()?;
+    (MIN_CPS..=MAX_CPS_LIMIT).contains(&cps).then_some(cps)
+}
+
+// Top-level settings of config.toml, to tell unknown ones from bad values.
+const FILE_KEYS: &[&str] = &[
+    "active_profile", "backend", "battery_cps", "bindings", "blocklist", "command_files", "cps_presets", "day_starts_at", "font_size", "hooks", "idle_stop", "indicator", "keep_awake", "listener_retry", "macro_hotkeys", "max_cps", "mqtt", "on_battery", "on_lock", "overlay_port", "pause_key", "pin_core", "profiles", "scale_recordings", "sound_theme", "superspeed", "sync_dir", "telemetry", "telemetry_endpoint", "text_trigger", "theme", "title_rules", "twitch", "typer", "typing_pause", "ui_scale", "verify_clicks", "webhook", "week_starts_on", "window",
+];
+
+// The UI scale, if `item` is a number within range.
+fn parse_ui_scale(item: &Item) -> Option<f32> {
+    let scale = item.as_value()?.to_string().trim().parse::<f32>().ok()?;
+    (MIN_UI_SCALE..=MAX_UI_SCALE).contains(&scale).then_some(scale)
+}
+
+// The font size, if `item` is a number within range.
+fn parse_font_size(item: &Item) -> Option<f32> {
+    let size = item.as_value()?.to_string().trim().parse::<f32>().ok()?;
+    (MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&size).then_some(size)
+}
+
+// Up to `MAX_CPS_PRESETS` target rates, if every one is valid.
+fn parse_cps_presets(item: &Item) -> Option<Vec<f64>> {
+    let rates: Option<Vec<f64>> = item
+        .as_array()?
+        .iter()
+        .map(|rate| rate.to_string().trim().parse::<f64>().ok().filter(|cps| (MIN_CPS..=MAX_CPS).contains(cps)))
+        .collect();
+    rates.filter(|rates| rates.len() <= MAX_CPS_PRESETS)
+}
+
+/// Whole minutes from 1 up to `MAX_IDLE_STOP`.
+fn parse_minutes(item: &Item) -> Option<Duration> {
+    let minutes = item.as_integer().filter(|&m| m > 0)?;
+    Some(Duration::from_secs(minutes as u64 * 60)).filter(|&d| d <= MAX_IDLE_STOP)
+}
+
+/// Milliseconds from 1 up to `MAX_TYPING_PAUSE`.
+fn parse_typing_pause(item: &Item) -> Option<Duration> {
+    let ms = item.as_integer().filter(|&ms| ms > 0)?;
+    Some(Duration::from_millis(ms as u64)).filter(|&d| d <= MAX_TYPING_PAUSE)
+}
+
+/// Process names, if every entry is a non-blank string.
+fn parse_apps(item: &Item) -> Option<Vec<String>> {
+    item.as_array()?
+        .iter()
+        .map(|app| app.as_str().map(str::trim).filter(|app| !app.is_empty()).map(str::to_string))
+        .collect()
+}
+
+/// Whole seconds from `MIN_LISTENER_RETRY` up to `MAX_LISTENER_RETRY`.
+fn parse_listener_retry(item: &Item) -> Option<Duration> {
+    let secs = item.as_integer().and_then(|secs| u64::try_from(secs).ok())?;
+    Some(Duration::from_secs(secs)).filter(|d| (MIN_LISTENER_RETRY..=MAX_LISTENER_RETRY).contains(d))
+}
+
+fn parse_hour(item: &Item) -> Option<u32> {
+    item.as_integer().and_then(|hour| u32::try_from(hour).ok()).filter(|&hour| hour < 24)
+}
+
+fn parse_core(item: &Item) -> Option<usize> {
+    item.as_integer().and_then(|core| usize::try_from(core).ok()).filter(|&core| core <= MAX_CORE)
+}
+
+fn parse_port(item: &Item) -> Option<u16> {
+    item.as_integer().and_then(|port| u16::try_from(port).ok()).filter(|&port| port > 0)
+}
+
+impl ConfigFile {
+    /// Location of config.toml in the per-user config directory.
+    pub fn path() -> PathBuf {
+        config_dir().join("config.toml")
+    }
+
+    /// Loads config.toml, returning the sanitized settings and any problems found.
+    pub fn load() -> Option<(ConfigFile, Vec<ConfigIssue>)> {
+        let text = fs::read_to_string(ConfigFile::path()).ok()?;
+        Some(ConfigFile::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> (ConfigFile, Vec<ConfigIssue>) {
+        let mut issues = Vec::new();
+        let doc = match text.parse::<Document>() {
+            Ok(doc) => doc,
+            Err(e) => {
+                let text = e.to_string().lines().next().unwrap_or_default().to_string();
+                issues.push(ConfigIssue::Malformed { at: "config.toml".to_string(), text });
+                return (ConfigFile::default(), issues);
+            }
+        };
+
+        let mut file = ConfigFile::default();
+        file.profiles.clear();
+        for (key, item) in doc.iter() {
+            match (key, item) {
+                ("active_profile", item) if item.as_str().is_some() => {
+                    file.active_profile = item.as_str().unwrap_or_default().to_string();
+                }
+                ("backend", item) if item.as_str().and_then(BackendKind::from_name).is_some() => {
+                    file.backend = item.as_str().and_then(BackendKind::from_name).unwrap_or(BackendKind::Enigo);
+                }
+                ("max_cps", item) if parse_cps_limit(item).is_some() => file.max_cps = parse_cps_limit(item),
+                ("theme", item) if item.as_str().and_then(ThemeMode::from_name).is_some() => {
+                    file.theme = item.as_str().and_then(ThemeMode::from_name).unwrap_or(ThemeMode::System);
+                }
+                ("sound_theme", item) if item.as_str().is_some_and(|name| !name.trim().is_empty()) => {
+                    file.sound_theme = item.as_str().unwrap_or_default().trim().to_string();
+                }
+                ("ui_scale", item) if parse_ui_scale(item).is_some() => file.ui_scale = parse_ui_scale(item).unwrap_or(1.0),
+                ("font_size", item) if parse_font_size(item).is_some() => file.font_size = parse_font_size(item).unwrap_or(DEFAULT_FONT_SIZE),
+                ("window", item) if item.as_str().and_then(Rect::parse).is_some() => file.window = item.as_str().and_then(Rect::parse),
+                ("superspeed", item) if item.as_str().and_then(SuperspeedMode::from_name).is_some() => {
+                    file.superspeed = item.as_str().and_then(SuperspeedMode::from_name).unwrap_or(SuperspeedMode::Balanced);
+                }
+                ("on_lock", item) if item.as_str().and_then(LockPolicy::from_name).is_some() => {
+                    file.on_lock = item.as_str().and_then(LockPolicy::from_name).unwrap_or(LockPolicy::Pause);
+                }
+                ("indicator", item) if item.as_str().and_then(IndicatorPlace::from_name).is_some() => {
+                    file.indicator = item.as_str().and_then(IndicatorPlace::from_name).unwrap_or(IndicatorPlace::Off);
+                }
+                ("on_battery", item) if item.as_str().and_then(BatteryPolicy::from_name).is_some() => {
+                    file.on_battery = item.as_str().and_then(BatteryPolicy::from_name).unwrap_or(BatteryPolicy::Ignore);
+                }
+                ("idle_stop", item) if parse_minutes(item).is_some() => file.idle_stop = parse_minutes(item),
+                ("typing_pause", item) if parse_typing_pause(item).is_some() => file.typing_pause = parse_typing_pause(item),
+                ("battery_cps", item) if parse_cps_limit(item).is_some_and(|cps| cps <= MAX_CPS) => {
+                    file.battery_cps = parse_cps_limit(item).unwrap_or(10.0);
+                }
+                ("cps_presets", item) if parse_cps_presets(item).is_some() => {
+                    file.cps_presets = parse_cps_presets(item).unwrap_or_default();
+                }
+                ("blocklist", item) if parse_apps(item).is_some() => file.blocklist = parse_apps(item).unwrap_or_default(),
+                ("overlay_port", item) if parse_port(item).is_some() => file.overlay_port = parse_port(item),
+                ("pin_core", item) if parse_core(item).is_some() => file.pin_core = parse_core(item),
+                ("telemetry", item) if item.as_bool().is_some() => file.telemetry = item.as_bool().unwrap_or(false),
+                ("command_files", item) if item.as_bool().is_some() => file.command_files = item.as_bool().unwrap_or(false),
+                ("verify_clicks", item) if item.as_bool().is_some() => file.verify_clicks = item.as_bool().unwrap_or(false),
+                ("scale_recordings", item) if item.as_bool().is_some() => file.scale_recordings = item.as_bool().unwrap_or(false),
+                ("keep_awake", item) if item.as_bool().is_some() => file.keep_awake = item.as_bool().unwrap_or(true),
+                ("pause_key", item) if item.as_str().and_then(string_to_rdev_key).is_some() => {
+                    file.pause_key = item.as_str().and_then(string_to_rdev_key);
+                }
+                ("listener_retry", item) if parse_listener_retry(item).is_some() => {
+                    file.listener_retry = parse_listener_retry(item).unwrap_or(MAX_LISTENER_RETRY);
+                }
+                ("day_starts_at", item) if parse_hour(item).is_some() => file.rollover.day_start = parse_hour(item).unwrap_or(0),
+                ("week_starts_on", item) if item.as_str().and_then(Weekday::from_name).is_some() => {
+                    file.rollover.week_start = item.as_str().and_then(Weekday::from_name).unwrap_or(Weekday::Monday);
+                }
+                ("sync_dir", item) if item.as_str().is_some() => {
+                    file.sync_dir = item.as_str().map(str::trim).filter(|dir| !dir.is_empty()).map(PathBuf::from);
+                }
+                ("telemetry_endpoint", item) if item.as_str().is_some() => {
+                    file.telemetry_endpoint = item.as_str().unwrap_or_default().to_string();
+                }
+                ("profiles", Item::Table(profiles)) => {
+                    for (name, item) in profiles.iter() {
+                        let at = format!("profile \"{}\"", name);
+                        match item.as_table() {
+                            Some(table) => {
+                                let config = Config::from_table(table, &at, &mut issues);
+                                file.profiles.insert(name.to_string(), config);
+                            }
+                            None => issues.push(ConfigIssue::Malformed {
+                                at,
+                                text: item.to_string().trim().to_string(),
+                            }),
+                        }
+                    }
+                }
+                ("twitch", Item::Table(table)) => file.chat_trigger = ChatTrigger::from_table(table, &mut issues),
+                ("text_trigger", Item::Table(table)) => file.text_trigger = TextTrigger::from_table(table, &mut issues),
+                ("mqtt", Item::Table(table)) => file.mqtt = MqttConfig::from_table(table, &mut issues),
+                ("webhook", Item::Table(table)) => file.webhook = WebhookConfig::from_table(table, &mut issues),
+                ("hooks", Item::Table(table)) => file.hooks = Hooks::from_table(table, &mut issues),
+                ("typer", Item::Table(table)) => file.typer = TextTyper::from_table(table, &mut issues),
+                ("bindings", Item::ArrayOfTables(bindings)) => {
+                    for (index, table) in bindings.iter().enumerate() {
+                        let at = format!("binding {}", index + 1);
+                        file.bindings.extend(AppBinding::from_table(table, &at, &mut issues));
+                    }
+                }
+                ("title_rules", Item::ArrayOfTables(rules)) => {
+                    for (index, table) in rules.iter().enumerate() {
+                        let at = format!("title rule {}", index + 1);
+                        file.title_rules.extend(TitleRule::from_table(table, &at, &mut issues));
+                    }
+                }
+                ("macro_hotkeys", Item::ArrayOfTables(hotkeys)) => {
+                    for (index, table) in hotkeys.iter().enumerate() {
+                        let at = format!("macro hotkey {}", index + 1);
+                        file.macro_hotkeys.extend(MacroHotkey::from_table(table, &at, &mut issues));
+                    }
+                }
+                (key, item) if FILE_KEYS.contains(&key) => issues.push(ConfigIssue::InvalidValue {
+                    at: "config.toml".to_string(),
+                    key: key.to_string(),
+                    value: item.to_string().trim().to_string(),
+                }),
+                (key, _) => issues.push(ConfigIssue::UnknownKey { at: "config.toml".to_string(), key: key.to_string() }),
+            }
+        }
+
+        if !file.profiles.contains_key(&file.active_profile) {
+            issues.push(ConfigIssue::MissingProfile { name: file.active_profile.clone() });
+            file.active_profile = DEFAULT_PROFILE.to_string();
+            file.profiles.entry(DEFAULT_PROFILE.to_string()).or_default();
+        }
+        let profiles = &file.profiles;
+        file.bindings.retain(|binding| {
+            let known = profiles.contains_key(&binding.profile);
+            if !known {
+                issues.push(ConfigIssue::UnboundProfile { app: binding.app.clone(), profile: binding.profile.clone() });
+            }
+            known
+        });
+        (file, issues)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut doc = Document::new();
+        doc["active_profile"] = value(self.active_profile.as_str());
+        doc["backend"] = value(self.backend.name());
+        doc["theme"] = value(self.theme.name());
+        doc["sound_theme"] = value(self.sound_theme.as_str());
+        doc["ui_scale"] = value(f64::from(self.ui_scale));
+        doc["font_size"] = value(f64::from(self.font_size));
+        if let Some(window) = self.window {
+            doc["window"] = value(window.to_string());
+        }
+        doc["superspeed"] = value(self.superspeed.name());
+        if let Some(core) = self.pin_core {
+            doc["pin_core"] = value(core as i64);
+        }
+        doc["on_lock"] = value(self.on_lock.name());
+        doc["on_battery"] = value(self.on_battery.name());
+        doc["battery_cps"] = value(self.battery_cps);
+        doc["cps_presets"] = value(self.cps_presets.iter().copied().collect::<Array>());
+        doc["day_starts_at"] = value(i64::from(self.rollover.day_start));
+        doc["week_starts_on"] = value(self.rollover.week_start.name());
+        doc["listener_retry"] = value(self.listener_retry.as_secs() as i64);
+        if let Some(cps) = self.max_cps {
+            doc["max_cps"] = value(cps);
+        }
+        if let Some(port) = self.overlay_port {
+            doc["overlay_port"] = value(i64::from(port));
+        }
+        if let Some(idle) = self.idle_stop {
+            doc["idle_stop"] = value((idle.as_secs() / 60) as i64);
+        }
+        if let Some(pause) = self.typing_pause {
+            doc["typing_pause"] = value(pause.as_millis() as i64);
+        }
+        if self.telemetry {
+            doc["telemetry"] = value(true);
+        }
+        if self.command_files {
+            doc["command_files"] = value(true);
+        }
+        if self.verify_clicks {
+            doc["verify_clicks"] = value(true);
+        }
+        if self.scale_recordings {
+            doc["scale_recordings"] = value(true);
+        }
+        if !self.keep_awake {
+            doc["keep_awake"] = value(false);
+        }
+        if let Some(key) = self.pause_key {
+            doc["pause_key"] = value(key_to_string(&key));
+        }
+        if self.indicator != IndicatorPlace::Off {
+            doc["indicator"] = value(self.indicator.name());
+        }
+        if !self.blocklist.is_empty() {
+            doc["blocklist"] = value(self.blocklist.iter().map(String::as_str).collect::<Array>());
+        }
+        if let Some(dir) = &self.sync_dir {
+            doc["sync_dir"] = value(dir.to_string_lossy().as_ref());
+        }
+        if !self.telemetry_endpoint.is_empty() {
+            doc["telemetry_endpoint"] = value(self.telemetry_endpoint.as_str());
+        }
+        let mut profiles = Table::new();
+        profiles.set_implicit(true);
+        for (name, config) in &self.profiles {
+            profiles.insert(name, Item::Table(config.to_table()));
+        }
+        doc["profiles"] = Item::Table(profiles);
+        if let Some(typer) = &self.typer {
+            doc["typer"] = Item::Table(typer.to_table());
+        }
+        if let Some(trigger) = &self.chat_trigger {
+            doc["twitch"] = Item::Table(trigger.to_table());
+        }
+        if let Some(trigger) = &self.text_trigger {
+            doc["text_trigger"] = Item::Table(trigger.to_table());
+        }
+        if let Some(mqtt) = &self.mqtt {
+            doc["mqtt"] = Item::Table(mqtt.to_table());
+        }
+        if let Some(webhook) = &self.webhook {
+            doc["webhook"] = Item::Table(webhook.to_table());
+        }
+        if self.hooks != Hooks::default() {
+            doc["hooks"] = Item::Table(self.hooks.to_table());
+        }
+        if !self.bindings.is_empty() {
+            let mut bindings = ArrayOfTables::new();
+            for binding in &self.bindings {
+                bindings.push(binding.to_table());
+            }
+            doc["bindings"] = Item::ArrayOfTables(bindings);
+        }
+        if !self.title_rules.is_empty() {
+            let mut rules = ArrayOfTables::new();
+            for rule in &self.title_rules {
+                rules.push(rule.to_table());
+            }
+            doc["title_rules"] = Item::ArrayOfTables(rules);
+        }
+        if !self.macro_hotkeys.is_empty() {
+            let mut hotkeys = ArrayOfTables::new();
+            for hotkey in &self.macro_hotkeys {
+                hotkeys.push(hotkey.to_table());
+            }
+            doc["macro_hotkeys"] = Item::ArrayOfTables(hotkeys);
+        }
+        doc.to_string()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = ConfigFile::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    pub fn active(&self) -> &Config {
+        &self.profiles[&self.active_profile]
+    }
+
+    /// Looks up a profile by name in the saved config.toml.
+    pub fn load_profile(name: &str) -> Option<Config> {
+        let (file, _) = ConfigFile::load()?;
+        file.profiles.get(name).cloned()
+    }
+}
+
+/// Per-user directory holding config.toml.
+pub fn config_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join("superspeed-autoclicker")
+}
+
+/// Converts a legacy config.txt (and any profiles/*.txt) into config.toml.
+/// Returns a description of what happened for the UI and the problems found
+/// in the legacy files, or `None` when there was nothing to migrate.
+pub fn migrate_legacy() -> Option<(Result<String, String>, Vec<ConfigIssue>)> {
+    let legacy = Path::new(LEGACY_CONFIG);
+    let target = ConfigFile::path();
+    if !legacy.exists() || target.exists() {
+        return None;
+    }
+
+    let (config, mut issues) = Config::load_legacy(legacy)?;
+    let mut file = ConfigFile::default();
+    file.profiles.insert(DEFAULT_PROFILE.to_string(), config);
+    if let Ok(entries) = fs::read_dir(LEGACY_PROFILE_DIR) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("txt") { continue; }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else { continue; };
+            if let Some((profile, profile_issues)) = Config::load_legacy(&path) {
+                file.profiles.insert(name.to_string(), profile);
+                issues.extend(profile_issues);
+            }
+        }
+    }
+
+    let backup = Path::new("config.txt.bak");
+    let result = file
+        .save()
+        .and_then(|_| fs::rename(legacy, backup))
+        .map(|_| {
+            format!(
+                "Migrated config.txt ({} profile(s)) to {}. The original was kept as {}.",
+                file.profiles.len(),
+                target.display(),
+                backup.display()
+            )
+        })
+        .map_err(|e| format!("Failed to migrate config.txt: {}", e));
+    Some((result, issues))
+}
+
+// Marks files and codes holding a single shared profile.
+const PROFILE_FORMAT: &str = "superspeed-autoclicker-profile";
+const PROFILE_VERSION: i64 = 1;
+// Start of a profile shared as one line of text, base64 of its file.
+const SHARE_CODE_PREFIX: &str = "SSAC1:";
+pub const PROFILE_EXTENSION: &str = "profile.toml";
+
+/// A profile read from a share code or file.
+#[derive(Debug)]
+pub struct SharedProfile {
+    pub name: String,
+    pub config: Config,
+    // Settings that were out of range or unusable here.
+    pub issues: Vec<ConfigIssue>,
+}
+
+/// Shareable file for a single profile saved as `name`.
+pub fn export_profile(name: &str, config: &Config) -> String {
+    let mut doc = Document::new();
+    doc["format"] = value(PROFILE_FORMAT);
+    doc["version"] = value(PROFILE_VERSION);
+    doc["name"] = value(name);
+    doc["settings"] = Item::Table(config.to_table());
+    doc.to_string()
+}
+
+/// The profile file as one line of text, for pasting into chats.
+pub fn share_code(name: &str, config: &Config) -> String {
+    format!("{}{}", SHARE_CODE_PREFIX, base64_encode(export_profile(name, config).as_bytes()))
+}
+
+/// Reads a profile from a share code or the contents of an exported file.
+pub fn import_profile(text: &str) -> Result<SharedProfile, String> {
+    let text = text.trim();
+    let decoded;
+    let text = match text.strip_prefix(SHARE_CODE_PREFIX) {
+        Some(code) => {
+            let bytes = base64_decode(code).ok_or("the share code is damaged")?;
+            decoded = String::from_utf8(bytes).map_err(|_| "the share code is damaged")?;
+            decoded.as_str()
+        }
+        None => text,
+    };
+    let doc = text.parse::<Document>().map_err(|_| "not a shared profile".to_string())?;
+    if doc.get("format").and_then(Item::as_str) != Some(PROFILE_FORMAT) {
+        return Err("not a shared profile".to_string());
+    }
+    match doc.get("version").and_then(Item::as_integer) {
+        Some(version) if version <= PROFILE_VERSION => {}
+        _ => return Err("made by a newer version of the autoclicker".to_string()),
+    }
+    let name = doc.get("name").and_then(Item::as_str).map(str::trim).filter(|name| !name.is_empty());
+    let settings = doc.get("settings").and_then(Item::as_table).ok_or("no settings in the profile")?;
+    let mut issues = Vec::new();
+    let config = Config::from_table(settings, "shared profile", &mut issues);
+    Ok(SharedProfile { name: name.unwrap_or("imported").to_string(), config, issues })
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+// Whitespace is skipped, since chat apps like to wrap long lines.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        let digit = BASE64.iter().position(|&d| d == c)? as u32;
+        bits = bits << 6 | digit;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+            bits &= (1 << count) - 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::SUPPORTED_KEYS;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const CASES: usize = 500;
+
+    fn random_config(rng: &mut StdRng) -> Config {
+        let hotkey = SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())];
+        Config {
+            hotkey: key_to_string(&hotkey),
+            fast_mode: rng.gen(),
+            target_cps: rng.gen_range(MIN_CPS..=MAX_CPS),
+            left_click: rng.gen(),
+            middle_click: rng.gen(),
+            right_click: rng.gen(),
+            play_sound: rng.gen(),
+            sound_rate: rng.gen_range(1..=MAX_SOUND_RATE),
+            vary_pitch: rng.gen(),
+            toggle_beep: rng.gen(),
+            start_delay: Duration::from_millis(rng.gen_range(0..=MAX_START_DELAY.as_millis() as u64)),
+            countdown_beeps: rng.gen(),
+            activation: Activation::ALL[rng.gen_range(0..Activation::ALL.len())],
+            turbo_button: [None, Some(ClickButton::Left), Some(ClickButton::Middle), Some(ClickButton::Right)][rng.gen_range(0..4)],
+            region: rng.gen_bool(0.5).then(|| random_rect(rng)),
+            exclusions: (0..rng.gen_range(0..3)).map(|_| random_rect(rng)).collect(),
+            workers: rng.gen_range(1..=MAX_WORKERS),
+            sequence: (0..rng.gen_range(0..4)).map(|_| (rng.gen_range(-2000.0..4000.0), rng.gen_range(-2000.0..4000.0))).collect(),
+            offsets: (0..rng.gen_range(0..4)).map(|_| (rng.gen_range(-50.0..50.0), rng.gen_range(-50.0..50.0))).collect(),
+            sequence_resolution: rng.gen_bool(0.5).then(|| (rng.gen_range(1..8000), rng.gen_range(1..8000))),
+            sequence_wait: rng.gen_bool(0.5).then(|| SequenceWait {
+                at: (rng.gen_range(-2000.0..4000.0), rng.gen_range(-2000.0..4000.0)),
+                timeout: Duration::from_millis(rng.gen_range(0..=MAX_SEQUENCE_WAIT.as_millis() as u64)),
+            }),
+            relative_to: rng.gen_bool(0.5).then(|| random_name(rng).trim().to_string()).filter(|app| !app.is_empty()),
+            press_key: rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]),
+            key_injection: KeyInjection::ALL[rng.gen_range(0..KeyInjection::ALL.len())],
+            hold_key: rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]),
+            chord_key: rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]),
+            human_travel: rng.gen(),
+            travel_speed: rng.gen_range(MIN_TRAVEL_SPEED..=MAX_TRAVEL_SPEED),
+            return_cursor: rng.gen(),
+            jiggle: rng.gen(),
+            jiggle_interval: Duration::from_secs(rng.gen_range(1..=MAX_JIGGLE_INTERVAL.as_secs())),
+            jiggle_back: rng.gen(),
+            cps_up_key: rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]),
+            cps_down_key: rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]),
+            cps_step: rng.gen_range(0.1..=MAX_CPS_STEP),
+            cps_beep: rng.gen(),
+            abort_key: SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())],
+            step_key: SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())],
+        }
+    }
+
+    fn random_rect(rng: &mut StdRng) -> Rect {
+        Rect {
+            x: rng.gen_range(-2000.0..4000.0),
+            y: rng.gen_range(-2000.0..4000.0),
+            width: rng.gen_range(1.0..4000.0),
+            height: rng.gen_range(1.0..4000.0),
+        }
+    }
+
+    fn random_name(rng: &mut StdRng) -> String {
+        const CHARS: &[char] = &['a', 'Z', '0', ' ', '-', '_', '.', '"', '\'', '=', '#', '[', 'é', '漢'];
+        let len = rng.gen_range(1..12);
+        (0..len).map(|_| CHARS[rng.gen_range(0..CHARS.len())]).collect()
+    }
+
+    fn random_file(rng: &mut StdRng) -> ConfigFile {
+        let mut file = ConfigFile::default();
+        for _ in 0..rng.gen_range(0..5) {
+            file.profiles.insert(random_name(rng), random_config(rng));
+        }
+        let names: Vec<String> = file.profiles.keys().cloned().collect();
+        file.active_profile = names[rng.gen_range(0..names.len())].clone();
+        file.backend = BackendKind::ALL[rng.gen_range(0..BackendKind::ALL.len())];
+        file.max_cps = rng.gen_bool(0.5).then(|| rng.gen_range(MIN_CPS..=MAX_CPS_LIMIT));
+        file.telemetry = rng.gen_bool(0.5);
+        file.command_files = rng.gen_bool(0.5);
+        file.verify_clicks = rng.gen_bool(0.5);
+        file.scale_recordings = rng.gen_bool(0.5);
+        file.keep_awake = rng.gen_bool(0.5);
+        file.pause_key = rng.gen_bool(0.5).then(|| SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())]);
+        file.indicator = IndicatorPlace::ALL[rng.gen_range(0..IndicatorPlace::ALL.len())];
+        file.listener_retry = Duration::from_secs(rng.gen_range(MIN_LISTENER_RETRY.as_secs()..=MAX_LISTENER_RETRY.as_secs()));
+        file.sync_dir = rng.gen_bool(0.5).then(|| PathBuf::from(format!("/home/user/Dropbox/clicker {}", rng.gen_range(0..1000))));
+        file.mqtt = rng.gen_bool(0.5).then(|| MqttConfig {
+            topic: format!("home/{}", random_name(rng).replace([' ', '#'], "_")),
+            username: if rng.gen_bool(0.5) { random_name(rng) } else { String::new() },
+            password: if rng.gen_bool(0.5) { random_name(rng) } else { String::new() },
+            ..MqttConfig::new(&format!("{}.local:1883", rng.gen_range(0..1000)))
+        });
+        file.webhook = rng.gen_bool(0.5).then(|| WebhookConfig {
+            url: format!("http://{}.local:{}/hook", rng.gen_range(0..1000), rng.gen_range(1..=u16::MAX)),
+            every: rng.gen_range(0..=MAX_WEBHOOK_EVERY),
+        });
+        file.hooks = Hooks {
+            enabled: rng.gen_bool(0.5),
+            on_start: if rng.gen_bool(0.5) { format!("echo {}", random_name(rng)) } else { String::new() },
+            on_stop: if rng.gen_bool(0.5) { random_name(rng) } else { String::new() },
+        };
+        file.overlay_port = rng.gen_bool(0.5).then(|| rng.gen_range(1..=u16::MAX));
+        file.chat_trigger = rng.gen_bool(0.5).then(|| ChatTrigger {
+            command: format!("!{}", random_name(rng).replace(' ', "_")),
+            max_clicks: rng.gen_range(1..=MAX_CHAT_CLICKS),
+            cooldown: Duration::from_secs(rng.gen_range(0..=MAX_CHAT_COOLDOWN.as_secs())),
+            cps: rng.gen_range(MIN_CPS..=MAX_CHAT_CPS),
+            ..ChatTrigger::new(&format!("chan_{}", rng.gen_range(0..1000)))
+        });
+        file.text_trigger = rng.gen_bool(0.5).then(|| TextTrigger {
+            action: TextAction::ALL[rng.gen_range(0..TextAction::ALL.len())],
+            every: Duration::from_millis(
+                rng.gen_range(MIN_TEXT_TRIGGER_EVERY.as_millis() as u64..=MAX_TEXT_TRIGGER_EVERY.as_millis() as u64),
+            ),
+            ..TextTrigger::new(random_rect(rng), &format!("Text {}", random_name(rng)))
+        });
+        file.typer = rng.gen_bool(0.5).then(|| TextTyper {
+            hotkey: SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())],
+            char_delay: Duration::from_millis(rng.gen_range(0..=MAX_TYPER_CHAR_DELAY.as_millis() as u64)),
+            repeat_delay: Duration::from_millis(rng.gen_range(0..=MAX_TYPER_REPEAT_DELAY.as_millis() as u64)),
+            ..TextTyper::new(&format!("{}\n{}", random_name(rng), random_name(rng)))
+        });
+        file.theme = ThemeMode::ALL[rng.gen_range(0..ThemeMode::ALL.len())];
+        file.sound_theme = format!("theme{}", random_name(rng)).trim().to_string();
+        file.ui_scale = rng.gen_range(MIN_UI_SCALE..=MAX_UI_SCALE);
+        file.font_size = rng.gen_range(MIN_FONT_SIZE..=MAX_FONT_SIZE);
+        file.window = rng.gen_bool(0.5).then(|| random_rect(rng));
+        file.rollover = Rollover { day_start: rng.gen_range(0..24), week_start: Weekday::ALL[rng.gen_range(0..Weekday::ALL.len())] };
+        file.on_lock = LockPolicy::ALL[rng.gen_range(0..LockPolicy::ALL.len())];
+        file.superspeed = SuperspeedMode::ALL[rng.gen_range(0..SuperspeedMode::ALL.len())];
+        file.pin_core = rng.gen_bool(0.5).then(|| rng.gen_range(0..=MAX_CORE));
+        file.on_battery = BatteryPolicy::ALL[rng.gen_range(0..BatteryPolicy::ALL.len())];
+        file.battery_cps = rng.gen_range(MIN_CPS..=MAX_CPS);
+        file.cps_presets = (0..rng.gen_range(0..=MAX_CPS_PRESETS)).map(|_| rng.gen_range(MIN_CPS..=MAX_CPS)).collect();
+        file.typing_pause = rng.gen_bool(0.5).then(|| Duration::from_millis(rng.gen_range(1..=MAX_TYPING_PAUSE.as_millis() as u64)));
+        file.idle_stop = rng.gen_bool(0.5).then(|| Duration::from_secs(rng.gen_range(1..=MAX_IDLE_STOP.as_secs() / 60) * 60));
+        file.blocklist = (0..rng.gen_range(0..3)).map(|_| format!("app{}.exe", random_name(rng))).collect();
+        if rng.gen_bool(0.5) {
+            file.telemetry_endpoint = format!("http://{}.example:8080/usage", random_name(rng));
+        }
+        for _ in 0..rng.gen_range(0..3) {
+            let profile = names[rng.gen_range(0..names.len())].clone();
+            file.bindings.push(AppBinding { app: format!("{}.exe", random_name(rng)), profile, stop_on_blur: rng.gen_bool(0.5) });
+        }
+        for _ in 0..rng.gen_range(0..3) {
+            let kind = TitleMatch::ALL[rng.gen_range(0..TitleMatch::ALL.len())];
+            let title = match kind {
+                TitleMatch::Contains => format!("Title {}", random_name(rng)),
+                TitleMatch::Regex => format!("^{}.*$", regex::escape(&random_name(rng))),
+            };
+            file.title_rules.push(TitleRule::with(&title, kind, TextAction::ALL[rng.gen_range(0..TextAction::ALL.len())]));
+        }
+        for _ in 0..rng.gen_range(0..3) {
+            let key = SUPPORTED_KEYS[rng.gen_range(0..SUPPORTED_KEYS.len())];
+            file.macro_hotkeys.push(MacroHotkey { key, name: format!("m{}", random_name(rng)) });
+        }
+        file
+    }
+
+    #[test]
+    fn config_file_round_trips() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..CASES {
+            let file = random_file(&mut rng);
+            let text = file.to_toml();
+            let (parsed, issues) = ConfigFile::parse(&text);
+            assert!(issues.is_empty(), "issues {:?} for\n{}", issues, text);
+            assert_eq!(parsed, file, "mismatch for\n{}", text);
+            let doc = text.parse::<Document>().unwrap();
+            assert!(doc.iter().all(|(key, _)| FILE_KEYS.contains(&key)), "unlisted key in\n{}", text);
+        }
+    }
+
+    #[test]
+    fn legacy_config_round_trips() {
+        let mut rng = StdRng::seed_from_u64(0x1e6a);
+        for _ in 0..CASES {
+            let config = random_config(&mut rng);
+            // Same keys and value syntax as the TOML tables, one per line.
+            let text: String = config
+                .to_table()
+                .iter()
+                .map(|(key, item)| {
+                    let value = item.as_value().unwrap();
+                    match value.as_str() {
+                        Some(text) => format!("{}={}\n", key, text),
+                        None => format!("{}={}\n", key, value.to_string().trim()),
+                    }
+                })
+                .collect();
+            let (parsed, issues) = Config::parse_legacy(&text);
+            assert!(issues.is_empty(), "issues {:?} for\n{}", issues, text);
+            assert_eq!(parsed, config);
+        }
+    }
+
+    #[test]
+    fn parsed_values_are_always_valid() {
+        // Whatever the input, the loaded config only holds usable values.
+        let mut rng = StdRng::seed_from_u64(0xbad);
+        let fragments = [
+            "target_cps = ", "hotkey = ", "fast_mode = ", "[profiles.", "]", "\"", "=", "\n",
+            "1e9", "-5", "nan", "inf", "true", "F13", "{", "}", "[[", "active_profile = ", "#", "backend = ", "\"enigo\"", "max_cps = ", "overlay_port = ", "65536", "theme = ", "\"dark\"", "sound_theme = ", "ui_scale = ", "3.5", "font_size = ", "33", "window = ", "day_starts_at = ", "24", "week_starts_on = ", "\"sunday\"", "on_lock = ", "\"resume\"", "superspeed = ", "\"balanced\"", "pin_core = ", "1024", "on_battery = ", "\"throttle\"", "battery_cps = ", "cps_presets = ", "[5, 0]", "[]", "idle_stop = ", "1441", "typing_pause = ", "[twitch]", "channel = ", "\"x y\"", "max_clicks = ", "1001", "cps = ", "51", "command_files = ", "verify_clicks = ", "scale_recordings = ", "keep_awake = ", "false", "pause_key = ", "indicator = ", "\"cursor\"", "listener_retry = ", "3601", "sync_dir = ", "[mqtt]", "broker = ", "topic = ", "\"a/#\"", "[webhook]", "url = ", "\"https://x\"", "every = ", "1000001", "[text_trigger]", "action = ", "\"stop\"", "\"Inventory full\"", "[hooks]", "enabled = ", "on_start = ", "on_stop = ", "[typer]", "text = ", "char_delay = ", "10001",
+            "[[title_rules]]", "title = ", "\"(\"", "match = ", "\"regex\"", "[[bindings]]", "app = ", "profile = ", "stop_on_blur = ", "blocklist = ", "[\"bank.exe\", \" \"]", "[[macro_hotkeys]]", "key = ", "macro = ", "region = ", "\"1,2,3,4\"", "0,0,-1,5", "exclusions = ", ";", "sequence = ", "\"5,5;1e400,2\"", "offsets = ", "\"0,0;30,0\"", "sequence_resolution = ", "\"0x600\"", "sequence_wait = ", "\"5,5 3600001\"", "relative_to = ", "\" \"", "workers = ", "0", "16", "press_key = ", "\"W\"", "hold_key = ", "chord_key = ", "turbo_button = ", "\"left\"", "cps_up_key = ", "cps_step = ", "0.0", "step_key = ", "key_injection = ", "\"scancode\"", "travel_speed = ", "50", "sound_rate = ", "101", "vary_pitch = ", "start_delay = ", "60001", "jiggle_interval = ", "3601",
+        ];
+        for _ in 0..CASES {
+            let text: String = (0..rng.gen_range(0..30))
+                .map(|_| fragments[rng.gen_range(0..fragments.len())])
+                .collect();
+            let (file, _) = ConfigFile::parse(&text);
+            assert!(file.profiles.contains_key(&file.active_profile));
+            assert!(file.bindings.iter().all(|b| file.profiles.contains_key(&b.profile)));
+            assert!(file.title_rules.iter().all(TitleRule::is_valid));
+            assert!((MIN_UI_SCALE..=MAX_UI_SCALE).contains(&file.ui_scale));
+            assert!((MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&file.font_size));
+            assert!(file.rollover.day_start < 24);
+            assert!(file.blocklist.iter().all(|app| !app.trim().is_empty()));
+            assert!(!file.sound_theme.is_empty() && file.sound_theme.trim() == file.sound_theme);
+            assert!((MIN_LISTENER_RETRY..=MAX_LISTENER_RETRY).contains(&file.listener_retry));
+            assert!(file.pin_core.is_none_or(|core| core <= MAX_CORE));
+            assert!(file.max_cps.is_none_or(|cps| (MIN_CPS..=MAX_CPS_LIMIT).contains(&cps)));
+            assert!(file.webhook.as_ref().is_none_or(|webhook| WebhookConfig::valid_url(&webhook.url) && webhook.every <= MAX_WEBHOOK_EVERY));
+            assert!(file.chat_trigger.as_ref().is_none_or(|trigger| trigger.max_clicks <= MAX_CHAT_CLICKS && trigger.cps <= MAX_CHAT_CPS));
+            assert!(file.typer.as_ref().is_none_or(|typer| !typer.text.is_empty() && typer.char_delay <= MAX_TYPER_CHAR_DELAY));
+            assert!(file.text_trigger.as_ref().is_none_or(|trigger| !trigger.text.is_empty() && trigger.every >= MIN_TEXT_TRIGGER_EVERY));
+            assert!(file.cps_presets.len() <= MAX_CPS_PRESETS && file.cps_presets.iter().all(|cps| (MIN_CPS..=MAX_CPS).contains(cps)));
+            for config in file.profiles.values() {
+                assert!((MIN_CPS..=MAX_CPS).contains(&config.target_cps));
+                assert!(crate::keys::string_to_rdev_key(&config.hotkey).is_some());
+                assert!((1..=MAX_WORKERS).contains(&config.workers));
+                assert!(config.start_delay <= MAX_START_DELAY);
+                assert!(!config.jiggle_interval.is_zero() && config.jiggle_interval <= MAX_JIGGLE_INTERVAL);
+                assert!((MIN_TRAVEL_SPEED..=MAX_TRAVEL_SPEED).contains(&config.travel_speed));
+                assert!(config.cps_step > 0.0 && config.cps_step <= MAX_CPS_STEP);
+                assert!((1..=MAX_SOUND_RATE).contains(&config.sound_rate));
+                assert!(config.sequence_resolution.is_none_or(|(width, height)| width > 0 && height > 0));
+                assert!(config.sequence_wait.is_none_or(|wait| wait.timeout <= MAX_SEQUENCE_WAIT));
+            }
+            let (config, _) = Config::parse_legacy(&text);
+            assert!((MIN_CPS..=MAX_CPS).contains(&config.target_cps));
+        }
+    }
+
+    #[test]
+    fn shared_profiles_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0x5ba3e);
+        for _ in 0..50 {
+            let config = random_config(&mut rng);
+            for text in [export_profile("pvp", &config), share_code("pvp", &config)] {
+                let shared = import_profile(&text).unwrap();
+                assert!(shared.issues.is_empty(), "issues {:?} for\n{}", shared.issues, text);
+                assert_eq!((shared.name.as_str(), shared.config), ("pvp", config.clone()));
+            }
+        }
+        let code = share_code("x", &Config::default());
+        let wrapped: String = code.chars().enumerate().flat_map(|(i, c)| (i % 40 == 39).then_some('\n').into_iter().chain([c])).collect();
+        assert!(import_profile(&wrapped).is_ok());
+        assert!(import_profile("SSAC1:!!!").is_err());
+        assert!(import_profile("target_cps = 5").is_err());
+        let shared = import_profile(&export_profile("x", &Config::default()).replace("target_cps = 10.0", "target_cps = 1e9")).unwrap();
+        assert_eq!(shared.config.target_cps, MAX_CPS);
+        assert_eq!(shared.issues.len(), 1);
+    }
+
+    #[test]
+    fn legacy_file_problems_name_the_file() {
+        let path = std::env::temp_dir().join(format!("autoclicker-legacy-{}.txt", std::process::id()));
+        fs::write(&path, "target_cps=20\ngarbage\n").unwrap();
+        let loaded = Config::load_legacy(&path);
+        fs::remove_file(&path).unwrap();
+        let (config, issues) = loaded.unwrap();
+        assert_eq!(config.target_cps, 20.0);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].to_string().starts_with(&format!("{} line 2:", path.display())));
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let (_, issues) = Config::parse_legacy("hotkey=Nope\ntarget_cps=50000\nspeed=3\nfast_mode=maybe\ngarbage\n");
+        assert!(matches!(issues[0], ConfigIssue::UnknownHotkey { .. }));
+        assert!(matches!(issues[1], ConfigIssue::CpsOutOfRange { .. }));
+        assert!(matches!(issues[2], ConfigIssue::UnknownKey { .. }));
+        assert!(matches!(issues[3], ConfigIssue::InvalidValue { .. }));
+        assert!(matches!(issues[4], ConfigIssue::Malformed { .. }));
+
+        // A misspelt setting is unknown; a known one with a bad value is invalid.
+        let (_, issues) = ConfigFile::parse("ui_scael = 2\nui_scale = \"big\"\n");
+        assert!(matches!(&issues[0], ConfigIssue::UnknownKey { key, .. } if key == "ui_scael"));
+        assert!(matches!(&issues[1], ConfigIssue::InvalidValue { key, .. } if key == "ui_scale"));
+    }
+
+    #[test]
+    fn bindings_match_process_names() {
+        let binding = AppBinding { app: "Minecraft.exe".to_string(), profile: "pvp".to_string(), stop_on_blur: false };
+        assert!(binding.matches("minecraft.exe"));
+        assert!(binding.matches("minecraft"));
+        assert!(!binding.matches("minecraft-launcher"));
+
+        let (file, issues) = ConfigFile::parse("[[bindings]]\napp = \"javaw.exe\"\nprofile = \"missing\"\n");
+        assert!(file.bindings.is_empty());
+        assert!(issues.iter().any(|issue| matches!(issue, ConfigIssue::UnboundProfile { .. })));
+
+        let (file, issues) = ConfigFile::parse("active_profile = \"pvp\"\n[profiles.pvp]\n[[bindings]]\napp = \"javaw.exe\"\nprofile = \"pvp\"\nstop_on_blur = true\n");
+        assert!(issues.is_empty());
+        assert!(file.bindings[0].stop_on_blur);
+        let (file, issues) = ConfigFile::parse("active_profile = \"pvp\"\n[profiles.pvp]\n[[bindings]]\napp = \"javaw.exe\"\nprofile = \"pvp\"\nstop_on_blur = 1\n");
+        assert!(file.bindings.is_empty());
+        assert!(matches!(issues[..], [ConfigIssue::Malformed { .. }]));
+    }
+
+    #[test]
+    fn title_rules_match_by_text_or_regex() {
+        let (file, issues) = ConfigFile::parse(
+            "[profiles.default]\n[[title_rules]]\ntitle = \"roblox\"\naction = \"start\"\n\
+             [[title_rules]]\ntitle = \"^sign in( to .*)?$\"\nmatch = \"regex\"\naction = \"stop\"\n",
+        );
+        assert!(issues.is_empty(), "{:?}", issues);
+        let [start, stop] = &file.title_rules[..] else { panic!("{:?}", file.title_rules) };
+        assert!(start.matches("Roblox - Adopt Me!"));
+        assert!(!start.matches("Minecraft"));
+        assert!(stop.matches("Sign in to Roblox"));
+        assert!(!stop.matches("Roblox - Sign in"));
+        assert_eq!(stop.action, TextAction::Stop);
+        // An edited rule matches on its new title once compiled.
+        let mut edited = stop.clone();
+        edited.title = "^minecraft$".to_string();
+        edited.compile();
+        assert!(edited.matches("Minecraft") && !edited.matches("Sign in to Roblox"));
+
+        // A regex that doesn't compile is dropped rather than never matching.
+        let (file, issues) = ConfigFile::parse("[profiles.default]\n[[title_rules]]\ntitle = \"(\"\nmatch = \"regex\"\naction = \"stop\"\n");
+        assert!(file.title_rules.is_empty());
+        assert!(matches!(issues[..], [ConfigIssue::Malformed { .. }]));
+    }
+
+    #[test]
+    fn endpoints_are_plain_http_urls() {
+        assert_eq!(parse_endpoint("http://example.org"), Ok(("example.org".to_string(), 80, "/".to_string())));
+        assert_eq!(parse_endpoint("http://10.0.0.2:8080/usage"), Ok(("10.0.0.2".to_string(), 8080, "/usage".to_string())));
+        assert!(parse_endpoint("https://example.org").is_err());
+        assert!(parse_endpoint("http://:80/").is_err());
+        assert!(parse_endpoint("http://host:99999").is_err());
+    }
+}
@@ -0,0 +1,2004 @@
+// Click engine: runs the timing loop on its own thread and drives a backend.
+
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    mpsc::{self, Receiver},
+    Arc, Mutex,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+
+use crate::activity::{Activity, Reporting};
+use crate::affinity::{self, JitterReadout};
+use crate::backend::{DryRunBackend, InputBackend, SelectedBackend};
+use crate::config::{same_app, BackendKind, KeyInjection, SequenceWait, SuperspeedMode, MAX_CPS, MAX_WORKERS, MIN_CPS};
+use crate::geometry::{format_point, scale_point, Point, Rect};
+use crate::guard::{GuardHandles, Guarded};
+use crate::log::Log;
+use crate::macros::PixelSource;
+use crate::receipts::Receipts;
+use crate::screen::{Color, Screen};
+use crate::stats::{Heatmap, Intervals, Latency};
+use crate::telemetry::Telemetry;
+use crate::timer::{self, Timer};
+use crate::travel::{self, Jitter};
+
+// How long the engine waits between checks while stopped.
+const IDLE_POLL: Duration = Duration::from_millis(10);
+// Stretch before a slow round waited out on the precise timer; a 10 ms sleep
+// can take a whole 15.6 ms timer step on Windows.
+const PRECISE_LEAD: Duration = Duration::from_millis(20);
+// How often an engine adds its click positions and intervals to the shared statistics.
+const HEAT_FLUSH: Duration = Duration::from_millis(250);
+// How often the watchdog checks the engine, and how long without progress
+// counts as stuck.
+const WATCHDOG_POLL: Duration = Duration::from_millis(250);
+const STALL_TIMEOUT: Duration = Duration::from_secs(3);
+// Longest wait for a thread to finish on shutdown before leaving it behind.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(1);
+// Superspeed rounds planned at a time.
+const TRAIN_ROUNDS: usize = 64;
+// Rounds a run needs before its spread says anything about jitter.
+const MIN_JITTER_ROUNDS: u64 = 20;
+// A balanced superspeed round taking this many times the fastest one means
+// input is queuing up, and the engine backs off for at most MAX_BACKOFF.
+const CONGESTED: u32 = 4;
+const MAX_BACKOFF: Duration = Duration::from_millis(5);
+// How far past the widest offset the cursor has to be for a spread around it
+// to follow it rather than stay where it was.
+const SPREAD_FOLLOW: f64 = 20.0;
+
+/// Source of time for the engine, so tests can run on virtual time.
+pub trait Clock: Send {
+    /// Time elapsed since the clock was created.
+    fn now(&self) -> Duration;
+    fn sleep(&mut self, duration: Duration);
+    /// Sleeps on the OS high resolution timer, where there is one.
+    fn sleep_precisely(&mut self, duration: Duration) {
+        self.sleep(duration);
+    }
+}
+
+pub struct SystemClock {
+    start: Instant,
+    // Made on the first precise sleep, on the thread that sleeps; None after
+    // it couldn't be.
+    timer: Option<Option<Timer>>,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock { start: Instant::now(), timer: None }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn sleep_precisely(&mut self, duration: Duration) {
+        match self.timer.get_or_insert_with(|| Timer::new().ok()) {
+            Some(timer) => timer.wait(duration),
+            None => thread::sleep(duration),
+        }
+    }
+}
+
+/// Liveness of the main engine thread. The engine beats on every pass of its
+/// loop; the watchdog flags it as stalled when the beats stop.
+#[derive(Default)]
+pub struct Watchdog {
+    beats: AtomicU64,
+    stalled: AtomicBool,
+    restart: AtomicBool,
+    // Engine threads of an older generation exit once they get unstuck.
+    generation: AtomicU64,
+}
+
+impl Watchdog {
+    fn beat(&self) {
+        self.beats.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stalled(&self) -> bool {
+        self.stalled.load(Ordering::SeqCst)
+    }
+
+    /// Asks the watchdog to replace the engine thread with a fresh one.
+    pub fn request_restart(&self) {
+        self.restart.store(true, Ordering::SeqCst);
+    }
+
+    /// Compares the beats with those `seen` last, at `now`. Returns a message
+    /// when the engine stalls or recovers.
+    fn check(&self, seen: &mut (u64, Duration), now: Duration) -> Option<&'static str> {
+        let beats = self.beats.load(Ordering::Relaxed);
+        if beats != seen.0 {
+            *seen = (beats, now);
+            return self.stalled.swap(false, Ordering::SeqCst).then_some("Click engine recovered");
+        }
+        let stuck = now.saturating_sub(seen.1) >= STALL_TIMEOUT;
+        (stuck && !self.stalled.swap(true, Ordering::SeqCst)).then_some("Click engine stopped responding")
+    }
+}
+
+/// Shared state read by the engine threads.
+#[derive(Clone)]
+pub struct EngineHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub fast_mode: Arc<AtomicBool>,
+    pub target_cps: Arc<Mutex<f64>>,
+    pub selected_buttons: Arc<Mutex<Vec<MouseButton>>>,
+    // When set, clicks go to the dry-run backend instead of the real one.
+    pub dry_run: Arc<AtomicBool>,
+    // Total clicks emitted (or simulated) since startup.
+    pub emitted: Arc<AtomicU64>,
+    pub log: Arc<Log>,
+    // Last known cursor position, and the autoclicker's own window; clicking
+    // pauses while the cursor is over the window.
+    pub cursor: Arc<Mutex<Option<Point>>>,
+    pub own_window: Arc<Mutex<Option<Rect>>>,
+    // Test pad inside the window, while it is shown; clicks on it are allowed.
+    pub test_pad: Arc<Mutex<Option<Rect>>>,
+    // Optional active region; clicking pauses while the cursor is outside it.
+    pub region: Arc<Mutex<Option<Rect>>>,
+    // Zones that must never be clicked; clicking pauses inside them.
+    pub exclusions: Arc<Mutex<Vec<Rect>>>,
+    // Applications never clicked while focused, and the focused one; checked
+    // here so no hotkey, remote command or trigger can get around them.
+    pub blocklist: Arc<Mutex<Vec<String>>>,
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    // Points clicked in turn, one per round; empty to click at the cursor.
+    pub sequence: Arc<Mutex<Vec<Point>>>,
+    // Offsets added to the click position in turn, so the same pixel isn't hit every round.
+    pub offsets: Arc<Mutex<Vec<Point>>>,
+    // Screen size the points were made on, this screen's size, and whether
+    // points made on another size are moved to this one in proportion.
+    pub sequence_resolution: Arc<Mutex<Option<(u32, u32)>>>,
+    pub screen_size: Arc<Mutex<Option<(u32, u32)>>>,
+    // Pixel the sequence waits on to change after each point, if any.
+    pub sequence_wait: Arc<Mutex<Option<SequenceWait>>>,
+    pub scale_recordings: Arc<AtomicBool>,
+    // App whose window the points are offsets into, and where that window is.
+    pub relative_to: Arc<Mutex<Option<String>>>,
+    pub window_origin: Arc<Mutex<Option<Point>>>,
+    // Injection threads to run in superspeed mode.
+    pub workers: Arc<AtomicUsize>,
+    pub backend: Arc<Mutex<BackendKind>>,
+    // Key pressed each round instead of the mouse buttons, if set.
+    pub press_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub key_injection: Arc<Mutex<KeyInjection>>,
+    // Key held down by the main engine while clicking is active.
+    pub hold_key: Arc<Mutex<Option<rdev::Key>>>,
+    // Curved cursor travel to sequence points, in pixels per second.
+    pub human_travel: Arc<AtomicBool>,
+    // Move back to where the cursor was after clicking a fixed target.
+    pub return_cursor: Arc<AtomicBool>,
+    // Mouse jiggler mode: nudge the cursor every interval instead of clicking.
+    pub jiggle: Arc<AtomicBool>,
+    pub jiggle_interval: Arc<Mutex<Duration>>,
+    pub jiggle_back: Arc<AtomicBool>,
+    pub travel_speed: Arc<Mutex<f64>>,
+    // Global cap on clicks per second, whatever the mode or target asks for.
+    pub max_cps: Arc<Mutex<Option<f64>>>,
+    // Lower cap set while on battery; the smaller of the two applies.
+    pub power_cap: Arc<Mutex<Option<f64>>>,
+    pub superspeed: Arc<Mutex<SuperspeedMode>>,
+    // Where clicks landed, filled in batches from each engine's own map.
+    pub heatmap: Arc<Mutex<Heatmap>>,
+    // Time between the main engine's rounds, filled in batches like the heatmap.
+    pub intervals: Arc<Mutex<Intervals>>,
+    pub watchdog: Arc<Watchdog>,
+    // Set on exit; the engine threads clean up and return.
+    pub shutdown: Arc<AtomicBool>,
+    pub telemetry: Arc<Telemetry>,
+    // Wait between clicking being turned on and the first round.
+    pub start_delay: Arc<Mutex<Duration>>,
+    // Told about the input sent, so the listener doesn't take it for the user's,
+    // and asked whether the user is typing.
+    pub activity: Arc<Activity>,
+    // Clicking holds while the user typed within this long.
+    pub typing_pause: Arc<Mutex<Option<Duration>>>,
+    // Button presses sent to the system, for click receipt verification.
+    pub receipts: Arc<Receipts>,
+    // Timed from a start hotkey press to the first click sent after it.
+    pub latency: Arc<Mutex<Latency>>,
+    // Core the main engine thread runs on, taking effect when clicking starts,
+    // and the spread of its rounds with and without it.
+    pub pin_core: Arc<Mutex<Option<usize>>>,
+    pub jitter: Arc<Mutex<JitterReadout>>,
+    // Spaces the presses sent under the same caps as macros and chat bursts.
+    pub guard: GuardHandles,
+}
+
+/// One lock per mouse button and one for the pressed key, held for each
+/// down/up pair so concurrent workers never interleave presses.
+#[derive(Default)]
+pub struct ButtonLocks([Mutex<()>; 4]);
+
+impl ButtonLocks {
+    fn get(&self, button: MouseButton) -> &Mutex<()> {
+        match button {
+            MouseButton::Middle => &self.0[1],
+            MouseButton::Right => &self.0[2],
+            _ => &self.0[0],
+        }
+    }
+
+    fn key(&self) -> &Mutex<()> {
+        &self.0[3]
+    }
+}
+
+/// Decides where rounds click and what they send, and keeps the place in
+/// the click sequence.
+struct Planner {
+    handles: EngineHandles,
+    sequence_index: usize,
+    offset_index: usize,
+    // Cursor position a spread at the cursor is laid around.
+    anchor: Option<Point>,
+    // Color of the sequence's wait pixel when the last point was planned,
+    // and when that was; the next point waits for it to change.
+    waiting: Option<(Option<Color>, Instant)>,
+    // Opened the first time the sequence waits on a pixel.
+    pixels: Option<Box<dyn PixelSource + Send>>,
+}
+
+impl Planner {
+    fn new(handles: EngineHandles) -> Self {
+        Planner { handles, sequence_index: 0, offset_index: 0, anchor: None, waiting: None, pixels: None }
+    }
+
+    fn pixel(&mut self, at: Point) -> Option<Color> {
+        self.pixels.get_or_insert_with(|| Box::new(Screen::new())).pixel(at)
+    }
+
+    /// The next offset of the spread, if one is set.
+    fn next_offset(&mut self) -> Option<Point> {
+        let offsets = self.handles.offsets.lock().unwrap();
+        let offset = *offsets.get(self.offset_index % offsets.len().max(1))?;
+        self.offset_index = (self.offset_index + 1) % offsets.len();
+        Some(offset)
+    }
+
+    /// Where a spread at the cursor is laid around: where it was while the
+    /// cursor stays on the spread, which the clicker moves it along, and
+    /// wherever the cursor is once the user takes it further.
+    fn anchor(&mut self, cursor: Point) -> Point {
+        let reach = self.handles.offsets.lock().unwrap().iter().map(|(x, y)| x.hypot(*y)).fold(0.0, f64::max);
+        match self.anchor {
+            Some(anchor) if (cursor.0 - anchor.0).hypot(cursor.1 - anchor.1) <= reach + SPREAD_FOLLOW => anchor,
+            _ => *self.anchor.insert(cursor),
+        }
+    }
+
+    /// Shortest time between this engine's rounds that keeps every running
+    /// engine together under the CPS guard or power cap, if one is set.
+    fn guard_interval(&self, fast: bool) -> Option<Duration> {
+        let caps = [*self.handles.max_cps.lock().unwrap(), *self.handles.power_cap.lock().unwrap()];
+        let limit = caps.into_iter().flatten().reduce(f64::min)?.max(MIN_CPS);
+        let per_round = match *self.handles.press_key.lock().unwrap() {
+            Some(_) => 1,
+            None => self.handles.selected_buttons.lock().unwrap().len().max(1),
+        };
+        let engines = if fast { self.handles.workers.load(Ordering::SeqCst).clamp(1, MAX_WORKERS) } else { 1 };
+        Some(Duration::from_secs_f64((per_round * engines) as f64 / limit))
+    }
+
+    /// The focused application, if it is on the blocklist.
+    fn blocked_app(&self) -> Option<String> {
+        let app = self.handles.focused_app.lock().unwrap().clone()?;
+        self.handles.blocklist.lock().unwrap().iter().any(|blocked| same_app(blocked, &app)).then_some(app)
+    }
+
+    /// Why a click at `point` isn't allowed, if it isn't.
+    fn blocked_at(&self, (x, y): Point) -> Option<&'static str> {
+        let on_pad = self.handles.test_pad.lock().unwrap().is_some_and(|rect| rect.contains(x, y));
+        if !on_pad && self.handles.own_window.lock().unwrap().is_some_and(|rect| rect.contains(x, y)) {
+            Some("over the autoclicker window")
+        } else if self.handles.exclusions.lock().unwrap().iter().any(|rect| rect.contains(x, y)) {
+            Some("in an exclusion zone")
+        } else if self.handles.region.lock().unwrap().is_some_and(|rect| !rect.contains(x, y)) {
+            Some("outside the active region")
+        } else {
+            None
+        }
+    }
+
+    /// Where the next round clicks: the next allowed sequence point, or
+    /// `None` for wherever the cursor is. Fails with the reason to pause.
+    fn next_target(&mut self) -> Result<Option<Point>, String> {
+        if let Some(app) = self.blocked_app() {
+            return Err(format!("{} is on the blocklist", app));
+        }
+        if self.handles.typing_pause.lock().unwrap().is_some_and(|window| self.handles.activity.typing(window)) {
+            return Err("typing".to_string());
+        }
+        // Held apart from `self`, which moves on through the spread below.
+        let sequence = self.handles.sequence.clone();
+        let sequence = sequence.lock().unwrap();
+        if sequence.is_empty() {
+            drop(sequence);
+            let cursor = *self.handles.cursor.lock().unwrap();
+            if let Some(cursor) = cursor.filter(|_| !self.handles.offsets.lock().unwrap().is_empty()) {
+                let anchor = self.anchor(cursor);
+                let (dx, dy) = self.next_offset().unwrap_or_default();
+                let point = (anchor.0 + dx, anchor.1 + dy);
+                return match self.blocked_at(point) {
+                    Some(reason) => Err(format!("spread point is {}", reason)),
+                    None => Ok(Some(point)),
+                };
+            }
+            return match cursor {
+                Some(point) => match self.blocked_at(point) {
+                    Some(reason) => Err(format!("cursor is {}", reason)),
+                    None => Ok(None),
+                },
+                // Without a known position the exclusion zones can't be honoured.
+                None if !self.handles.exclusions.lock().unwrap().is_empty() => {
+                    Err("cursor position unknown, exclusion zones are set".to_string())
+                }
+                None => Ok(None),
+            };
+        }
+        let (dx, dy) = match self.handles.relative_to.lock().unwrap().as_deref() {
+            Some(app) => match *self.handles.window_origin.lock().unwrap() {
+                Some(origin) => origin,
+                None => return Err(format!("no {} window", app)),
+            },
+            None => (0.0, 0.0),
+        };
+        // Window offsets stay as they are; only screen positions are scaled.
+        let scale = match (*self.handles.sequence_resolution.lock().unwrap(), *self.handles.screen_size.lock().unwrap()) {
+            (Some(from), Some(to)) if from != to && self.handles.scale_recordings.load(Ordering::SeqCst) => {
+                self.handles.relative_to.lock().unwrap().is_none().then_some((from, to))
+            }
+            _ => None,
+        };
+        let wait = *self.handles.sequence_wait.lock().unwrap();
+        if let (Some(wait), Some((before, since))) = (wait, self.waiting) {
+            // An unreadable pixel waits out the timeout, like the macro step.
+            let changed = before.is_some() && self.pixel(wait.at) != before;
+            if !changed && since.elapsed() < wait.timeout {
+                return Err(format!("waiting for {} to change", format_point(wait.at)));
+            }
+        }
+        self.waiting = None;
+        let (sx, sy) = self.next_offset().unwrap_or_default();
+        // Blocked points are skipped rather than pausing the whole sequence.
+        for _ in 0..sequence.len() {
+            let point = sequence[self.sequence_index % sequence.len()];
+            let point = match scale {
+                Some((from, to)) => scale_point(point, from, to),
+                None => (point.0 + dx, point.1 + dy),
+            };
+            let point = (point.0 + sx, point.1 + sy);
+            self.sequence_index = (self.sequence_index + 1) % sequence.len();
+            if self.blocked_at(point).is_none() {
+                if let Some(wait) = wait {
+                    self.waiting = Some((self.pixel(wait.at), Instant::now()));
+                }
+                return Ok(Some(point));
+            }
+        }
+        Err("every sequence point is blocked".to_string())
+    }
+
+    /// What the next round sends.
+    fn plan(&self) -> Plan {
+        let mut buttons = self.handles.selected_buttons.lock().unwrap().clone();
+        if buttons.is_empty() {
+            buttons.push(MouseButton::Left);
+        }
+        let key = self.handles.press_key.lock().unwrap().map(|key| (key, *self.handles.key_injection.lock().unwrap()));
+        Plan {
+            buttons,
+            key,
+            dry_run: self.handles.dry_run.load(Ordering::SeqCst),
+            superspeed: *self.handles.superspeed.lock().unwrap(),
+        }
+    }
+
+    /// The next `TRAIN_ROUNDS` superspeed rounds, fewer if clicking has to
+    /// pause part way.
+    fn train(&mut self) -> Train {
+        let mut targets = VecDeque::with_capacity(TRAIN_ROUNDS);
+        while targets.len() < TRAIN_ROUNDS {
+            match self.next_target() {
+                Ok(target) => targets.push_back(target),
+                Err(reason) if targets.is_empty() => {
+                    return Train { targets: Err(reason), plan: Arc::new(self.plan()), guard: None };
+                }
+                Err(_) => break,
+            }
+        }
+        Train { targets: Ok(targets), plan: Arc::new(self.plan()), guard: self.guard_interval(true) }
+    }
+}
+
+// Where a round clicks, the CPS guard's spacing and what it sends.
+type Round = (Option<Point>, Option<Duration>, Arc<Plan>);
+
+/// What every round of a train sends.
+#[derive(PartialEq)]
+struct Plan {
+    buttons: Vec<MouseButton>,
+    key: Option<(rdev::Key, KeyInjection)>,
+    dry_run: bool,
+    superspeed: SuperspeedMode,
+}
+
+/// How long balanced superspeed rounds take to send, smoothed, against the
+/// fastest one since clicking started.
+#[derive(Default)]
+struct Backpressure {
+    fastest: Option<Duration>,
+    smoothed: Duration,
+}
+
+impl Backpressure {
+    /// Adds a round that took `took` to send; returns how long to wait
+    /// before the next, since sending more into a queue only makes it longer.
+    fn round(&mut self, took: Duration) -> Duration {
+        let fastest = self.fastest.map_or(took, |fastest| fastest.min(took));
+        self.smoothed = match self.fastest {
+            Some(_) => (self.smoothed * 7 + took) / 8,
+            None => took,
+        };
+        self.fastest = Some(fastest);
+        match self.smoothed > fastest * CONGESTED {
+            true => (self.smoothed - fastest).min(MAX_BACKOFF),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+/// Superspeed rounds planned ahead on a producer thread, so the engine
+/// sending them doesn't lock the settings and check the click position for
+/// every one.
+pub struct Train {
+    // Where each round clicks, or why clicking is paused.
+    targets: Result<VecDeque<Option<Point>>, String>,
+    plan: Arc<Plan>,
+    guard: Option<Duration>,
+}
+
+pub struct Engine<C: Clock, B: InputBackend> {
+    handles: EngineHandles,
+    clock: C,
+    backend: B,
+    simulated: DryRunBackend,
+    // When the next consistent-rate round is due; `None` until the first round.
+    next_round: Option<Duration>,
+    // Why clicking is currently paused because of the click position, if it is.
+    paused: Option<String>,
+    planner: Planner,
+    // Superspeed rounds from a producer thread, if one runs, and the train being sent.
+    trains: Option<Receiver<Train>>,
+    train: Option<Train>,
+    // 0 for the main engine; helpers only run in superspeed mode.
+    worker: usize,
+    locks: Arc<ButtonLocks>,
+    // Where the cursor was last moved, used when the listener hasn't seen it.
+    last_target: Option<Point>,
+    jitter: Jitter,
+    // Key currently held down, and whether it went to the dry-run backend.
+    held_key: Option<(rdev::Key, bool)>,
+    // Click positions not yet added to the shared heatmap, and when it was last updated.
+    heat: Heatmap,
+    heat_flushed: Duration,
+    // When the main engine's last round went out while clicking ran without a break.
+    last_round: Option<Duration>,
+    intervals: Intervals,
+    // The same for the current run alone, and the core this thread is pinned to.
+    run_intervals: Intervals,
+    pinned: Option<usize>,
+    // Whether clicking was active on the last tick, to count each start once.
+    running: bool,
+    // Direction of the next jiggle when it doesn't move back.
+    jiggle_right: bool,
+    backpressure: Backpressure,
+}
+
+impl<C: Clock, B: InputBackend> Engine<C, B> {
+    /// Engine for worker `worker` (0 for the main one), sharing `locks` with the others.
+    pub fn new(handles: EngineHandles, clock: C, backend: B, worker: usize, locks: Arc<ButtonLocks>) -> Self {
+        let simulated = DryRunBackend::new(handles.log.clone());
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        let planner = Planner::new(handles.clone());
+        Engine {
+            handles,
+            clock,
+            backend,
+            simulated,
+            next_round: None,
+            paused: None,
+            planner,
+            trains: None,
+            train: None,
+            worker,
+            locks,
+            last_target: None,
+            jitter: Jitter::new(seed ^ worker as u64),
+            held_key: None,
+            heat: Heatmap::default(),
+            heat_flushed: Duration::ZERO,
+            last_round: None,
+            intervals: Intervals::default(),
+            run_intervals: Intervals::default(),
+            pinned: None,
+            running: false,
+            jiggle_right: true,
+            backpressure: Backpressure::default(),
+        }
+    }
+
+    /// Runs one iteration of the loop: a click round while clicking is
+    /// active, otherwise a short idle wait. Returns whether a round was emitted.
+    pub fn tick(&mut self) -> bool {
+        if self.worker == 0 {
+            self.handles.watchdog.beat();
+            self.update_held_key();
+        }
+        let jiggle = self.handles.jiggle.load(Ordering::SeqCst);
+        let helper_idle = self.worker > 0
+            && (jiggle
+                || !self.handles.fast_mode.load(Ordering::SeqCst)
+                || self.worker >= self.handles.workers.load(Ordering::SeqCst));
+        if helper_idle || !self.handles.clicking.load(Ordering::SeqCst) {
+            if self.running && self.worker == 0 {
+                self.record_jitter();
+            }
+            self.flush_stats();
+            self.simulated.flush();
+            self.next_round = None;
+            self.last_round = None;
+            self.running = false;
+            self.train = None;
+            // Trains planned before clicking stopped would send old positions on restart.
+            if let Some(trains) = &self.trains {
+                while trains.try_recv().is_ok() {}
+            }
+            self.backpressure = Backpressure::default();
+            self.clock.sleep(IDLE_POLL);
+            return false;
+        }
+        if !self.running {
+            self.running = true;
+            if self.worker == 0 {
+                self.record_usage();
+                self.pin();
+            }
+            let delay = *self.handles.start_delay.lock().unwrap();
+            if !delay.is_zero() {
+                self.handles.latency.lock().unwrap().cancel();
+                // Stopping during the delay cancels the start.
+                let deadline = self.clock.now() + delay;
+                self.sleep_until(deadline);
+                return false;
+            }
+        }
+        if jiggle {
+            return self.jiggle();
+        }
+
+        let fast = self.handles.fast_mode.load(Ordering::SeqCst);
+        let planned = if fast { self.next_planned() } else { None };
+        let round = match planned {
+            Some(round) => round,
+            None => self
+                .planner
+                .next_target()
+                .map(|target| (target, self.planner.guard_interval(fast), Arc::new(self.planner.plan()))),
+        };
+        let (target, guard, plan) = match round {
+            Ok(round) => {
+                self.set_paused(None);
+                round
+            }
+            Err(reason) => {
+                // The schedule restarts once clicking is allowed again.
+                self.set_paused(Some(reason));
+                self.handles.latency.lock().unwrap().cancel();
+                self.next_round = None;
+                self.last_round = None;
+                self.clock.sleep(IDLE_POLL);
+                return false;
+            }
+        };
+
+        if fast && guard.is_none() {
+            self.next_round = None;
+            let sent = self.clock.now();
+            self.click_round(target, &plan);
+            match plan.superspeed {
+                SuperspeedMode::MaxPerformance => std::hint::spin_loop(),
+                SuperspeedMode::Balanced => match self.backpressure.round(self.clock.now() - sent) {
+                    wait if wait.is_zero() => thread::yield_now(),
+                    wait => {
+                        let deadline = self.clock.now() + wait;
+                        self.sleep_until(deadline);
+                    }
+                },
+            }
+            return true;
+        }
+
+        // Superspeed has no rate of its own; only the guard spaces its rounds.
+        let interval = match fast {
+            true => Duration::ZERO,
+            false => Duration::from_secs_f64(1.0 / self.handles.target_cps.lock().unwrap().clamp(MIN_CPS, MAX_CPS)),
+        };
+        let interval = interval.max(guard.unwrap_or_default());
+        let start = self.next_round.unwrap_or_else(|| self.clock.now());
+        self.click_round(target, &plan);
+        // Schedule from the deadline rather than from "now" so the rate doesn't
+        // drift, but don't try to catch up after falling a whole interval behind.
+        let mut next = start + interval;
+        let now = self.clock.now();
+        if now >= next + interval {
+            next = now + interval;
+        }
+        self.next_round = Some(next);
+        self.wait_until(next, timer::is_precise(interval));
+        true
+    }
+
+    /// Nudges the cursor by a pixel, then waits out the jiggle interval.
+    fn jiggle(&mut self) -> bool {
+        let Some((x, y)) = *self.handles.cursor.lock().unwrap() else {
+            self.set_paused(Some("cursor position unknown, move the mouse once".to_string()));
+            self.next_round = None;
+            self.clock.sleep(IDLE_POLL);
+            return false;
+        };
+        self.set_paused(None);
+        let start = self.next_round.unwrap_or_else(|| self.clock.now());
+        let back = self.handles.jiggle_back.load(Ordering::SeqCst);
+        let dx = if back || self.jiggle_right { 1.0 } else { -1.0 };
+        if !back {
+            self.jiggle_right = !self.jiggle_right;
+        }
+        let dry_run = self.handles.dry_run.load(Ordering::SeqCst);
+        let mut moves = vec![(x + dx, y)];
+        if back {
+            moves.push((x, y));
+        }
+        for point in moves {
+            if dry_run {
+                self.simulated.move_to(point);
+            } else {
+                self.backend.move_to(point);
+            }
+        }
+        let next = start + *self.handles.jiggle_interval.lock().unwrap();
+        self.next_round = Some(next.max(self.clock.now()));
+        self.sleep_until(next);
+        true
+    }
+
+    /// Presses the hold key when clicking starts and releases it when
+    /// clicking stops or the key changes.
+    fn update_held_key(&mut self) {
+        let wanted = match self.handles.clicking.load(Ordering::SeqCst) {
+            true => self.handles.hold_key.lock().unwrap().filter(|_| self.planner.blocked_app().is_none()),
+            false => None,
+        };
+        self.set_held_key(wanted);
+    }
+
+    fn set_held_key(&mut self, wanted: Option<rdev::Key>) {
+        if wanted == self.held_key.map(|(key, _)| key) {
+            return;
+        }
+        let injection = *self.handles.key_injection.lock().unwrap();
+        if let Some((key, dry_run)) = self.held_key.take() {
+            let backend: &mut dyn InputBackend = if dry_run { &mut self.simulated } else { &mut self.backend };
+            backend.key_up(key, injection);
+        }
+        if let Some(key) = wanted {
+            let dry_run = self.handles.dry_run.load(Ordering::SeqCst);
+            let backend: &mut dyn InputBackend = if dry_run { &mut self.simulated } else { &mut self.backend };
+            backend.key_down(key, injection);
+            self.held_key = Some((key, dry_run));
+        }
+    }
+
+    /// Sleeps until `deadline`, waking early if clicking is stopped.
+    fn sleep_until(&mut self, deadline: Duration) {
+        self.wait_until(deadline, false);
+    }
+
+    /// Like sleep_until, but a `precise` deadline has its last stretch waited
+    /// out on the OS high resolution timer.
+    fn wait_until(&mut self, deadline: Duration, precise: bool) {
+        loop {
+            let now = self.clock.now();
+            if now >= deadline || !self.handles.clicking.load(Ordering::SeqCst) {
+                return;
+            }
+            if self.worker == 0 {
+                self.handles.watchdog.beat();
+            }
+            if precise && deadline - now <= PRECISE_LEAD {
+                self.clock.sleep_precisely(deadline - now);
+                return;
+            }
+            self.clock.sleep((deadline - now).min(IDLE_POLL));
+        }
+    }
+
+    /// Takes trains from a producer thread instead of planning each
+    /// superspeed round here.
+    pub fn with_trains(mut self, trains: Receiver<Train>) -> Self {
+        self.trains = Some(trains);
+        self
+    }
+
+    /// The next round of the current train, waiting briefly for a new train
+    /// when it runs out. `None` without a producer or when none came.
+    fn next_planned(&mut self) -> Option<Result<Round, String>> {
+        let trains = self.trains.as_ref()?;
+        loop {
+            if let Some(train) = &mut self.train {
+                match &mut train.targets {
+                    Ok(targets) => {
+                        if let Some(target) = targets.pop_front() {
+                            return Some(Ok((target, train.guard, train.plan.clone())));
+                        }
+                    }
+                    Err(reason) => {
+                        let reason = std::mem::take(reason);
+                        self.train = None;
+                        return Some(Err(reason));
+                    }
+                }
+            }
+            // A train waits while the last one is sent, which the guard can
+            // make take seconds. Its targets go on from the last train's, so
+            // it is sent whatever its age, on the buttons and key set now.
+            let mut train = trains.recv_timeout(IDLE_POLL).ok()?;
+            let plan = self.planner.plan();
+            if *train.plan != plan {
+                train.plan = Arc::new(plan);
+            }
+            self.train = Some(train);
+        }
+    }
+
+    /// Records why clicking is paused, logging when that changes.
+    fn set_paused(&mut self, reason: Option<String>) {
+        if reason == self.paused {
+            return;
+        }
+        match &reason {
+            Some(reason) => self.handles.log.push(format!("Paused: {}", reason)),
+            None => self.handles.log.push("Resumed"),
+        }
+        self.paused = reason;
+    }
+
+    /// Moves the cursor to `to`, along a curved path if human travel is on.
+    /// Returns false if clicking was stopped on the way.
+    fn move_cursor(&mut self, to: Point, dry_run: bool) -> bool {
+        let from = self.handles.cursor.lock().unwrap().or(self.last_target);
+        self.last_target = Some(to);
+        let path = match from {
+            Some(from) if self.handles.human_travel.load(Ordering::SeqCst) => {
+                let speed = *self.handles.travel_speed.lock().unwrap();
+                travel::path(from, to, speed, &mut self.jitter)
+            }
+            _ => vec![to],
+        };
+        for (n, point) in path.iter().enumerate() {
+            if n > 0 {
+                if self.worker == 0 {
+                    self.handles.watchdog.beat();
+                }
+                self.clock.sleep(travel::STEP);
+                if !self.handles.clicking.load(Ordering::SeqCst) {
+                    return false;
+                }
+            }
+            // A dry run keeps the timing but only logs where the cursor ends up.
+            if !dry_run {
+                self.backend.move_to(*point);
+            } else if n + 1 == path.len() {
+                self.simulated.move_to(*point);
+            }
+        }
+        true
+    }
+
+    fn click_round(&mut self, target: Option<Point>, plan: &Plan) {
+        let dry_run = plan.dry_run;
+        let Some(point) = target else {
+            self.emit_round(None, plan);
+            return;
+        };
+        let home = *self.handles.cursor.lock().unwrap();
+        if !self.move_cursor(point, dry_run) {
+            return;
+        }
+        self.emit_round(target, plan);
+        // Straight back, so the user's cursor is only away for the click.
+        if let Some(home) = home.filter(|_| self.handles.return_cursor.load(Ordering::SeqCst)) {
+            if dry_run {
+                self.simulated.move_to(home);
+            } else {
+                self.backend.move_to(home);
+            }
+        }
+    }
+
+    fn emit_round(&mut self, target: Option<Point>, plan: &Plan) {
+        let dry_run = plan.dry_run;
+        let backend: &mut dyn InputBackend = if dry_run {
+            &mut self.simulated
+        } else {
+            &mut self.backend
+        };
+        let buttons = &plan.buttons;
+        if self.worker == 0 {
+            let now = self.clock.now();
+            if let Some(last) = self.last_round.replace(now) {
+                self.intervals.add(now - last);
+                self.run_intervals.add(now - last);
+            }
+        }
+        if let Some((key, injection)) = plan.key {
+            let _pair = self.locks.key().lock().unwrap();
+            backend.press(key, injection);
+            self.handles.emitted.fetch_add(1, Ordering::Relaxed);
+            self.time_latency(dry_run);
+            return;
+        }
+        for btn in buttons {
+            let _pair = self.locks.get(*btn).lock().unwrap();
+            backend.click(*btn);
+        }
+        self.time_latency(dry_run);
+        self.handles.emitted.fetch_add(buttons.len() as u64, Ordering::Relaxed);
+        if !dry_run {
+            self.handles.receipts.sent(buttons.len() as u64);
+        }
+        if let Some(point) = target.or_else(|| *self.handles.cursor.lock().unwrap()) {
+            self.heat.add(point, buttons.len() as u64);
+        }
+        if self.clock.now() >= self.heat_flushed + HEAT_FLUSH {
+            self.flush_stats();
+        }
+    }
+
+    /// Times a waiting hotkey press against the click just sent; simulated clicks don't count.
+    fn time_latency(&self, dry_run: bool) {
+        let mut latency = self.handles.latency.lock().unwrap();
+        match dry_run {
+            true => latency.cancel(),
+            false => latency.clicked(*self.handles.backend.lock().unwrap(), Instant::now()),
+        }
+    }
+
+    /// Moves this thread to the chosen core, or lets it run anywhere again.
+    fn pin(&mut self) {
+        let wanted = *self.handles.pin_core.lock().unwrap();
+        if wanted == self.pinned {
+            return;
+        }
+        match affinity::pin(wanted) {
+            Ok(()) => {
+                self.pinned = wanted;
+                match wanted {
+                    Some(core) => self.handles.log.push(format!("Click engine pinned to core {}", core)),
+                    None => self.handles.log.push("Click engine unpinned"),
+                }
+            }
+            Err(e) => self.handles.log.push(format!("Couldn't pin the click engine to core {}: {}", wanted.unwrap_or_default(), e)),
+        }
+    }
+
+    /// Hands over the spread of the run that just ended, by where it ran.
+    fn record_jitter(&mut self) {
+        if self.run_intervals.count() >= MIN_JITTER_ROUNDS {
+            if let Some((_, deviation)) = self.run_intervals.mean_and_deviation() {
+                self.handles.jitter.lock().unwrap().record(self.pinned, deviation);
+            }
+        }
+        self.run_intervals.clear();
+    }
+
+    /// Counts the features a run of clicking uses, for opt-in usage metrics.
+    fn record_usage(&self) {
+        let telemetry = &self.handles.telemetry;
+        let fast = self.handles.fast_mode.load(Ordering::SeqCst);
+        telemetry.record(if fast { "superspeed" } else { "consistent_rate" });
+        if fast && self.handles.workers.load(Ordering::SeqCst) > 1 {
+            telemetry.record("workers");
+        }
+        if !self.handles.sequence.lock().unwrap().is_empty() {
+            telemetry.record("click_sequence");
+        }
+        if self.handles.region.lock().unwrap().is_some() {
+            telemetry.record("active_region");
+        }
+        if self.handles.press_key.lock().unwrap().is_some() {
+            telemetry.record("key_press");
+        }
+        if self.handles.hold_key.lock().unwrap().is_some() {
+            telemetry.record("hold_key");
+        }
+        if self.handles.human_travel.load(Ordering::SeqCst) {
+            telemetry.record("human_travel");
+        }
+        if self.handles.max_cps.lock().unwrap().is_some() {
+            telemetry.record("cps_guard");
+        }
+    }
+
+    /// Releases the hold key and hands over the last click positions, before
+    /// the engine thread exits.
+    pub fn finish(&mut self) {
+        self.set_held_key(None);
+        self.flush_stats();
+    }
+
+    /// Adds the click positions and intervals gathered so far to the shared statistics.
+    fn flush_stats(&mut self) {
+        if !self.heat.is_empty() {
+            self.handles.heatmap.lock().unwrap().absorb(&mut self.heat);
+        }
+        if !self.intervals.is_empty() {
+            self.handles.intervals.lock().unwrap().absorb(&mut self.intervals);
+        }
+        self.heat_flushed = self.clock.now();
+    }
+}
+
+/// Waits up to `timeout` for `thread` to finish. A thread that is stuck is
+/// left running rather than holding up the caller.
+pub fn join_within(thread: JoinHandle<()>, timeout: Duration) {
+    let start = Instant::now();
+    while !thread.is_finished() {
+        if start.elapsed() >= timeout {
+            return;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let _ = thread.join();
+}
+
+/// Starts the autoclicker thread and its watchdog. `on_click` runs after
+/// every click round. Superspeed helper threads are started as the worker
+/// count asks for them. The returned thread ends once `shutdown` is set and
+/// the engine threads have cleaned up.
+pub fn spawn(handles: EngineHandles, on_click: impl Fn() + Send + Sync + 'static) -> JoinHandle<()> {
+    let on_click = Arc::new(on_click);
+    let mut engine = start_engine(handles.clone(), on_click.clone());
+    thread::spawn(move || {
+        let watchdog = handles.watchdog.clone();
+        let start = Instant::now();
+        let mut seen = (0, Duration::ZERO);
+        loop {
+            thread::sleep(WATCHDOG_POLL);
+            if handles.shutdown.load(Ordering::SeqCst) {
+                if !watchdog.stalled() {
+                    join_within(engine, JOIN_TIMEOUT);
+                }
+                return;
+            }
+            // A stuck thread can't be stopped, only abandoned: it exits on
+            // its own if the blocking call ever returns.
+            if watchdog.restart.swap(false, Ordering::SeqCst) {
+                watchdog.generation.fetch_add(1, Ordering::SeqCst);
+                watchdog.stalled.store(false, Ordering::SeqCst);
+                handles.log.push("Restarting click engine");
+                engine = start_engine(handles.clone(), on_click.clone());
+                seen = (watchdog.beats.load(Ordering::Relaxed), start.elapsed());
+                continue;
+            }
+            if let Some(message) = watchdog.check(&mut seen, start.elapsed()) {
+                handles.log.push(message);
+            }
+        }
+    })
+}
+
+/// Plans superspeed trains for worker `worker` while it sends them, until
+/// `running` turns false or the engine goes away.
+fn spawn_producer(handles: EngineHandles, worker: usize, running: impl Fn(&EngineHandles) -> bool + Send + 'static) -> Receiver<Train> {
+    // One train waiting while the engine sends another.
+    let (sender, trains) = mpsc::sync_channel(1);
+    thread::spawn(move || {
+        let mut planner = Planner::new(handles.clone());
+        while running(&handles) {
+            let sending = handles.clicking.load(Ordering::SeqCst)
+                && handles.fast_mode.load(Ordering::SeqCst)
+                && !handles.jiggle.load(Ordering::SeqCst)
+                && worker < handles.workers.load(Ordering::SeqCst).clamp(1, MAX_WORKERS);
+            if !sending {
+                thread::sleep(IDLE_POLL);
+            } else if sender.send(planner.train()).is_err() {
+                return;
+            }
+        }
+    });
+    trains
+}
+
+fn start_engine(handles: EngineHandles, on_click: Arc<impl Fn() + Send + Sync + 'static>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let generation = handles.watchdog.generation.load(Ordering::SeqCst);
+        let running = move |handles: &EngineHandles| {
+            handles.watchdog.generation.load(Ordering::SeqCst) == generation && !handles.shutdown.load(Ordering::SeqCst)
+        };
+        let locks = Arc::new(ButtonLocks::default());
+        let mut helpers = Vec::new();
+        let backend = SelectedBackend::new(handles.backend.clone(), handles.log.clone());
+        let backend = Reporting { inner: backend, activity: handles.activity.clone() };
+        let backend = Guarded::new(backend, handles.guard.clone(), handles.clicking.clone());
+        let trains = spawn_producer(handles.clone(), 0, running);
+        let mut engine = Engine::new(handles.clone(), SystemClock::new(), backend, 0, locks.clone()).with_trains(trains);
+        while running(&handles) {
+            let wanted = handles.workers.load(Ordering::SeqCst).clamp(1, MAX_WORKERS);
+            for worker in helpers.len() + 1..wanted {
+                let (handles, locks) = (handles.clone(), locks.clone());
+                helpers.push(thread::spawn(move || {
+                    let backend = SelectedBackend::new(handles.backend.clone(), handles.log.clone());
+                    let backend = Reporting { inner: backend, activity: handles.activity.clone() };
+                    let backend = Guarded::new(backend, handles.guard.clone(), handles.clicking.clone());
+                    let trains = spawn_producer(handles.clone(), worker, running);
+                    let mut engine = Engine::new(handles.clone(), SystemClock::new(), backend, worker, locks).with_trains(trains);
+                    while running(&handles) {
+                        engine.tick();
+                    }
+                    engine.finish();
+                }));
+            }
+            if engine.tick() {
+                on_click();
+            }
+        }
+        engine.finish();
+        for helper in helpers {
+            join_within(helper, JOIN_TIMEOUT);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guard::Pacer;
+
+    /// Virtual clock: sleeping advances time instantly.
+    #[derive(Clone, Default)]
+    struct VirtualClock {
+        nanos: Arc<AtomicU64>,
+        precise_sleeps: Arc<AtomicU64>,
+    }
+
+    impl VirtualClock {
+        fn advance(&self, duration: Duration) {
+            self.nanos.fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for VirtualClock {
+        fn now(&self) -> Duration {
+            Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.advance(duration);
+        }
+
+        fn sleep_precisely(&mut self, duration: Duration) {
+            self.precise_sleeps.fetch_add(1, Ordering::SeqCst);
+            self.advance(duration);
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Action {
+        Down(MouseButton),
+        Up(MouseButton),
+        Move(Point),
+        KeyDown(rdev::Key, KeyInjection),
+        KeyUp(rdev::Key),
+    }
+
+    /// Records every event with the virtual time it was emitted at.
+    struct MockBackend {
+        clock: VirtualClock,
+        events: Arc<Mutex<Vec<(Duration, Action)>>>,
+    }
+
+    impl InputBackend for MockBackend {
+        fn button_down(&mut self, button: MouseButton) {
+            self.events.lock().unwrap().push((self.clock.now(), Action::Down(button)));
+        }
+
+        fn button_up(&mut self, button: MouseButton) {
+            self.events.lock().unwrap().push((self.clock.now(), Action::Up(button)));
+        }
+
+        fn move_to(&mut self, point: Point) {
+            self.events.lock().unwrap().push((self.clock.now(), Action::Move(point)));
+        }
+
+        fn key_down(&mut self, key: rdev::Key, injection: KeyInjection) {
+            self.events.lock().unwrap().push((self.clock.now(), Action::KeyDown(key, injection)));
+        }
+
+        fn key_up(&mut self, key: rdev::Key, _injection: KeyInjection) {
+            self.events.lock().unwrap().push((self.clock.now(), Action::KeyUp(key)));
+        }
+    }
+
+    struct Harness {
+        engine: Engine<VirtualClock, MockBackend>,
+        clock: VirtualClock,
+        events: Arc<Mutex<Vec<(Duration, Action)>>>,
+        clicking: Arc<AtomicBool>,
+        fast_mode: Arc<AtomicBool>,
+        target_cps: Arc<Mutex<f64>>,
+        buttons: Arc<Mutex<Vec<MouseButton>>>,
+        dry_run: Arc<AtomicBool>,
+        emitted: Arc<AtomicU64>,
+        cursor: Arc<Mutex<Option<Point>>>,
+        own_window: Arc<Mutex<Option<Rect>>>,
+        test_pad: Arc<Mutex<Option<Rect>>>,
+        region: Arc<Mutex<Option<Rect>>>,
+        exclusions: Arc<Mutex<Vec<Rect>>>,
+        sequence: Arc<Mutex<Vec<Point>>>,
+        handles: EngineHandles,
+    }
+
+    impl Harness {
+        fn new(fast_mode: bool, cps: f64) -> Self {
+            let clock = VirtualClock::default();
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let clicking = Arc::new(AtomicBool::new(true));
+            let fast_mode = Arc::new(AtomicBool::new(fast_mode));
+            let target_cps = Arc::new(Mutex::new(cps));
+            let buttons = Arc::new(Mutex::new(vec![MouseButton::Left]));
+            let dry_run = Arc::new(AtomicBool::new(false));
+            let emitted = Arc::new(AtomicU64::new(0));
+            let cursor = Arc::new(Mutex::new(None));
+            let own_window = Arc::new(Mutex::new(None));
+            let test_pad = Arc::new(Mutex::new(None));
+            let region = Arc::new(Mutex::new(None));
+            let exclusions = Arc::new(Mutex::new(Vec::new()));
+            let sequence = Arc::new(Mutex::new(Vec::new()));
+            let handles = EngineHandles {
+                clicking: clicking.clone(),
+                fast_mode: fast_mode.clone(),
+                target_cps: target_cps.clone(),
+                selected_buttons: buttons.clone(),
+                dry_run: dry_run.clone(),
+                emitted: emitted.clone(),
+                log: Arc::new(Log::new()),
+                cursor: cursor.clone(),
+                own_window: own_window.clone(),
+                test_pad: test_pad.clone(),
+                region: region.clone(),
+                exclusions: exclusions.clone(),
+                blocklist: Arc::new(Mutex::new(Vec::new())),
+                focused_app: Arc::new(Mutex::new(None)),
+                sequence: sequence.clone(),
+                offsets: Arc::new(Mutex::new(Vec::new())),
+                sequence_resolution: Arc::new(Mutex::new(None)),
+                screen_size: Arc::new(Mutex::new(None)),
+                sequence_wait: Arc::new(Mutex::new(None)),
+                scale_recordings: Arc::new(AtomicBool::new(false)),
+                relative_to: Arc::new(Mutex::new(None)),
+                window_origin: Arc::new(Mutex::new(None)),
+                workers: Arc::new(AtomicUsize::new(1)),
+                backend: Arc::new(Mutex::new(BackendKind::Enigo)),
+                press_key: Arc::new(Mutex::new(None)),
+                key_injection: Arc::new(Mutex::new(KeyInjection::VirtualKey)),
+                hold_key: Arc::new(Mutex::new(None)),
+                human_travel: Arc::new(AtomicBool::new(false)),
+                return_cursor: Arc::new(AtomicBool::new(false)),
+                jiggle: Arc::new(AtomicBool::new(false)),
+                jiggle_interval: Arc::new(Mutex::new(Duration::from_secs(60))),
+                jiggle_back: Arc::new(AtomicBool::new(true)),
+                travel_speed: Arc::new(Mutex::new(2000.0)),
+                max_cps: Arc::new(Mutex::new(None)),
+                power_cap: Arc::new(Mutex::new(None)),
+                superspeed: Arc::new(Mutex::new(SuperspeedMode::Balanced)),
+                heatmap: Arc::new(Mutex::new(Heatmap::default())),
+                intervals: Arc::new(Mutex::new(Intervals::default())),
+                watchdog: Arc::new(Watchdog::default()),
+                shutdown: Arc::new(AtomicBool::new(false)),
+                telemetry: Arc::new(Telemetry::default()),
+                start_delay: Arc::new(Mutex::new(Duration::ZERO)),
+                activity: Arc::new(Activity::default()),
+                typing_pause: Arc::new(Mutex::new(None)),
+                receipts: Arc::new(Receipts::default()),
+                latency: Arc::new(Mutex::new(Latency::default())),
+                pin_core: Arc::new(Mutex::new(None)),
+                jitter: Arc::new(Mutex::new(JitterReadout::default())),
+                guard: GuardHandles {
+                    max_cps: Arc::new(Mutex::new(None)),
+                    power_cap: Arc::new(Mutex::new(None)),
+                    blocklist: Arc::new(Mutex::new(Vec::new())),
+                    focused_app: Arc::new(Mutex::new(None)),
+                    pacer: Arc::new(Pacer::default()),
+                    log: Arc::new(Log::new()),
+                },
+            };
+            let backend = MockBackend { clock: clock.clone(), events: events.clone() };
+            Harness {
+                engine: Engine::new(handles.clone(), clock.clone(), backend, 0, Arc::new(ButtonLocks::default())),
+                clock,
+                events,
+                clicking,
+                fast_mode,
+                target_cps,
+                buttons,
+                dry_run,
+                emitted,
+                cursor,
+                own_window,
+                test_pad,
+                region,
+                exclusions,
+                sequence,
+                handles,
+            }
+        }
+
+        /// Ticks until the virtual clock reaches `until`.
+        fn run_until(&mut self, until: Duration) {
+            while self.clock.now() < until {
+                self.engine.tick();
+            }
+        }
+
+        fn click_times(&self) -> Vec<Duration> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, action)| matches!(action, Action::Down(_)))
+                .map(|(time, _)| *time)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn consistent_rate_emits_exact_count() {
+        let mut h = Harness::new(false, 10.0);
+        h.run_until(Duration::from_secs(1));
+        // Rounds at 0, 100, ..., 900 ms.
+        assert_eq!(h.click_times().len(), 10);
+        assert_eq!(h.emitted.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn consistent_rate_intervals_are_exact() {
+        let mut h = Harness::new(false, 50.0);
+        h.run_until(Duration::from_secs(2));
+        let times = h.click_times();
+        assert_eq!(times.len(), 100);
+        for pair in times.windows(2) {
+            assert_eq!(pair[1] - pair[0], Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn consistent_rate_does_not_drift_at_fractional_intervals() {
+        let mut h = Harness::new(false, 3.0);
+        h.run_until(Duration::from_secs(30));
+        let times = h.click_times();
+        assert!((89..=91).contains(&times.len()));
+        for (n, time) in times.iter().enumerate() {
+            let ideal = Duration::from_secs_f64(n as f64 / 3.0);
+            let error = time.abs_diff(ideal);
+            assert!(error < Duration::from_micros(1), "click {} off by {:?}", n, error);
+        }
+    }
+
+    #[test]
+    fn slow_rates_end_each_wait_on_the_precise_timer() {
+        let mut h = Harness::new(false, 10.0);
+        h.run_until(Duration::from_secs(1));
+        assert_eq!(h.click_times().len(), 10);
+        assert_eq!(h.clock.precise_sleeps.load(Ordering::SeqCst), 10);
+
+        let mut h = Harness::new(false, 50.0);
+        h.run_until(Duration::from_secs(1));
+        assert_eq!(h.clock.precise_sleeps.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn rate_change_takes_effect_on_next_round() {
+        let mut h = Harness::new(false, 10.0);
+        h.run_until(Duration::from_millis(500));
+        assert_eq!(h.click_times().len(), 5);
+        *h.target_cps.lock().unwrap() = 100.0;
+        h.run_until(Duration::from_millis(1500));
+        let times = h.click_times();
+        let last = times[times.len() - 1];
+        assert_eq!(times[times.len() - 1] - times[times.len() - 2], Duration::from_millis(10));
+        assert!(last < Duration::from_millis(1500));
+        assert_eq!(times.len(), 5 + 100);
+    }
+
+    #[test]
+    fn cps_is_clamped_to_limits() {
+        let mut slow = Harness::new(false, 0.0);
+        slow.run_until(Duration::from_secs(25));
+        // Clamped to MIN_CPS (one click every 10 s) instead of dividing by zero.
+        assert_eq!(slow.click_times().len(), 3);
+
+        let mut fast = Harness::new(false, 1_000_000.0);
+        fast.run_until(Duration::from_secs(1));
+        // Clamped to MAX_CPS.
+        assert_eq!(fast.click_times().len(), MAX_CPS as usize);
+    }
+
+    #[test]
+    fn typing_holds_clicking() {
+        let mut harness = Harness::new(false, 10.0);
+        *harness.handles.typing_pause.lock().unwrap() = Some(Duration::from_secs(60));
+        harness.handles.activity.typed(Instant::now());
+        harness.run_until(Duration::from_secs(1));
+        assert!(harness.click_times().is_empty());
+        // Long enough after the last key press, clicking goes on.
+        harness.handles.activity.typed(Instant::now() - Duration::from_secs(61));
+        harness.run_until(Duration::from_secs(2));
+        assert_eq!(harness.click_times().len(), 10);
+    }
+
+    #[test]
+    fn cps_guard_caps_every_mode() {
+        let mut fast = Harness::new(true, 10.0);
+        *fast.handles.max_cps.lock().unwrap() = Some(50.0);
+        fast.run_until(Duration::from_secs(1));
+        assert_eq!(fast.click_times().len(), 50);
+
+        // Both buttons and all four workers share the budget.
+        let mut shared = Harness::new(true, 10.0);
+        *shared.handles.max_cps.lock().unwrap() = Some(200.0);
+        shared.handles.workers.store(4, Ordering::SeqCst);
+        *shared.buttons.lock().unwrap() = vec![MouseButton::Left, MouseButton::Right];
+        shared.run_until(Duration::from_secs(1));
+        assert_eq!(shared.click_times().len(), 2 * 25);
+
+        let mut consistent = Harness::new(false, 100.0);
+        *consistent.handles.max_cps.lock().unwrap() = Some(20.0);
+        consistent.run_until(Duration::from_secs(1));
+        assert_eq!(consistent.click_times().len(), 20);
+        // A guard above the target leaves it alone.
+        *consistent.handles.max_cps.lock().unwrap() = Some(500.0);
+        consistent.run_until(Duration::from_secs(2));
+        assert_eq!(consistent.click_times().len(), 20 + 100);
+        // The battery cap applies when it is the lower one.
+        *consistent.handles.power_cap.lock().unwrap() = Some(5.0);
+        consistent.run_until(Duration::from_secs(3));
+        assert_eq!(consistent.click_times().len(), 20 + 100 + 5);
+    }
+
+    #[test]
+    fn start_delay_postpones_the_first_click() {
+        let mut h = Harness::new(false, 10.0);
+        *h.handles.start_delay.lock().unwrap() = Duration::from_secs(3);
+        h.run_until(Duration::from_millis(3250));
+        assert_eq!(h.click_times(), [3000, 3100, 3200].map(Duration::from_millis));
+
+        // Every start waits again.
+        h.clicking.store(false, Ordering::SeqCst);
+        h.engine.tick();
+        h.clicking.store(true, Ordering::SeqCst);
+        let restarted = h.clock.now();
+        h.run_until(restarted + Duration::from_millis(3050));
+        assert_eq!(h.click_times().len(), 4);
+        assert_eq!(h.click_times()[3], restarted + Duration::from_secs(3));
+    }
+
+    #[test]
+    fn superspeed_never_sleeps() {
+        let mut h = Harness::new(true, 10.0);
+        for _ in 0..1000 {
+            assert!(h.engine.tick());
+        }
+        assert_eq!(h.clock.now(), Duration::ZERO);
+        assert_eq!(h.click_times().len(), 1000);
+    }
+
+    #[test]
+    fn balanced_superspeed_backs_off_while_input_queues() {
+        let us = Duration::from_micros;
+        let mut pressure = Backpressure::default();
+        assert_eq!(pressure.round(us(10)), Duration::ZERO);
+        assert_eq!(pressure.round(us(12)), Duration::ZERO);
+        // The system stops keeping up: rounds take ten times as long.
+        let waits: Vec<Duration> = (0..20).map(|_| pressure.round(us(100))).collect();
+        assert_eq!(waits[0], Duration::ZERO);
+        assert!(waits[19] > us(50) && waits[19] <= MAX_BACKOFF);
+        // Caught up again: full speed once the average comes back down.
+        let waits: Vec<Duration> = (0..40).map(|_| pressure.round(us(10))).collect();
+        assert_eq!(waits[39], Duration::ZERO);
+    }
+
+    #[test]
+    fn superspeed_sends_trains_planned_ahead() {
+        let mut h = Harness::new(true, 10.0);
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0), (20.0, 10.0)];
+        let mut planner = Planner::new(h.handles.clone());
+        let (sender, trains) = mpsc::sync_channel(2);
+        sender.send(planner.train()).unwrap();
+        sender.send(planner.train()).unwrap();
+        h.engine.trains = Some(trains);
+
+        // The train was planned for the left button; changes wait for the next one.
+        h.engine.tick();
+        *h.buttons.lock().unwrap() = vec![MouseButton::Right];
+        for _ in 1..TRAIN_ROUNDS + 1 {
+            assert!(h.engine.tick());
+        }
+        let events = h.events.lock().unwrap();
+        let downs: Vec<&Action> = events.iter().map(|(_, action)| action).filter(|action| matches!(action, Action::Down(_))).collect();
+        assert_eq!(downs.len(), TRAIN_ROUNDS + 1);
+        assert!(downs[..TRAIN_ROUNDS].iter().all(|action| matches!(action, Action::Down(MouseButton::Left))));
+        // The waiting train goes to the newly picked button.
+        assert!(matches!(downs[TRAIN_ROUNDS], Action::Down(MouseButton::Right)));
+        let moves: Vec<&Action> = events.iter().map(|(_, action)| action).filter(|action| matches!(action, Action::Move(_))).take(3).collect();
+        assert!(matches!(moves[..], [Action::Move((10.0, 10.0)), Action::Move((20.0, 10.0)), Action::Move((10.0, 10.0))]));
+    }
+
+    #[test]
+    fn guard_paced_trains_keep_every_sequence_point() {
+        let mut h = Harness::new(true, 10.0);
+        let sequence: Vec<Point> = (0..5).map(|i| (10.0 * i as f64, 10.0)).collect();
+        *h.sequence.lock().unwrap() = sequence.clone();
+        *h.handles.max_cps.lock().unwrap() = Some(100.0);
+        h.engine.trains = Some(spawn_producer(h.handles.clone(), 0, |handles| !handles.shutdown.load(Ordering::SeqCst)));
+
+        // The train waiting behind the first gets old while that one is sent.
+        h.engine.tick();
+        thread::sleep(Duration::from_millis(30));
+        for _ in 1..TRAIN_ROUNDS * 3 {
+            assert!(h.engine.tick());
+        }
+        h.handles.shutdown.store(true, Ordering::SeqCst);
+        let moves: Vec<Point> = h.events.lock().unwrap().iter().filter_map(|(_, action)| match action {
+            Action::Move(point) => Some(*point),
+            _ => None,
+        }).collect();
+        assert_eq!(moves.len(), TRAIN_ROUNDS * 3);
+        assert!(moves.iter().enumerate().all(|(i, point)| *point == sequence[i % sequence.len()]));
+        // Paced by the guard, on the virtual clock.
+        assert_eq!(h.click_times()[1], Duration::from_millis(10));
+    }
+
+    #[test]
+    fn stopped_engine_idles_without_clicking() {
+        let mut h = Harness::new(false, 100.0);
+        h.clicking.store(false, Ordering::SeqCst);
+        h.run_until(Duration::from_secs(1));
+        assert!(h.click_times().is_empty());
+        assert_eq!(h.clock.now(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn restart_clicks_immediately() {
+        let mut h = Harness::new(false, 1.0);
+        h.engine.tick();
+        h.engine.tick();
+        h.clicking.store(false, Ordering::SeqCst);
+        h.engine.tick();
+        h.clicking.store(true, Ordering::SeqCst);
+        h.engine.tick();
+        let times = h.click_times();
+        assert_eq!(times, vec![Duration::ZERO, Duration::from_secs(1), Duration::from_millis(2010)]);
+    }
+
+    #[test]
+    fn switching_modes_mid_run() {
+        let mut h = Harness::new(true, 10.0);
+        for _ in 0..50 {
+            h.engine.tick();
+        }
+        h.fast_mode.store(false, Ordering::SeqCst);
+        h.run_until(Duration::from_secs(1));
+        assert_eq!(h.click_times().len(), 50 + 10);
+    }
+
+    #[test]
+    fn each_selected_button_is_pressed_and_released_per_round() {
+        let mut h = Harness::new(false, 10.0);
+        *h.buttons.lock().unwrap() = vec![MouseButton::Left, MouseButton::Right];
+        h.engine.tick();
+        let events = h.events.lock().unwrap();
+        let actions: Vec<&Action> = events.iter().map(|(_, action)| action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                &Action::Down(MouseButton::Left),
+                &Action::Up(MouseButton::Left),
+                &Action::Down(MouseButton::Right),
+                &Action::Up(MouseButton::Right),
+            ]
+        );
+        assert_eq!(h.emitted.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn no_selected_buttons_falls_back_to_left() {
+        let mut h = Harness::new(false, 10.0);
+        h.buttons.lock().unwrap().clear();
+        h.engine.tick();
+        assert_eq!(h.events.lock().unwrap()[0].1, Action::Down(MouseButton::Left));
+    }
+
+    #[test]
+    fn dry_run_emits_nothing_but_keeps_timing_and_stats() {
+        let mut h = Harness::new(false, 10.0);
+        h.dry_run.store(true, Ordering::SeqCst);
+        h.run_until(Duration::from_secs(1));
+        assert!(h.events.lock().unwrap().is_empty());
+        assert_eq!(h.emitted.load(Ordering::SeqCst), 10);
+    }
+
+    #[test]
+    fn falling_behind_does_not_burst() {
+        let mut h = Harness::new(false, 100.0);
+        h.engine.tick();
+        // Simulate the thread being descheduled for half a second.
+        h.clock.advance(Duration::from_millis(500));
+        h.run_until(Duration::from_millis(600));
+        let times = h.click_times();
+        for pair in times.windows(2).skip(1) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn pauses_while_cursor_is_over_own_window() {
+        let mut h = Harness::new(false, 10.0);
+        *h.own_window.lock().unwrap() = Some(Rect { x: 100.0, y: 100.0, width: 300.0, height: 400.0 });
+        *h.cursor.lock().unwrap() = Some((50.0, 50.0));
+        h.run_until(Duration::from_millis(500));
+        assert_eq!(h.click_times().len(), 5);
+
+        *h.cursor.lock().unwrap() = Some((200.0, 200.0));
+        h.run_until(Duration::from_millis(1000));
+        assert_eq!(h.click_times().len(), 5);
+        assert!(!h.engine.tick());
+
+        // Clicking resumes right away once the cursor leaves.
+        *h.cursor.lock().unwrap() = Some((450.0, 200.0));
+        let resumed = h.clock.now();
+        h.run_until(resumed + Duration::from_millis(500));
+        let times = h.click_times();
+        assert_eq!(times.len(), 10);
+        assert_eq!(times[5], resumed);
+    }
+
+    #[test]
+    fn clicks_the_test_pad_inside_own_window() {
+        let mut h = Harness::new(false, 10.0);
+        *h.own_window.lock().unwrap() = Some(Rect { x: 100.0, y: 100.0, width: 300.0, height: 400.0 });
+        *h.test_pad.lock().unwrap() = Some(Rect { x: 120.0, y: 300.0, width: 260.0, height: 100.0 });
+        *h.cursor.lock().unwrap() = Some((200.0, 350.0));
+        h.run_until(Duration::from_millis(500));
+        assert_eq!(h.click_times().len(), 5);
+
+        *h.cursor.lock().unwrap() = Some((200.0, 200.0));
+        h.run_until(Duration::from_millis(1000));
+        assert_eq!(h.click_times().len(), 5);
+    }
+
+    #[test]
+    fn clicks_only_inside_active_region() {
+        let mut h = Harness::new(false, 10.0);
+        *h.region.lock().unwrap() = Some(Rect { x: 0.0, y: 0.0, width: 800.0, height: 600.0 });
+        // Unknown cursor positions aren't held against the region.
+        h.run_until(Duration::from_millis(200));
+        assert_eq!(h.click_times().len(), 2);
+
+        *h.cursor.lock().unwrap() = Some((900.0, 100.0));
+        h.run_until(Duration::from_millis(700));
+        assert_eq!(h.click_times().len(), 2);
+
+        *h.cursor.lock().unwrap() = Some((400.0, 300.0));
+        let resumed = h.clock.now();
+        h.run_until(resumed + Duration::from_millis(300));
+        assert_eq!(h.click_times().len(), 5);
+    }
+
+    #[test]
+    fn never_clicks_in_exclusion_zones() {
+        let mut h = Harness::new(true, 10.0);
+        *h.exclusions.lock().unwrap() = vec![Rect { x: 500.0, y: 500.0, width: 100.0, height: 50.0 }];
+        h.run_until(Duration::from_millis(100));
+        assert!(h.click_times().is_empty(), "clicked without a known cursor position");
+
+        *h.cursor.lock().unwrap() = Some((550.0, 520.0));
+        h.run_until(Duration::from_millis(200));
+        assert!(h.click_times().is_empty());
+
+        *h.cursor.lock().unwrap() = Some((550.0, 560.0));
+        assert!(h.engine.tick());
+        assert_eq!(h.click_times().len(), 1);
+    }
+
+    #[test]
+    fn sequence_visits_points_in_turn_skipping_excluded_ones() {
+        let mut h = Harness::new(false, 10.0);
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0), (20.0, 10.0), (30.0, 10.0)];
+        *h.exclusions.lock().unwrap() = vec![Rect { x: 15.0, y: 0.0, width: 10.0, height: 50.0 }];
+        h.run_until(Duration::from_millis(400));
+        let moves: Vec<Point> = h
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, action)| match action {
+                Action::Move(point) => Some(*point),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moves, vec![(10.0, 10.0), (30.0, 10.0), (10.0, 10.0), (30.0, 10.0)]);
+        // Each move comes right before its click.
+        let events = h.events.lock().unwrap();
+        assert!(matches!(events[0].1, Action::Move(_)));
+        assert!(matches!(events[1].1, Action::Down(MouseButton::Left)));
+    }
+
+    /// Pixel source whose one color the test sets.
+    struct SharedPixel(Arc<Mutex<Option<Color>>>);
+
+    impl PixelSource for SharedPixel {
+        fn pixel(&self, _at: Point) -> Option<Color> {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn sequences_wait_for_the_pixel_to_change_between_points() {
+        let h = Harness::new(false, 10.0);
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0), (20.0, 10.0)];
+        *h.handles.sequence_wait.lock().unwrap() = Some(SequenceWait { at: (5.0, 5.0), timeout: Duration::from_secs(3600) });
+        let color = Arc::new(Mutex::new(Some([0, 0, 0])));
+        let mut planner = Planner::new(h.handles.clone());
+        planner.pixels = Some(Box::new(SharedPixel(color.clone())));
+        assert_eq!(planner.next_target(), Ok(Some((10.0, 10.0))));
+        assert_eq!(planner.next_target(), Err("waiting for 5,5 to change".to_string()));
+        *color.lock().unwrap() = Some([255, 255, 255]);
+        assert_eq!(planner.next_target(), Ok(Some((20.0, 10.0))));
+        // Past the timeout it moves on, changed or not.
+        *h.handles.sequence_wait.lock().unwrap() = Some(SequenceWait { at: (5.0, 5.0), timeout: Duration::ZERO });
+        assert_eq!(planner.next_target(), Ok(Some((10.0, 10.0))));
+    }
+
+    #[test]
+    fn offsets_spread_clicks_around_the_cursor() {
+        let mut h = Harness::new(false, 10.0);
+        *h.cursor.lock().unwrap() = Some((100.0, 100.0));
+        *h.handles.offsets.lock().unwrap() = vec![(0.0, 0.0), (30.0, 0.0)];
+        h.run_until(Duration::from_millis(300));
+        // Seen on the spread, the cursor doesn't move it; taken elsewhere, it does.
+        *h.cursor.lock().unwrap() = Some((130.0, 100.0));
+        h.run_until(Duration::from_millis(400));
+        *h.cursor.lock().unwrap() = Some((500.0, 500.0));
+        h.run_until(Duration::from_millis(600));
+        let moves: Vec<Point> = h
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, action)| match action {
+                Action::Move(point) => Some(*point),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moves, vec![(100.0, 100.0), (130.0, 100.0), (100.0, 100.0), (130.0, 100.0), (500.0, 500.0), (530.0, 500.0)]);
+
+        // Sequence points are spread the same way.
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0)];
+        h.events.lock().unwrap().clear();
+        h.run_until(Duration::from_millis(800));
+        assert!(matches!(h.events.lock().unwrap()[..], [(_, Action::Move((10.0, 10.0))), _, _, (_, Action::Move((40.0, 10.0))), _, _]));
+    }
+
+    #[test]
+    fn relative_sequences_follow_the_window() {
+        let mut h = Harness::new(false, 10.0);
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0), (20.0, 10.0)];
+        *h.handles.relative_to.lock().unwrap() = Some("game.exe".to_string());
+        // Exclusion zones stay in screen coordinates.
+        *h.exclusions.lock().unwrap() = vec![Rect { x: 115.0, y: 0.0, width: 10.0, height: 100.0 }];
+        h.engine.tick();
+        assert!(h.events.lock().unwrap().is_empty());
+
+        *h.handles.window_origin.lock().unwrap() = Some((100.0, 50.0));
+        h.engine.tick();
+        h.engine.tick();
+        *h.handles.window_origin.lock().unwrap() = Some((-5.0, 0.0));
+        h.engine.tick();
+        let moves: Vec<Point> = h
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, action)| match action {
+                Action::Move(point) => Some(*point),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moves, vec![(110.0, 60.0), (110.0, 60.0), (15.0, 10.0)]);
+    }
+
+    #[test]
+    fn sequences_from_other_screens_scale_when_asked() {
+        let mut h = Harness::new(false, 10.0);
+        *h.sequence.lock().unwrap() = vec![(960.0, 540.0)];
+        *h.handles.sequence_resolution.lock().unwrap() = Some((1920, 1080));
+        *h.handles.screen_size.lock().unwrap() = Some((1280, 720));
+        h.engine.tick();
+        h.handles.scale_recordings.store(true, Ordering::SeqCst);
+        h.engine.tick();
+        let moves: Vec<Point> = h
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(_, action)| match action {
+                Action::Move(point) => Some(*point),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(moves, vec![(960.0, 540.0), (640.0, 360.0)]);
+    }
+
+    #[test]
+    fn click_positions_and_intervals_reach_the_statistics() {
+        let mut h = Harness::new(false, 10.0);
+        *h.sequence.lock().unwrap() = vec![(10.0, 10.0), (100.0, 10.0)];
+        h.run_until(Duration::from_millis(400));
+        // Flushed once the engine goes idle.
+        h.clicking.store(false, Ordering::SeqCst);
+        h.engine.tick();
+        let mut expected = Heatmap::default();
+        expected.add((10.0, 10.0), 2);
+        expected.add((100.0, 10.0), 2);
+        assert_eq!(*h.handles.heatmap.lock().unwrap(), expected);
+        assert_eq!(h.handles.intervals.lock().unwrap().bars(10), [(100, 100, 3)]);
+    }
+
+    #[test]
+    fn watchdog_flags_an_engine_that_stops_beating() {
+        let mut h = Harness::new(false, 1.0);
+        let watchdog = h.handles.watchdog.clone();
+        let mut seen = (0, Duration::ZERO);
+        // A slow rate still beats while waiting for the next round.
+        for second in 1..=10 {
+            h.run_until(Duration::from_secs(second));
+            assert_eq!(watchdog.check(&mut seen, h.clock.now()), None);
+        }
+        // No beats, e.g. a backend call that never returns.
+        let stuck = h.clock.now();
+        assert_eq!(watchdog.check(&mut seen, stuck + STALL_TIMEOUT / 2), None);
+        assert_eq!(watchdog.check(&mut seen, stuck + STALL_TIMEOUT), Some("Click engine stopped responding"));
+        assert!(watchdog.stalled());
+        assert_eq!(watchdog.check(&mut seen, stuck + STALL_TIMEOUT * 2), None);
+        h.engine.tick();
+        assert_eq!(watchdog.check(&mut seen, stuck + STALL_TIMEOUT * 3), Some("Click engine recovered"));
+        assert!(!watchdog.stalled());
+    }
+
+    #[test]
+    fn finishing_releases_the_hold_key() {
+        let mut h = Harness::new(false, 10.0);
+        *h.handles.hold_key.lock().unwrap() = Some(rdev::Key::ShiftLeft);
+        *h.cursor.lock().unwrap() = Some((5.0, 5.0));
+        h.engine.tick();
+        h.engine.finish();
+        let events = h.events.lock().unwrap();
+        assert_eq!(events.first().map(|e| &e.1), Some(&Action::KeyDown(rdev::Key::ShiftLeft, KeyInjection::VirtualKey)));
+        assert_eq!(events.last().map(|e| &e.1), Some(&Action::KeyUp(rdev::Key::ShiftLeft)));
+        assert!(!h.handles.heatmap.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pressing_a_key_replaces_clicks() {
+        let mut h = Harness::new(false, 10.0);
+        *h.handles.press_key.lock().unwrap() = Some(rdev::Key::KeyW);
+        *h.handles.key_injection.lock().unwrap() = KeyInjection::Scancode;
+        h.run_until(Duration::from_millis(250));
+        let actions: Vec<Action> = h.events.lock().unwrap().drain(..).map(|(_, action)| action).collect();
+        // Rounds at 0, 100 and 200 ms, no mouse buttons.
+        let expected: Vec<Action> = (0..3)
+            .flat_map(|_| [Action::KeyDown(rdev::Key::KeyW, KeyInjection::Scancode), Action::KeyUp(rdev::Key::KeyW)])
+            .collect();
+        assert_eq!(actions, expected);
+        assert_eq!(h.emitted.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn human_travel_moves_gradually_before_clicking() {
+        let mut h = Harness::new(false, 1.0);
+        *h.cursor.lock().unwrap() = Some((0.0, 0.0));
+        *h.sequence.lock().unwrap() = vec![(400.0, 0.0)];
+        h.handles.human_travel.store(true, Ordering::SeqCst);
+        h.engine.tick();
+        let events = h.events.lock().unwrap();
+        let (moves, clicks) = events.split_at(events.iter().position(|(_, a)| matches!(a, Action::Down(_))).unwrap());
+        // 400 px at 2000 px/s: 200 ms in 8 ms steps.
+        assert_eq!(moves.len(), 25);
+        assert_eq!(moves.last().unwrap(), &(Duration::from_millis(192), Action::Move((400.0, 0.0))));
+        assert_eq!(clicks[0].0, Duration::from_millis(192));
+    }
+
+    #[test]
+    fn return_cursor_moves_back_after_each_click() {
+        let mut h = Harness::new(false, 10.0);
+        *h.cursor.lock().unwrap() = Some((5.0, 5.0));
+        *h.sequence.lock().unwrap() = vec![(100.0, 100.0), (200.0, 200.0)];
+        h.handles.return_cursor.store(true, Ordering::SeqCst);
+        h.engine.tick();
+        h.engine.tick();
+        let events = h.events.lock().unwrap();
+        let actions: Vec<&Action> = events.iter().map(|(_, a)| a).filter(|a| !matches!(a, Action::Up(_))).collect();
+        assert_eq!(
+            actions,
+            [
+                &Action::Move((100.0, 100.0)),
+                &Action::Down(MouseButton::Left),
+                &Action::Move((5.0, 5.0)),
+                &Action::Move((200.0, 200.0)),
+                &Action::Down(MouseButton::Left),
+                &Action::Move((5.0, 5.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn jiggler_nudges_without_clicking() {
+        let mut h = Harness::new(false, 10.0);
+        h.handles.jiggle.store(true, Ordering::SeqCst);
+        h.engine.tick();
+        // Nothing to nudge from until the cursor position is known.
+        assert!(h.events.lock().unwrap().is_empty());
+        *h.cursor.lock().unwrap() = Some((50.0, 50.0));
+        h.run_until(Duration::from_secs(150));
+        h.handles.jiggle_back.store(false, Ordering::SeqCst);
+        h.run_until(Duration::from_secs(270));
+        let events = h.events.lock().unwrap();
+        let rounds: Vec<(u64, &Action)> = events.iter().map(|(at, a)| (at.as_secs(), a)).collect();
+        let start = rounds[0].0;
+        assert_eq!(
+            rounds.iter().map(|&(at, a)| (at - start, a)).collect::<Vec<_>>(),
+            [
+                (0, &Action::Move((51.0, 50.0))),
+                (0, &Action::Move((50.0, 50.0))),
+                (60, &Action::Move((51.0, 50.0))),
+                (60, &Action::Move((50.0, 50.0))),
+                (120, &Action::Move((51.0, 50.0))),
+                (120, &Action::Move((50.0, 50.0))),
+                // Without moving back the cursor goes left and right in turn.
+                (180, &Action::Move((51.0, 50.0))),
+                (240, &Action::Move((49.0, 50.0))),
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_key_is_held_while_clicking() {
+        let mut h = Harness::new(false, 10.0);
+        *h.handles.hold_key.lock().unwrap() = Some(rdev::Key::ShiftLeft);
+        h.run_until(Duration::from_millis(250));
+        h.clicking.store(false, Ordering::SeqCst);
+        h.engine.tick();
+        h.engine.tick();
+        let events = h.events.lock().unwrap();
+        let holds: Vec<&Action> = events.iter().map(|(_, a)| a).filter(|a| matches!(a, Action::KeyDown(..) | Action::KeyUp(_))).collect();
+        assert_eq!(holds, [&Action::KeyDown(rdev::Key::ShiftLeft, KeyInjection::VirtualKey), &Action::KeyUp(rdev::Key::ShiftLeft)]);
+        // Pressed before the first click and released after the last one.
+        assert!(matches!(events.first().unwrap().1, Action::KeyDown(..)));
+        assert!(matches!(events.last().unwrap().1, Action::KeyUp(_)));
+        assert_eq!(events.iter().filter(|(_, a)| matches!(a, Action::Down(_))).count(), 3);
+    }
+
+    #[test]
+    fn blocklisted_apps_are_never_clicked() {
+        let mut h = Harness::new(false, 10.0);
+        *h.handles.hold_key.lock().unwrap() = Some(rdev::Key::ShiftLeft);
+        *h.handles.blocklist.lock().unwrap() = vec!["Bank.exe".to_string()];
+        *h.handles.focused_app.lock().unwrap() = Some("bank".to_string());
+        h.run_until(Duration::from_millis(500));
+        assert!(h.events.lock().unwrap().is_empty());
+
+        *h.handles.focused_app.lock().unwrap() = Some("game.exe".to_string());
+        assert!(h.engine.tick());
+        *h.handles.focused_app.lock().unwrap() = Some("bank.exe".to_string());
+        h.engine.tick();
+        let events = h.events.lock().unwrap();
+        assert_eq!(events.iter().filter(|(_, a)| matches!(a, Action::Down(_))).count(), 1);
+        assert!(matches!(events.last().unwrap().1, Action::KeyUp(rdev::Key::ShiftLeft)));
+    }
+
+    #[test]
+    fn hotkey_latency_is_timed_to_the_first_click() {
+        let mut h = Harness::new(false, 10.0);
+        h.handles.latency.lock().unwrap().pressed(Instant::now());
+        assert!(h.engine.tick());
+        assert!(h.engine.tick());
+        let summary = h.handles.latency.lock().unwrap().summary();
+        assert!(matches!(summary[..], [(BackendKind::Enigo, _, _, 1)]));
+
+        // Simulated clicks aren't sent, so there's nothing to time.
+        h.dry_run.store(true, Ordering::SeqCst);
+        h.handles.latency.lock().unwrap().pressed(Instant::now());
+        h.engine.tick();
+        h.dry_run.store(false, Ordering::SeqCst);
+        h.engine.tick();
+        assert_eq!(h.handles.latency.lock().unwrap().summary()[0].3, 1);
+    }
+
+    #[test]
+    fn helper_workers_only_run_in_superspeed() {
+        let h = Harness::new(true, 10.0);
+        let backend = MockBackend { clock: h.clock.clone(), events: h.events.clone() };
+        let locks = Arc::new(ButtonLocks::default());
+        let mut helper = Engine::new(h.handles.clone(), h.clock.clone(), backend, 1, locks);
+        assert!(!helper.tick(), "ran with a single worker configured");
+
+        h.handles.workers.store(2, Ordering::SeqCst);
+        assert!(helper.tick());
+        h.fast_mode.store(false, Ordering::SeqCst);
+        assert!(!helper.tick(), "ran in consistent-rate mode");
+        assert_eq!(h.click_times().len(), 1);
+    }
+
+    #[test]
+    fn concurrent_workers_keep_down_up_pairs_together() {
+        let h = Harness::new(true, 10.0);
+        *h.buttons.lock().unwrap() = vec![MouseButton::Left, MouseButton::Right];
+        h.handles.workers.store(4, Ordering::SeqCst);
+        let locks = Arc::new(ButtonLocks::default());
+        let threads: Vec<_> = (0..4)
+            .map(|worker| {
+                let backend = MockBackend { clock: h.clock.clone(), events: h.events.clone() };
+                let mut engine = Engine::new(h.handles.clone(), h.clock.clone(), backend, worker, locks.clone());
+                thread::spawn(move || {
+                    for _ in 0..2000 {
+                        engine.tick();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let events = h.events.lock().unwrap();
+        assert_eq!(events.len(), 4 * 2000 * 2 * 2);
+        assert_eq!(h.emitted.load(Ordering::SeqCst), 4 * 2000 * 2);
+        for button in [MouseButton::Left, MouseButton::Right] {
+            let presses: Vec<&Action> = events
+                .iter()
+                .map(|(_, action)| action)
+                .filter(|action| matches!(action, Action::Down(b) | Action::Up(b) if *b == button))
+                .collect();
+            for pair in presses.chunks(2) {
+                assert_eq!(pair, [&Action::Down(button), &Action::Up(button)]);
+            }
+        }
+    }
+}
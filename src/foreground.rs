@@ -0,0 +1,378 @@
+// Detection of the focused application and the watcher that applies
+// per-application profile bindings and window title rules, plus the tracker
+// that follows the window sequence points are relative to.
+//
+// Supported on Windows and on Linux under X11 (through the EWMH hints most
+// window managers set). Elsewhere the focused application is never known and
+// bindings have no effect.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::config::{AppBinding, Config, ConfigFile, TextAction, TitleRule};
+use crate::geometry::Point;
+use crate::log::Log;
+use crate::stats::AppClicks;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Often enough that a dragged window is followed closely.
+const TRACK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Shared state the foreground watcher is allowed to touch.
+pub struct ForegroundHandles {
+    pub bindings: Arc<Mutex<Vec<AppBinding>>>,
+    pub title_rules: Arc<Mutex<Vec<TitleRule>>>,
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    pub pending_profile: Arc<Mutex<Option<(String, Config)>>>,
+    // Cleared when a binding with stop_on_blur loses focus.
+    pub clicking: Arc<AtomicBool>,
+    // Emitted clicks, attributed to the focused application at every poll.
+    pub emitted: Arc<AtomicU64>,
+    pub app_clicks: Arc<Mutex<AppClicks>>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+// Name under which clicks sent while no application is known are counted.
+pub const UNKNOWN_APP: &str = "(unknown)";
+
+/// Watches the focused application and queues the profile bound to it.
+/// Applications without a binding leave the current profile in place, and
+/// bindings set to stop on blur stop clicking once their application loses focus.
+/// Title rules start or stop clicking when the focused window's title changes
+/// to one they match. The watcher returns after counting the last clicks once `shutdown` is set.
+pub fn spawn_watcher(handles: ForegroundHandles) -> Option<JoinHandle<()>> {
+    let Some(detector) = Detector::new() else {
+        handles.log.push("Focused application can't be detected here; app bindings are disabled.");
+        return None;
+    };
+    let own_name = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()));
+    Some(thread::spawn(move || {
+        let mut last: Option<String> = None;
+        let mut last_title: Option<String> = None;
+        let mut counted = handles.emitted.load(Ordering::SeqCst);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            // Clicks since the last poll went to the application focused during it.
+            let emitted = handles.emitted.load(Ordering::SeqCst);
+            let app = handles.focused_app.lock().unwrap().clone();
+            handles.app_clicks.lock().unwrap().add(app.as_deref().unwrap_or(UNKNOWN_APP), emitted - counted);
+            counted = emitted;
+            if handles.shutdown.load(Ordering::SeqCst) {
+                return;
+            }
+
+            let process = detector.process_name();
+            let title = detector.window_title();
+            if title != last_title {
+                last_title = title.clone();
+                // Our own window's title never applies a rule.
+                let own = process.is_some() && process == own_name;
+                let rule = title.filter(|_| !own).and_then(|title| {
+                    let rules = handles.title_rules.lock().unwrap();
+                    rules.iter().find(|rule| rule.matches(&title)).map(|rule| (rule.title.clone(), rule.action))
+                });
+                if let Some((matched, action)) = rule {
+                    let start = action == TextAction::Start;
+                    if handles.clicking.swap(start, Ordering::SeqCst) != start {
+                        let what = if start { "started" } else { "stopped" };
+                        handles.log.push(format!("Window title matches \"{}\"; clicking {}", matched, what));
+                    }
+                }
+            }
+            if process == last {
+                continue;
+            }
+            let previous = std::mem::replace(&mut last, process.clone());
+            // Our own window taking focus counts as the application losing it.
+            if let Some(previous) = previous {
+                let stops = handles.bindings.lock().unwrap().iter().any(|b| b.stop_on_blur && b.matches(&previous));
+                if stops && handles.clicking.swap(false, Ordering::SeqCst) {
+                    handles.log.push(format!("{} lost focus, clicking stopped", previous));
+                }
+            }
+            // Focusing our own window to edit settings shouldn't count.
+            let Some(process) = process.filter(|p| Some(p) != own_name.as_ref()) else { continue };
+            *handles.focused_app.lock().unwrap() = Some(process.clone());
+
+            let bound = {
+                let bindings = handles.bindings.lock().unwrap();
+                bindings.iter().find(|b| b.matches(&process)).map(|b| b.profile.clone())
+            };
+            let Some(name) = bound else { continue };
+            match ConfigFile::load_profile(&name) {
+                Some(config) => {
+                    handles.log.push(format!("{} focused, using profile {}", process, name));
+                    *handles.pending_profile.lock().unwrap() = Some((name, config));
+                }
+                None => handles.log.push(format!("{} is bound to unknown profile {}", process, name)),
+            }
+        }
+    }))
+}
+
+/// Shared state the window tracker is allowed to touch.
+pub struct WindowHandles {
+    pub relative_to: Arc<Mutex<Option<String>>>,
+    // Screen position of that app's window's client area, if it has one.
+    pub window_origin: Arc<Mutex<Option<Point>>>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Keeps `window_origin` on the topmost window of the app sequence points
+/// are relative to, until shutdown.
+pub fn spawn_window_tracker(handles: WindowHandles) {
+    thread::spawn(move || {
+        let Some(detector) = Detector::new() else {
+            return;
+        };
+        let mut was_missing = false;
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(TRACK_INTERVAL);
+            let app = handles.relative_to.lock().unwrap().clone();
+            let origin = app.as_deref().and_then(|app| detector.client_origin(app));
+            let missing = app.is_some() && origin.is_none();
+            if missing && !was_missing {
+                handles.log.push(format!("No {} window to click relative to", app.unwrap_or_default()));
+            }
+            was_missing = missing;
+            *handles.window_origin.lock().unwrap() = origin;
+        }
+    });
+}
+
+pub use platform::Detector;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::path::Path;
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    use crate::config::same_app;
+    use crate::geometry::Point;
+
+    pub struct Detector {
+        conn: RustConnection,
+        root: Window,
+        active_window: Atom,
+        client_list: Atom,
+        wm_pid: Atom,
+        wm_name: Atom,
+        utf8_string: Atom,
+    }
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            let (conn, screen) = x11rb::connect(None).ok()?;
+            let root = conn.setup().roots[screen].root;
+            let active_window = conn.intern_atom(false, b"_NET_ACTIVE_WINDOW").ok()?.reply().ok()?.atom;
+            let client_list = conn.intern_atom(false, b"_NET_CLIENT_LIST_STACKING").ok()?.reply().ok()?.atom;
+            let wm_pid = conn.intern_atom(false, b"_NET_WM_PID").ok()?.reply().ok()?.atom;
+            let wm_name = conn.intern_atom(false, b"_NET_WM_NAME").ok()?.reply().ok()?.atom;
+            let utf8_string = conn.intern_atom(false, b"UTF8_STRING").ok()?.reply().ok()?.atom;
+            Some(Detector { conn, root, active_window, client_list, wm_pid, wm_name, utf8_string })
+        }
+
+        fn property(&self, window: Window, atom: Atom, kind: AtomEnum) -> Option<u32> {
+            let reply = self.conn.get_property(false, window, atom, kind, 0, 1).ok()?.reply().ok()?;
+            let value = reply.value32()?.next();
+            value
+        }
+
+        /// Executable name of the process owning the focused window, e.g. "firefox".
+        pub fn process_name(&self) -> Option<String> {
+            let window = self.property(self.root, self.active_window, AtomEnum::WINDOW)?;
+            if window == 0 {
+                return None;
+            }
+            self.window_process(window)
+        }
+
+        /// Title of the focused window, from the EWMH name or the older WM_NAME.
+        pub fn window_title(&self) -> Option<String> {
+            let window = self.property(self.root, self.active_window, AtomEnum::WINDOW)?;
+            if window == 0 {
+                return None;
+            }
+            let text = |atom: Atom, kind: Atom| {
+                let reply = self.conn.get_property(false, window, atom, kind, 0, 1024).ok()?.reply().ok()?;
+                Some(reply.value).filter(|value| !value.is_empty())
+            };
+            if let Some(name) = text(self.wm_name, self.utf8_string) {
+                return Some(String::from_utf8_lossy(&name).into_owned());
+            }
+            // WM_NAME is Latin-1.
+            text(AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()).map(|name| name.into_iter().map(char::from).collect())
+        }
+
+        fn window_process(&self, window: Window) -> Option<String> {
+            let pid = self.property(window, self.wm_pid, AtomEnum::CARDINAL)?;
+            // /proc/PID/comm is truncated to 15 bytes, so prefer the executable path.
+            if let Ok(exe) = std::fs::read_link(format!("/proc/{}/exe", pid)) {
+                if let Some(name) = Path::new(&exe).file_name() {
+                    return Some(name.to_string_lossy().into_owned());
+                }
+            }
+            let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            Some(comm.trim().to_string())
+        }
+
+        /// Screen position of the top-left of the topmost window of `app`.
+        pub fn client_origin(&self, app: &str) -> Option<Point> {
+            let reply = self.conn.get_property(false, self.root, self.client_list, AtomEnum::WINDOW, 0, 1024).ok()?.reply().ok()?;
+            // Bottom to top.
+            let windows: Vec<Window> = reply.value32()?.collect();
+            let window = windows.into_iter().rev().find(|&w| self.window_process(w).is_some_and(|p| same_app(app, &p)))?;
+            let at = self.conn.translate_coordinates(window, self.root, 0, 0).ok()?.reply().ok()?;
+            Some((f64::from(at.dst_x), f64::from(at.dst_y)))
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStringExt;
+    use std::path::PathBuf;
+
+    use crate::config::same_app;
+    use crate::geometry::Point;
+
+    type Handle = *mut c_void;
+
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[repr(C)]
+    struct WinPoint {
+        x: i32,
+        y: i32,
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> Handle;
+        fn GetWindowThreadProcessId(window: Handle, process_id: *mut u32) -> u32;
+        fn EnumWindows(callback: unsafe extern "system" fn(Handle, isize) -> i32, param: isize) -> i32;
+        fn IsWindowVisible(window: Handle) -> i32;
+        fn IsIconic(window: Handle) -> i32;
+        fn ClientToScreen(window: Handle, point: *mut WinPoint) -> i32;
+        fn GetWindowTextW(window: Handle, text: *mut u16, max: i32) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, process_id: u32) -> Handle;
+        fn QueryFullProcessImageNameW(process: Handle, flags: u32, name: *mut u16, size: *mut u32) -> i32;
+        fn CloseHandle(handle: Handle) -> i32;
+    }
+
+    pub struct Detector;
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            Some(Detector)
+        }
+
+        /// Executable name of the process owning the focused window, e.g. "javaw.exe".
+        pub fn process_name(&self) -> Option<String> {
+            let window = unsafe { GetForegroundWindow() };
+            if window.is_null() {
+                return None;
+            }
+            window_process(window)
+        }
+
+        /// Title of the focused window.
+        pub fn window_title(&self) -> Option<String> {
+            let window = unsafe { GetForegroundWindow() };
+            if window.is_null() {
+                return None;
+            }
+            let mut buffer = [0u16; 512];
+            let len = unsafe { GetWindowTextW(window, buffer.as_mut_ptr(), buffer.len() as i32) };
+            (len > 0).then(|| String::from_utf16_lossy(&buffer[..len as usize]))
+        }
+
+        /// Screen position of the client area of the topmost window of `app`.
+        pub fn client_origin(&self, app: &str) -> Option<Point> {
+            let mut search = Search { app, found: std::ptr::null_mut() };
+            unsafe {
+                EnumWindows(visit, &mut search as *mut Search as isize);
+                if search.found.is_null() {
+                    return None;
+                }
+                let mut point = WinPoint { x: 0, y: 0 };
+                if ClientToScreen(search.found, &mut point) == 0 {
+                    return None;
+                }
+                Some((f64::from(point.x), f64::from(point.y)))
+            }
+        }
+    }
+
+    struct Search<'a> {
+        app: &'a str,
+        found: Handle,
+    }
+
+    // Windows are enumerated top to bottom; stops at the first one of the app.
+    unsafe extern "system" fn visit(window: Handle, search: isize) -> i32 {
+        let search = &mut *(search as *mut Search);
+        if IsWindowVisible(window) != 0 && IsIconic(window) == 0 && window_process(window).is_some_and(|p| same_app(search.app, &p)) {
+            search.found = window;
+            return 0;
+        }
+        1
+    }
+
+    fn window_process(window: Handle) -> Option<String> {
+        unsafe {
+            let mut pid = 0;
+            GetWindowThreadProcessId(window, &mut pid);
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+            let mut buffer = [0u16; 1024];
+            let mut size = buffer.len() as u32;
+            let ok = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut size);
+            CloseHandle(process);
+            if ok == 0 {
+                return None;
+            }
+            let path = PathBuf::from(std::ffi::OsString::from_wide(&buffer[..size as usize]));
+            path.file_name().map(|name| name.to_string_lossy().into_owned())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub struct Detector;
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn process_name(&self) -> Option<String> {
+            None
+        }
+
+        pub fn window_title(&self) -> Option<String> {
+            None
+        }
+
+        pub fn client_origin(&self, _app: &str) -> Option<crate::geometry::Point> {
+            None
+        }
+    }
+}
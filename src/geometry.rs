@@ -0,0 +1,140 @@
+// Screen geometry shared by the engine and the front end. Coordinates are
+// physical pixels relative to the top left corner of the primary display, as
+// reported by the global listener.
+
+use std::fmt;
+
+/// Screen position in pixels.
+pub type Point = (f64, f64);
+
+/// Axis-aligned screen rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Parses the "x,y" form used in config files.
+pub fn parse_point(text: &str) -> Option<Point> {
+    let (x, y) = text.split_once(',')?;
+    let point = (x.trim().parse::<f64>().ok()?, y.trim().parse::<f64>().ok()?);
+    (point.0.is_finite() && point.1.is_finite()).then_some(point)
+}
+
+pub fn format_point((x, y): Point) -> String {
+    format!("{},{}", x, y)
+}
+
+/// Parses a "1920x1080" screen size; both sides must be positive.
+pub fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once('x')?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+pub fn format_size((width, height): (u32, u32)) -> String {
+    format!("{}x{}", width, height)
+}
+
+/// `point` on a screen of size `from` moved to the same spot, in proportion,
+/// on a screen of size `to`.
+pub fn scale_point((x, y): Point, from: (u32, u32), to: (u32, u32)) -> Point {
+    ((x * to.0 as f64 / from.0 as f64).round(), (y * to.1 as f64 / from.1 as f64).round())
+}
+
+/// Indices of the points a Douglas–Peucker simplification of the path
+/// through `points` keeps: every dropped point lies within `tolerance` of the
+/// simplified path. The first and last points are always kept.
+pub fn simplify(points: &[Point], tolerance: f64) -> Vec<usize> {
+    let mut keep = vec![false; points.len()];
+    let mut spans = Vec::new();
+    if let Some(last) = points.len().checked_sub(1) {
+        keep[0] = true;
+        keep[last] = true;
+        spans.push((0, last));
+    }
+    while let Some((first, last)) = spans.pop() {
+        let (index, distance) = (first + 1..last)
+            .map(|i| (i, distance_to_segment(points[i], points[first], points[last])))
+            .fold((first, 0.0), |farthest, point| if point.1 > farthest.1 { point } else { farthest });
+        if distance > tolerance {
+            keep[index] = true;
+            spans.push((first, index));
+            spans.push((index, last));
+        }
+    }
+    (0..points.len()).filter(|&i| keep[i]).collect()
+}
+
+fn distance_to_segment((x, y): Point, (ax, ay): Point, (bx, by): Point) -> f64 {
+    let (dx, dy) = (bx - ax, by - ay);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 { 0.0 } else { (((x - ax) * dx + (y - ay) * dy) / length_squared).clamp(0.0, 1.0) };
+    (x - ax - t * dx).hypot(y - ay - t * dy)
+}
+
+impl Rect {
+    /// Rectangle spanned by two opposite corners, in any order.
+    pub fn from_corners(a: Point, b: Point) -> Rect {
+        Rect { x: a.0.min(b.0), y: a.1.min(b.1), width: (a.0 - b.0).abs(), height: (a.1 - b.1).abs() }
+    }
+
+    /// Parses the "x,y,width,height" form used in config files.
+    pub fn parse(text: &str) -> Option<Rect> {
+        let values: Vec<f64> = text.split(',').map(|part| part.trim().parse().ok()).collect::<Option<_>>()?;
+        let [x, y, width, height] = values[..] else { return None };
+        let valid = values.iter().all(|v| v.is_finite()) && width > 0.0 && height > 0.0;
+        valid.then_some(Rect { x, y, width, height })
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// This rectangle moved, and shrunk if it is too big, to lie inside `bounds`.
+    pub fn moved_inside(&self, bounds: Rect) -> Rect {
+        let width = self.width.min(bounds.width);
+        let height = self.height.min(bounds.height);
+        Rect {
+            x: self.x.clamp(bounds.x, bounds.x + bounds.width - width),
+            y: self.y.clamp(bounds.y, bounds.y + bounds.height - height),
+            width,
+            height,
+        }
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rects_move_inside_bounds() {
+        let screen = Rect { x: 0.0, y: 0.0, width: 1920.0, height: 1080.0 };
+        let inside = Rect { x: 100.0, y: 50.0, width: 380.0, height: 560.0 };
+        assert_eq!(inside.moved_inside(screen), inside);
+        // Left on a monitor that is gone.
+        let off = Rect { x: 2500.0, y: -300.0, ..inside };
+        assert_eq!(off.moved_inside(screen), Rect { x: 1540.0, y: 0.0, ..inside });
+        let huge = Rect { x: -10.0, y: 0.0, width: 4000.0, height: 500.0 };
+        assert_eq!(huge.moved_inside(screen), Rect { x: 0.0, y: 0.0, width: 1920.0, height: 500.0 });
+    }
+
+    #[test]
+    fn simplification_keeps_the_corners() {
+        // Along the top with a little wobble, down the side, and back to the start.
+        let path = [(0.0, 0.0), (5.0, 0.5), (10.0, -0.5), (20.0, 0.0), (20.0, 10.0), (20.0, 20.0), (0.0, 0.0)];
+        assert_eq!(simplify(&path, 1.0), vec![0, 3, 5, 6]);
+        assert_eq!(simplify(&path, 100.0), vec![0, 6]);
+        assert_eq!(simplify(&path[..1], 1.0), vec![0]);
+        assert!(simplify(&[], 1.0).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+// The CPS guard, power cap and app blocklist for everything the clicker
+// sends, not only the engine's rounds: macros, the chat bursts played as
+// macros and the engine inject through a `Guarded` backend, and the typer
+// asks before each character. Presses are given slots on one schedule shared
+// by every sender, so together they never go over the lower of the two caps,
+// and none are sent while a blocklisted app is focused. Releases and cursor
+// moves pass straight through.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+
+use crate::backend::InputBackend;
+use crate::config::{same_app, KeyInjection, MIN_CPS};
+use crate::geometry::Point;
+use crate::log::Log;
+
+// Longest wait for a slot before checking whether the sender was stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// When the next press may be sent, shared by every sender, and the
+/// blocklisted app input was last held back from.
+#[derive(Default)]
+pub struct Pacer {
+    next: Mutex<Option<Instant>>,
+    blocked: Mutex<Option<String>>,
+}
+
+/// Shared state the guard is allowed to touch.
+#[derive(Clone)]
+pub struct GuardHandles {
+    pub max_cps: Arc<Mutex<Option<f64>>>,
+    pub power_cap: Arc<Mutex<Option<f64>>>,
+    pub blocklist: Arc<Mutex<Vec<String>>>,
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    pub pacer: Arc<Pacer>,
+    pub log: Arc<Log>,
+}
+
+impl GuardHandles {
+    /// The lower of the CPS guard and power cap, if either is set.
+    fn limit(&self) -> Option<f64> {
+        let caps = [*self.max_cps.lock().unwrap(), *self.power_cap.lock().unwrap()];
+        caps.into_iter().flatten().reduce(f64::min).map(|limit| limit.max(MIN_CPS))
+    }
+
+    /// The focused application, if it is on the blocklist.
+    pub fn blocked_app(&self) -> Option<String> {
+        let app = self.focused_app.lock().unwrap().clone()?;
+        self.blocklist.lock().unwrap().iter().any(|blocked| same_app(blocked, &app)).then_some(app)
+    }
+
+    /// Takes the next free slot, no earlier than `now`.
+    fn slot(&self, now: Instant) -> Instant {
+        let Some(limit) = self.limit() else { return now };
+        let mut next = self.pacer.next.lock().unwrap();
+        let slot = next.map_or(now, |next| next.max(now));
+        *next = Some(slot + Duration::from_secs_f64(1.0 / limit));
+        slot
+    }
+
+    /// Waits for the next slot; false if `running` was cleared meanwhile or
+    /// a blocklisted app is focused, in which case the press must not be sent.
+    pub fn admit(&self, running: &AtomicBool) -> bool {
+        let mut blocked = self.pacer.blocked.lock().unwrap();
+        match self.blocked_app() {
+            Some(app) if blocked.as_ref() != Some(&app) => {
+                self.log.push(format!("Holding back input: {} is on the blocklist", app));
+                *blocked = Some(app);
+                return false;
+            }
+            Some(_) => return false,
+            None => *blocked = None,
+        }
+        drop(blocked);
+        let slot = self.slot(Instant::now());
+        while running.load(Ordering::SeqCst) {
+            let Some(left) = slot.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) else { break };
+            thread::sleep(left.min(POLL_INTERVAL));
+        }
+        running.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Held {
+    Button(MouseButton),
+    Key(rdev::Key),
+}
+
+/// Backend that sends presses only as the guard allows. A press that isn't
+/// sent takes its release with it.
+pub struct Guarded<B> {
+    inner: B,
+    guard: GuardHandles,
+    running: Arc<AtomicBool>,
+    held_back: Vec<Held>,
+}
+
+impl<B: InputBackend> Guarded<B> {
+    /// Guards `inner` for a sender that stops when `running` is cleared.
+    pub fn new(inner: B, guard: GuardHandles, running: Arc<AtomicBool>) -> Self {
+        Guarded { inner, guard, running, held_back: Vec::new() }
+    }
+
+    /// Whether the release of `held` belongs to a press that wasn't sent.
+    fn was_held_back(&mut self, held: Held) -> bool {
+        let index = self.held_back.iter().position(|h| *h == held);
+        index.map(|index| self.held_back.remove(index)).is_some()
+    }
+}
+
+impl<B: InputBackend> InputBackend for Guarded<B> {
+    fn button_down(&mut self, button: MouseButton) {
+        match self.guard.admit(&self.running) {
+            true => self.inner.button_down(button),
+            false => self.held_back.push(Held::Button(button)),
+        }
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        if !self.was_held_back(Held::Button(button)) {
+            self.inner.button_up(button);
+        }
+    }
+
+    fn move_to(&mut self, point: Point) {
+        self.inner.move_to(point);
+    }
+
+    fn key_down(&mut self, key: rdev::Key, injection: KeyInjection) {
+        match self.guard.admit(&self.running) {
+            true => self.inner.key_down(key, injection),
+            false => self.held_back.push(Held::Key(key)),
+        }
+    }
+
+    fn key_up(&mut self, key: rdev::Key, injection: KeyInjection) {
+        if !self.was_held_back(Held::Key(key)) {
+            self.inner.key_up(key, injection);
+        }
+    }
+
+    fn click(&mut self, button: MouseButton) {
+        if self.guard.admit(&self.running) {
+            self.inner.click(button);
+        }
+    }
+
+    fn press(&mut self, key: rdev::Key, injection: KeyInjection) {
+        if self.guard.admit(&self.running) {
+            self.inner.press(key, injection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guard(max_cps: Option<f64>, power_cap: Option<f64>) -> GuardHandles {
+        GuardHandles {
+            max_cps: Arc::new(Mutex::new(max_cps)),
+            power_cap: Arc::new(Mutex::new(power_cap)),
+            blocklist: Arc::new(Mutex::new(Vec::new())),
+            focused_app: Arc::new(Mutex::new(None)),
+            pacer: Arc::new(Pacer::default()),
+            log: Arc::new(Log::new()),
+        }
+    }
+
+    #[test]
+    fn presses_get_slots_under_the_lower_cap() {
+        let start = Instant::now();
+        assert_eq!(guard(None, None).slot(start), start);
+        let capped = guard(Some(100.0), Some(50.0));
+        let slots: Vec<Duration> = (0..3).map(|_| capped.slot(start) - start).collect();
+        assert_eq!(slots, [Duration::ZERO, Duration::from_millis(20), Duration::from_millis(40)]);
+        // Idle time doesn't build up credit for a burst later.
+        let later = start + Duration::from_secs(1);
+        assert_eq!(capped.slot(later), later);
+        assert_eq!(capped.slot(later) - later, Duration::from_millis(20));
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        downs: usize,
+        ups: usize,
+    }
+
+    impl InputBackend for Counter {
+        fn button_down(&mut self, _button: MouseButton) {
+            self.downs += 1;
+        }
+
+        fn button_up(&mut self, _button: MouseButton) {
+            self.ups += 1;
+        }
+
+        fn move_to(&mut self, _point: Point) {}
+        fn key_down(&mut self, _key: rdev::Key, _injection: KeyInjection) {}
+        fn key_up(&mut self, _key: rdev::Key, _injection: KeyInjection) {}
+    }
+
+    #[test]
+    fn stopping_drops_the_press_and_its_release() {
+        let running = Arc::new(AtomicBool::new(false));
+        let mut backend = Guarded::new(Counter::default(), guard(None, None), running.clone());
+        backend.button_down(MouseButton::Left);
+        backend.button_up(MouseButton::Left);
+        backend.click(MouseButton::Left);
+        assert_eq!((backend.inner.downs, backend.inner.ups), (0, 0));
+        // A release with no press held back is the real one and goes out.
+        running.store(true, Ordering::SeqCst);
+        backend.button_up(MouseButton::Left);
+        backend.click(MouseButton::Left);
+        assert_eq!((backend.inner.downs, backend.inner.ups), (1, 2));
+    }
+
+    #[test]
+    fn nothing_is_sent_while_a_blocklisted_app_is_focused() {
+        let guard = guard(None, None);
+        *guard.blocklist.lock().unwrap() = vec!["Bank.exe".to_string()];
+        *guard.focused_app.lock().unwrap() = Some("bank.exe".to_string());
+        let running = Arc::new(AtomicBool::new(true));
+        let mut backend = Guarded::new(Counter::default(), guard.clone(), running);
+        backend.click(MouseButton::Left);
+        backend.button_down(MouseButton::Left);
+        // Focus moved on before the release; it still goes with its press.
+        *guard.focused_app.lock().unwrap() = Some("notepad.exe".to_string());
+        backend.button_up(MouseButton::Left);
+        assert_eq!((backend.inner.downs, backend.inner.ups), (0, 0));
+        backend.click(MouseButton::Left);
+        assert_eq!((backend.inner.downs, backend.inner.ups), (1, 1));
+        // Held back input is logged once each time the app comes up.
+        let held: Vec<String> = guard.log.lines().into_iter().filter(|line| line.contains("blocklist")).collect();
+        assert_eq!(held.len(), 1);
+    }
+}
@@ -0,0 +1,3144 @@
+// egui front end (built with the `gui` feature).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::{egui, App, Frame};
+use enigo::MouseButton;
+
+use crate::affinity;
+use crate::backend;
+use crate::bench::{self, BenchReport, BENCH_CLICKS, BENCH_COUNTDOWN};
+use crate::buttons::ClickButton;
+use crate::command_files;
+use crate::config::{
+    self, config_dir, Activation, AppBinding, BackendKind, BatteryPolicy, ChatTrigger, Config, ConfigFile, ConfigIssue, IndicatorPlace, KeyInjection, LockPolicy, MacroHotkey, MqttConfig, SequenceWait, SharedProfile, SuperspeedMode, TextAction, TextTyper, TitleMatch, TitleRule, WebhookConfig, CHORD_WINDOW, MAX_CHAT_CLICKS,
+    MAX_CHAT_COOLDOWN, MAX_CPS, MAX_CPS_LIMIT, MAX_CPS_PRESETS, MAX_CPS_STEP, MAX_IDLE_STOP, MAX_SOUND_RATE, MAX_JIGGLE_INTERVAL, MAX_SEQUENCE_WAIT, MAX_TYPING_PAUSE, MAX_TRAVEL_SPEED, MAX_WORKERS, MAX_START_DELAY, MAX_TYPER_CHAR_DELAY, MAX_TYPER_REPEAT_DELAY, MAX_FONT_SIZE, MAX_UI_SCALE, MAX_WEBHOOK_EVERY, DEFAULT_FONT_SIZE, MIN_CPS, MIN_FONT_SIZE, MIN_TRAVEL_SPEED, MIN_UI_SCALE, REALISTIC_CPS, ThemeMode, Weekday,
+};
+use crate::geometry::{Point, Rect};
+use crate::history::{self, Run};
+use crate::hotkeys::{self, Binding, Conflict, Slot};
+use crate::keys::SUPPORTED_KEYS;
+use crate::layout::display_name;
+use crate::macro_json;
+use crate::macros::{self, Macro, Recording};
+use crate::mqtt;
+use crate::overlay;
+use crate::palette::{self, Action, Command, Palette};
+use crate::patterns::Pattern;
+use crate::screen;
+use crate::sounds;
+use crate::state::Shared;
+use crate::stats::{self, AppClicks, Heatmap, Intervals, HEAT_CELL};
+use crate::sync::{self, SyncState};
+use crate::totals::Totals;
+use crate::twitch;
+use crate::webhook;
+
+// Commands listed in the palette at once.
+const PALETTE_RESULTS: usize = 10;
+// Runs listed under Statistics, newest first.
+const RECENT_RUNS: usize = 10;
+
+// The window position is its outer corner but the size excludes decorations.
+const TITLE_BAR_HEIGHT: f32 = 32.0;
+
+/// What the corners being picked on screen are for.
+#[derive(Clone, Copy, PartialEq)]
+enum PickTarget {
+    Region,
+    Exclusion,
+    // The pixel a click sequence waits on; one point.
+    WaitPixel,
+}
+
+/// A saved macro as listed in the library.
+struct LibraryEntry {
+    name: String,
+    // Comma-separated tags being edited; saved when the field loses focus.
+    tags: String,
+    last_used: Option<SystemTime>,
+    steps: usize,
+    // Why the file could not be parsed.
+    error: Option<String>,
+}
+
+impl LibraryEntry {
+    fn load(name: String) -> Self {
+        match macros::load(&name) {
+            Ok(mac) => LibraryEntry { name, tags: mac.tags.join(", "), last_used: mac.last_used, steps: mac.steps.len(), error: None },
+            Err(e) => LibraryEntry { name, tags: String::new(), last_used: None, steps: 0, error: Some(e) },
+        }
+    }
+}
+
+/// Dialog reading a shared macro file before it is added to the library.
+struct ImportDialog {
+    path: String,
+    // Name and macro read from the file, or why it can't be used.
+    read: Option<Result<(String, Macro), String>>,
+    name: String,
+    // Move the coordinates to this screen's size when it differs.
+    scale: bool,
+}
+
+/// Clicks the test pad received and when, for its rate.
+#[derive(Default)]
+struct TestPad {
+    // Left, middle and right.
+    clicks: [u64; 3],
+    recent: Vec<Instant>,
+}
+
+/// Dialog reading a shared profile from a pasted code or an exported file.
+struct ProfileImport {
+    // Share code, file contents or the path of an exported file.
+    text: String,
+    read: Option<Result<SharedProfile, String>>,
+    name: String,
+}
+
+/// Window listing past runs, filtered by profile and date.
+#[derive(Default)]
+struct HistoryBrowser {
+    // Profile to list the runs of; every profile if unset.
+    profile: Option<String>,
+    // Start of the local date, like "2026-10" or "2026-10-14".
+    date: String,
+    // Index of the run shown in detail.
+    selected: Option<usize>,
+}
+
+/// GUI state on top of the shared application state.
+pub struct AppState {
+    shared: Shared,
+    // All saved profiles; the active one is written back on save.
+    config_file: ConfigFile,
+    // Problems found in config.toml at startup, shown until dismissed.
+    config_issues: Vec<ConfigIssue>,
+    // Result of migrating a legacy config.txt, shown until dismissed.
+    migration_notice: Option<Result<String, String>>,
+    // Count/time of the last engine rate sample.
+    emitted_sample: (u64, Instant),
+    emitted_rate: f64,
+    // Superspeed rate last measured with a single worker, for the gain display.
+    single_worker_rate: Option<f64>,
+    pick_target: PickTarget,
+    // Generator settings for click sequences.
+    pattern: Pattern,
+    // Latest backend benchmark results, filled in by the benchmark thread.
+    bench_report: Arc<Mutex<Option<BenchReport>>>,
+    // When the running benchmark was started, for its countdown.
+    bench_started: Instant,
+    // Saved macro names and the macro being edited.
+    macro_names: Vec<String>,
+    macro_name: String,
+    macro_text: String,
+    // Playback settings of the edited macro, kept out of the step text.
+    macro_loops: Option<u32>,
+    macro_loop_delay: u64,
+    // Metadata of the edited macro, kept when it is saved again; its steps are unused.
+    macro_meta: Macro,
+    macro_status: Option<String>,
+    // Leave cursor movement out of recordings.
+    record_clicks_only: bool,
+    // Desktop size for the heatmap preview, read once at startup.
+    screen_size: Option<(u32, u32)>,
+    // Where the window is while it is neither maximized nor minimized, in the units of `ConfigFile::window`.
+    window_geometry: Option<Rect>,
+    // Whether the restored window was checked against the screen yet.
+    window_placed: bool,
+    heatmap_status: Option<String>,
+    stats_status: Option<String>,
+    // Port typed for the stream overlay, and why it last failed to start.
+    overlay_port: u16,
+    overlay_error: Option<String>,
+    // Chat trigger being edited while it is off.
+    chat_trigger: ChatTrigger,
+    mqtt: MqttConfig,
+    webhook: WebhookConfig,
+    // Result of the last usage metrics submission, set by its thread.
+    telemetry_status: Arc<Mutex<Option<String>>>,
+    library: Vec<LibraryEntry>,
+    library_filter: String,
+    // Macro being renamed and the new name typed so far.
+    renaming: Option<(String, String)>,
+    // Macro waiting for its delete to be confirmed.
+    confirm_delete: Option<String>,
+    import: Option<ImportDialog>,
+    profile_import: Option<ProfileImport>,
+    history_browser: Option<HistoryBrowser>,
+    // Result of the last profile export.
+    profile_status: Option<String>,
+    // Sync folder being typed, and the result of the last sync.
+    sync_dir: String,
+    sync_status: Option<String>,
+    // Why the last sound theme picked couldn't be loaded.
+    sound_status: Option<String>,
+    sync_state: SyncState,
+    // Profiles as last written to or read from the sync folder.
+    last_synced: BTreeMap<String, Config>,
+    // Clicks of every synced machine up to their last exit, this one included.
+    synced_clicks: AppClicks,
+    palette: Option<Palette>,
+    // Section to open on the next frame, picked in the palette.
+    open_section: Option<&'static str>,
+    // The UI scale slider is being dragged; scaling waits for its release.
+    scaling: bool,
+    // Hotkey conflicts dialog shown.
+    resolving_conflicts: bool,
+    test_pad: TestPad,
+    // Screen position of the window's top left corner below the title bar.
+    client_origin: Option<Point>,
+}
+
+impl AppState {
+    pub fn new(
+        shared: Shared,
+        config_file: ConfigFile,
+        config_issues: Vec<ConfigIssue>,
+        migration_notice: Option<Result<String, String>>,
+    ) -> Self {
+        let overlay_port = config_file.overlay_port.unwrap_or(overlay::DEFAULT_PORT);
+        let chat_trigger = config_file.chat_trigger.clone().unwrap_or_else(|| ChatTrigger::new(""));
+        let mqtt = config_file.mqtt.clone().unwrap_or_else(|| MqttConfig::new(""));
+        let webhook = config_file.webhook.clone().unwrap_or_else(|| WebhookConfig::new(""));
+        let sync_dir = config_file.sync_dir.as_ref().map(|dir| dir.display().to_string()).unwrap_or_default();
+        let synced_clicks = config_file.sync_dir.as_deref().map(sync::load_stats).unwrap_or_default();
+        let window_geometry = config_file.window;
+        let last_synced = config_file.profiles.clone();
+        let mut app = AppState {
+            shared,
+            config_file,
+            config_issues,
+            migration_notice,
+            emitted_sample: (0, Instant::now()),
+            emitted_rate: 0.0,
+            single_worker_rate: None,
+            pick_target: PickTarget::Region,
+            pattern: Pattern::Grid { columns: 3, rows: 3 },
+            bench_report: Arc::new(Mutex::new(None)),
+            bench_started: Instant::now(),
+            macro_names: Vec::new(),
+            macro_name: String::new(),
+            macro_text: String::new(),
+            macro_loops: Some(1),
+            macro_loop_delay: 0,
+            macro_meta: Macro::default(),
+            macro_status: None,
+            record_clicks_only: true,
+            screen_size: screen::size(),
+            window_geometry,
+            window_placed: false,
+            heatmap_status: None,
+            stats_status: None,
+            telemetry_status: Arc::new(Mutex::new(None)),
+            overlay_port,
+            overlay_error: None,
+            chat_trigger,
+            mqtt,
+            webhook,
+            library: Vec::new(),
+            library_filter: String::new(),
+            renaming: None,
+            confirm_delete: None,
+            import: None,
+            profile_import: None,
+            history_browser: None,
+            profile_status: None,
+            sync_dir,
+            sync_status: None,
+            sound_status: None,
+            sync_state: SyncState::load(),
+            last_synced,
+            synced_clicks,
+            palette: None,
+            open_section: None,
+            scaling: false,
+            resolving_conflicts: false,
+            test_pad: TestPad::default(),
+            client_origin: None,
+        };
+        app.refresh_macros();
+        app
+    }
+
+    /// Re-reads the saved macros from disk.
+    fn refresh_macros(&mut self) {
+        self.macro_names = macros::list();
+        self.library = self.macro_names.iter().cloned().map(LibraryEntry::load).collect();
+    }
+
+    /// Loads a saved macro into the editor.
+    fn open_macro(&mut self, name: String) {
+        match macros::load_text(&name) {
+            Ok(text) => {
+                // Broken files are shown as they are, to be fixed in the editor.
+                let parsed = Macro::parse(&text);
+                let mac = parsed.as_ref().cloned().unwrap_or_default();
+                self.macro_text = if parsed.is_ok() { mac.steps_text() } else { text };
+                self.macro_loops = mac.loops;
+                self.macro_loop_delay = mac.loop_delay.as_millis() as u64;
+                self.macro_meta = mac;
+                self.macro_name = name;
+                self.macro_status = parsed.err();
+            }
+            Err(e) => self.macro_status = Some(format!("Failed to open {}: {}", name, e)),
+        }
+    }
+
+    /// Switches to a saved profile, keeping the current settings of the old one in memory.
+    fn switch_profile(&mut self, name: String, config: Config) {
+        self.shared.telemetry.record("profile_switch");
+        let current = self.shared.to_config();
+        let active = self.config_file.active_profile.clone();
+        self.config_file.profiles.insert(active, current);
+        self.config_file.profiles.insert(name.clone(), config.clone());
+        *self.shared.profile.lock().unwrap() = name.clone();
+        self.config_file.active_profile = name;
+        self.shared.apply_config(&config);
+    }
+
+    /// Writes the current settings into the active profile and saves config.toml.
+    fn save_config(&mut self) -> std::io::Result<()> {
+        let active = self.config_file.active_profile.clone();
+        self.config_file.profiles.insert(active, self.shared.to_config());
+        self.config_file.backend = *self.shared.backend.lock().unwrap();
+        self.config_file.max_cps = *self.shared.max_cps.lock().unwrap();
+        self.config_file.superspeed = *self.shared.superspeed.lock().unwrap();
+        self.config_file.pin_core = *self.shared.pin_core.lock().unwrap();
+        self.config_file.overlay_port = *self.shared.overlay_port.lock().unwrap();
+        self.config_file.telemetry = self.shared.telemetry.enabled.load(Ordering::SeqCst);
+        self.config_file.chat_trigger = self.shared.chat_trigger.lock().unwrap().clone();
+        self.config_file.typer = self.shared.typer.lock().unwrap().clone().filter(|typer| !typer.text.is_empty());
+        self.config_file.command_files = self.shared.command_files.load(Ordering::SeqCst);
+        self.config_file.verify_clicks = self.shared.receipts.enabled.load(Ordering::SeqCst);
+        self.config_file.scale_recordings = self.shared.scale_recordings.load(Ordering::SeqCst);
+        self.config_file.keep_awake = self.shared.keep_awake.load(Ordering::SeqCst);
+        self.config_file.pause_key = *self.shared.pause_key.lock().unwrap();
+        self.config_file.indicator = *self.shared.indicator.lock().unwrap();
+        self.config_file.listener_retry = *self.shared.listener_retry.lock().unwrap();
+        self.config_file.sound_theme = self.shared.sound_theme.lock().unwrap().name.clone();
+        self.config_file.mqtt = self.shared.mqtt.lock().unwrap().clone();
+        self.config_file.webhook = self.shared.webhook.lock().unwrap().clone();
+        self.config_file.hooks = self.shared.hooks.lock().unwrap().clone();
+        self.config_file.on_lock = *self.shared.lock_policy.lock().unwrap();
+        self.config_file.on_battery = *self.shared.battery_policy.lock().unwrap();
+        self.config_file.battery_cps = *self.shared.battery_cps.lock().unwrap();
+        self.config_file.idle_stop = *self.shared.idle_stop.lock().unwrap();
+        self.config_file.typing_pause = *self.shared.typing_pause.lock().unwrap();
+        let bindings = self.shared.bindings.lock().unwrap();
+        self.config_file.bindings = bindings.iter().filter(|b| !b.app.trim().is_empty()).cloned().collect();
+        self.config_file.title_rules = self.shared.title_rules.lock().unwrap().iter().filter(|r| r.is_valid()).cloned().collect();
+        let blocklist = self.shared.blocklist.lock().unwrap();
+        self.config_file.blocklist = blocklist.iter().map(|app| app.trim()).filter(|app| !app.is_empty()).map(str::to_string).collect();
+        let hotkeys = self.shared.macro_hotkeys.lock().unwrap();
+        self.config_file.macro_hotkeys = hotkeys.iter().filter(|h| !h.name.trim().is_empty()).cloned().collect();
+        self.config_file.sync_dir = self.shared.sync_dir.lock().unwrap().clone();
+        self.config_file.rollover = *self.shared.rollover.lock().unwrap();
+        self.config_file.window = self.window_geometry;
+        self.config_file.save()?;
+        match &self.config_file.sync_dir {
+            Some(dir) => sync::write_changed(dir, &self.config_file.profiles, &mut self.last_synced, &mut self.sync_state),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts syncing through the folder typed in, merging its profiles now.
+    fn start_sync(&mut self) {
+        let dir = PathBuf::from(self.sync_dir.trim());
+        let active = self.config_file.active_profile.clone();
+        self.config_file.profiles.insert(active.clone(), self.shared.to_config());
+        match sync::merge(&mut self.config_file, &dir, &mut self.sync_state) {
+            Ok(message) => {
+                self.shared.apply_config(&self.config_file.profiles[&active]);
+                self.last_synced = self.config_file.profiles.clone();
+                self.synced_clicks = sync::load_stats(&dir);
+                *self.shared.sync_dir.lock().unwrap() = Some(dir);
+                self.sync_status = Some(match self.save_config() {
+                    Ok(()) => message,
+                    Err(e) => format!("Failed to save config: {}", e),
+                });
+            }
+            Err(e) => self.sync_status = Some(e),
+        }
+    }
+
+    /// Remembers where the window is. On the first frame, a window restored
+    /// where no screen is anymore (a monitor was unplugged) is moved back.
+    fn track_window(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        let window = frame.info().window_info.clone();
+        let Some(pos) = window.position else { return };
+        if window.minimized || window.maximized || window.fullscreen {
+            return;
+        }
+        let native = frame.info().native_pixels_per_point.unwrap_or(1.0);
+        // Window info is in UI points, which include the UI scale.
+        let factor = f64::from(ctx.pixels_per_point() / native);
+        let geometry = Rect {
+            x: pos.x as f64 * factor,
+            y: pos.y as f64 * factor,
+            width: window.size.x as f64 * factor,
+            height: window.size.y as f64 * factor,
+        };
+        self.window_geometry = Some(geometry);
+        if std::mem::replace(&mut self.window_placed, true) {
+            return;
+        }
+        let Some((width, height)) = self.screen_size else { return };
+        let native = f64::from(native);
+        let screen = Rect { x: 0.0, y: 0.0, width: width as f64 / native, height: height as f64 / native };
+        let placed = geometry.moved_inside(screen);
+        if placed != geometry {
+            frame.set_window_pos(egui::pos2(placed.x as f32, placed.y as f32));
+            frame.set_window_size(egui::vec2((placed.width / factor) as f32, (placed.height / factor) as f32));
+            self.window_geometry = Some(placed);
+        }
+    }
+
+    /// Puts a finished recording into the editor.
+    fn finish_recording(&mut self) {
+        let Some(recording) = self.shared.recording.lock().unwrap().take() else { return };
+        let mac = recording.finish();
+        self.macro_status = Some(format!("Recorded {} steps.", mac.steps.len()));
+        self.macro_text = mac.steps_text();
+        // The coordinates are this screen's now.
+        self.macro_meta.resolution = screen::size();
+    }
+
+    /// Warns when coordinates made on a screen of size `made` are played on
+    /// this one, with the switch to scale them.
+    fn resolution_warning(&self, ui: &mut egui::Ui, made: Option<(u32, u32)>) {
+        let (Some(made), Some(here)) = (made, self.screen_size) else { return };
+        if made == here {
+            return;
+        }
+        ui.horizontal(|ui| {
+            let warning = egui::Color32::from_rgb(220, 140, 0);
+            ui.colored_label(warning, format!("Made for {}×{}; this screen is {}×{}.", made.0, made.1, here.0, here.1));
+            let mut scale = self.shared.scale_recordings.load(Ordering::SeqCst);
+            ui.checkbox(&mut scale, "Scale to this screen")
+                .on_hover_text("Move every point in proportion to the screen size, for macros and click sequences");
+            self.shared.scale_recordings.store(scale, Ordering::SeqCst);
+        });
+    }
+
+    /// The macro in the editor, with the playback settings from the controls.
+    fn edited_macro(&self) -> Result<Macro, String> {
+        let steps = Macro::parse(&self.macro_text)?.steps;
+        let loop_delay = Duration::from_millis(self.macro_loop_delay);
+        Ok(Macro { steps, loops: self.macro_loops, loop_delay, ..self.macro_meta.clone() })
+    }
+
+    /// Import dialog for shared macro files.
+    fn show_import(&mut self, ctx: &egui::Context) {
+        let Some(import) = &mut self.import else { return };
+        let mut open = !escape_pressed(ctx);
+        let mut imported = None;
+        egui::Window::new("Import macro").collapsible(false).open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File:");
+                ui.text_edit_singleline(&mut import.path).named("Macro file");
+                if ui.button("Read").clicked() {
+                    let read = std::fs::read_to_string(import.path.trim())
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| macro_json::from_json(&text));
+                    if let Ok((name, _)) = &read {
+                        import.name = name.clone();
+                    }
+                    import.read = Some(read);
+                }
+            });
+            match &import.read {
+                None => {}
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, format!("Can't import: {}", e));
+                }
+                Some(Ok((_, mac))) => {
+                    if !mac.author.is_empty() {
+                        ui.label(format!("By {}", mac.author));
+                    }
+                    if !mac.description.is_empty() {
+                        ui.label(&mac.description);
+                    }
+                    ui.label(format!("{} steps", mac.steps.len()));
+                    let here = screen::size();
+                    match (mac.resolution, here) {
+                        (Some(made), Some(here)) if made != here => {
+                            ui.label(format!("Made for {}×{}; this screen is {}×{}.", made.0, made.1, here.0, here.1));
+                            ui.checkbox(&mut import.scale, "Scale coordinates to this screen");
+                        }
+                        (None, _) => {
+                            ui.label("No screen size recorded; coordinates are used as they are.");
+                        }
+                        _ => {}
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Save as:");
+                        ui.text_edit_singleline(&mut import.name).named("Save macro as");
+                    });
+                    let name = import.name.trim();
+                    let exists = self.macro_names.iter().any(|n| n == name);
+                    if exists {
+                        ui.label("A macro with this name exists and will be replaced.");
+                    }
+                    if ui.add_enabled(macros::valid_name(name), egui::Button::new("Import")).clicked() {
+                        let mut mac = mac.clone();
+                        if let (true, Some(here)) = (import.scale, here) {
+                            mac.scale_to(here);
+                        }
+                        imported = Some((name.to_string(), mac));
+                        self.shared.telemetry.record("macro_import");
+                    }
+                }
+            }
+        });
+        if let Some((name, mac)) = imported {
+            self.macro_status = Some(match macros::save(&name, &mac) {
+                Ok(()) => {
+                    self.refresh_macros();
+                    self.open_macro(name.clone());
+                    self.import = None;
+                    format!("Imported {}.", name)
+                }
+                Err(e) => format!("Failed to save {}: {}", name, e),
+            });
+        } else if !open {
+            self.import = None;
+        }
+    }
+
+    /// Import dialog for shared profiles.
+    fn show_profile_import(&mut self, ctx: &egui::Context) {
+        let Some(import) = &mut self.profile_import else { return };
+        let mut open = !escape_pressed(ctx);
+        let mut imported = None;
+        egui::Window::new("Import profile").collapsible(false).open(&mut open).show(ctx, |ui| {
+            ui.label("Paste a share code or the path of an exported profile file:");
+            ui.add(egui::TextEdit::multiline(&mut import.text).desired_rows(3).hint_text("SSAC1:...")).named("Share code or file");
+            if ui.button("Read").clicked() {
+                let text = import.text.trim();
+                let path = Path::new(text);
+                let read = if path.is_file() {
+                    std::fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| config::import_profile(&text))
+                } else {
+                    config::import_profile(text)
+                };
+                if let Ok(shared) = &read {
+                    import.name = shared.name.clone();
+                }
+                import.read = Some(read);
+            }
+            match &import.read {
+                None => {}
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::RED, format!("Can't import: {}", e));
+                }
+                Some(Ok(SharedProfile { config, issues, .. })) => {
+                    let mode = if config.jiggle {
+                        "mouse jiggler".to_string()
+                    } else if config.fast_mode {
+                        "superspeed".to_string()
+                    } else {
+                        format!("{} CPS", config.target_cps)
+                    };
+                    ui.label(format!("Clicks at {}, toggled with {}.", mode, config.hotkey));
+                    if !issues.is_empty() {
+                        ui.colored_label(egui::Color32::from_rgb(220, 140, 0), "Some settings were adjusted or left at their defaults:");
+                        for issue in issues {
+                            ui.label(format!("• {}", issue));
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Save as:");
+                        ui.text_edit_singleline(&mut import.name).named("Save profile as");
+                    });
+                    let name = import.name.trim();
+                    if self.config_file.profiles.contains_key(name) {
+                        ui.label("A profile with this name exists and will be replaced.");
+                    }
+                    if ui.add_enabled(!name.is_empty(), egui::Button::new("Import")).clicked() {
+                        imported = Some((name.to_string(), config.clone()));
+                        self.shared.telemetry.record("profile_import");
+                    }
+                }
+            }
+        });
+        if let Some((name, config)) = imported {
+            self.switch_profile(name.clone(), config);
+            self.profile_import = None;
+            self.profile_status = Some(match self.save_config() {
+                Ok(()) => format!("Imported {}.", name),
+                Err(e) => format!("Imported {}, but failed to save config: {}", name, e),
+            });
+        } else if !open {
+            self.profile_import = None;
+        }
+    }
+
+    /// Collapsible section `name`, opened if the palette asked for it.
+    fn section(&mut self, name: &'static str) -> egui::CollapsingHeader {
+        let header = egui::CollapsingHeader::new(name);
+        if self.open_section == Some(name) {
+            self.open_section = None;
+            return header.open(Some(true));
+        }
+        header
+    }
+
+    /// Everything the palette can do right now.
+    fn commands(&self) -> Vec<Command> {
+        let clicking = self.shared.clicking.load(Ordering::SeqCst);
+        let mut commands = vec![
+            Command::new(if clicking { "Stop clicking" } else { "Start clicking" }, Action::ToggleClicking),
+            Command::new("Mode: Superspeed", Action::Superspeed),
+            Command::new("Mode: Consistent Rate", Action::ConsistentRate),
+            Command::new("Mode: Mouse Jiggler", Action::MouseJiggler),
+            Command::new("Save config", Action::SaveConfig),
+            Command::new("Import profile", Action::ImportProfile),
+            Command::new("Copy profile share code", Action::CopyProfileCode),
+        ];
+        for name in self.config_file.profiles.keys().filter(|name| **name != self.config_file.active_profile) {
+            commands.push(Command::new(format!("Switch to profile {}", name), Action::SwitchProfile(name.clone())));
+        }
+        if self.shared.macro_playing.load(Ordering::SeqCst) {
+            commands.push(Command::new("Stop macro", Action::StopMacro));
+        } else {
+            for name in &self.macro_names {
+                commands.push(Command::new(format!("Play macro {}", name), Action::PlayMacro(name.clone())));
+            }
+        }
+        for section in palette::SECTIONS {
+            commands.push(Command::new(format!("Open {}", section), Action::OpenSection(section)));
+        }
+        commands
+    }
+
+    fn run_command(&mut self, ctx: &egui::Context, action: Action) {
+        self.shared.telemetry.record("palette");
+        match action {
+            Action::ToggleClicking => {
+                self.shared.clicking.fetch_xor(true, Ordering::SeqCst);
+            }
+            Action::Superspeed | Action::ConsistentRate => {
+                self.shared.fast_mode.store(action == Action::Superspeed, Ordering::SeqCst);
+                self.shared.jiggle.store(false, Ordering::SeqCst);
+            }
+            Action::MouseJiggler => self.shared.jiggle.store(true, Ordering::SeqCst),
+            Action::SaveConfig => match self.save_config() {
+                Ok(()) => self.shared.log.push("Config saved"),
+                Err(e) => self.shared.log.push(format!("Failed to save config: {}", e)),
+            },
+            Action::SwitchProfile(name) => {
+                if let Some(config) = self.config_file.profiles.get(&name).cloned() {
+                    self.switch_profile(name, config);
+                }
+            }
+            Action::ImportProfile => {
+                self.profile_import = Some(ProfileImport { text: String::new(), read: None, name: String::new() });
+            }
+            Action::CopyProfileCode => {
+                let code = config::share_code(&self.config_file.active_profile, &self.shared.to_config());
+                ctx.output_mut(|output| output.copied_text = code);
+                self.profile_status = Some("Share code copied to the clipboard.".to_string());
+            }
+            Action::PlayMacro(name) => match macros::load(&name) {
+                Ok(mac) => macros::spawn_player(self.shared.macro_handles(), name, mac, false),
+                Err(e) => self.shared.log.push(format!("Failed to open {}: {}", name, e)),
+            },
+            Action::StopMacro => self.shared.macro_playing.store(false, Ordering::SeqCst),
+            Action::OpenSection(name) => self.open_section = Some(name),
+        }
+    }
+
+    /// The Ctrl+K palette, if it is open.
+    fn show_palette(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::K)) {
+            self.palette = match self.palette {
+                Some(_) => None,
+                None => Some(Palette::default()),
+            };
+        }
+        let commands = self.commands();
+        let Some(palette) = &mut self.palette else { return };
+        let (up, down, enter, escape) = ctx.input_mut(|input| {
+            (
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                input.key_pressed(egui::Key::Enter),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let mut chosen = None;
+        egui::Window::new("Command palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .show(ctx, |ui| {
+                let field = ui.add(egui::TextEdit::singleline(&mut palette.query).hint_text("Type a command...").desired_width(320.0)).named("Command");
+                field.request_focus();
+                if field.changed() {
+                    palette.selected = 0;
+                }
+                let found = palette::search(commands, &palette.query);
+                let shown = found.len().min(PALETTE_RESULTS);
+                if down && palette.selected + 1 < shown {
+                    palette.selected += 1;
+                }
+                if up {
+                    palette.selected = palette.selected.saturating_sub(1);
+                }
+                if found.is_empty() {
+                    ui.label("No matching command");
+                }
+                for (index, command) in found.into_iter().take(shown).enumerate() {
+                    if ui.selectable_label(index == palette.selected, &command.label).clicked() || (enter && index == palette.selected) {
+                        chosen = Some(command.action);
+                    }
+                }
+            });
+        if escape || chosen.is_some() {
+            self.palette = None;
+        }
+        if let Some(action) = chosen {
+            self.run_command(ctx, action);
+        }
+    }
+
+    /// Startup dialog listing problems found in config.toml.
+    /// Area counting the clicks it receives, which the engine may click even
+    /// though it is inside this window. Returns its screen rect while visible.
+    fn show_test_pad(&mut self, ui: &mut egui::Ui) -> Option<Rect> {
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 120.0), egui::Sense::hover());
+        let visible = rect.intersect(ui.clip_rect());
+        let now = Instant::now();
+        let pad = &mut self.test_pad;
+        ui.input(|input| {
+            for event in &input.events {
+                if let egui::Event::PointerButton { pos, button, pressed: true, .. } = event {
+                    let index = match button {
+                        egui::PointerButton::Primary => 0,
+                        egui::PointerButton::Middle => 1,
+                        egui::PointerButton::Secondary => 2,
+                        _ => continue,
+                    };
+                    if visible.contains(*pos) {
+                        pad.clicks[index] += 1;
+                        pad.recent.push(now);
+                    }
+                }
+            }
+        });
+        pad.recent.retain(|&t| now.duration_since(t) <= Duration::from_secs(1));
+        let total: u64 = pad.clicks.iter().sum();
+        let painter = ui.painter();
+        painter.rect_filled(rect, 4.0, ui.visuals().extreme_bg_color);
+        let text = if total == 0 { "Test pad: point here and start clicking".to_string() } else { format!("{} clicks", total) };
+        painter.text(rect.center(), egui::Align2::CENTER_CENTER, text, egui::FontId::proportional(18.0), ui.visuals().text_color());
+        response.named("Test pad");
+        ui.horizontal(|ui| {
+            let [left, middle, right] = pad.clicks;
+            ui.label(format!("Left {} · Middle {} · Right {} · {} CPS", left, middle, right, pad.recent.len()));
+            if total > 0 && ui.small_button("Reset").clicked() {
+                *pad = TestPad::default();
+            }
+        });
+        let origin = self.client_origin?;
+        let scale = ui.ctx().pixels_per_point() as f64;
+        (visible.width() > 0.0 && visible.height() > 0.0).then(|| Rect {
+            x: origin.0 + visible.min.x as f64 * scale,
+            y: origin.1 + visible.min.y as f64 * scale,
+            width: visible.width() as f64 * scale,
+            height: visible.height() as f64 * scale,
+        })
+    }
+
+    /// Keys of the settings being edited.
+    fn bindings(&self) -> Vec<Binding> {
+        let typer = self.shared.typer.lock().unwrap().clone();
+        let macro_hotkeys = self.shared.macro_hotkeys.lock().unwrap().clone();
+        let pause_key = *self.shared.pause_key.lock().unwrap();
+        hotkeys::bindings(&self.shared.to_config(), typer.as_ref(), pause_key, &macro_hotkeys)
+    }
+
+    /// Moves `slot` to `key`; `None` turns an optional slot off.
+    fn rebind(&self, slot: Slot, key: Option<rdev::Key>) {
+        let shared = &self.shared;
+        match (slot, key) {
+            (Slot::CpsUp, key) => *shared.cps_up_key.lock().unwrap() = key,
+            (Slot::CpsDown, key) => *shared.cps_down_key.lock().unwrap() = key,
+            (Slot::PressKey, key) => *shared.press_key.lock().unwrap() = key,
+            (Slot::HoldKey, key) => *shared.hold_key.lock().unwrap() = key,
+            (Slot::Chord, key) => *shared.chord_key.lock().unwrap() = key,
+            (Slot::PauseAll, key) => *shared.pause_key.lock().unwrap() = key,
+            (_, None) => {}
+            (Slot::Toggle, Some(key)) => *shared.hotkey.lock().unwrap() = key,
+            (Slot::Abort, Some(key)) => *shared.abort_key.lock().unwrap() = key,
+            (Slot::Step, Some(key)) => *shared.step_key.lock().unwrap() = key,
+            (Slot::Typer, Some(key)) => {
+                if let Some(typer) = shared.typer.lock().unwrap().as_mut() {
+                    typer.hotkey = key;
+                }
+            }
+            (Slot::Macro(index), Some(key)) => {
+                if let Some(hotkey) = shared.macro_hotkeys.lock().unwrap().get_mut(index) {
+                    hotkey.key = key;
+                }
+            }
+        }
+    }
+
+    /// Summary of the run that just ended, until dismissed.
+    fn show_run_summary(&mut self, ctx: &egui::Context) {
+        let Some(run) = self.shared.last_run.lock().unwrap().clone() else { return };
+        let mut open = !escape_pressed(ctx);
+        let mut close = false;
+        egui::Window::new("Run summary").collapsible(false).resizable(false).open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("run_summary").show(ui, |ui| {
+                for (label, text) in run_rows(&run) {
+                    ui.label(label);
+                    ui.label(text);
+                    ui.end_row();
+                }
+            });
+            close = ui.button("Close").clicked();
+        });
+        if !open || close {
+            *self.shared.last_run.lock().unwrap() = None;
+        }
+    }
+
+    fn show_history_browser(&mut self, ctx: &egui::Context) {
+        let Some(browser) = &mut self.history_browser else { return };
+        let history = self.shared.history.lock().unwrap();
+        let mut profiles: Vec<&str> = history.runs.iter().map(|run| run.profile.as_str()).collect();
+        profiles.sort();
+        profiles.dedup();
+        let mut open = !escape_pressed(ctx);
+        egui::Window::new("Run history").collapsible(false).open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Profile:");
+                egui::ComboBox::from_id_source("history_profile")
+                    .selected_text(browser.profile.as_deref().unwrap_or("All"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut browser.profile, None, "All");
+                        for profile in &profiles {
+                            ui.selectable_value(&mut browser.profile, Some(profile.to_string()), *profile);
+                        }
+                    }).response.named("Profile of the runs listed");
+                ui.label("Date:");
+                ui.add(egui::TextEdit::singleline(&mut browser.date).hint_text("YYYY-MM-DD").desired_width(90.0))
+                    .on_hover_text("Runs started on a day, or in a month or year, like 2026-10")
+                    .named("Date of the runs listed");
+            });
+            let listed: Vec<usize> = (0..history.runs.len())
+                .rev()
+                .filter(|&index| history.runs[index].matches(browser.profile.as_deref(), &browser.date))
+                .collect();
+            ui.label(format!("{} of {} runs", listed.len(), history.runs.len()));
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for &index in &listed {
+                    let run = &history.runs[index];
+                    let text = format!(
+                        "{} · {} · {} · {} clicks · {:.1} CPS",
+                        history::format_time(run.started),
+                        run.profile,
+                        stats::format_duration(run.duration),
+                        stats::format_count(run.clicks),
+                        run.average_cps()
+                    );
+                    if ui.selectable_label(browser.selected == Some(index), text).clicked() {
+                        browser.selected = Some(index);
+                    }
+                }
+            });
+            if let Some(run) = browser.selected.and_then(|index| history.runs.get(index)) {
+                ui.separator();
+                egui::Grid::new("run_detail").show(ui, |ui| {
+                    for (label, text) in run_rows(run) {
+                        ui.label(label);
+                        ui.label(text);
+                        ui.end_row();
+                    }
+                });
+                if run.curve.is_empty() {
+                    ui.label("No CPS curve was kept for this run.");
+                } else {
+                    paint_curve(ui, run);
+                }
+            }
+        });
+        drop(history);
+        if !open {
+            self.history_browser = None;
+        }
+    }
+
+    /// Lets each action of a conflict be moved to a key nothing else uses.
+    fn show_conflicts(&mut self, ctx: &egui::Context, conflicts: &[Conflict]) {
+        if conflicts.is_empty() {
+            self.resolving_conflicts = false;
+        }
+        if !self.resolving_conflicts {
+            return;
+        }
+        let used: Vec<rdev::Key> = self.bindings().iter().map(|binding| binding.key).collect();
+        let mut open = !escape_pressed(ctx);
+        let mut rebound = None;
+        egui::Window::new("Hotkey conflicts").collapsible(false).open(&mut open).show(ctx, |ui| {
+            ui.label("Move all but one action of each key to another key:");
+            for conflict in conflicts {
+                ui.separator();
+                for binding in &conflict.bindings {
+                    ui.horizontal(|ui| {
+                        let mut label = binding.label.clone();
+                        label[..1].make_ascii_uppercase();
+                        ui.label(format!("{}:", label));
+                        egui::ComboBox::from_id_source(("conflict", binding.slot))
+                            .selected_text(display_name(&binding.key))
+                            .show_ui(ui, |ui| {
+                                if binding.slot.optional() && ui.selectable_label(false, "Off").clicked() {
+                                    rebound = Some((binding.slot, None));
+                                }
+                                for key in SUPPORTED_KEYS.iter().filter(|key| !used.contains(key)) {
+                                    if ui.selectable_label(false, display_name(key)).clicked() {
+                                        rebound = Some((binding.slot, Some(*key)));
+                                    }
+                                }
+                            }).response.named(&label);
+                    });
+                }
+            }
+        });
+        if let Some((slot, key)) = rebound {
+            self.rebind(slot, key);
+        }
+        self.resolving_conflicts = open;
+    }
+
+    fn show_config_issues(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Config problems")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label("The config contains settings that could not be used:");
+                for issue in &self.config_issues {
+                    ui.label(format!("• {}", issue));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Fix").on_hover_text("Save the corrected values back to config.toml").clicked() {
+                        if let Err(e) = self.save_config() {
+                            eprintln!("Failed to save config: {}", e);
+                        }
+                        self.config_issues.clear();
+                    }
+                    if ui.button("Reset").on_hover_text("Overwrite config.toml with the defaults").clicked() {
+                        self.config_file = ConfigFile::default();
+                        self.shared.apply_config(&Config::default());
+                        self.shared.bindings.lock().unwrap().clear();
+                        self.shared.title_rules.lock().unwrap().clear();
+                        self.shared.macro_hotkeys.lock().unwrap().clear();
+                        *self.shared.backend.lock().unwrap() = self.config_file.backend;
+                        *self.shared.max_cps.lock().unwrap() = self.config_file.max_cps;
+                        if let Err(e) = self.config_file.save() {
+                            eprintln!("Failed to save config: {}", e);
+                        }
+                        self.config_issues.clear();
+                    }
+                    if ui.button("Ignore").clicked() {
+                        self.config_issues.clear();
+                    }
+                });
+            });
+    }
+}
+
+/// Heatmap preview scaled to the screen, or to the clicked area if the screen size is unknown.
+fn paint_heatmap(ui: &mut egui::Ui, heatmap: &Heatmap, screen_size: Option<(u32, u32)>) {
+    let cells = heatmap.cells();
+    let (origin, size) = match screen_size {
+        Some((width, height)) => ((0.0, 0.0), (width as f64, height as f64)),
+        None => {
+            let min = cells.iter().fold((f64::MAX, f64::MAX), |m, ((x, y), _)| (m.0.min(*x), m.1.min(*y)));
+            let max = cells.iter().fold((f64::MIN, f64::MIN), |m, ((x, y), _)| (m.0.max(*x), m.1.max(*y)));
+            (min, (max.0 - min.0 + HEAT_CELL, max.1 - min.1 + HEAT_CELL))
+        }
+    };
+    let width = 320.0_f32;
+    let scale = width / size.0 as f32;
+    let (response, painter) = ui.allocate_painter(egui::vec2(width, size.1 as f32 * scale), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(24));
+    let cell = HEAT_CELL as f32 * scale;
+    for ((x, y), heat) in cells {
+        let [r, g, b] = stats::heat_color(heat);
+        let min = rect.min + egui::vec2((x - origin.0) as f32 * scale, (y - origin.1) as f32 * scale);
+        let cell_rect = egui::Rect::from_min_size(min, egui::vec2(cell.max(1.0), cell.max(1.0)));
+        painter.rect_filled(cell_rect.intersect(rect), 0.0, egui::Color32::from_rgb(r, g, b));
+    }
+}
+
+/// Bar chart of the click intervals, with the range of each bar on hover.
+fn paint_intervals(ui: &mut egui::Ui, intervals: &Intervals) {
+    const BARS: u64 = 60;
+    let bars = intervals.bars(BARS);
+    let highest = bars.iter().map(|bar| bar.2).max().unwrap_or(1).max(1);
+    let (response, painter) = ui.allocate_painter(egui::vec2(320.0, 100.0), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    let width = rect.width() / bars.len() as f32;
+    let mut hovered = None;
+    for (index, &(first, last, count)) in bars.iter().enumerate() {
+        let height = rect.height() * count as f32 / highest as f32;
+        let left = rect.left() + index as f32 * width;
+        let column = egui::Rect::from_x_y_ranges(left..=left + width, rect.top()..=rect.bottom());
+        let bar = egui::Rect::from_x_y_ranges(left..=left + (width - 1.0).max(1.0), rect.bottom() - height..=rect.bottom());
+        painter.rect_filled(bar, 0.0, ui.visuals().selection.bg_fill);
+        if response.hover_pos().is_some_and(|pos| column.contains(pos)) {
+            hovered = Some((first, last, count));
+        }
+    }
+    let (first, last) = (bars[0].0, bars[bars.len() - 1].1);
+    let response = response.named(&format!("Click interval histogram from {} to {} ms", first, last));
+    if let Some((first, last, count)) = hovered {
+        let range = if first == last { format!("{} ms", first) } else { format!("{}-{} ms", first, last) };
+        response.on_hover_text(format!("{}: {} intervals", range, count));
+    }
+    ui.horizontal(|ui| {
+        ui.label(format!("{} ms", first));
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| ui.label(format!("{} ms", last)));
+    });
+}
+
+/// CPS over the course of `run`.
+fn paint_curve(ui: &mut egui::Ui, run: &Run) {
+    let highest = run.curve.iter().copied().fold(1.0, f64::max);
+    let (response, painter) = ui.allocate_painter(egui::vec2(320.0, 100.0), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    let step = rect.width() / run.curve.len().saturating_sub(1).max(1) as f32;
+    let points: Vec<egui::Pos2> = run
+        .curve
+        .iter()
+        .enumerate()
+        .map(|(index, &cps)| egui::pos2(rect.left() + index as f32 * step, rect.bottom() - rect.height() * (cps / highest) as f32))
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, ui.visuals().selection.bg_fill)));
+    let response = response.named(&format!("CPS curve, peak {:.1} CPS", highest));
+    if let Some(pos) = response.hover_pos() {
+        let index = (((pos.x - rect.left()) / step).round().max(0.0) as usize).min(run.curve.len() - 1);
+        let at = run.curve_step * index as u32;
+        response.on_hover_text(format!("After {}: {:.1} CPS", stats::format_duration(at), run.curve[index]));
+    }
+    ui.horizontal(|ui| {
+        ui.label("Start");
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| ui.label(stats::format_duration(run.duration)));
+    });
+}
+
+fn write_heatmap_png(heatmap: &Heatmap, path: &Path) -> Result<(), String> {
+    let (width, height, pixels) = heatmap.render().ok_or("no clicks recorded")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())
+}
+
+/// Editor text with the line of step `step` (0-based) highlighted. Blank and
+/// `key = value` lines are not steps.
+fn step_highlight(ui: &egui::Ui, text: &str, step: Option<usize>) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let plain = egui::TextFormat { font_id: font_id.clone(), color, ..Default::default() };
+    let current = egui::TextFormat { font_id, color, background: ui.visuals().selection.bg_fill, ..Default::default() };
+    let mut job = egui::text::LayoutJob::default();
+    let mut index = 0;
+    for line in text.split_inclusive('\n') {
+        let is_step = !line.trim().is_empty() && !line.contains('=');
+        let format = if is_step && step == Some(index) { current.clone() } else { plain.clone() };
+        job.append(line, 0.0, format);
+        index += usize::from(is_step);
+    }
+    job
+}
+
+/// `mac`, noting this screen's size if it doesn't say which screen it was made for.
+fn with_resolution(mac: &Macro) -> Macro {
+    Macro { resolution: mac.resolution.or_else(screen::size), ..mac.clone() }
+}
+
+impl App for AppState {
+    fn on_close_event(&mut self) -> bool {
+        // Only the window geometry is saved on exit; other changes wait for Save Config.
+        let (mut file, issues) = ConfigFile::load().unwrap_or_default();
+        if issues.is_empty() && file.window != self.window_geometry {
+            file.window = self.window_geometry;
+            if let Err(e) = file.save() {
+                eprintln!("Failed to save the window position: {}", e);
+            }
+        }
+        true
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        // Tell the engine where our window is so it doesn't click on it.
+        let window = &frame.info().window_info;
+        let (own_window, client_origin) = match window.position {
+            Some(pos) if !window.minimized => {
+                let scale = ctx.pixels_per_point() as f64;
+                // The title bar follows the system's scaling, not the UI scale.
+                let title_bar = TITLE_BAR_HEIGHT * frame.info().native_pixels_per_point.unwrap_or(1.0);
+                let rect = Rect {
+                    x: pos.x as f64 * scale,
+                    y: pos.y as f64 * scale,
+                    width: window.size.x as f64 * scale,
+                    height: window.size.y as f64 * scale + title_bar as f64,
+                };
+                (Some(rect), Some((rect.x, rect.y + title_bar as f64)))
+            }
+            _ => (None, None),
+        };
+        *self.shared.own_window.lock().unwrap() = own_window;
+        self.client_origin = client_origin;
+        self.track_window(ctx, frame);
+
+        // Follow the OS dark/light preference (eframe tracks its changes) unless a theme is picked.
+        let dark = match self.config_file.theme {
+            ThemeMode::System => frame.info().system_theme != Some(eframe::Theme::Light),
+            ThemeMode::Dark => true,
+            ThemeMode::Light => false,
+        };
+        if ctx.style().visuals.dark_mode != dark {
+            ctx.set_visuals(if dark { egui::Visuals::dark() } else { egui::Visuals::light() });
+        }
+
+        let scale = frame.info().native_pixels_per_point.unwrap_or(1.0) * self.config_file.ui_scale;
+        if !self.scaling && (ctx.pixels_per_point() - scale).abs() > 0.001 {
+            ctx.set_pixels_per_point(scale);
+        }
+
+        // Every text style keeps its size relative to body text.
+        let body = ctx.style().text_styles.get(&egui::TextStyle::Body).map(|font| font.size);
+        if body.is_some_and(|size| (size - self.config_file.font_size).abs() > 0.001) {
+            let factor = self.config_file.font_size / DEFAULT_FONT_SIZE;
+            let mut style = (*ctx.style()).clone();
+            style.text_styles = egui::style::default_text_styles()
+                .into_iter()
+                .map(|(text_style, font)| (text_style, egui::FontId::new(font.size * factor, font.family)))
+                .collect();
+            ctx.set_style(style);
+        }
+
+        // Finish picking a region once both corners have been clicked, or the wait pixel once it has.
+        let needed = if self.pick_target == PickTarget::WaitPixel { 1 } else { 2 };
+        let picked = {
+            let mut picked = self.shared.picked_points.lock().unwrap();
+            if picked.as_ref().is_some_and(|points| points.len() >= needed) { picked.take() } else { None }
+        };
+        match picked {
+            Some(points) if self.pick_target == PickTarget::WaitPixel => {
+                let mut wait = self.shared.sequence_wait.lock().unwrap();
+                let timeout = wait.map_or(Duration::from_secs(5), |wait| wait.timeout);
+                *wait = Some(SequenceWait { at: points[0], timeout });
+            }
+            Some(points) => {
+                let rect = Rect::from_corners(points[0], points[1]);
+                if rect.width < 1.0 || rect.height < 1.0 {
+                    self.shared.log.push("Picked area too small, click two opposite corners");
+                } else if self.pick_target == PickTarget::Region {
+                    *self.shared.region.lock().unwrap() = Some(rect);
+                } else {
+                    self.shared.exclusions.lock().unwrap().push(rect);
+                }
+            }
+            None => {}
+        }
+
+        // The `quit` command closes the window as if the user had.
+        if self.shared.quit.load(Ordering::SeqCst) {
+            frame.close();
+        }
+
+        let pending = self.shared.pending_profile.lock().unwrap().take();
+        if let Some((name, config)) = pending {
+            self.switch_profile(name, config);
+        }
+        // The control channel switches between the profiles as they are here.
+        {
+            let mut profiles = self.shared.profiles.lock().unwrap();
+            if *profiles != self.config_file.profiles {
+                profiles.clone_from(&self.config_file.profiles);
+            }
+        }
+
+        // Remove old clicks (older than 1 sec) for CPS count.
+        let now = Instant::now();
+        {
+            let mut clicks = self.shared.clicks.lock().unwrap();
+            clicks.retain(|&t| now.duration_since(t) <= Duration::from_secs(1));
+        }
+        let cps = self.shared.clicks.lock().unwrap().len() as f64;
+
+        // Sample the engine's own output rate twice a second.
+        let (last_count, last_time) = self.emitted_sample;
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+        if elapsed >= 0.5 {
+            let count = self.shared.emitted.load(Ordering::Relaxed);
+            self.emitted_rate = (count - last_count) as f64 / elapsed;
+            self.emitted_sample = (count, now);
+            let superspeed = self.shared.clicking.load(Ordering::SeqCst) && self.shared.fast_mode.load(Ordering::SeqCst);
+            if superspeed && self.shared.workers.load(Ordering::SeqCst) == 1 && self.emitted_rate > 0.0 {
+                self.single_worker_rate = Some(self.emitted_rate);
+            }
+        }
+
+        if !self.config_issues.is_empty() {
+            self.show_config_issues(ctx);
+        }
+        // The palette goes first so the dialogs below it don't see its Escape.
+        self.show_palette(ctx);
+        self.show_import(ctx);
+        self.show_profile_import(ctx);
+        self.show_run_summary(ctx);
+        self.show_history_browser(ctx);
+        let conflicts = hotkeys::conflicts(self.bindings());
+        self.show_conflicts(ctx, &conflicts);
+        if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::S)) {
+            self.run_command(ctx, Action::SaveConfig);
+        }
+
+        // Set while the test pad is on screen.
+        let mut test_pad = None;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Scrolls once the content outgrows the window, e.g. at a larger UI scale.
+            egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                ui.heading("Superspeed Autoclicker");
+
+                if let Some(notice) = &self.migration_notice {
+                    let mut dismissed = false;
+                    ui.horizontal_wrapped(|ui| {
+                        match notice {
+                            Ok(msg) => ui.label(msg),
+                            Err(msg) => ui.colored_label(egui::Color32::RED, msg),
+                        };
+                        dismissed = ui.small_button("OK").clicked();
+                    });
+                    if dismissed {
+                        self.migration_notice = None;
+                    }
+                    ui.separator();
+                }
+
+                if self.shared.watchdog.stalled() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.colored_label(
+                            egui::Color32::RED,
+                            "The click engine stopped responding; a backend call may be stuck.",
+                        );
+                        if ui.button("Restart engine").clicked() {
+                            self.shared.watchdog.request_restart();
+                        }
+                    });
+                    ui.separator();
+                }
+
+                // Profile selection.
+                ui.horizontal(|ui| {
+                    ui.label("Profile:");
+                    let mut selected = self.config_file.active_profile.clone();
+                    egui::ComboBox::from_id_source("profile")
+                        .selected_text(selected.clone())
+                        .show_ui(ui, |ui| {
+                            for name in self.config_file.profiles.keys() {
+                                ui.selectable_value(&mut selected, name.clone(), name);
+                            }
+                        }).response.named("Profile");
+                    if selected != self.config_file.active_profile {
+                        let config = self.config_file.profiles[&selected].clone();
+                        self.switch_profile(selected, config);
+                    }
+                    let name = self.config_file.active_profile.clone();
+                    if ui.button("Export").on_hover_text("Write the profile to a file others can import").clicked() {
+                        self.shared.telemetry.record("profile_export");
+                        let path = config_dir().join(format!("{}.{}", name, config::PROFILE_EXTENSION));
+                        let written = std::fs::create_dir_all(config_dir())
+                            .and_then(|_| std::fs::write(&path, config::export_profile(&name, &self.shared.to_config())));
+                        self.profile_status = Some(match written {
+                            Ok(()) => format!("Exported to {}", path.display()),
+                            Err(e) => format!("Failed to export {}: {}", name, e),
+                        });
+                    }
+                    if ui.button("Copy code").on_hover_text("Copy the profile as one line of text").clicked() {
+                        self.shared.telemetry.record("profile_export");
+                        let code = config::share_code(&name, &self.shared.to_config());
+                        ui.output_mut(|output| output.copied_text = code);
+                        self.profile_status = Some("Share code copied to the clipboard.".to_string());
+                    }
+                    if ui.button("Import...").on_hover_text("Add a profile from a share code or file").clicked() {
+                        self.profile_import = Some(ProfileImport { text: String::new(), read: None, name: String::new() });
+                    }
+                });
+                if let Some(status) = &self.profile_status {
+                    ui.label(status);
+                }
+                ui.separator();
+
+                // Autoclicker toggle.
+                ui.horizontal(|ui| {
+                    let state_str = if self.shared.watchdog.stalled() {
+                        "Not responding"
+                    } else if self.shared.clicking.load(Ordering::SeqCst) {
+                        "Running"
+                    } else {
+                        "Stopped"
+                    };
+                    ui.label(format!("Autoclicker: {}", state_str));
+                    if ui.button("Toggle Autoclicker").clicked() {
+                        let new_state = !self.shared.clicking.load(Ordering::SeqCst);
+                        self.shared.clicking.store(new_state, Ordering::SeqCst);
+                    }
+                });
+                if let Some(drops) = *self.shared.dropped.lock().unwrap() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(220, 140, 0),
+                        format!("{:.0}% of clicks dropped: the system delivers about {:.0} CPS", drops.share * 100.0, drops.delivered_cps),
+                    );
+                }
+                ui.separator();
+
+                // Hotkey section.
+                if cfg!(feature = "listener") {
+                    let current_hotkey = {
+                        let hk = self.shared.hotkey.lock().unwrap();
+                        display_name(&hk)
+                    };
+                    ui.label(format!("Start/Stop Key: {}", current_hotkey));
+                    if let Some(error) = self.shared.listener_error.lock().unwrap().clone() {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(egui::Color32::RED, "Global hotkeys unavailable").on_hover_text(error);
+                            if ui.button("Retry now").clicked() {
+                                self.shared.retry_listener.store(true, Ordering::SeqCst);
+                            }
+                        });
+                    }
+                    if self.shared.changing_hotkey.load(Ordering::SeqCst) {
+                        let rejected = *self.shared.rejected_key.lock().unwrap();
+                        match rejected {
+                            Some(key) => ui.label(format!(
+                                "{} can't be used as a hotkey. Press another key...",
+                                display_name(&key)
+                            )),
+                            None => ui.label("Press a key to set as new hotkey..."),
+                        };
+                        if ui.button("Cancel").clicked() {
+                            self.shared.changing_hotkey.store(false, Ordering::SeqCst);
+                        }
+                    } else if ui.button("Change start/stop key").clicked() {
+                        *self.shared.rejected_key.lock().unwrap() = None;
+                        self.shared.changing_hotkey.store(true, Ordering::SeqCst);
+                    }
+                    if !conflicts.is_empty() {
+                        for conflict in &conflicts {
+                            ui.colored_label(egui::Color32::from_rgb(220, 140, 0), conflict.to_string());
+                        }
+                        if ui.button("Resolve...").on_hover_text("Move the clashing actions to free keys").clicked() {
+                            self.resolving_conflicts = true;
+                        }
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Activation:");
+                        let mut activation = self.shared.activation.lock().unwrap();
+                        egui::ComboBox::from_id_source("activation")
+                            .selected_text(activation_label(*activation))
+                            .show_ui(ui, |ui| {
+                                for option in Activation::ALL {
+                                    ui.selectable_value(&mut *activation, option, activation_label(option));
+                                }
+                            }).response.named("Activation");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Chord:");
+                        let mut chord_key = self.shared.chord_key.lock().unwrap();
+                        egui::ComboBox::from_id_source("chord_key")
+                            .selected_text(chord_key.as_ref().map_or("Off".to_string(), display_name))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut *chord_key, None, "Off");
+                                for key in SUPPORTED_KEYS {
+                                    ui.selectable_value(&mut *chord_key, Some(*key), display_name(key));
+                                }
+                            }).response.named("First key of the start/stop chord")
+                            .on_hover_text(format!("The start/stop key only counts within {} s of pressing this one first", CHORD_WINDOW.as_secs()));
+                        if chord_key.is_some() {
+                            ui.label(format!("then {}", display_name(&self.shared.hotkey.lock().unwrap())));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Turbo:");
+                        let mut turbo = self.shared.turbo_button.lock().unwrap();
+                        let label = |button: Option<ClickButton>| match button {
+                            Some(ClickButton::Left) => "Hold left button",
+                            Some(ClickButton::Middle) => "Hold middle button",
+                            Some(ClickButton::Right) => "Hold right button",
+                            None => "Off",
+                        };
+                        egui::ComboBox::from_id_source("turbo_button")
+                            .selected_text(label(*turbo))
+                            .show_ui(ui, |ui| {
+                                for button in [None, Some(ClickButton::Left), Some(ClickButton::Middle), Some(ClickButton::Right)] {
+                                    // Its own clicks would let go of a turbo button it clicks too.
+                                    let clicked = button.is_some_and(|button| self.shared.selected_buttons.lock().unwrap().contains(&button.into()));
+                                    ui.add_enabled_ui(!clicked, |ui| ui.selectable_value(&mut *turbo, button, label(button)))
+                                        .response
+                                        .on_disabled_hover_text("Clicked by the autoclicker");
+                                }
+                            }).response.named("Turbo button")
+                            .on_hover_text("Clicks for as long as this mouse button is held down, and stops when it is let go");
+                        if turbo.is_some_and(|button| self.shared.selected_buttons.lock().unwrap().contains(&button.into())) {
+                            ui.colored_label(ui.visuals().warn_fg_color, "Off while the autoclicker clicks this button");
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Pause all:");
+                        let mut pause_key = self.shared.pause_key.lock().unwrap();
+                        egui::ComboBox::from_id_source("pause_key")
+                            .selected_text(pause_key.as_ref().map_or("Off".to_string(), display_name))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut *pause_key, None, "Off");
+                                for key in SUPPORTED_KEYS {
+                                    ui.selectable_value(&mut *pause_key, Some(*key), display_name(key));
+                                }
+                            }).response.named("Pause-all key")
+                            .on_hover_text("Pauses clicking, the playing macro and the typer at once; press again to resume what was running");
+                    });
+                } else {
+                    ui.label("Global hotkeys are not available in this build.");
+                }
+                ui.separator();
+
+                // Click rate mode.
+                let fast_mode = self.shared.fast_mode.load(Ordering::SeqCst);
+                let jiggle = self.shared.jiggle.load(Ordering::SeqCst);
+                ui.horizontal(|ui| {
+                    ui.label("Click Rate Mode:");
+                    if ui.radio(fast_mode && !jiggle, "Superspeed").clicked() {
+                        self.shared.fast_mode.store(true, Ordering::SeqCst);
+                        self.shared.jiggle.store(false, Ordering::SeqCst);
+                    }
+                    if ui.radio(!fast_mode && !jiggle, "Consistent Rate").clicked() {
+                        self.shared.fast_mode.store(false, Ordering::SeqCst);
+                        self.shared.jiggle.store(false, Ordering::SeqCst);
+                    }
+                    if ui.radio(jiggle, "Mouse Jiggler")
+                        .on_hover_text("Nudge the mouse by a pixel now and then without clicking, to stay out of idle and AFK timeouts")
+                        .clicked()
+                    {
+                        self.shared.jiggle.store(true, Ordering::SeqCst);
+                    }
+                });
+                if self.shared.jiggle.load(Ordering::SeqCst) {
+                    ui.horizontal(|ui| {
+                        let mut interval = self.shared.jiggle_interval.lock().unwrap();
+                        let mut secs = interval.as_secs();
+                        ui.add(egui::DragValue::new(&mut secs).clamp_range(1..=MAX_JIGGLE_INTERVAL.as_secs()).prefix("Every ").suffix(" s")).named("Jiggle interval in seconds");
+                        *interval = Duration::from_secs(secs);
+                        drop(interval);
+                        let mut back = self.shared.jiggle_back.load(Ordering::SeqCst);
+                        ui.checkbox(&mut back, "Move back right away")
+                            .on_hover_text("Otherwise the cursor goes a pixel left and right in turn");
+                        self.shared.jiggle_back.store(back, Ordering::SeqCst);
+                    });
+                } else if self.shared.fast_mode.load(Ordering::SeqCst) {
+                    ui.horizontal(|ui| {
+                        let mut workers = self.shared.workers.load(Ordering::SeqCst);
+                        ui.add(egui::DragValue::new(&mut workers).clamp_range(1..=MAX_WORKERS).prefix("Workers: ")).named("Superspeed workers")
+                            .on_hover_text("Injection threads used in superspeed mode");
+                        self.shared.workers.store(workers, Ordering::SeqCst);
+                        if workers > 1 && self.shared.clicking.load(Ordering::SeqCst) {
+                            match self.single_worker_rate {
+                                Some(single) => ui.label(format!("{:.2}x the single-worker rate", self.emitted_rate / single)),
+                                None => ui.label("Run with 1 worker first to measure the gain"),
+                            };
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut mode = self.shared.superspeed.lock().unwrap();
+                        for option in SuperspeedMode::ALL {
+                            let (label, hover) = match option {
+                                SuperspeedMode::MaxPerformance => ("Max performance", "Send every click the moment the last one is out; keeps a CPU core busy"),
+                                SuperspeedMode::Balanced => ("Balanced", "Let other programs run between clicks and wait while the system catches up"),
+                            };
+                            ui.selectable_value(&mut *mode, option, label).on_hover_text(hover);
+                        }
+                    });
+                } else {
+                    let mut cps_target = *self.shared.target_cps.lock().unwrap();
+                    ui.horizontal(|ui| {
+                        ui.add(egui::Slider::new(&mut cps_target, MIN_CPS..=MAX_CPS).logarithmic(true).show_value(false)).named("Target CPS");
+                        ui.add(egui::DragValue::new(&mut cps_target).clamp_range(MIN_CPS..=MAX_CPS).speed(0.1).max_decimals(2)).named("Target CPS")
+                            .on_hover_text("Drag or double-click to type an exact rate");
+                        ui.label("Target CPS");
+                    });
+                    if cps_target > REALISTIC_CPS {
+                        ui.colored_label(
+                            egui::Color32::from_rgb(220, 140, 0),
+                            "Unrealistic rate: few apps or games register over 1000 clicks per second.",
+                        );
+                    }
+                    // One-click rates; right-click one to remove it.
+                    let presets = &mut self.config_file.cps_presets;
+                    ui.horizontal_wrapped(|ui| {
+                        let mut remove = None;
+                        for (index, &preset) in presets.iter().enumerate() {
+                            let button = ui.selectable_label(cps_target == preset, format!("{} CPS", preset));
+                            if button.clicked() {
+                                cps_target = preset;
+                            }
+                            button.context_menu(|ui| {
+                                if ui.button("Remove preset").clicked() {
+                                    remove = Some(index);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                        if let Some(index) = remove {
+                            presets.remove(index);
+                        }
+                        if presets.len() < MAX_CPS_PRESETS
+                            && !presets.contains(&cps_target)
+                            && ui.small_button("+").on_hover_text("Save the current rate as a preset").named("Add preset").clicked()
+                        {
+                            presets.push(cps_target);
+                        }
+                    });
+                    *self.shared.target_cps.lock().unwrap() = cps_target;
+                }
+                self.section("CPS Hotkeys").show(ui, |ui| {
+                    ui.label("Raise or lower the target CPS while clicking.");
+                    for (label, id, slot) in [
+                        ("Faster:", "cps_up_key", &self.shared.cps_up_key),
+                        ("Slower:", "cps_down_key", &self.shared.cps_down_key),
+                    ] {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            let mut key = slot.lock().unwrap();
+                            egui::ComboBox::from_id_source(id)
+                                .selected_text(key.as_ref().map_or("Off".to_string(), display_name))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut *key, None, "Off");
+                                    for supported in SUPPORTED_KEYS {
+                                        ui.selectable_value(&mut *key, Some(*supported), display_name(supported));
+                                    }
+                                }).response.named(&format!("{} CPS hotkey", label.trim_end_matches(':')));
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        let mut step = self.shared.cps_step.lock().unwrap();
+                        ui.add(egui::DragValue::new(&mut *step).clamp_range(0.1..=MAX_CPS_STEP).speed(0.1).prefix("Step: ").suffix(" CPS")).named("CPS step");
+                        drop(step);
+                        let mut beep = self.shared.cps_beep.load(Ordering::SeqCst);
+                        ui.add_enabled(cfg!(feature = "audio"), egui::Checkbox::new(&mut beep, "Beep the new rate"))
+                            .on_hover_text("Higher pitch for faster");
+                        self.shared.cps_beep.store(beep, Ordering::SeqCst);
+                    });
+                });
+                ui.separator();
+
+                // Mouse button selection.
+                ui.heading("Mouse Button Actions");
+                {
+                    let mut buttons = self.shared.selected_buttons.lock().unwrap();
+                    let mut left_click = buttons.contains(&MouseButton::Left);
+                    let mut middle_click = buttons.contains(&MouseButton::Middle);
+                    let mut right_click = buttons.contains(&MouseButton::Right);
+                    ui.checkbox(&mut left_click, "Left Click");
+                    ui.checkbox(&mut middle_click, "Middle Click");
+                    ui.checkbox(&mut right_click, "Right Click");
+                    buttons.clear();
+                    if left_click { buttons.push(MouseButton::Left); }
+                    if middle_click { buttons.push(MouseButton::Middle); }
+                    if right_click { buttons.push(MouseButton::Right); }
+                }
+                ui.separator();
+
+                // Keyboard auto-presser.
+                self.section("Key Auto-Press").show(ui, |ui| {
+                    ui.label("Presses a key each round instead of clicking, or holds one down while clicking.");
+                    ui.horizontal(|ui| {
+                        ui.label("Key:");
+                        let mut press_key = self.shared.press_key.lock().unwrap();
+                        egui::ComboBox::from_id_source("press_key")
+                            .selected_text(press_key.as_ref().map_or("Off".to_string(), display_name))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut *press_key, None, "Off");
+                                for key in SUPPORTED_KEYS {
+                                    ui.selectable_value(&mut *press_key, Some(*key), display_name(key));
+                                }
+                            }).response.named("Auto-press key");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Hold while clicking:");
+                        let mut hold_key = self.shared.hold_key.lock().unwrap();
+                        egui::ComboBox::from_id_source("hold_key")
+                            .selected_text(hold_key.as_ref().map_or("Off".to_string(), display_name))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut *hold_key, None, "Off");
+                                for key in SUPPORTED_KEYS {
+                                    ui.selectable_value(&mut *hold_key, Some(*key), display_name(key));
+                                }
+                            }).response.named("Key held while clicking");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Send as:");
+                        let mut injection = self.shared.key_injection.lock().unwrap();
+                        ui.radio_value(&mut *injection, KeyInjection::VirtualKey, "Virtual key");
+                        ui.radio_value(&mut *injection, KeyInjection::Scancode, "Scancode")
+                            .on_hover_text("Hardware-level codes, for games that ignore virtual-key input");
+                    });
+                });
+                ui.separator();
+
+                // Text typer.
+                self.section("Text Typer").show(ui, |ui| {
+                    ui.label("Types a text over and over, started and stopped with its own hotkey.");
+                    let mut typer = self.shared.typer.lock().unwrap();
+                    let mut on = typer.is_some();
+                    if ui.add_enabled(cfg!(feature = "listener"), egui::Checkbox::new(&mut on, "Enabled")).changed() {
+                        *typer = on.then(|| TextTyper::new(""));
+                        self.shared.typer_running.store(false, Ordering::SeqCst);
+                    }
+                    if let Some(typer) = typer.as_mut() {
+                        ui.add(egui::TextEdit::multiline(&mut typer.text).hint_text("Text to type").desired_rows(2)).named("Text to type");
+                        ui.horizontal(|ui| {
+                            ui.label("Hotkey:");
+                            egui::ComboBox::from_id_source("typer_hotkey")
+                                .selected_text(display_name(&typer.hotkey))
+                                .show_ui(ui, |ui| {
+                                    for key in SUPPORTED_KEYS {
+                                        ui.selectable_value(&mut typer.hotkey, *key, display_name(key));
+                                    }
+                                }).response.named("Typer hotkey");
+                        });
+                        ui.horizontal(|ui| {
+                            let mut char_ms = typer.char_delay.as_millis() as u64;
+                            ui.add(
+                                egui::DragValue::new(&mut char_ms)
+                                    .clamp_range(0..=MAX_TYPER_CHAR_DELAY.as_millis() as u64)
+                                    .prefix("Between characters: ")
+                                    .suffix(" ms"),
+                            ).named("Delay between characters in milliseconds");
+                            typer.char_delay = Duration::from_millis(char_ms);
+                            let mut repeat_ms = typer.repeat_delay.as_millis() as u64;
+                            ui.add(
+                                egui::DragValue::new(&mut repeat_ms)
+                                    .clamp_range(0..=MAX_TYPER_REPEAT_DELAY.as_millis() as u64)
+                                    .prefix("Between repeats: ")
+                                    .suffix(" ms"),
+                            ).named("Delay between repeats in milliseconds");
+                            typer.repeat_delay = Duration::from_millis(repeat_ms);
+                        });
+                        let hotkey = display_name(&typer.hotkey);
+                        if self.shared.typer_running.load(Ordering::SeqCst) {
+                            ui.label(format!("Typing... press {} or the macro abort key to stop.", hotkey));
+                        } else {
+                            ui.label(format!("Focus the target window and press {} to start.", hotkey));
+                        }
+                    }
+                });
+                ui.separator();
+
+                // Active region.
+                self.section("Click Area").show(ui, |ui| {
+                    let region = *self.shared.region.lock().unwrap();
+                    ui.label(match region {
+                        Some(r) => format!("Active region: {:.0}x{:.0} at ({:.0}, {:.0})", r.width, r.height, r.x, r.y),
+                        None => "Active region: whole screen".to_string(),
+                    });
+                    let mut picked = self.shared.picked_points.lock().unwrap();
+                    let picking = picked.as_ref().map(Vec::len);
+                    let picking_here = picking.filter(|_| self.pick_target != PickTarget::WaitPixel);
+                    if picking.is_none() {
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(cfg!(feature = "listener"), egui::Button::new("Pick region")).clicked() {
+                                self.pick_target = PickTarget::Region;
+                                *picked = Some(Vec::new());
+                            }
+                            if region.is_some() && ui.button("Clear").clicked() {
+                                *self.shared.region.lock().unwrap() = None;
+                            }
+                        });
+                    }
+
+                    let mut exclusions = self.shared.exclusions.lock().unwrap();
+                    let mut remove = None;
+                    for (index, zone) in exclusions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Excluded: {:.0}x{:.0} at ({:.0}, {:.0})",
+                                zone.width, zone.height, zone.x, zone.y
+                            ));
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        exclusions.remove(index);
+                    }
+                    if picking.is_none()
+                        && ui.add_enabled(cfg!(feature = "listener"), egui::Button::new("Add exclusion zone")).clicked()
+                    {
+                        self.pick_target = PickTarget::Exclusion;
+                        *picked = Some(Vec::new());
+                    }
+
+                    if let Some(n) = picking_here {
+                        let what = if self.pick_target == PickTarget::Region { "region" } else { "exclusion zone" };
+                        if n == 0 {
+                            ui.label(format!("Click one corner of the {}...", what));
+                        } else {
+                            ui.label("Click the opposite corner...");
+                        }
+                        if ui.button("Cancel").clicked() {
+                            *picked = None;
+                        }
+                    }
+                });
+                ui.separator();
+
+                // Click sequence generators.
+                self.section("Click Pattern").show(ui, |ui| {
+                    let mut sequence = self.shared.sequence.lock().unwrap();
+                    ui.horizontal(|ui| {
+                        if sequence.is_empty() {
+                            ui.label("Clicking at the cursor");
+                        } else {
+                            ui.label(format!("Clicking a sequence of {} points", sequence.len()));
+                            if ui.small_button("Clear").clicked() {
+                                sequence.clear();
+                                *self.shared.sequence_resolution.lock().unwrap() = None;
+                            }
+                        }
+                    });
+                    // Window offsets don't depend on the screen size.
+                    if !sequence.is_empty() && self.shared.relative_to.lock().unwrap().is_none() {
+                        self.resolution_warning(ui, *self.shared.sequence_resolution.lock().unwrap());
+                    }
+                    if !sequence.is_empty() {
+                        let mut picked = self.shared.picked_points.lock().unwrap();
+                        let mut wait = self.shared.sequence_wait.lock().unwrap();
+                        ui.horizontal(|ui| {
+                            if picked.is_some() && self.pick_target == PickTarget::WaitPixel {
+                                ui.label("Click the pixel to wait on...");
+                                if ui.button("Cancel").clicked() {
+                                    *picked = None;
+                                }
+                                return;
+                            }
+                            match wait.as_mut() {
+                                Some(SequenceWait { at, timeout }) => {
+                                    ui.label(format!("After each point, waits for {},{} to change, for up to", at.0, at.1));
+                                    let mut ms = timeout.as_millis() as u64;
+                                    ui.add(egui::DragValue::new(&mut ms).clamp_range(0..=MAX_SEQUENCE_WAIT.as_millis() as u64).suffix(" ms"))
+                                        .named("Longest wait for the pixel to change");
+                                    *timeout = Duration::from_millis(ms);
+                                }
+                                None => {
+                                    ui.label("No wait between points");
+                                }
+                            }
+                            let pick = ui
+                                .add_enabled(cfg!(feature = "listener") && picked.is_none(), egui::Button::new("Pick wait pixel"))
+                                .on_hover_text("Wait after each point until this pixel changes color, e.g. for a loading screen to end");
+                            if pick.clicked() {
+                                self.pick_target = PickTarget::WaitPixel;
+                                *picked = Some(Vec::new());
+                            }
+                            if wait.is_some() && ui.small_button("No wait").clicked() {
+                                *wait = None;
+                            }
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        let pattern = &mut self.pattern;
+                        let (grid, circle, line) = (
+                            matches!(pattern, Pattern::Grid { .. }),
+                            matches!(pattern, Pattern::Circle { .. }),
+                            matches!(pattern, Pattern::Line { .. }),
+                        );
+                        if ui.radio(grid, "Grid").clicked() {
+                            *pattern = Pattern::Grid { columns: 3, rows: 3 };
+                        }
+                        if ui.radio(circle, "Circle").clicked() {
+                            *pattern = Pattern::Circle { points: 12 };
+                        }
+                        if ui.radio(line, "Line").clicked() {
+                            *pattern = Pattern::Line { points: 10 };
+                        }
+                    });
+                    ui.horizontal(|ui| match &mut self.pattern {
+                        Pattern::Grid { columns, rows } => {
+                            ui.add(egui::DragValue::new(columns).clamp_range(1..=100).prefix("columns: ")).named("Grid columns");
+                            ui.add(egui::DragValue::new(rows).clamp_range(1..=100).prefix("rows: ")).named("Grid rows");
+                        }
+                        Pattern::Circle { points } | Pattern::Line { points } => {
+                            ui.add(egui::DragValue::new(points).clamp_range(1..=500).prefix("points: ")).named("Pattern points");
+                        }
+                    });
+                    let mut relative_to = self.shared.relative_to.lock().unwrap();
+                    let origin = *self.shared.window_origin.lock().unwrap();
+                    ui.horizontal(|ui| {
+                        let mut relative = relative_to.is_some();
+                        let toggled = ui
+                            .checkbox(&mut relative, "Relative to the window of")
+                            .on_hover_text("Points are offsets from the app's window, so they follow it when it moves")
+                            .changed();
+                        if toggled {
+                            *relative_to = relative.then(|| self.shared.focused_app.lock().unwrap().clone().unwrap_or_default());
+                        }
+                        if let Some(app) = relative_to.as_mut() {
+                            ui.add(egui::TextEdit::singleline(app).hint_text("game.exe").desired_width(120.0)).named("Application to click relative to");
+                            if origin.is_none() {
+                                ui.label("(no such window)");
+                            }
+                        }
+                    });
+                    let region = *self.shared.region.lock().unwrap();
+                    // Relative points are generated over the window's current position.
+                    let offset = if relative_to.is_some() { origin } else { Some((0.0, 0.0)) };
+                    let generate = ui
+                        .add_enabled(region.is_some() && offset.is_some(), egui::Button::new("Generate over active region"))
+                        .on_disabled_hover_text(if region.is_none() { "Pick an active region first" } else { "The window isn't open" });
+                    if let (true, Some(region), Some((dx, dy))) = (generate.clicked(), region, offset) {
+                        *sequence = self.pattern.generate(region).into_iter().map(|(x, y)| (x - dx, y - dy)).collect();
+                        *self.shared.sequence_resolution.lock().unwrap() = if relative_to.is_some() { None } else { self.screen_size };
+                    }
+                    drop(relative_to);
+                    drop(sequence);
+
+                    let mut offsets = self.shared.offsets.lock().unwrap();
+                    let mut remove = None;
+                    for (index, (x, y)) in offsets.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label("Offset:");
+                            ui.add(egui::DragValue::new(x).speed(1.0).suffix(" px")).named("Horizontal offset");
+                            ui.add(egui::DragValue::new(y).speed(1.0).suffix(" px")).named("Vertical offset");
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        offsets.remove(index);
+                    }
+                    if ui
+                        .button("Add offset")
+                        .on_hover_text("Each click moves to the next offset from the cursor or sequence point, so the same pixel isn't hit every time")
+                        .clicked()
+                    {
+                        // Starting from none, the first click stays on the point and the next one moves off it.
+                        if offsets.is_empty() {
+                            offsets.push((0.0, 0.0));
+                        }
+                        offsets.push((30.0, 0.0));
+                    }
+                    drop(offsets);
+
+                    let mut return_cursor = self.shared.return_cursor.load(Ordering::SeqCst);
+                    ui.checkbox(&mut return_cursor, "Return the cursor after each click")
+                        .on_hover_text("Move back to where the cursor was, so the mouse stays usable between clicks");
+                    self.shared.return_cursor.store(return_cursor, Ordering::SeqCst);
+
+                    let mut human_travel = self.shared.human_travel.load(Ordering::SeqCst);
+                    ui.checkbox(&mut human_travel, "Human-like cursor travel")
+                        .on_hover_text("Curve to each point and overshoot slightly instead of teleporting");
+                    self.shared.human_travel.store(human_travel, Ordering::SeqCst);
+                    if human_travel {
+                        let mut speed = self.shared.travel_speed.lock().unwrap();
+                        ui.add(
+                            egui::Slider::new(&mut *speed, MIN_TRAVEL_SPEED..=MAX_TRAVEL_SPEED)
+                                .logarithmic(true)
+                                .suffix(" px/s")
+                                .text("Travel speed"),
+                        );
+                    }
+                });
+                ui.separator();
+
+                // Sound option.
+                let mut play_sound_val = self.shared.play_sound.load(Ordering::SeqCst);
+                ui.horizontal(|ui| {
+                    ui.add_enabled(cfg!(feature = "audio"), egui::Checkbox::new(&mut play_sound_val, "Play sound on click"));
+                    let enabled = cfg!(feature = "audio") && play_sound_val;
+                    let mut rate = self.shared.sound_rate.lock().unwrap();
+                    ui.add_enabled(enabled, egui::DragValue::new(&mut *rate).clamp_range(1..=MAX_SOUND_RATE).prefix("at most ").suffix(" a second"))
+                        .on_hover_text("Faster clicking plays fewer sounds, so they don't blur into a buzz")
+                        .named("Most click sounds a second");
+                    let mut vary = self.shared.vary_pitch.load(Ordering::SeqCst);
+                    ui.add_enabled(enabled, egui::Checkbox::new(&mut vary, "Vary pitch"))
+                        .on_hover_text("Play each click slightly higher or lower");
+                    self.shared.vary_pitch.store(vary, Ordering::SeqCst);
+                });
+                self.shared.play_sound.store(play_sound_val, Ordering::SeqCst);
+                let mut toggle_beep = self.shared.toggle_beep.load(Ordering::SeqCst);
+                ui.add_enabled(cfg!(feature = "audio"), egui::Checkbox::new(&mut toggle_beep, "Beep when toggled"))
+                    .on_hover_text("High beep on start, low beep on stop, even with the click sound off");
+                self.shared.toggle_beep.store(toggle_beep, Ordering::SeqCst);
+                ui.horizontal(|ui| {
+                    ui.label("Sound theme:");
+                    let current = self.shared.sound_theme.lock().unwrap().name.clone();
+                    let mut selected = current.clone();
+                    ui.add_enabled_ui(cfg!(feature = "audio"), |ui| {
+                        egui::ComboBox::from_id_source("sound_theme")
+                            .selected_text(current.as_str())
+                            .show_ui(ui, |ui| {
+                                for name in sounds::names() {
+                                    ui.selectable_value(&mut selected, name.clone(), name);
+                                }
+                            }).response.named("Sound theme")
+                            .on_hover_text(format!("Themes of your own go in {}", sounds::themes_dir().display()));
+                    });
+                    if selected != current {
+                        match sounds::load(&selected) {
+                            Ok(theme) => {
+                                *self.shared.sound_theme.lock().unwrap() = Arc::new(theme);
+                                self.sound_status = None;
+                            }
+                            Err(e) => self.sound_status = Some(e),
+                        }
+                    }
+                });
+                if let Some(status) = &self.sound_status {
+                    ui.colored_label(egui::Color32::from_rgb(220, 140, 0), status);
+                }
+                ui.horizontal(|ui| {
+                    let mut delay = self.shared.start_delay.lock().unwrap();
+                    let mut millis = delay.as_millis() as u64;
+                    ui.add(
+                        egui::DragValue::new(&mut millis)
+                            .clamp_range(0..=MAX_START_DELAY.as_millis() as u64)
+                            .speed(50)
+                            .prefix("Start delay: ")
+                            .suffix(" ms"),
+                    )
+                    .on_hover_text("Wait after starting before the first click").named("Start delay in milliseconds");
+                    *delay = Duration::from_millis(millis);
+                    let mut countdown = self.shared.countdown_beeps.load(Ordering::SeqCst);
+                    ui.add_enabled(cfg!(feature = "audio") && millis > 0, egui::Checkbox::new(&mut countdown, "Countdown beeps"))
+                        .on_hover_text("3-2-1-go beeps before the first click");
+                    self.shared.countdown_beeps.store(countdown, Ordering::SeqCst);
+                });
+
+                // Dry-run option.
+                let mut dry_run_val = self.shared.dry_run.load(Ordering::SeqCst);
+                ui.checkbox(&mut dry_run_val, "Dry run (log clicks instead of sending them)");
+                self.shared.dry_run.store(dry_run_val, Ordering::SeqCst);
+                ui.separator();
+
+                // CPS test.
+                self.section("CPS Test")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        if cfg!(feature = "listener") {
+                            ui.label(format!("Current CPS: {:.1}", cps));
+                            ui.label("Click anywhere using the left mouse button to test.");
+                        }
+                        ui.label(format!("Engine rate: {:.1} clicks/s", self.emitted_rate));
+                        test_pad = self.show_test_pad(ui);
+                    });
+                ui.separator();
+
+                // Emitted clicks per focused application.
+                self.section("Statistics").show(ui, |ui| {
+                    let emitted = self.shared.emitted.load(Ordering::SeqCst);
+                    ui.label(format!("Clicks sent this session: {}", stats::format_count(emitted)));
+                    let mut app_clicks = self.shared.app_clicks.lock().unwrap();
+                    let ranked = app_clicks.ranked();
+                    if ranked.is_empty() {
+                        ui.label("Clicks are counted per focused application (Windows and X11).");
+                    }
+                    let total = app_clicks.total().max(1);
+                    egui::Grid::new("app_clicks").striped(true).show(ui, |ui| {
+                        for (app, clicks) in &ranked {
+                            ui.label(app);
+                            ui.label(stats::format_count(*clicks)).on_hover_text(clicks.to_string());
+                            let share = *clicks as f32 / total as f32;
+                            ui.add(egui::ProgressBar::new(share).desired_width(120.0).text(format!("{:.0}%", share * 100.0)));
+                            ui.end_row();
+                        }
+                    });
+                    if !ranked.is_empty() && ui.button("Reset").clicked() {
+                        app_clicks.clear();
+                    }
+                    if self.shared.sync_dir.lock().unwrap().is_some() {
+                        let all = self.synced_clicks.total() + app_clicks.total();
+                        ui.label(format!("All synced machines: {} clicks", stats::format_count(all)))
+                            .on_hover_text("Other machines' clicks are counted up to when they last exited");
+                    }
+                    drop(app_clicks);
+
+                    let mut totals = self.shared.totals.lock().unwrap();
+                    ui.label(format!(
+                        "Today: {} · This week: {} · All time: {}",
+                        stats::format_count(totals.today),
+                        stats::format_count(totals.this_week),
+                        stats::format_count(totals.lifetime.total()),
+                    ));
+                    ui.horizontal(|ui| {
+                        let mut rollover = self.shared.rollover.lock().unwrap();
+                        ui.label("Days start at");
+                        ui.add(egui::DragValue::new(&mut rollover.day_start).clamp_range(0..=23).suffix(":00")).named("Hour days start at");
+                        ui.label("and weeks on");
+                        egui::ComboBox::from_id_source("week_start")
+                            .selected_text(weekday_label(rollover.week_start))
+                            .show_ui(ui, |ui| {
+                                for day in Weekday::ALL {
+                                    ui.selectable_value(&mut rollover.week_start, day, weekday_label(day));
+                                }
+                            }).response.named("Day weeks start on");
+                    });
+                    ui.horizontal(|ui| {
+                        for (label, extension) in [("Export JSON", "json"), ("Export CSV", "csv")] {
+                            if ui.button(label).clicked() {
+                                let path = config_dir().join(format!("stats.{}", extension));
+                                let text = if extension == "json" { totals.to_json() } else { totals.to_csv() };
+                                let written = std::fs::create_dir_all(config_dir()).and_then(|_| std::fs::write(&path, text));
+                                self.stats_status = Some(match written {
+                                    Ok(()) => format!("Saved {}", path.display()),
+                                    Err(e) => format!("Failed to export the statistics: {}", e),
+                                });
+                            }
+                        }
+                        ui.menu_button("Reset totals", |ui| {
+                            if ui.button("Forget today, this week and all time").clicked() {
+                                *totals = Totals::default();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    drop(totals);
+                    if let Some(status) = &self.stats_status {
+                        ui.label(status);
+                    }
+
+                    ui.label("Recent runs:");
+                    let mut history = self.shared.history.lock().unwrap();
+                    if history.runs.is_empty() {
+                        ui.label("Every run is summarized here once clicking stops.");
+                    } else {
+                        egui::Grid::new("recent_runs").striped(true).show(ui, |ui| {
+                            for run in history.runs.iter().rev().take(RECENT_RUNS) {
+                                ui.label(history::format_time(run.started));
+                                ui.label(&run.profile);
+                                ui.label(stats::format_duration(run.duration));
+                                ui.label(format!("{} clicks", stats::format_count(run.clicks))).on_hover_text(run.clicks.to_string());
+                                ui.label(format!("{:.1} CPS", run.average_cps()))
+                                    .on_hover_text(format!("Peak {:.1} CPS", run.peak_cps));
+                                ui.end_row();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Browse...").on_hover_text("Every run kept, by profile and date, with its CPS curve").clicked() {
+                                self.history_browser = Some(HistoryBrowser::default());
+                            }
+                            ui.menu_button("Clear history", |ui| {
+                                if ui.button("Forget every run").clicked() {
+                                    history.runs.clear();
+                                    self.history_browser = None;
+                                    if let Err(e) = history.save() {
+                                        self.shared.log.push(format!("Failed to save the run history: {}", e));
+                                    }
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                    }
+                    drop(history);
+
+                    ui.label("Click heatmap:");
+                    let mut heatmap = self.shared.heatmap.lock().unwrap();
+                    if heatmap.is_empty() {
+                        ui.label("Positions of the clicks sent appear here.");
+                    } else {
+                        paint_heatmap(ui, &heatmap, self.screen_size);
+                        ui.horizontal(|ui| {
+                            if ui.button("Export PNG").clicked() {
+                                self.shared.telemetry.record("heatmap_export");
+                                let path = config_dir().join("heatmap.png");
+                                self.heatmap_status = Some(match write_heatmap_png(&heatmap, &path) {
+                                    Ok(()) => format!("Saved {}", path.display()),
+                                    Err(e) => format!("Failed to save the heatmap: {}", e),
+                                });
+                            }
+                            if ui.button("Reset").clicked() {
+                                heatmap.clear();
+                                self.heatmap_status = None;
+                            }
+                        });
+                    }
+                    if let Some(status) = &self.heatmap_status {
+                        ui.label(status);
+                    }
+                    drop(heatmap);
+
+                    ui.label("Time between clicks:");
+                    let mut intervals = self.shared.intervals.lock().unwrap();
+                    match intervals.mean_and_deviation() {
+                        None => {
+                            ui.label("A histogram of the intervals the engine achieved appears here.");
+                        }
+                        Some((mean, deviation)) => {
+                            paint_intervals(ui, &intervals);
+                            ui.horizontal(|ui| {
+                                ui.label(format!(
+                                    "{} intervals, mean {:.2} ms, std dev {:.2} ms",
+                                    stats::format_count(intervals.count()),
+                                    mean,
+                                    deviation
+                                ));
+                                if ui.button("Reset").clicked() {
+                                    intervals.clear();
+                                }
+                            });
+                        }
+                    }
+                });
+                ui.separator();
+
+                // Opt-in usage metrics, shown exactly as they would be sent.
+                self.section("Usage Metrics").show(ui, |ui| {
+                    let telemetry = self.shared.telemetry.clone();
+                    let mut enabled = telemetry.enabled.load(Ordering::SeqCst);
+                    if ui.checkbox(&mut enabled, "Count which features I use").changed() {
+                        telemetry.enabled.store(enabled, Ordering::SeqCst);
+                        if !enabled {
+                            telemetry.clear();
+                        }
+                        if let Err(e) = telemetry.save() {
+                            eprintln!("Failed to save usage counts: {}", e);
+                        }
+                    }
+                    ui.label("Off by default. Counts stay on this computer until you press Send; no names, paths or IDs are included.");
+                    if enabled {
+                        ui.label("What would be sent:");
+                        ui.monospace(telemetry.summary());
+                        ui.horizontal(|ui| {
+                            ui.label("Endpoint:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.config_file.telemetry_endpoint)
+                                    .hint_text("http://host/path")
+                                    .desired_width(160.0),
+                            ).named("Usage metrics endpoint");
+                        });
+                        ui.horizontal(|ui| {
+                            let endpoint = self.config_file.telemetry_endpoint.trim().to_string();
+                            if ui.add_enabled(!endpoint.is_empty(), egui::Button::new("Send")).clicked() {
+                                let status = self.telemetry_status.clone();
+                                *status.lock().unwrap() = Some("Sending...".to_string());
+                                thread::spawn(move || {
+                                    let result = match telemetry.submit(&endpoint) {
+                                        Ok(()) => "Sent, counts cleared".to_string(),
+                                        Err(e) => format!("Failed to send: {}", e),
+                                    };
+                                    *status.lock().unwrap() = Some(result);
+                                });
+                            }
+                            if ui.button("Clear").clicked() {
+                                self.shared.telemetry.clear();
+                                let _ = self.shared.telemetry.save();
+                            }
+                        });
+                    }
+                    if let Some(status) = self.telemetry_status.lock().unwrap().as_ref() {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+
+                // Per-application profile bindings.
+                self.section("App Bindings").show(ui, |ui| {
+                    ui.label("Switch to a saved profile when an application gains focus, and optionally stop clicking when it loses focus.");
+                    let mut bindings = self.shared.bindings.lock().unwrap();
+                    let mut remove = None;
+                    for (index, binding) in bindings.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut binding.app).desired_width(110.0).hint_text("process name")).named("Bound application");
+                            egui::ComboBox::from_id_source(("binding", index))
+                                .selected_text(binding.profile.clone())
+                                .show_ui(ui, |ui| {
+                                    for name in self.config_file.profiles.keys() {
+                                        ui.selectable_value(&mut binding.profile, name.clone(), name);
+                                    }
+                                }).response.named("Profile for the application");
+                            ui.checkbox(&mut binding.stop_on_blur, "Stop on blur")
+                                .on_hover_text("Stop clicking when the application loses focus");
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        bindings.remove(index);
+                    }
+                    let focused = self.shared.focused_app.lock().unwrap().clone();
+                    ui.horizontal(|ui| {
+                        let profile = self.config_file.active_profile.clone();
+                        if ui.button("Add binding").clicked() {
+                            bindings.push(AppBinding { app: String::new(), profile: profile.clone(), stop_on_blur: false });
+                        }
+                        if let Some(app) = focused {
+                            if ui.button(format!("Bind {}", app)).on_hover_text("Last focused application").clicked() {
+                                bindings.push(AppBinding { app, profile, stop_on_blur: false });
+                            }
+                        }
+                    });
+                    drop(bindings);
+                    ui.separator();
+                    ui.label("Blocklist: never click while one of these applications is focused, whatever starts clicking.");
+                    let mut blocklist = self.shared.blocklist.lock().unwrap();
+                    let mut remove = None;
+                    for (index, app) in blocklist.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(app).desired_width(110.0).hint_text("process name")).named("Blocked application");
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        blocklist.remove(index);
+                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("Add application").clicked() {
+                            blocklist.push(String::new());
+                        }
+                        if let Some(app) = self.shared.focused_app.lock().unwrap().clone() {
+                            if ui.button(format!("Block {}", app)).on_hover_text("Last focused application").clicked() {
+                                blocklist.push(app);
+                            }
+                        }
+                    });
+                    drop(blocklist);
+                    ui.separator();
+                    ui.label("Title rules: start or stop clicking when a window with a matching title gains focus. The first match applies.");
+                    let mut rules = self.shared.title_rules.lock().unwrap();
+                    let mut remove = None;
+                    for (index, rule) in rules.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source(("title_action", index))
+                                .selected_text(if rule.action == TextAction::Start { "Start" } else { "Stop" })
+                                .width(60.0)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut rule.action, TextAction::Start, "Start");
+                                    ui.selectable_value(&mut rule.action, TextAction::Stop, "Stop");
+                                }).response.named("Title rule action");
+                            ui.label("when the title");
+                            let kind = rule.kind;
+                            egui::ComboBox::from_id_source(("title_match", index))
+                                .selected_text(if rule.kind == TitleMatch::Contains { "contains" } else { "matches regex" })
+                                .width(110.0)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut rule.kind, TitleMatch::Contains, "contains");
+                                    ui.selectable_value(&mut rule.kind, TitleMatch::Regex, "matches regex");
+                                }).response.named("How the title is matched");
+                            let edited = ui.add(egui::TextEdit::singleline(&mut rule.title).desired_width(140.0).hint_text("Roblox")).named("Window title").changed();
+                            if edited || rule.kind != kind {
+                                rule.compile();
+                            }
+                            if !rule.title.trim().is_empty() && !rule.is_valid() {
+                                ui.colored_label(egui::Color32::RED, "Invalid regex");
+                            }
+                            if ui.small_button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = remove {
+                        rules.remove(index);
+                    }
+                    if ui.button("Add title rule").clicked() {
+                        rules.push(TitleRule::new(""));
+                    }
+                });
+                ui.separator();
+
+                // Macro editor and playback.
+                self.section("Macros").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut selected = None;
+                        egui::ComboBox::from_id_source("macro")
+                            .selected_text("Open...")
+                            .show_ui(ui, |ui| {
+                                for name in &self.macro_names {
+                                    if ui.selectable_label(*name == self.macro_name, name).clicked() {
+                                        selected = Some(name.clone());
+                                    }
+                                }
+                            }).response.named("Open a saved macro");
+                        if let Some(name) = selected {
+                            self.open_macro(name);
+                        }
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.macro_name).named("Macro name");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Author:");
+                        ui.add(egui::TextEdit::singleline(&mut self.macro_meta.author).desired_width(100.0)).named("Macro author");
+                        ui.label("Description:");
+                        ui.text_edit_singleline(&mut self.macro_meta.description).named("Macro description");
+                    });
+                    self.resolution_warning(ui, self.macro_meta.resolution);
+                    let debug_step = self.shared.macro_stepper.current();
+                    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                        let mut job = step_highlight(ui, text, debug_step);
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|fonts| fonts.layout_job(job))
+                    };
+                    let mut editor = egui::TextEdit::multiline(&mut self.macro_text).code_editor().desired_rows(6);
+                    if debug_step.is_some() {
+                        // Locked while debugging so the highlighted line stays the running step.
+                        editor = editor.layouter(&mut layouter).interactive(false);
+                    }
+                    ui.add(editor).named("Macro steps")
+                        .on_hover_text("move X,Y · click left|middle|right [X,Y] · key K · wait MS\nif pixel X,Y is #RRGGBB goto N · wait until pixel X,Y changes timeout MS · repeat from N times K");
+                    ui.horizontal(|ui| {
+                        let mut forever = self.macro_loops.is_none();
+                        ui.checkbox(&mut forever, "Loop forever");
+                        let mut loops = self.macro_loops.unwrap_or(1);
+                        if !forever {
+                            ui.add(egui::DragValue::new(&mut loops).clamp_range(1..=100_000).prefix("Loops: ")).named("Macro loops");
+                        }
+                        self.macro_loops = (!forever).then_some(loops);
+                        ui.add(
+                            egui::DragValue::new(&mut self.macro_loop_delay)
+                                .clamp_range(0..=3_600_000)
+                                .prefix("Delay between: ")
+                                .suffix(" ms"),
+                        ).named("Delay between loops in milliseconds");
+                        ui.add(
+                            egui::Slider::new(&mut self.macro_meta.speed, macros::MIN_SPEED..=macros::MAX_SPEED)
+                                .logarithmic(true)
+                                .text("Speed")
+                                .suffix("×"),
+                        )
+                        .on_hover_text(format!(
+                            "Divides every wait; inputs stay at least {} ms apart",
+                            macros::MIN_EVENT_GAP.as_millis()
+                        ));
+                    });
+                    if cfg!(feature = "listener") {
+                        let recording = self.shared.recording.lock().unwrap().as_ref().map(|r| r.active);
+                        ui.horizontal(|ui| match recording {
+                            Some(true) => {
+                                let abort_key = *self.shared.abort_key.lock().unwrap();
+                                ui.label(format!("Recording... press {} or Stop to finish.", display_name(&abort_key)));
+                                if ui.button("Stop").clicked() {
+                                    self.finish_recording();
+                                }
+                            }
+                            Some(false) => self.finish_recording(),
+                            None => {
+                                if ui.button("Record").on_hover_text("Replace the steps with input recorded from now on").clicked() {
+                                    *self.shared.recording.lock().unwrap() = Some(Recording::new(self.record_clicks_only));
+                                    self.shared.telemetry.record("macro_record");
+                                }
+                                ui.checkbox(&mut self.record_clicks_only, "Clicks and keys only")
+                                    .on_hover_text("Record clicks at their positions instead of every cursor movement");
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Abort key:");
+                            let mut abort_key = self.shared.abort_key.lock().unwrap();
+                            egui::ComboBox::from_id_source("abort_key")
+                                .selected_text(display_name(&abort_key))
+                                .show_ui(ui, |ui| {
+                                    for key in SUPPORTED_KEYS {
+                                        ui.selectable_value(&mut *abort_key, *key, display_name(key));
+                                    }
+                                }).response.named("Macro abort key");
+                            ui.label("Next step key:");
+                            let mut step_key = self.shared.step_key.lock().unwrap();
+                            egui::ComboBox::from_id_source("step_key")
+                                .selected_text(display_name(&step_key))
+                                .show_ui(ui, |ui| {
+                                    for key in SUPPORTED_KEYS {
+                                        ui.selectable_value(&mut *step_key, *key, display_name(key));
+                                    }
+                                }).response.named("Next step key");
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        let parsed = self.edited_macro();
+                        let save = ui.add_enabled(macros::valid_name(&self.macro_name), egui::Button::new("Save"));
+                        if save.clicked() {
+                            self.macro_status = Some(match &parsed {
+                                Ok(mac) => match macros::save(self.macro_name.trim(), &with_resolution(mac)) {
+                                    Ok(()) => {
+                                        self.refresh_macros();
+                                        "Macro saved.".to_string()
+                                    }
+                                    Err(e) => format!("Failed to save macro: {}", e),
+                                },
+                                Err(e) => e.clone(),
+                            });
+                        }
+                        let playing = self.shared.macro_playing.load(Ordering::SeqCst);
+                        if playing {
+                            if ui.button("Stop").clicked() {
+                                self.shared.macro_playing.store(false, Ordering::SeqCst);
+                            }
+                        } else {
+                            let play = ui.button("Play").clicked();
+                            let debug = ui.button("Debug").on_hover_text("Play one step at a time").clicked();
+                            if play || debug {
+                                match parsed.clone() {
+                                    Ok(mac) => {
+                                        macros::spawn_player(self.shared.macro_handles(), self.macro_name.trim().to_string(), mac, debug);
+                                        self.macro_status = None;
+                                    }
+                                    Err(e) => self.macro_status = Some(e),
+                                }
+                            }
+                        }
+                        if let (Some(step), Ok(mac)) = (debug_step, &parsed) {
+                            let step_key = *self.shared.step_key.lock().unwrap();
+                            let next = if cfg!(feature = "listener") {
+                                format!("Next step ({})", display_name(&step_key))
+                            } else {
+                                "Next step".to_string()
+                            };
+                            if ui.button(next).clicked() {
+                                self.shared.macro_stepper.request_next();
+                            }
+                            if let Some(about) = mac.steps.get(step) {
+                                ui.label(format!("Step {}: {}", step + 1, about));
+                            }
+                        }
+                    });
+                    if let Some(status) = &self.macro_status {
+                        ui.label(status);
+                    }
+
+                    if cfg!(feature = "listener") {
+                        ui.label("Hotkeys (press again to stop):");
+                        let mut hotkeys = self.shared.macro_hotkeys.lock().unwrap();
+                        let mut remove = None;
+                        for (index, hotkey) in hotkeys.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_source(("macro_key", index))
+                                    .selected_text(display_name(&hotkey.key))
+                                    .show_ui(ui, |ui| {
+                                        for key in SUPPORTED_KEYS {
+                                            ui.selectable_value(&mut hotkey.key, *key, display_name(key));
+                                        }
+                                    }).response.named("Macro hotkey");
+                                egui::ComboBox::from_id_source(("macro_name", index))
+                                    .selected_text(hotkey.name.clone())
+                                    .show_ui(ui, |ui| {
+                                        for name in &self.macro_names {
+                                            ui.selectable_value(&mut hotkey.name, name.clone(), name);
+                                        }
+                                    }).response.named("Macro to play");
+                                if ui.small_button("Remove").clicked() {
+                                    remove = Some(index);
+                                }
+                            });
+                        }
+                        if let Some(index) = remove {
+                            hotkeys.remove(index);
+                        }
+                        if ui.button("Add hotkey").clicked() {
+                            let name = self.macro_names.first().cloned().unwrap_or_default();
+                            hotkeys.push(MacroHotkey { key: rdev::Key::F7, name });
+                        }
+                    }
+                });
+                ui.separator();
+
+                // Saved macros with their tags and when they last ran.
+                self.section("Macro Library").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Filter by tag:");
+                        ui.text_edit_singleline(&mut self.library_filter).named("Tag filter");
+                        if ui.button("Refresh").clicked() {
+                            self.refresh_macros();
+                        }
+                        if ui.button("Import...").on_hover_text("Add a macro shared as a .json file").clicked() {
+                            self.import = Some(ImportDialog { path: String::new(), read: None, name: String::new(), scale: true });
+                        }
+                    });
+                    let filter = self.library_filter.trim().to_lowercase();
+                    let now = SystemTime::now();
+                    // Actions are applied after the grid, which borrows the entries.
+                    let mut open = None;
+                    let mut duplicate = None;
+                    let mut export = None;
+                    let mut rename = None;
+                    let mut delete = None;
+                    let mut tags_changed = None;
+                    egui::Grid::new("macro_library").striped(true).show(ui, |ui| {
+                        ui.strong("Name");
+                        ui.strong("Tags");
+                        ui.strong("Last used");
+                        ui.end_row();
+                        for entry in &mut self.library {
+                            if !filter.is_empty() && !macros::parse_tags(&entry.tags).iter().any(|tag| tag.to_lowercase().contains(&filter)) {
+                                continue;
+                            }
+                            match &mut self.renaming {
+                                Some((old, new)) if *old == entry.name => {
+                                    ui.text_edit_singleline(new).named("New macro name");
+                                }
+                                _ => {
+                                    let label = ui.label(&entry.name);
+                                    match &entry.error {
+                                        Some(e) => label.on_hover_text(e),
+                                        None => label.on_hover_text(format!("{} steps", entry.steps)),
+                                    };
+                                }
+                            }
+                            let tags = ui.add_enabled(entry.error.is_none(), egui::TextEdit::singleline(&mut entry.tags).hint_text("tag, tag")).named("Macro tags");
+                            if tags.lost_focus() {
+                                tags_changed = Some((entry.name.clone(), macros::parse_tags(&entry.tags)));
+                            }
+                            ui.label(macros::format_last_used(entry.last_used, now));
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Open").clicked() {
+                                    open = Some(entry.name.clone());
+                                }
+                                match &self.renaming {
+                                    Some((old, new)) if *old == entry.name => {
+                                        if ui.small_button("OK").clicked() {
+                                            rename = Some((old.clone(), new.trim().to_string()));
+                                        }
+                                        if ui.small_button("Cancel").clicked() {
+                                            rename = Some((old.clone(), old.clone()));
+                                        }
+                                    }
+                                    _ => {
+                                        if ui.small_button("Rename").clicked() {
+                                            self.renaming = Some((entry.name.clone(), entry.name.clone()));
+                                        }
+                                    }
+                                }
+                                if ui.small_button("Duplicate").clicked() {
+                                    duplicate = Some(entry.name.clone());
+                                }
+                                if ui.add_enabled(entry.error.is_none(), egui::Button::new("Export").small()).clicked() {
+                                    export = Some(entry.name.clone());
+                                }
+                                if self.confirm_delete.as_ref() == Some(&entry.name) {
+                                    if ui.small_button("Really delete").clicked() {
+                                        delete = Some(entry.name.clone());
+                                    }
+                                    if ui.small_button("Keep").clicked() {
+                                        self.confirm_delete = None;
+                                    }
+                                } else if ui.small_button("Delete").clicked() {
+                                    self.confirm_delete = Some(entry.name.clone());
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                    if self.library.is_empty() {
+                        ui.label(format!("No macros saved in {} yet.", macros::macros_dir().display()));
+                    }
+
+                    if let Some(name) = open {
+                        self.open_macro(name);
+                    }
+                    if let Some((name, tags)) = tags_changed {
+                        if let Err(e) = macros::set_tags(&name, tags.clone()) {
+                            self.macro_status = Some(format!("Failed to save tags of {}: {}", name, e));
+                        }
+                        if name == self.macro_name {
+                            self.macro_meta.tags = tags;
+                        }
+                    }
+                    if let Some((old, new)) = rename {
+                        self.renaming = None;
+                        if old != new {
+                            match macros::rename(&old, &new) {
+                                Ok(()) => {
+                                    for hotkey in self.shared.macro_hotkeys.lock().unwrap().iter_mut().filter(|h| h.name == old) {
+                                        hotkey.name = new.clone();
+                                    }
+                                    if self.macro_name == old {
+                                        self.macro_name = new;
+                                    }
+                                    self.refresh_macros();
+                                }
+                                Err(e) => self.macro_status = Some(format!("Failed to rename {}: {}", old, e)),
+                            }
+                        }
+                    }
+                    if let Some(name) = duplicate {
+                        match macros::duplicate(&name) {
+                            Ok(_) => self.refresh_macros(),
+                            Err(e) => self.macro_status = Some(format!("Failed to duplicate {}: {}", name, e)),
+                        }
+                    }
+                    if let Some(name) = export {
+                        self.shared.telemetry.record("macro_export");
+                        let path = macros::macros_dir().join(format!("{}.{}", name, macro_json::EXTENSION));
+                        let written = macros::load(&name)
+                            .and_then(|mac| std::fs::write(&path, macro_json::to_json(&name, &with_resolution(&mac))).map_err(|e| e.to_string()));
+                        self.macro_status = Some(match written {
+                            Ok(()) => format!("Exported to {}", path.display()),
+                            Err(e) => format!("Failed to export {}: {}", name, e),
+                        });
+                    }
+                    if let Some(name) = delete {
+                        self.confirm_delete = None;
+                        match macros::delete(&name) {
+                            Ok(()) => {
+                                self.shared.macro_hotkeys.lock().unwrap().retain(|h| h.name != name);
+                                self.refresh_macros();
+                            }
+                            Err(e) => self.macro_status = Some(format!("Failed to delete {}: {}", name, e)),
+                        }
+                    }
+                    if let Some(status) = &self.macro_status {
+                        ui.label(status);
+                    }
+                });
+                ui.separator();
+
+                // Injection backend and benchmark.
+                self.section("Diagnostics").show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Backend:");
+                        let mut backend = self.shared.backend.lock().unwrap();
+                        egui::ComboBox::from_id_source("backend")
+                            .selected_text(backend.name())
+                            .show_ui(ui, |ui| {
+                                for kind in BackendKind::ALL {
+                                    ui.selectable_value(&mut *backend, kind, kind.name());
+                                }
+                            }).response.named("Input backend");
+                        if let Some(problem) = backend::setup_problem(*backend) {
+                            ui.colored_label(egui::Color32::RED, problem);
+                        }
+                        if *backend == BackendKind::Interception {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(220, 140, 0),
+                                "Driver-level input. Anti-cheat may treat it as a violation; use at your own risk.",
+                            );
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let mut max_cps = self.shared.max_cps.lock().unwrap();
+                        let mut guarded = max_cps.is_some();
+                        ui.checkbox(&mut guarded, "CPS guard")
+                            .on_hover_text("Hard limit on clicks per second, whatever the mode, a macro or a remote command asks for");
+                        let mut limit = max_cps.unwrap_or(MAX_CPS);
+                        if guarded {
+                            ui.add(egui::DragValue::new(&mut limit).clamp_range(MIN_CPS..=MAX_CPS_LIMIT).suffix(" CPS")).named("CPS limit");
+                            let target = *self.shared.target_cps.lock().unwrap();
+                            if self.shared.fast_mode.load(Ordering::SeqCst) || target > limit {
+                                ui.colored_label(egui::Color32::from_rgb(220, 140, 0), "Clicking is being limited");
+                            }
+                        }
+                        *max_cps = guarded.then_some(limit);
+                    });
+                    ui.horizontal(|ui| {
+                        let mut on = self.shared.receipts.enabled.load(Ordering::SeqCst);
+                        ui.add_enabled(cfg!(feature = "listener"), egui::Checkbox::new(&mut on, "Verify clicks"))
+                            .on_hover_text("Count clicks the system never delivered and warn when more than a few are dropped")
+                            .on_disabled_hover_text("Needs the global listener, which is off in this build");
+                        self.shared.receipts.enabled.store(on, Ordering::SeqCst);
+                    });
+                    if cfg!(feature = "listener") {
+                        ui.horizontal_wrapped(|ui| {
+                            let summary = self.shared.latency.lock().unwrap().summary();
+                            ui.label("Hotkey latency:").on_hover_text("From pressing the start/stop key to the first click sent, per backend");
+                            if summary.is_empty() {
+                                ui.label("start clicking with the hotkey to measure it");
+                            }
+                            for (kind, median, last, presses) in &summary {
+                                ui.label(format!(
+                                    "{} {:.1} ms median, {:.1} ms last ({} presses)",
+                                    kind.name(),
+                                    median.as_secs_f64() * 1000.0,
+                                    last.as_secs_f64() * 1000.0,
+                                    presses
+                                ));
+                            }
+                            if !summary.is_empty() && ui.small_button("Reset").clicked() {
+                                self.shared.latency.lock().unwrap().clear();
+                            }
+                        });
+                    }
+                    ui.horizontal_wrapped(|ui| {
+                        let mut pin_core = self.shared.pin_core.lock().unwrap();
+                        let mut pinned = pin_core.is_some();
+                        if ui
+                            .checkbox(&mut pinned, "Pin the click engine to core")
+                            .on_hover_text("Keep the clicking thread on one CPU core, for steadier timing on a busy machine; applies when clicking starts")
+                            .changed()
+                        {
+                            *pin_core = pinned.then_some(0);
+                        }
+                        if let Some(core) = pin_core.as_mut() {
+                            ui.add(egui::DragValue::new(core).clamp_range(0..=affinity::cores() - 1)).named("Core to pin the click engine to");
+                        }
+                        let jitter = *self.shared.jitter.lock().unwrap();
+                        let mut readout = Vec::new();
+                        if let Some(deviation) = jitter.unpinned {
+                            readout.push(format!("{:.3} ms unpinned", deviation));
+                        }
+                        if let Some((core, deviation)) = jitter.pinned {
+                            readout.push(format!("{:.3} ms on core {}", deviation, core));
+                        }
+                        if !readout.is_empty() {
+                            ui.label(format!("Jitter: {}", readout.join(", ")))
+                                .on_hover_text("Standard deviation of the time between clicks in the last run each way");
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let serving = *self.shared.overlay_port.lock().unwrap();
+                        let mut on = serving.is_some();
+                        let toggled = ui
+                            .checkbox(&mut on, "Stream overlay")
+                            .on_hover_text("Status page with a transparent background, for an OBS browser source")
+                            .changed();
+                        match serving {
+                            Some(port) => {
+                                let url = format!("http://127.0.0.1:{}/", port);
+                                ui.hyperlink_to(&url, &url);
+                            }
+                            None => {
+                                ui.add(egui::DragValue::new(&mut self.overlay_port).clamp_range(1..=u16::MAX).prefix("port ")).named("Overlay port");
+                            }
+                        }
+                        if toggled {
+                            let port = on.then_some(self.overlay_port);
+                            *self.shared.overlay_port.lock().unwrap() = port;
+                            self.overlay_error = None;
+                            if let Some(port) = port {
+                                if let Err(e) = overlay::spawn_server(self.shared.overlay_handles(), port) {
+                                    *self.shared.overlay_port.lock().unwrap() = None;
+                                    self.overlay_error = Some(format!("Can't serve on port {}: {}", port, e));
+                                }
+                            }
+                        }
+                    });
+                    if let Some(error) = &self.overlay_error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                    ui.horizontal(|ui| {
+                        let joined = self.shared.chat_trigger.lock().unwrap().clone();
+                        let mut on = joined.is_some();
+                        let valid = ChatTrigger::valid_channel(&self.chat_trigger.channel);
+                        let toggled = ui
+                            .add_enabled(on || valid, egui::Checkbox::new(&mut on, "Twitch chat"))
+                            .on_hover_text("Viewers trigger short click bursts by sending the command, e.g. \"!click 10\"")
+                            .on_disabled_hover_text("Enter a channel name first")
+                            .changed();
+                        match joined {
+                            Some(trigger) => {
+                                ui.label(format!("#{}: {} up to {} clicks", trigger.channel, trigger.command, trigger.max_clicks));
+                            }
+                            None => {
+                                let trigger = &mut self.chat_trigger;
+                                ui.add(egui::TextEdit::singleline(&mut trigger.channel).hint_text("channel").desired_width(100.0)).named("Twitch channel");
+                                ui.add(egui::TextEdit::singleline(&mut trigger.command).desired_width(60.0)).named("Chat command");
+                                ui.add(egui::DragValue::new(&mut trigger.max_clicks).clamp_range(1..=MAX_CHAT_CLICKS).prefix("max ")).named("Most clicks per command");
+                                let mut cooldown = trigger.cooldown.as_secs();
+                                ui.add(
+                                    egui::DragValue::new(&mut cooldown)
+                                        .clamp_range(0..=MAX_CHAT_COOLDOWN.as_secs())
+                                        .prefix("cooldown ")
+                                        .suffix(" s"),
+                                ).named("Chat command cooldown in seconds");
+                                trigger.cooldown = Duration::from_secs(cooldown);
+                                trigger.channel = trigger.channel.trim().trim_start_matches('#').to_lowercase();
+                            }
+                        }
+                        if toggled {
+                            let trigger = on.then(|| self.chat_trigger.clone());
+                            *self.shared.chat_trigger.lock().unwrap() = trigger.clone();
+                            if let Some(trigger) = trigger {
+                                twitch::spawn(self.shared.twitch_handles(), trigger);
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Sync folder:").on_hover_text(
+                            "Keep profiles and click counts in a folder that follows you across machines, e.g. inside Dropbox or OneDrive",
+                        );
+                        ui.add(egui::TextEdit::singleline(&mut self.sync_dir).hint_text("Off").desired_width(220.0)).named("Sync folder");
+                        let dir = self.sync_dir.trim();
+                        if ui.add_enabled(!dir.is_empty() && Path::new(dir).is_dir(), egui::Button::new("Sync now")).clicked() {
+                            self.start_sync();
+                        }
+                        if self.shared.sync_dir.lock().unwrap().is_some() && ui.button("Stop syncing").clicked() {
+                            *self.shared.sync_dir.lock().unwrap() = None;
+                            self.sync_status = Some(match self.save_config() {
+                                Ok(()) => "Sync off; the folder was left as it is.".to_string(),
+                                Err(e) => format!("Failed to save config: {}", e),
+                            });
+                        }
+                    });
+                    if let Some(status) = &self.sync_status {
+                        ui.label(status);
+                    }
+                    ui.horizontal(|ui| {
+                        let mut on = self.shared.command_files.load(Ordering::SeqCst);
+                        ui.checkbox(&mut on, "Command files").on_hover_text(
+                            "Run start.cmd, stop.cmd, toggle.cmd or set-cps.cmd (with the CPS inside) when dropped in the folder, \
+                             and every line appended to commands.txt",
+                        );
+                        self.shared.command_files.store(on, Ordering::SeqCst);
+                        if on {
+                            ui.monospace(command_files::commands_dir().display().to_string());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let connected = self.shared.mqtt.lock().unwrap().clone();
+                        let mut on = connected.is_some();
+                        let valid = MqttConfig::valid_broker(&self.mqtt.broker) && MqttConfig::valid_topic(&self.mqtt.topic);
+                        let toggled = ui
+                            .add_enabled(on || valid, egui::Checkbox::new(&mut on, "MQTT"))
+                            .on_hover_text("Takes commands like \"start\" or \"set-cps 50\" from <topic>/set and publishes <topic>/state")
+                            .on_disabled_hover_text("Enter a broker and a topic without wildcards first")
+                            .changed();
+                        match connected {
+                            Some(mqtt) => {
+                                ui.label(format!("{} on {}", mqtt.topic, mqtt.broker));
+                            }
+                            None => {
+                                ui.add(egui::TextEdit::singleline(&mut self.mqtt.broker).hint_text("broker:1883").desired_width(140.0)).named("MQTT broker");
+                                ui.add(egui::TextEdit::singleline(&mut self.mqtt.topic).hint_text("topic").desired_width(100.0)).named("MQTT topic");
+                                self.mqtt.broker = self.mqtt.broker.trim().to_string();
+                            }
+                        }
+                        if toggled {
+                            let mqtt = on.then(|| self.mqtt.clone());
+                            *self.shared.mqtt.lock().unwrap() = mqtt.clone();
+                            if let Some(mqtt) = mqtt {
+                                mqtt::spawn(self.shared.mqtt_handles(), mqtt);
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        let posting = self.shared.webhook.lock().unwrap().clone();
+                        let mut on = posting.is_some();
+                        let valid = WebhookConfig::valid_url(&self.webhook.url);
+                        let toggled = ui
+                            .add_enabled(on || valid, egui::Checkbox::new(&mut on, "Webhook"))
+                            .on_hover_text("Posts JSON to the URL when clicking starts, when it stops and every set number of clicks")
+                            .on_disabled_hover_text("Enter an http:// URL first")
+                            .changed();
+                        match posting {
+                            Some(webhook) => {
+                                ui.label(format!("to {}", webhook.url));
+                            }
+                            None => {
+                                ui.add(egui::TextEdit::singleline(&mut self.webhook.url).hint_text("http://host:port/path").desired_width(180.0)).named("Webhook URL");
+                                ui.add(egui::DragValue::new(&mut self.webhook.every).clamp_range(0..=MAX_WEBHOOK_EVERY).prefix("every ").suffix(" clicks"))
+                                    .on_hover_text("0 posts only the start and stop")
+                                    .named("Clicks between webhook posts");
+                                self.webhook.url = self.webhook.url.trim().to_string();
+                            }
+                        }
+                        if toggled {
+                            let webhook = on.then(|| self.webhook.clone());
+                            *self.shared.webhook.lock().unwrap() = webhook.clone();
+                            if let Some(webhook) = webhook {
+                                webhook::spawn(self.shared.webhook_handles(), webhook);
+                            }
+                        }
+                    });
+                    let mut guard = self.shared.hooks.lock().unwrap();
+                    let hooks = &mut *guard;
+                    ui.checkbox(&mut hooks.enabled, "Run hooks")
+                        .on_hover_text("Shell commands run when clicking starts and stops, with their output in the log");
+                    for (label, command) in [("On start:", &mut hooks.on_start), ("On stop:", &mut hooks.on_stop)] {
+                        ui.horizontal(|ui| {
+                            ui.label(label);
+                            ui.add(egui::TextEdit::singleline(command).hint_text("command").desired_width(260.0))
+                                .named(&format!("Hook command {}", label.trim_end_matches(':').to_lowercase()));
+                        });
+                    }
+                    drop(guard);
+                    ui.label(format!(
+                        "The benchmark sends {} left clicks per backend to the test pad in CPS Test, which must be on screen. Cancel or press the macro abort key to stop it.",
+                        BENCH_CLICKS
+                    ));
+                    let running = self.shared.bench_running.load(Ordering::SeqCst);
+                    if running {
+                        ui.horizontal(|ui| {
+                            let left = BENCH_COUNTDOWN.saturating_sub(self.bench_started.elapsed());
+                            if left.is_zero() {
+                                ui.label("Benchmarking...");
+                            } else {
+                                ui.label(format!("Starting in {} s", left.as_secs() + 1));
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.shared.bench_running.store(false, Ordering::SeqCst);
+                            }
+                        });
+                        ui.ctx().request_repaint_after(Duration::from_millis(200));
+                    } else {
+                        let idle = !self.shared.clicking.load(Ordering::SeqCst);
+                        let on_screen = self.shared.test_pad.lock().unwrap().is_some();
+                        let run = ui
+                            .add_enabled(idle && on_screen, egui::Button::new("Run benchmark"))
+                            .on_disabled_hover_text(if idle { "Scroll up so the test pad is on screen" } else { "Stop the autoclicker first" });
+                        if run.clicked() {
+                            self.shared.telemetry.record("benchmark");
+                            self.shared.bench_running.store(true, Ordering::SeqCst);
+                            self.bench_started = Instant::now();
+                            let (report, handles, log) = (self.bench_report.clone(), self.shared.bench_handles(), self.shared.log.clone());
+                            thread::spawn(move || {
+                                match bench::run(&handles) {
+                                    Some(result) => *report.lock().unwrap() = Some(result),
+                                    None => log.push("Benchmark cancelled"),
+                                }
+                                handles.running.store(false, Ordering::SeqCst);
+                            });
+                        }
+                    }
+
+                    let report = self.bench_report.lock().unwrap();
+                    if let Some(report) = report.as_ref() {
+                        egui::Grid::new("bench").striped(true).show(ui, |ui| {
+                            for (kind, result) in report {
+                                ui.label(kind.name());
+                                match result {
+                                    Ok(r) => {
+                                        ui.label(format!("{:.1} µs mean", r.mean.as_secs_f64() * 1e6));
+                                        ui.label(format!("{:.1} µs p99", r.p99.as_secs_f64() * 1e6));
+                                        ui.label(format!("{:.0} clicks/s", r.clicks_per_sec));
+                                    }
+                                    Err(e) => {
+                                        ui.label(e);
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(fastest) = bench::fastest(report) {
+                            if ui.button(format!("Use fastest ({})", fastest.name())).clicked() {
+                                *self.shared.backend.lock().unwrap() = fastest;
+                            }
+                        }
+                    }
+                });
+                ui.separator();
+
+                // Activity log.
+                self.section("Log").show(ui, |ui| {
+                    if ui.small_button("Clear").clicked() {
+                        self.shared.log.clear();
+                    }
+                    egui::ScrollArea::vertical()
+                        .max_height(120.0)
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            for line in self.shared.log.lines() {
+                                ui.monospace(line);
+                            }
+                        });
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("When the screen locks:");
+                    let mut policy = self.shared.lock_policy.lock().unwrap();
+                    for option in LockPolicy::ALL {
+                        let label = match option {
+                            LockPolicy::Ignore => "Keep clicking",
+                            LockPolicy::Pause => "Pause",
+                            LockPolicy::Resume => "Pause and resume",
+                        };
+                        ui.selectable_value(&mut *policy, option, label);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("On battery:");
+                    let mut policy = self.shared.battery_policy.lock().unwrap();
+                    for option in BatteryPolicy::ALL {
+                        let label = match option {
+                            BatteryPolicy::Ignore => "Keep clicking",
+                            BatteryPolicy::Pause => "Pause",
+                            BatteryPolicy::Throttle => "Limit to",
+                        };
+                        ui.selectable_value(&mut *policy, option, label)
+                            .on_hover_text("Also applies while battery saver is on");
+                    }
+                    if *policy == BatteryPolicy::Throttle {
+                        let mut cps = self.shared.battery_cps.lock().unwrap();
+                        ui.add(egui::DragValue::new(&mut *cps).clamp_range(MIN_CPS..=MAX_CPS).suffix(" CPS")).named("Battery CPS");
+                    }
+                    if self.shared.low_power.load(Ordering::SeqCst) {
+                        ui.weak("(on battery now)");
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let mut idle_stop = self.shared.idle_stop.lock().unwrap();
+                    let mut on = idle_stop.is_some();
+                    ui.checkbox(&mut on, "Stop after")
+                        .on_hover_text("Stops clicking and macros when no real keyboard or mouse input was seen for this long");
+                    let mut minutes = idle_stop.map_or(10, |idle| idle.as_secs() / 60);
+                    ui.add_enabled(on, egui::DragValue::new(&mut minutes).clamp_range(1..=MAX_IDLE_STOP.as_secs() / 60).suffix(" min")).named("Idle stop in minutes");
+                    ui.label("without input");
+                    *idle_stop = on.then(|| Duration::from_secs(minutes * 60));
+                });
+
+                ui.horizontal(|ui| {
+                    let mut on = self.shared.keep_awake.load(Ordering::SeqCst);
+                    ui.checkbox(&mut on, "Keep the computer awake")
+                        .on_hover_text("Keeps the system from sleeping and the display on while clicking or a macro runs");
+                    self.shared.keep_awake.store(on, Ordering::SeqCst);
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Click indicator:").on_hover_text("A badge that lights up while clicking, which clicks pass through");
+                    let mut place = self.shared.indicator.lock().unwrap();
+                    for option in IndicatorPlace::ALL {
+                        let label = match option {
+                            IndicatorPlace::Off => "Off",
+                            IndicatorPlace::Corner => "Screen corner",
+                            IndicatorPlace::Cursor => "Next to the cursor",
+                        };
+                        ui.selectable_value(&mut *place, option, label);
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let mut typing_pause = self.shared.typing_pause.lock().unwrap();
+                    let mut on = typing_pause.is_some();
+                    ui.checkbox(&mut on, "Pause while typing, for")
+                        .on_hover_text("Holds clicking on every real key press other than the hotkeys, until this long after the last one");
+                    let mut ms = typing_pause.map_or(1000, |pause| pause.as_millis() as u64);
+                    ui.add_enabled(on, egui::DragValue::new(&mut ms).clamp_range(1..=MAX_TYPING_PAUSE.as_millis() as u64).speed(10).suffix(" ms")).named("Typing pause in milliseconds");
+                    *typing_pause = on.then(|| Duration::from_millis(ms));
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Theme:");
+                    for theme in ThemeMode::ALL {
+                        let label = match theme {
+                            ThemeMode::System => "System",
+                            ThemeMode::Dark => "Dark",
+                            ThemeMode::Light => "Light",
+                        };
+                        ui.selectable_value(&mut self.config_file.theme, theme, label);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("UI scale:");
+                    let slider = egui::Slider::new(&mut self.config_file.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE).step_by(0.05).suffix("×");
+                    self.scaling = ui.add(slider).named("UI scale").dragged();
+                    if ui.small_button("Reset").clicked() {
+                        self.config_file.ui_scale = 1.0;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Font size:");
+                    let slider = egui::Slider::new(&mut self.config_file.font_size, MIN_FONT_SIZE..=MAX_FONT_SIZE).step_by(0.5).suffix(" pt");
+                    ui.add(slider).named("Font size");
+                    if ui.small_button("Reset").clicked() {
+                        self.config_file.font_size = DEFAULT_FONT_SIZE;
+                    }
+                });
+
+                // Save Config button.
+                if ui.button("Save Config").on_hover_text("Ctrl+S").clicked() {
+                    if let Err(e) = self.save_config() {
+                        ui.label(format!("Failed to save config: {}", e));
+                    } else {
+                        ui.label("Config saved.");
+                    }
+                }
+            });
+        });
+        *self.shared.test_pad.lock().unwrap() = test_pad;
+
+        ctx.request_repaint();
+    }
+}
+
+/// Escape closes dialogs.
+/// Labeled values of a run summary.
+fn run_rows(run: &Run) -> Vec<(&'static str, String)> {
+    let mut rows = vec![
+        ("Started", history::format_time(run.started)),
+        ("Profile", run.profile.clone()),
+        ("Duration", stats::format_duration(run.duration)),
+        ("Clicks", run.clicks.to_string()),
+        ("Average CPS", format!("{:.1}", run.average_cps())),
+        ("Peak CPS", format!("{:.1}", run.peak_cps)),
+    ];
+    if let Some(dropped) = run.dropped {
+        rows.push(("Dropped", dropped.to_string()));
+    }
+    rows
+}
+
+fn escape_pressed(ctx: &egui::Context) -> bool {
+    ctx.input(|input| input.key_pressed(egui::Key::Escape))
+}
+
+/// Screen reader names for widgets whose visible label is missing or sits next to them.
+trait Named {
+    fn named(self, name: &str) -> Self;
+}
+
+impl Named for egui::Response {
+    fn named(self, name: &str) -> Self {
+        self.ctx.accesskit_node_builder(self.id, |builder| builder.set_name(name));
+        self
+    }
+}
+
+fn activation_label(activation: Activation) -> &'static str {
+    match activation {
+        Activation::Press => "Press",
+        Activation::Hold => "Hold for 500 ms",
+        Activation::DoubleTap => "Double-tap",
+    }
+}
+
+fn weekday_label(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Monday => "Monday",
+        Weekday::Tuesday => "Tuesday",
+        Weekday::Wednesday => "Wednesday",
+        Weekday::Thursday => "Thursday",
+        Weekday::Friday => "Friday",
+        Weekday::Saturday => "Saturday",
+        Weekday::Sunday => "Sunday",
+    }
+}
+
+/// Runs the GUI on the current thread until the window is closed.
+pub fn run(state: AppState) {
+    // Configure eframe native options.
+    let window = state.config_file.window;
+    let native_options = eframe::NativeOptions {
+        initial_window_pos: window.map(|rect| egui::pos2(rect.x as f32, rect.y as f32)),
+        initial_window_size: Some(window.map_or(egui::vec2(380.0, 560.0), |rect| egui::vec2(rect.width as f32, rect.height as f32))),
+        min_window_size: Some(egui::vec2(280.0, 200.0)),
+        ..Default::default()
+    };
+
+    if let Err(e) = eframe::run_native("Superspeed Autoclicker", native_options, Box::new(|_cc| Box::new(state))) {
+        eprintln!("Failed to start GUI: {}", e);
+    }
+}
@@ -0,0 +1,318 @@
+// Run history: a summary of every run from start to stop, whether it was
+// stopped by hand or by a limit, with its CPS curve. Kept in history.toml
+// next to config.toml, newest last, and browsed from the Statistics section.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use toml_edit::{value, Array, ArrayOfTables, Document, Item, Table};
+
+use crate::config::config_dir;
+use crate::log::Log;
+use crate::receipts::Receipts;
+use crate::totals::utc_offset;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Oldest runs are forgotten beyond this many.
+pub const MAX_RUNS: usize = 1000;
+// Longer runs average neighbouring seconds of their curve down to this many points.
+const MAX_CURVE_POINTS: usize = 300;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    // Unix time the run started.
+    pub started: i64,
+    pub profile: String,
+    pub duration: Duration,
+    pub clicks: u64,
+    // Most clicks sent in one second of the run.
+    pub peak_cps: f64,
+    // Presses sent that the system never reported, if verification was on.
+    pub dropped: Option<u64>,
+    // CPS of every `curve_step` of the run, in order.
+    pub curve: Vec<f64>,
+    pub curve_step: Duration,
+}
+
+impl Run {
+    pub fn average_cps(&self) -> f64 {
+        match self.duration.as_secs_f64() {
+            secs if secs > 0.0 => self.clicks as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Whether the run used `profile` (any, if unset) and started on a local
+    /// date beginning with `date`, like "2026-10-14" or "2026-10".
+    pub fn matches(&self, profile: Option<&str>, date: &str) -> bool {
+        profile.is_none_or(|profile| profile == self.profile) && format_time(self.started).starts_with(date.trim())
+    }
+
+    fn from_table(table: &Table) -> Option<Run> {
+        let count = |key: &str| table.get(key).and_then(Item::as_integer).filter(|n| *n >= 0);
+        let rate = |item: &toml_edit::Value| item.as_float().filter(|c| c.is_finite() && *c >= 0.0);
+        let secs = table.get("seconds").and_then(Item::as_float).filter(|s| s.is_finite() && *s >= 0.0)?;
+        // Runs saved before curves were kept have none.
+        let curve = match table.get("curve").and_then(Item::as_array) {
+            Some(points) => points.iter().map(rate).collect::<Option<Vec<f64>>>()?,
+            None => Vec::new(),
+        };
+        Some(Run {
+            started: count("started")?,
+            profile: table.get("profile").and_then(Item::as_str)?.to_string(),
+            duration: Duration::from_secs_f64(secs),
+            clicks: count("clicks")? as u64,
+            peak_cps: table.get("peak_cps").and_then(Item::as_float).filter(|c| c.is_finite() && *c >= 0.0)?,
+            dropped: count("dropped").map(|n| n as u64),
+            curve,
+            curve_step: Duration::from_secs(count("curve_step").filter(|n| *n > 0).unwrap_or(1) as u64),
+        })
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table["started"] = value(self.started);
+        table["profile"] = value(self.profile.as_str());
+        table["seconds"] = value(self.duration.as_secs_f64());
+        table["clicks"] = value(self.clicks as i64);
+        table["peak_cps"] = value(self.peak_cps);
+        if let Some(dropped) = self.dropped {
+            table["dropped"] = value(dropped as i64);
+        }
+        table["curve"] = value(self.curve.iter().copied().collect::<Array>());
+        table["curve_step"] = value(self.curve_step.as_secs() as i64);
+        table
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct History {
+    pub runs: Vec<Run>,
+}
+
+impl History {
+    fn path() -> PathBuf {
+        config_dir().join("history.toml")
+    }
+
+    pub fn push(&mut self, run: Run) {
+        self.runs.push(run);
+        if self.runs.len() > MAX_RUNS {
+            self.runs.drain(..self.runs.len() - MAX_RUNS);
+        }
+    }
+
+    /// Runs of `text`, skipping any that can't be read.
+    pub fn parse(text: &str) -> History {
+        let mut history = History::default();
+        let Ok(doc) = text.parse::<Document>() else { return history };
+        if let Some(runs) = doc.get("runs").and_then(Item::as_array_of_tables) {
+            for run in runs.iter().filter_map(Run::from_table) {
+                history.push(run);
+            }
+        }
+        history
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut doc = Document::new();
+        let mut runs = ArrayOfTables::new();
+        for run in &self.runs {
+            runs.push(run.to_table());
+        }
+        doc["runs"] = Item::ArrayOfTables(runs);
+        doc.to_string()
+    }
+
+    pub fn load() -> History {
+        fs::read_to_string(History::path()).map(|text| History::parse(&text)).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(config_dir())?;
+        fs::write(History::path(), self.to_toml())
+    }
+}
+
+/// The run going on, measured from polls of the click count.
+struct RunMeter {
+    started: Instant,
+    started_at: i64,
+    profile: String,
+    emitted: u64,
+    // Receipt counts at the start, if verification was on.
+    receipts: Option<(u64, u64)>,
+    // Start of the second being counted, with the click count then.
+    second: (Instant, u64),
+    // CPS of every second so far.
+    seconds: Vec<f64>,
+    peak_cps: f64,
+}
+
+impl RunMeter {
+    fn start(now: Instant, started_at: i64, profile: String, emitted: u64, receipts: Option<(u64, u64)>) -> RunMeter {
+        RunMeter { started: now, started_at, profile, emitted, receipts, second: (now, emitted), seconds: Vec::new(), peak_cps: 0.0 }
+    }
+
+    fn sample(&mut self, now: Instant, emitted: u64) {
+        let (since, at_start) = self.second;
+        let elapsed = now.duration_since(since);
+        if elapsed >= Duration::from_secs(1) {
+            let cps = (emitted - at_start) as f64 / elapsed.as_secs_f64();
+            self.peak_cps = self.peak_cps.max(cps);
+            self.seconds.push(cps);
+            self.second = (now, emitted);
+        }
+    }
+
+    fn finish(mut self, now: Instant, emitted: u64, receipts: Option<(u64, u64)>) -> Run {
+        self.sample(now, emitted);
+        let step = self.seconds.len().div_ceil(MAX_CURVE_POINTS).max(1);
+        let curve = self.seconds.chunks(step).map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64).collect();
+        let mut run = Run {
+            started: self.started_at,
+            profile: self.profile,
+            duration: now.duration_since(self.started),
+            clicks: emitted - self.emitted,
+            peak_cps: self.peak_cps,
+            dropped: self.receipts.zip(receipts).map(|((sent, seen), (sent_now, seen_now))| {
+                (sent_now - sent).saturating_sub(seen_now - seen)
+            }),
+            curve,
+            curve_step: Duration::from_secs(step as u64),
+        };
+        // Runs shorter than a second peak at their average.
+        run.peak_cps = run.peak_cps.max(run.average_cps());
+        run
+    }
+}
+
+/// Shared state the run recorder is allowed to touch.
+pub struct HistoryHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub emitted: Arc<AtomicU64>,
+    pub receipts: Arc<Receipts>,
+    pub profile: Arc<Mutex<String>>,
+    pub history: Arc<Mutex<History>>,
+    // Summary of the run that just ended, until the user dismisses it.
+    pub last_run: Arc<Mutex<Option<Run>>>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Records every run into the history and saves it. Returns once `shutdown`
+/// is set, after recording a run cut short by it.
+pub fn spawn_recorder(handles: HistoryHandles) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let receipts = |handles: &HistoryHandles| handles.receipts.enabled.load(Ordering::SeqCst).then(|| handles.receipts.counts());
+        let mut meter: Option<RunMeter> = None;
+        loop {
+            let done = handles.shutdown.load(Ordering::SeqCst);
+            let clicking = handles.clicking.load(Ordering::SeqCst) && !done;
+            let (now, emitted) = (Instant::now(), handles.emitted.load(Ordering::SeqCst));
+            match (meter.as_mut(), clicking) {
+                (None, true) => {
+                    let unix = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs() as i64);
+                    let profile = handles.profile.lock().unwrap().clone();
+                    meter = Some(RunMeter::start(now, unix, profile, emitted, receipts(&handles)));
+                }
+                (Some(run), true) => run.sample(now, emitted),
+                (Some(_), false) => {
+                    let run = meter.take().unwrap().finish(now, emitted, receipts(&handles));
+                    let mut history = handles.history.lock().unwrap();
+                    history.push(run.clone());
+                    if let Err(e) = history.save() {
+                        handles.log.push(format!("Failed to save the run history: {}", e));
+                    }
+                    *handles.last_run.lock().unwrap() = Some(run);
+                }
+                (None, false) => {}
+            }
+            if done {
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    })
+}
+
+/// `unix_secs` as a local "YYYY-MM-DD HH:MM".
+pub fn format_time(unix_secs: i64) -> String {
+    let local = unix_secs + utc_offset();
+    let (year, month, day) = civil_date(local.div_euclid(86_400));
+    let minutes = local.rem_euclid(86_400) / 60;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+/// Year, month and day of the day `days` after 1970-01-01.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    // Counted in 400-year eras starting on March 1st, so leap days come last.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_measured_and_kept() {
+        let start = Instant::now();
+        let secs = |s: f64| start + Duration::from_secs_f64(s);
+        let mut meter = RunMeter::start(start, 1_700_000_000, "pvp".to_string(), 100, Some((50, 50)));
+        meter.sample(secs(0.5), 105);
+        meter.sample(secs(1.0), 110);
+        meter.sample(secs(2.0), 140);
+        let run = meter.finish(secs(2.5), 150, Some((100, 95)));
+        assert_eq!((run.clicks, run.peak_cps, run.average_cps()), (50, 30.0, 20.0));
+        assert_eq!((run.profile.as_str(), run.dropped), ("pvp", Some(5)));
+        assert_eq!(run.curve, [10.0, 30.0]);
+
+        // An hour of clicking averages every 12 seconds.
+        let mut meter = RunMeter::start(start, 0, "pvp".to_string(), 0, None);
+        for s in 1..=3600 {
+            meter.sample(secs(s as f64), s * 10);
+        }
+        let run = meter.finish(secs(3600.0), 36_000, None);
+        assert_eq!((run.curve.len(), run.curve_step), (MAX_CURVE_POINTS, Duration::from_secs(12)));
+
+        // Short runs peak at their average; no verification, no drops.
+        let run = RunMeter::start(start, 0, "x".to_string(), 0, None).finish(secs(0.5), 10, None);
+        assert_eq!((run.peak_cps, run.dropped), (20.0, None));
+
+        let mut history = History::default();
+        for n in 0..MAX_RUNS as u64 + 2 {
+            history.push(Run { clicks: n, ..run.clone() });
+        }
+        assert_eq!((history.runs.len(), history.runs[0].clicks), (MAX_RUNS, 2));
+        assert_eq!(History::parse(&history.to_toml()), history);
+        assert!(History::parse("[[runs]]\nstarted = 5\nprofile = \"x\"\nseconds = 1.0\nclicks = 2\npeak_cps = 2.0\n").runs[0].curve.is_empty());
+
+        let day = format_time(run.started)[..10].to_string();
+        assert!(run.matches(Some("x"), &day) && run.matches(None, &day[..7]) && run.matches(None, ""));
+        assert!(!run.matches(Some("pvp"), "") && !run.matches(None, "1969-13"));
+    }
+
+    #[test]
+    fn dates_follow_the_calendar() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(-1), (1969, 12, 31));
+        // 2000 was a leap year, 2100 won't be.
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(47_541), (2100, 3, 1));
+        assert_eq!(civil_date(20_740), (2026, 10, 14));
+    }
+}
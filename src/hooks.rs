@@ -0,0 +1,87 @@
+// Start and stop hooks: shell commands run when clicking starts and when it
+// stops, like muting the microphone or starting a screen recorder. Nothing
+// runs unless hooks are enabled. Each command runs on its own thread so a
+// slow one never holds up clicking, and what it prints goes to the log.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::Hooks;
+use crate::log::Log;
+
+const POLL: Duration = Duration::from_millis(100);
+// Lines of a hook's output worth logging; a chatty command logs the first ones.
+const MAX_LINES: usize = 20;
+
+/// Shared state the hook runner is allowed to touch.
+pub struct HooksHandles {
+    pub hooks: Arc<Mutex<Hooks>>,
+    pub clicking: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// `command` run by the system shell.
+fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Runs `command` to the end, logging its output and how it failed, if it did.
+fn run(label: &str, command: &str, log: &Log) {
+    let output = match shell(command).output() {
+        Ok(output) => output,
+        Err(e) => {
+            log.push(format!("{} couldn't run: {}", label, e));
+            return;
+        }
+    };
+    let (stdout, stderr) = (String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    for line in stdout.lines().chain(stderr.lines()).filter(|line| !line.trim().is_empty()).take(MAX_LINES) {
+        log.push(format!("{}: {}", label, line));
+    }
+    if !output.status.success() {
+        log.push(format!("{} failed ({})", label, output.status));
+    }
+}
+
+/// Runs the start or stop hook whenever clicking starts or stops, until shutdown.
+pub fn spawn_runner(handles: HooksHandles) {
+    thread::spawn(move || {
+        let mut was_clicking = handles.clicking.load(Ordering::SeqCst);
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL);
+            let clicking = handles.clicking.load(Ordering::SeqCst);
+            if clicking == was_clicking {
+                continue;
+            }
+            was_clicking = clicking;
+            let hooks = handles.hooks.lock().unwrap().clone();
+            let (label, command) = if clicking { ("Start hook", hooks.on_start) } else { ("Stop hook", hooks.on_stop) };
+            if hooks.enabled && !command.trim().is_empty() {
+                let log = handles.log.clone();
+                thread::spawn(move || run(label, &command, &log));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_output_goes_to_the_log() {
+        let log = Log::new();
+        run("Start hook", "echo recording", &log);
+        run("Stop hook", "exit 3", &log);
+        let lines = log.lines();
+        assert!(lines.iter().any(|line| line.ends_with("Start hook: recording")), "{:?}", lines);
+        assert!(lines.iter().any(|line| line.contains("Stop hook failed")), "{:?}", lines);
+    }
+}
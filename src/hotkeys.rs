@@ -0,0 +1,164 @@
+// Hotkey conflicts: two actions waiting for the same key, or a hotkey the
+// clicker presses itself as an auto-pressed or held key. Every profile is
+// checked at startup and the settings being edited on every frame.
+
+use std::fmt;
+
+use crate::config::{Config, ConfigFile, MacroHotkey, TextTyper};
+use crate::keys::string_to_rdev_key;
+use crate::layout::display_name;
+
+/// Setting a key is bound through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Toggle,
+    // First key of the start/stop chord.
+    Chord,
+    CpsUp,
+    CpsDown,
+    Abort,
+    Step,
+    Typer,
+    PauseAll,
+    // Index into the macro hotkeys.
+    Macro(usize),
+    // Keys the clicker presses rather than listens for.
+    PressKey,
+    HoldKey,
+}
+
+impl Slot {
+    fn pressed(self) -> bool {
+        matches!(self, Slot::PressKey | Slot::HoldKey)
+    }
+
+    /// Whether the slot belongs to every profile rather than to one.
+    fn global(self) -> bool {
+        matches!(self, Slot::Typer | Slot::PauseAll | Slot::Macro(_))
+    }
+
+    /// Whether the slot can be turned off instead of moved to another key.
+    pub fn optional(self) -> bool {
+        matches!(self, Slot::Chord | Slot::CpsUp | Slot::CpsDown | Slot::PauseAll | Slot::PressKey | Slot::HoldKey)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub slot: Slot,
+    pub key: rdev::Key,
+    pub label: String,
+}
+
+/// Bindings sharing one key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub key: rdev::Key,
+    pub bindings: Vec<Binding>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labels: Vec<&str> = self.bindings.iter().map(|b| b.label.as_str()).collect();
+        write!(f, "{} is bound more than once: {}", display_name(&self.key), labels.join(", "))
+    }
+}
+
+/// Every key of a profile, plus the typer, pause-all and macro hotkeys every
+/// profile shares.
+pub fn bindings(config: &Config, typer: Option<&TextTyper>, pause_key: Option<rdev::Key>, macro_hotkeys: &[MacroHotkey]) -> Vec<Binding> {
+    let mut bindings = Vec::new();
+    let mut bind = |slot, key: Option<rdev::Key>, label: String| {
+        if let Some(key) = key {
+            bindings.push(Binding { slot, key, label });
+        }
+    };
+    bind(Slot::Toggle, string_to_rdev_key(&config.hotkey), "start/stop key".to_string());
+    bind(Slot::Chord, config.chord_key, "first key of the start/stop chord".to_string());
+    bind(Slot::CpsUp, config.cps_up_key, "faster CPS hotkey".to_string());
+    bind(Slot::CpsDown, config.cps_down_key, "slower CPS hotkey".to_string());
+    bind(Slot::Abort, Some(config.abort_key), "macro abort key".to_string());
+    bind(Slot::Step, Some(config.step_key), "next step key".to_string());
+    bind(Slot::Typer, typer.map(|typer| typer.hotkey), "typer hotkey".to_string());
+    bind(Slot::PauseAll, pause_key, "pause-all key".to_string());
+    for (index, hotkey) in macro_hotkeys.iter().enumerate() {
+        bind(Slot::Macro(index), Some(hotkey.key), format!("hotkey of macro {}", hotkey.name));
+    }
+    bind(Slot::PressKey, config.press_key, "auto-pressed key".to_string());
+    bind(Slot::HoldKey, config.hold_key, "key held while clicking".to_string());
+    bindings
+}
+
+/// Keys bound more than once, in the order of their first binding. Keys the
+/// clicker only presses itself can share a key with each other.
+pub fn conflicts(bindings: Vec<Binding>) -> Vec<Conflict> {
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    for binding in bindings {
+        match conflicts.iter_mut().find(|conflict| conflict.key == binding.key) {
+            Some(conflict) => conflict.bindings.push(binding),
+            None => conflicts.push(Conflict { key: binding.key, bindings: vec![binding] }),
+        }
+    }
+    conflicts.retain(|conflict| conflict.bindings.len() > 1 && conflict.bindings.iter().any(|b| !b.slot.pressed()));
+    conflicts
+}
+
+/// Warnings for the conflicts of every profile in `file`, for the log.
+pub fn check_file(file: &ConfigFile) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (name, config) in &file.profiles {
+        for conflict in conflicts(bindings(config, file.typer.as_ref(), file.pause_key, &file.macro_hotkeys)) {
+            // Conflicts between shared hotkeys are the same in every profile.
+            let warning = if conflict.bindings.iter().all(|b| b.slot.global()) {
+                conflict.to_string()
+            } else {
+                format!("Profile {}: {}", name, conflict)
+            };
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdev::Key;
+
+    #[test]
+    fn shared_keys_are_conflicts() {
+        let config = Config::default();
+        assert_eq!(conflicts(bindings(&config, None, None, &[])), []);
+
+        let config = Config { press_key: Some(Key::F8), hold_key: Some(Key::KeyW), cps_up_key: Some(Key::Escape), ..Config::default() };
+        let found = conflicts(bindings(&config, Some(&TextTyper::new("hi")), None, &[]));
+        let slots: Vec<Vec<Slot>> = found.iter().map(|c| c.bindings.iter().map(|b| b.slot).collect()).collect();
+        assert_eq!(slots, [vec![Slot::CpsUp, Slot::Abort], vec![Slot::Step, Slot::PressKey]]);
+
+        // Pressing and holding the same key isn't a hotkey conflict.
+        let config = Config { press_key: Some(Key::KeyW), hold_key: Some(Key::KeyW), ..Config::default() };
+        assert_eq!(conflicts(bindings(&config, None, None, &[])), []);
+    }
+
+    #[test]
+    fn shared_hotkey_conflicts_are_reported_once() {
+        let mut file = ConfigFile::default();
+        file.profiles.insert("pvp".to_string(), Config { cps_down_key: Some(Key::F8), ..Config::default() });
+        file.macro_hotkeys = vec![
+            MacroHotkey { key: Key::F9, name: "farm".to_string() },
+            MacroHotkey { key: Key::F9, name: "fish".to_string() },
+        ];
+        assert_eq!(
+            check_file(&file),
+            [
+                "F9 is bound more than once: hotkey of macro farm, hotkey of macro fish",
+                "Profile pvp: F8 is bound more than once: slower CPS hotkey, next step key",
+            ]
+        );
+
+        file.pause_key = Some(Key::F9);
+        assert_eq!(check_file(&file)[0], "F9 is bound more than once: pause-all key, hotkey of macro farm, hotkey of macro fish");
+    }
+}
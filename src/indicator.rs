@@ -0,0 +1,384 @@
+// Click indicator: a small round badge in a screen corner or next to the
+// cursor, dim while idle and lit while clicking. It is its own OS window that
+// input passes through, so it never takes the clicks it sits over: an
+// override-redirect window with an empty input shape on X11, and a layered,
+// transparent topmost window on Windows. Elsewhere it can't be shown.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::IndicatorPlace;
+use crate::log::Log;
+
+// Across the badge, in pixels.
+const SIZE: u16 = 16;
+// Gap to the screen edges in the corner.
+const MARGIN: i32 = 12;
+// Offset from the cursor, clear of the pointer itself.
+const CURSOR_GAP: i32 = 20;
+// Following the cursor needs a refresh per frame; the corner doesn't.
+const FOLLOW_POLL: Duration = Duration::from_millis(16);
+const POLL: Duration = Duration::from_millis(100);
+
+/// Shared state the click indicator is allowed to touch.
+pub struct IndicatorHandles {
+    pub place: Arc<Mutex<IndicatorPlace>>,
+    pub clicking: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Top left of the badge, or None while it is off or the cursor is unknown.
+fn position(place: IndicatorPlace, screen_width: i32, cursor: Option<(i32, i32)>) -> Option<(i32, i32)> {
+    match place {
+        IndicatorPlace::Off => None,
+        IndicatorPlace::Corner => Some((screen_width - i32::from(SIZE) - MARGIN, MARGIN)),
+        IndicatorPlace::Cursor => cursor.map(|(x, y)| (x + CURSOR_GAP, y + CURSOR_GAP)),
+    }
+}
+
+/// Start and width of each row of a disc `size` pixels across.
+#[cfg(any(target_os = "linux", test))]
+fn disc_rows(size: u16) -> Vec<(u16, u16)> {
+    let radius = f32::from(size) / 2.0;
+    (0..size)
+        .map(|row| {
+            let dy = f32::from(row) + 0.5 - radius;
+            let half = (radius * radius - dy * dy).max(0.0).sqrt().round() as u16;
+            (size / 2 - half.min(size / 2), (half * 2).min(size))
+        })
+        .collect()
+}
+
+/// Shows the badge wherever it is placed until shutdown. Failing to open it is
+/// logged once, and tried again once it has been turned off and on.
+pub fn spawn(handles: IndicatorHandles) {
+    thread::spawn(move || {
+        let mut badge = None;
+        let mut failed = false;
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            let place = *handles.place.lock().unwrap();
+            if place == IndicatorPlace::Off {
+                badge = None;
+                failed = false;
+            } else if badge.is_none() && !failed {
+                match platform::Badge::open() {
+                    Ok(opened) => badge = Some(opened),
+                    Err(e) => {
+                        failed = true;
+                        handles.log.push(format!("Couldn't show the click indicator: {}", e));
+                    }
+                }
+            }
+            if let Some(badge) = &mut badge {
+                if let Some(at) = position(place, badge.screen_width(), badge.cursor()) {
+                    badge.show(at, handles.clicking.load(Ordering::SeqCst));
+                }
+            }
+            thread::sleep(if place == IndicatorPlace::Cursor { FOLLOW_POLL } else { POLL });
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::connection::{Connection, RequestConnection};
+    use x11rb::protocol::shape::{self, ConnectionExt as _, SK, SO};
+    use x11rb::protocol::xproto::{ChangeWindowAttributesAux, ClipOrdering, ConfigureWindowAux, ConnectionExt as _, CreateWindowAux, Rectangle, StackMode, Window, WindowClass};
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    use super::{disc_rows, SIZE};
+
+    // 24-bit TrueColor pixels.
+    const LIT: u32 = 0x30e060;
+    const DIM: u32 = 0x405040;
+
+    pub struct Badge {
+        conn: RustConnection,
+        root: Window,
+        window: Window,
+        screen_width: i32,
+        shown: Option<((i32, i32), bool)>,
+    }
+
+    impl Badge {
+        pub fn open() -> Result<Badge, String> {
+            let (conn, screen) = x11rb::connect(None).map_err(|e| format!("no X display: {}", e))?;
+            let root_screen = &conn.setup().roots[screen];
+            let (root, screen_width) = (root_screen.root, i32::from(root_screen.width_in_pixels));
+            if conn.extension_information(shape::X11_EXTENSION_NAME).ok().flatten().is_none() {
+                return Err("the X server has no Shape extension".to_string());
+            }
+            let window = conn.generate_id().map_err(|e| e.to_string())?;
+            let attributes = CreateWindowAux::new().override_redirect(1).background_pixel(DIM);
+            let rows: Vec<Rectangle> = disc_rows(SIZE)
+                .into_iter()
+                .enumerate()
+                .map(|(y, (x, width))| Rectangle { x: x as i16, y: y as i16, width, height: 1 })
+                .collect();
+            // Round outline, and no input area at all, so clicks land on what is below.
+            conn.create_window(COPY_DEPTH_FROM_PARENT, window, root, 0, 0, SIZE, SIZE, 0, WindowClass::INPUT_OUTPUT, 0, &attributes)
+                .and_then(|_| conn.shape_rectangles(SO::SET, SK::BOUNDING, ClipOrdering::UNSORTED, window, 0, 0, &rows))
+                .and_then(|_| conn.shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, window, 0, 0, &[]))
+                .and_then(|_| conn.map_window(window))
+                .and_then(|_| conn.flush())
+                .map_err(|e| e.to_string())?;
+            Ok(Badge { conn, root, window, screen_width, shown: None })
+        }
+
+        pub fn screen_width(&self) -> i32 {
+            self.screen_width
+        }
+
+        pub fn cursor(&self) -> Option<(i32, i32)> {
+            let pointer = self.conn.query_pointer(self.root).ok()?.reply().ok()?;
+            Some((i32::from(pointer.root_x), i32::from(pointer.root_y)))
+        }
+
+        /// Moves the badge to `at` and lights it up or dims it, if either changed.
+        pub fn show(&mut self, at: (i32, i32), lit: bool) {
+            if self.shown == Some((at, lit)) {
+                return;
+            }
+            self.shown = Some((at, lit));
+            let place = ConfigureWindowAux::new().x(at.0).y(at.1).stack_mode(StackMode::ABOVE);
+            let color = ChangeWindowAttributesAux::new().background_pixel(if lit { LIT } else { DIM });
+            let _ = self.conn.configure_window(self.window, &place);
+            let _ = self.conn.change_window_attributes(self.window, &color);
+            let _ = self.conn.clear_area(false, self.window, 0, 0, 0, 0);
+            let _ = self.conn.flush();
+        }
+    }
+
+    impl Drop for Badge {
+        fn drop(&mut self) {
+            let _ = self.conn.destroy_window(self.window);
+            let _ = self.conn.flush();
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr;
+
+    use super::SIZE;
+
+    type Handle = *mut c_void;
+    type WndProc = unsafe extern "system" fn(Handle, u32, usize, isize) -> isize;
+
+    const WS_POPUP: u32 = 0x8000_0000;
+    const WS_EX_TOPMOST: u32 = 0x0000_0008;
+    const WS_EX_TRANSPARENT: u32 = 0x0000_0020;
+    const WS_EX_TOOLWINDOW: u32 = 0x0000_0080;
+    const WS_EX_LAYERED: u32 = 0x0008_0000;
+    const WS_EX_NOACTIVATE: u32 = 0x0800_0000;
+    const LWA_ALPHA: u32 = 0x2;
+    const SWP_NOSIZE: u32 = 0x0001;
+    const SWP_NOACTIVATE: u32 = 0x0010;
+    const SWP_SHOWWINDOW: u32 = 0x0040;
+    const SM_CXSCREEN: i32 = 0;
+    const PM_REMOVE: u32 = 0x1;
+    // Green, as a COLORREF (0x00bbggrr).
+    const COLOR: u32 = 0x0060_e030;
+    const LIT_ALPHA: u8 = 255;
+    const DIM_ALPHA: u8 = 70;
+
+    #[repr(C)]
+    struct WndClassW {
+        style: u32,
+        wnd_proc: WndProc,
+        cls_extra: i32,
+        wnd_extra: i32,
+        instance: Handle,
+        icon: Handle,
+        cursor: Handle,
+        background: Handle,
+        menu_name: *const u16,
+        class_name: *const u16,
+    }
+
+    #[repr(C)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[repr(C)]
+    struct Msg {
+        hwnd: Handle,
+        message: u32,
+        wparam: usize,
+        lparam: isize,
+        time: u32,
+        pt: Point,
+        private: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleW(name: *const u16) -> Handle;
+    }
+
+    #[link(name = "gdi32")]
+    extern "system" {
+        fn CreateSolidBrush(color: u32) -> Handle;
+        fn CreateEllipticRgn(left: i32, top: i32, right: i32, bottom: i32) -> Handle;
+    }
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn RegisterClassW(class: *const WndClassW) -> u16;
+        fn CreateWindowExW(
+            ex_style: u32,
+            class_name: *const u16,
+            window_name: *const u16,
+            style: u32,
+            x: i32,
+            y: i32,
+            width: i32,
+            height: i32,
+            parent: Handle,
+            menu: Handle,
+            instance: Handle,
+            param: *mut c_void,
+        ) -> Handle;
+        fn DefWindowProcW(hwnd: Handle, message: u32, wparam: usize, lparam: isize) -> isize;
+        fn DestroyWindow(hwnd: Handle) -> i32;
+        fn SetWindowRgn(hwnd: Handle, region: Handle, redraw: i32) -> i32;
+        fn SetLayeredWindowAttributes(hwnd: Handle, key: u32, alpha: u8, flags: u32) -> i32;
+        fn SetWindowPos(hwnd: Handle, after: Handle, x: i32, y: i32, width: i32, height: i32, flags: u32) -> i32;
+        fn GetCursorPos(point: *mut Point) -> i32;
+        fn GetSystemMetrics(index: i32) -> i32;
+        fn PeekMessageW(msg: *mut Msg, hwnd: Handle, min: u32, max: u32, remove: u32) -> i32;
+        fn DispatchMessageW(msg: *const Msg) -> isize;
+    }
+
+    pub struct Badge {
+        hwnd: Handle,
+        shown: Option<((i32, i32), bool)>,
+    }
+
+    impl Badge {
+        pub fn open() -> Result<Badge, String> {
+            let class_name: Vec<u16> = "RustAutoclickerIndicator\0".encode_utf16().collect();
+            let size = i32::from(SIZE);
+            let hwnd = unsafe {
+                let instance = GetModuleHandleW(ptr::null());
+                let class = WndClassW {
+                    style: 0,
+                    wnd_proc: DefWindowProcW,
+                    cls_extra: 0,
+                    wnd_extra: 0,
+                    instance,
+                    icon: ptr::null_mut(),
+                    cursor: ptr::null_mut(),
+                    background: CreateSolidBrush(COLOR),
+                    menu_name: ptr::null(),
+                    class_name: class_name.as_ptr(),
+                };
+                // Fails harmlessly when the class is left from an earlier badge.
+                RegisterClassW(&class);
+                CreateWindowExW(
+                    WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                    class_name.as_ptr(),
+                    ptr::null(),
+                    WS_POPUP,
+                    0,
+                    0,
+                    size,
+                    size,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    instance,
+                    ptr::null_mut(),
+                )
+            };
+            if hwnd.is_null() {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            // A round region, as the disc is shaped on X11.
+            unsafe { SetWindowRgn(hwnd, CreateEllipticRgn(0, 0, size + 1, size + 1), 0) };
+            Ok(Badge { hwnd, shown: None })
+        }
+
+        pub fn screen_width(&self) -> i32 {
+            unsafe { GetSystemMetrics(SM_CXSCREEN) }
+        }
+
+        pub fn cursor(&self) -> Option<(i32, i32)> {
+            let mut point = Point { x: 0, y: 0 };
+            (unsafe { GetCursorPos(&mut point) } != 0).then_some((point.x, point.y))
+        }
+
+        /// Moves the badge to `at` and lights it up or dims it, if either changed.
+        pub fn show(&mut self, at: (i32, i32), lit: bool) {
+            // The window only paints when its messages are handled.
+            let mut msg: Msg = unsafe { std::mem::zeroed() };
+            while unsafe { PeekMessageW(&mut msg, self.hwnd, 0, 0, PM_REMOVE) } != 0 {
+                unsafe { DispatchMessageW(&msg) };
+            }
+            if self.shown == Some((at, lit)) {
+                return;
+            }
+            self.shown = Some((at, lit));
+            unsafe {
+                SetLayeredWindowAttributes(self.hwnd, 0, if lit { LIT_ALPHA } else { DIM_ALPHA }, LWA_ALPHA);
+                // HWND_TOPMOST.
+                SetWindowPos(self.hwnd, -1isize as Handle, at.0, at.1, 0, 0, SWP_NOSIZE | SWP_NOACTIVATE | SWP_SHOWWINDOW);
+            }
+        }
+    }
+
+    impl Drop for Badge {
+        fn drop(&mut self) {
+            unsafe { DestroyWindow(self.hwnd) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub struct Badge;
+
+    impl Badge {
+        pub fn open() -> Result<Badge, String> {
+            Err("not supported on this system".to_string())
+        }
+
+        pub fn screen_width(&self) -> i32 {
+            0
+        }
+
+        pub fn cursor(&self) -> Option<(i32, i32)> {
+            None
+        }
+
+        pub fn show(&mut self, _at: (i32, i32), _lit: bool) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_is_placed_and_rounded() {
+        assert_eq!(position(IndicatorPlace::Off, 1920, Some((5, 5))), None);
+        assert_eq!(position(IndicatorPlace::Corner, 1920, None), Some((1892, 12)));
+        assert_eq!(position(IndicatorPlace::Cursor, 1920, Some((100, 40))), Some((120, 60)));
+        assert_eq!(position(IndicatorPlace::Cursor, 1920, None), None);
+
+        let rows = disc_rows(SIZE);
+        assert_eq!(rows.len(), usize::from(SIZE));
+        // Narrow at the top and bottom, full width through the middle, always centred.
+        assert!(rows[0].1 < SIZE / 2 && rows[0] == rows[usize::from(SIZE) - 1]);
+        assert_eq!(rows[usize::from(SIZE) / 2], (0, SIZE));
+        assert!(rows.iter().all(|&(x, width)| x * 2 + width == SIZE));
+    }
+}
@@ -0,0 +1,167 @@
+// Injection through the Interception driver (Windows, `interception` feature).
+//
+// Strokes are sent to the driver's first mouse and keyboard devices, so games that filter
+// out injected userland input see them like hardware events. interception.dll
+// is loaded at runtime; without it or the driver, creating the backend fails
+// and the engine falls back to enigo.
+
+use std::ffi::c_void;
+
+use enigo::MouseButton;
+use libloading::Library;
+
+use rdev::Key;
+
+use crate::backend::InputBackend;
+use crate::config::KeyInjection;
+use crate::geometry::Point;
+use crate::keys::scancode;
+use crate::layout::display_name;
+
+type Context = *mut c_void;
+type Device = i32;
+
+// First keyboard and mouse devices; keyboards use 1-10.
+const KEYBOARD_DEVICE: Device = 1;
+const MOUSE_DEVICE: Device = 11;
+
+const KEY_UP: u16 = 0x01;
+const KEY_E0: u16 = 0x02;
+
+const LEFT_BUTTON_DOWN: u16 = 0x001;
+const LEFT_BUTTON_UP: u16 = 0x002;
+const RIGHT_BUTTON_DOWN: u16 = 0x004;
+const RIGHT_BUTTON_UP: u16 = 0x008;
+const MIDDLE_BUTTON_DOWN: u16 = 0x010;
+const MIDDLE_BUTTON_UP: u16 = 0x020;
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x001;
+
+// SM_CXSCREEN / SM_CYSCREEN.
+const SCREEN_WIDTH: i32 = 0;
+const SCREEN_HEIGHT: i32 = 1;
+
+#[repr(C)]
+#[derive(Default)]
+struct MouseStroke {
+    state: u16,
+    flags: u16,
+    rolling: i16,
+    x: i32,
+    y: i32,
+    information: u32,
+}
+
+#[repr(C)]
+struct KeyStroke {
+    code: u16,
+    state: u16,
+    information: u32,
+}
+
+type CreateContext = unsafe extern "C" fn() -> Context;
+type DestroyContext = unsafe extern "C" fn(Context);
+type SendStroke = unsafe extern "C" fn(Context, Device, *const c_void, u32) -> i32;
+
+#[link(name = "user32")]
+extern "system" {
+    fn GetSystemMetrics(index: i32) -> i32;
+}
+
+pub struct InterceptionBackend {
+    // Keeps the function pointers below valid.
+    _library: Library,
+    context: Context,
+    destroy: DestroyContext,
+    send: SendStroke,
+}
+
+// The context is a set of device handles the driver allows using from any thread.
+unsafe impl Send for InterceptionBackend {}
+
+impl InterceptionBackend {
+    pub fn new() -> Result<Self, String> {
+        unsafe {
+            let library = Library::new("interception.dll").map_err(|e| format!("interception.dll not found: {}", e))?;
+            let symbol_error = |e: libloading::Error| format!("interception.dll is incompatible: {}", e);
+            let create = *library.get::<CreateContext>(b"interception_create_context").map_err(symbol_error)?;
+            let destroy = *library.get::<DestroyContext>(b"interception_destroy_context").map_err(symbol_error)?;
+            let send = *library.get::<SendStroke>(b"interception_send").map_err(symbol_error)?;
+            let context = create();
+            if context.is_null() {
+                return Err("the Interception driver is not installed".to_string());
+            }
+            Ok(InterceptionBackend { _library: library, context, destroy, send })
+        }
+    }
+
+    fn send_stroke(&mut self, stroke: MouseStroke) {
+        unsafe {
+            (self.send)(self.context, MOUSE_DEVICE, &stroke as *const MouseStroke as *const c_void, 1);
+        }
+    }
+
+    // The driver only takes scancodes, whatever the injection setting.
+    fn send_key(&mut self, key: Key, down: bool) {
+        let Some(code) = scancode(&key) else {
+            eprintln!("{} has no scancode", display_name(&key));
+            return;
+        };
+        let mut state = if down { 0 } else { KEY_UP };
+        if code & 0xFF00 == 0xE000 {
+            state |= KEY_E0;
+        }
+        let stroke = KeyStroke { code: code & 0xFF, state, information: 0 };
+        unsafe {
+            (self.send)(self.context, KEYBOARD_DEVICE, &stroke as *const KeyStroke as *const c_void, 1);
+        }
+    }
+
+    fn button_state(button: MouseButton, down: bool) -> u16 {
+        match (button, down) {
+            (MouseButton::Right, true) => RIGHT_BUTTON_DOWN,
+            (MouseButton::Right, false) => RIGHT_BUTTON_UP,
+            (MouseButton::Middle, true) => MIDDLE_BUTTON_DOWN,
+            (MouseButton::Middle, false) => MIDDLE_BUTTON_UP,
+            (_, true) => LEFT_BUTTON_DOWN,
+            (_, false) => LEFT_BUTTON_UP,
+        }
+    }
+}
+
+impl Drop for InterceptionBackend {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)(self.context) }
+    }
+}
+
+impl InputBackend for InterceptionBackend {
+    fn button_down(&mut self, button: MouseButton) {
+        let state = InterceptionBackend::button_state(button, true);
+        self.send_stroke(MouseStroke { state, ..Default::default() });
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        let state = InterceptionBackend::button_state(button, false);
+        self.send_stroke(MouseStroke { state, ..Default::default() });
+    }
+
+    fn move_to(&mut self, (x, y): Point) {
+        // Absolute coordinates are normalized to 0-65535 across the primary screen.
+        let (width, height) = unsafe { (GetSystemMetrics(SCREEN_WIDTH), GetSystemMetrics(SCREEN_HEIGHT)) };
+        let normalize = |value: f64, size: i32| (value * 65535.0 / size.max(1) as f64).round() as i32;
+        self.send_stroke(MouseStroke {
+            flags: MOUSE_MOVE_ABSOLUTE,
+            x: normalize(x, width),
+            y: normalize(y, height),
+            ..Default::default()
+        });
+    }
+
+    fn key_down(&mut self, key: Key, _injection: KeyInjection) {
+        self.send_key(key, true);
+    }
+
+    fn key_up(&mut self, key: Key, _injection: KeyInjection) {
+        self.send_key(key, false);
+    }
+}
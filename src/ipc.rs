@@ -0,0 +1,266 @@
+// Local control channel between the GUI instance and the companion CLI.
+//
+// The running instance listens on a loopback TCP port. Each connection sends a
+// single command line and receives a single reply line, either "ok <message>"
+// or "err <message>".
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{Config, MAX_CPS, MIN_CPS};
+
+pub const IPC_PORT: u16 = 47653;
+
+// Exit codes used by the CLI subcommands.
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_STOPPED: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NO_INSTANCE: i32 = 3;
+pub const EXIT_REJECTED: i32 = 4;
+
+/// Shared state the control channel is allowed to touch.
+pub struct ControlHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub fast_mode: Arc<AtomicBool>,
+    pub target_cps: Arc<Mutex<f64>>,
+    // Profiles are handed to the GUI, which applies them on its next frame.
+    pub pending_profile: Arc<Mutex<Option<(String, Config)>>>,
+    // The profile in use and every profile to switch to, unsaved ones included.
+    pub profile: Arc<Mutex<String>>,
+    pub profiles: Arc<Mutex<BTreeMap<String, Config>>>,
+    // Asks the running instance to shut down and exit.
+    pub quit: Arc<AtomicBool>,
+}
+
+fn address() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::LOCALHOST, IPC_PORT))
+}
+
+/// Starts the control server on a background thread.
+pub fn spawn_server(handles: ControlHandles) {
+    let listener = match TcpListener::bind(address()) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Control channel unavailable: {}", e);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve(stream, &handles) {
+                eprintln!("Control channel error: {}", e);
+            }
+        }
+    });
+}
+
+fn serve(stream: TcpStream, handles: &ControlHandles) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let reply = match handle_command(line.trim(), handles) {
+        Ok(msg) => format!("ok {}", msg),
+        Err(msg) => format!("err {}", msg),
+    };
+    writeln!(&stream, "{}", reply)
+}
+
+/// Runs one command line, e.g. "set-cps 50", and returns the reply message.
+pub fn handle_command(line: &str, handles: &ControlHandles) -> Result<String, String> {
+    let mut parts = line.splitn(2, ' ');
+    let command = parts.next().unwrap_or("");
+    let arg = parts.next().map(str::trim).unwrap_or("");
+    match command {
+        "start" => {
+            handles.clicking.store(true, Ordering::SeqCst);
+            Ok("running".to_string())
+        }
+        "stop" => {
+            handles.clicking.store(false, Ordering::SeqCst);
+            Ok("stopped".to_string())
+        }
+        "toggle" => {
+            let running = !handles.clicking.fetch_xor(true, Ordering::SeqCst);
+            Ok(if running { "running" } else { "stopped" }.to_string())
+        }
+        "status" => {
+            let state = if handles.clicking.load(Ordering::SeqCst) { "running" } else { "stopped" };
+            let mode = if handles.fast_mode.load(Ordering::SeqCst) {
+                "superspeed".to_string()
+            } else {
+                format!("{} cps", *handles.target_cps.lock().unwrap())
+            };
+            Ok(format!("{} {}", state, mode))
+        }
+        "set-cps" => {
+            let cps = parse_cps(arg)?;
+            *handles.target_cps.lock().unwrap() = cps;
+            handles.fast_mode.store(false, Ordering::SeqCst);
+            Ok(format!("{} cps", cps))
+        }
+        "profile" => {
+            if arg.is_empty() {
+                return Err("missing profile name".to_string());
+            }
+            let config = handles.profiles.lock().unwrap().get(arg).cloned()
+                .ok_or_else(|| format!("unknown profile '{}'", arg))?;
+            // The profile in use may have changed since it was kept there.
+            if *handles.profile.lock().unwrap() != arg {
+                *handles.pending_profile.lock().unwrap() = Some((arg.to_string(), config));
+            }
+            Ok(format!("profile {}", arg))
+        }
+        "quit" => {
+            handles.clicking.store(false, Ordering::SeqCst);
+            handles.quit.store(true, Ordering::SeqCst);
+            Ok("quitting".to_string())
+        }
+        _ => Err(format!("unknown command '{}'", command)),
+    }
+}
+
+fn parse_cps(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(cps) if (MIN_CPS..=MAX_CPS).contains(&cps) => Ok(cps),
+        Ok(_) => Err(format!("cps must be between {} and {}", MIN_CPS, MAX_CPS)),
+        Err(_) => Err(format!("invalid cps '{}'", value)),
+    }
+}
+
+const USAGE: &str = "usage: rust_autoclicker <start|stop|toggle|status|set-cps N|profile NAME|quit>";
+
+/// Command line to send for the CLI `args`: None when they hold no
+/// subcommand, or what to print if they are not a valid one.
+fn command_line(args: &[String]) -> Option<Result<String, String>> {
+    let command = args.first()?;
+    Some(match (command.as_str(), args.len()) {
+        ("start" | "stop" | "toggle" | "status" | "quit", 1) => Ok(command.clone()),
+        ("set-cps", 2) => parse_cps(&args[1]).map(|_| format!("set-cps {}", args[1])),
+        ("profile", n) if n >= 2 => Ok(format!("profile {}", args[1..].join(" "))),
+        _ => Err(USAGE.to_string()),
+    })
+}
+
+/// Runs a CLI subcommand against the running instance and returns the exit code.
+/// Returns `None` when `args` contain no subcommand, meaning the GUI should start.
+pub fn run_cli(args: &[String]) -> Option<i32> {
+    let line = command_line(args)?;
+    attach_console();
+    let line = match line {
+        Ok(line) => line,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    let reply = match send(&line) {
+        Ok(reply) => reply,
+        Err(e) => {
+            eprintln!("No running instance found: {}", e);
+            return Some(EXIT_NO_INSTANCE);
+        }
+    };
+    if let Some(msg) = reply.strip_prefix("ok ") {
+        println!("{}", msg);
+        if args[0] == "status" && !msg.starts_with("running") {
+            return Some(EXIT_STOPPED);
+        }
+        Some(EXIT_OK)
+    } else {
+        eprintln!("{}", reply.strip_prefix("err ").unwrap_or(&reply));
+        Some(EXIT_REJECTED)
+    }
+}
+
+/// Windows GUI builds start without a console, so what the CLI prints would
+/// go nowhere; this hooks stdout and stderr up to the terminal it was run from.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process: u32) -> i32;
+    }
+
+    // Fails when there is no parent console, or one is attached already.
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn send(line: &str) -> std::io::Result<String> {
+    let stream = TcpStream::connect_timeout(&address(), Duration::from_secs(1))?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    writeln!(&stream, "{}", line)?;
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    Ok(reply.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn cli_arguments_become_command_lines() {
+        assert_eq!(command_line(&[]), None);
+        assert_eq!(command_line(&args("toggle")), Some(Ok("toggle".to_string())));
+        assert_eq!(command_line(&args("set-cps 50")), Some(Ok("set-cps 50".to_string())));
+        assert_eq!(command_line(&args("profile pvp fast")), Some(Ok("profile pvp fast".to_string())));
+        assert!(matches!(command_line(&args("set-cps 0")), Some(Err(msg)) if msg.starts_with("cps must be")));
+        assert_eq!(command_line(&args("quit")), Some(Ok("quit".to_string())));
+        for bad in ["start now", "set-cps", "profile", "jump", "quit now"] {
+            assert_eq!(command_line(&args(bad)), Some(Err(USAGE.to_string())), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn commands_change_state_and_reply() {
+        let handles = ControlHandles {
+            clicking: Arc::new(AtomicBool::new(false)),
+            fast_mode: Arc::new(AtomicBool::new(true)),
+            target_cps: Arc::new(Mutex::new(10.0)),
+            pending_profile: Arc::new(Mutex::new(None)),
+            profile: Arc::new(Mutex::new("default".to_string())),
+            profiles: Arc::new(Mutex::new(BTreeMap::new())),
+            quit: Arc::new(AtomicBool::new(false)),
+        };
+        // Profiles made or removed since the last save count, the file doesn't.
+        for name in ["default", "unsaved"] {
+            handles.profiles.lock().unwrap().insert(name.to_string(), Config::default());
+        }
+        assert_eq!(handle_command("status", &handles), Ok("stopped superspeed".to_string()));
+        assert_eq!(handle_command("toggle", &handles), Ok("running".to_string()));
+        assert!(handles.clicking.load(Ordering::SeqCst));
+        assert_eq!(handle_command("set-cps 25", &handles), Ok("25 cps".to_string()));
+        assert_eq!(handle_command("status", &handles), Ok("running 25 cps".to_string()));
+        assert_eq!(handle_command("stop", &handles), Ok("stopped".to_string()));
+        assert_eq!(handle_command("set-cps fast", &handles), Err("invalid cps 'fast'".to_string()));
+        assert_eq!(handle_command("profile", &handles), Err("missing profile name".to_string()));
+        assert_eq!(handle_command("jump", &handles), Err("unknown command 'jump'".to_string()));
+        assert!(handles.pending_profile.lock().unwrap().is_none());
+        assert_eq!(handle_command("profile default", &handles), Ok("profile default".to_string()));
+        assert!(handles.pending_profile.lock().unwrap().is_none());
+        assert_eq!(handle_command("profile unsaved", &handles), Ok("profile unsaved".to_string()));
+        assert_eq!(handles.pending_profile.lock().unwrap().take().map(|(name, _)| name), Some("unsaved".to_string()));
+        handles.profiles.lock().unwrap().remove("unsaved");
+        assert_eq!(handle_command("profile unsaved", &handles), Err("unknown profile 'unsaved'".to_string()));
+        handles.clicking.store(true, Ordering::SeqCst);
+        assert_eq!(handle_command("quit", &handles), Ok("quitting".to_string()));
+        assert!(handles.quit.load(Ordering::SeqCst) && !handles.clicking.load(Ordering::SeqCst));
+    }
+}
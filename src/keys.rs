@@ -0,0 +1,377 @@
+/// Every key the hotkey mapping understands. `rdev::Key::Unknown` is left out
+/// because raw key codes differ between platforms.
+pub const SUPPORTED_KEYS: &[rdev::Key] = &[
+    // Function row.
+    rdev::Key::Escape, rdev::Key::F1, rdev::Key::F2, rdev::Key::F3, rdev::Key::F4, rdev::Key::F5,
+    rdev::Key::F6, rdev::Key::F7, rdev::Key::F8, rdev::Key::F9, rdev::Key::F10, rdev::Key::F11,
+    rdev::Key::F12, rdev::Key::PrintScreen, rdev::Key::ScrollLock, rdev::Key::Pause,
+    // Number row.
+    rdev::Key::BackQuote, rdev::Key::Num1, rdev::Key::Num2, rdev::Key::Num3, rdev::Key::Num4,
+    rdev::Key::Num5, rdev::Key::Num6, rdev::Key::Num7, rdev::Key::Num8, rdev::Key::Num9,
+    rdev::Key::Num0, rdev::Key::Minus, rdev::Key::Equal, rdev::Key::Backspace,
+    // Letters.
+    rdev::Key::KeyA, rdev::Key::KeyB, rdev::Key::KeyC, rdev::Key::KeyD, rdev::Key::KeyE,
+    rdev::Key::KeyF, rdev::Key::KeyG, rdev::Key::KeyH, rdev::Key::KeyI, rdev::Key::KeyJ,
+    rdev::Key::KeyK, rdev::Key::KeyL, rdev::Key::KeyM, rdev::Key::KeyN, rdev::Key::KeyO,
+    rdev::Key::KeyP, rdev::Key::KeyQ, rdev::Key::KeyR, rdev::Key::KeyS, rdev::Key::KeyT,
+    rdev::Key::KeyU, rdev::Key::KeyV, rdev::Key::KeyW, rdev::Key::KeyX, rdev::Key::KeyY,
+    rdev::Key::KeyZ,
+    // Punctuation.
+    rdev::Key::LeftBracket, rdev::Key::RightBracket, rdev::Key::BackSlash, rdev::Key::IntlBackslash,
+    rdev::Key::SemiColon, rdev::Key::Quote, rdev::Key::Comma, rdev::Key::Dot, rdev::Key::Slash,
+    // Modifiers and whitespace.
+    rdev::Key::Tab, rdev::Key::CapsLock, rdev::Key::ShiftLeft, rdev::Key::ShiftRight,
+    rdev::Key::ControlLeft, rdev::Key::ControlRight, rdev::Key::MetaLeft, rdev::Key::MetaRight,
+    rdev::Key::Alt, rdev::Key::AltGr, rdev::Key::Function, rdev::Key::Space, rdev::Key::Return,
+    // Navigation cluster.
+    rdev::Key::Insert, rdev::Key::Delete, rdev::Key::Home, rdev::Key::End, rdev::Key::PageUp,
+    rdev::Key::PageDown, rdev::Key::UpArrow, rdev::Key::DownArrow, rdev::Key::LeftArrow,
+    rdev::Key::RightArrow,
+    // Numpad.
+    rdev::Key::NumLock, rdev::Key::KpDivide, rdev::Key::KpMultiply, rdev::Key::KpMinus,
+    rdev::Key::KpPlus, rdev::Key::KpReturn, rdev::Key::KpDelete, rdev::Key::Kp0, rdev::Key::Kp1,
+    rdev::Key::Kp2, rdev::Key::Kp3, rdev::Key::Kp4, rdev::Key::Kp5, rdev::Key::Kp6, rdev::Key::Kp7,
+    rdev::Key::Kp8, rdev::Key::Kp9,
+];
+
+// Utility functions for converting rdev::Key to/from String.
+// Letters are written as "A".."Z", the number row as "Digit1" and the numpad
+// as "Numpad1", since rdev's "Num1" reads like a numpad key but is the number
+// row. Every other key is written as its rdev variant name.
+pub fn key_to_string(key: &rdev::Key) -> String {
+    match key {
+        rdev::Key::KeyA => "A".to_string(),
+        rdev::Key::KeyB => "B".to_string(),
+        rdev::Key::KeyC => "C".to_string(),
+        rdev::Key::KeyD => "D".to_string(),
+        rdev::Key::KeyE => "E".to_string(),
+        rdev::Key::KeyF => "F".to_string(),
+        rdev::Key::KeyG => "G".to_string(),
+        rdev::Key::KeyH => "H".to_string(),
+        rdev::Key::KeyI => "I".to_string(),
+        rdev::Key::KeyJ => "J".to_string(),
+        rdev::Key::KeyK => "K".to_string(),
+        rdev::Key::KeyL => "L".to_string(),
+        rdev::Key::KeyM => "M".to_string(),
+        rdev::Key::KeyN => "N".to_string(),
+        rdev::Key::KeyO => "O".to_string(),
+        rdev::Key::KeyP => "P".to_string(),
+        rdev::Key::KeyQ => "Q".to_string(),
+        rdev::Key::KeyR => "R".to_string(),
+        rdev::Key::KeyS => "S".to_string(),
+        rdev::Key::KeyT => "T".to_string(),
+        rdev::Key::KeyU => "U".to_string(),
+        rdev::Key::KeyV => "V".to_string(),
+        rdev::Key::KeyW => "W".to_string(),
+        rdev::Key::KeyX => "X".to_string(),
+        rdev::Key::KeyY => "Y".to_string(),
+        rdev::Key::KeyZ => "Z".to_string(),
+        rdev::Key::Num0 => "Digit0".to_string(),
+        rdev::Key::Num1 => "Digit1".to_string(),
+        rdev::Key::Num2 => "Digit2".to_string(),
+        rdev::Key::Num3 => "Digit3".to_string(),
+        rdev::Key::Num4 => "Digit4".to_string(),
+        rdev::Key::Num5 => "Digit5".to_string(),
+        rdev::Key::Num6 => "Digit6".to_string(),
+        rdev::Key::Num7 => "Digit7".to_string(),
+        rdev::Key::Num8 => "Digit8".to_string(),
+        rdev::Key::Num9 => "Digit9".to_string(),
+        rdev::Key::Kp0 => "Numpad0".to_string(),
+        rdev::Key::Kp1 => "Numpad1".to_string(),
+        rdev::Key::Kp2 => "Numpad2".to_string(),
+        rdev::Key::Kp3 => "Numpad3".to_string(),
+        rdev::Key::Kp4 => "Numpad4".to_string(),
+        rdev::Key::Kp5 => "Numpad5".to_string(),
+        rdev::Key::Kp6 => "Numpad6".to_string(),
+        rdev::Key::Kp7 => "Numpad7".to_string(),
+        rdev::Key::Kp8 => "Numpad8".to_string(),
+        rdev::Key::Kp9 => "Numpad9".to_string(),
+        rdev::Key::KpReturn => "NumpadEnter".to_string(),
+        rdev::Key::KpMinus => "NumpadMinus".to_string(),
+        rdev::Key::KpPlus => "NumpadPlus".to_string(),
+        rdev::Key::KpMultiply => "NumpadMultiply".to_string(),
+        rdev::Key::KpDivide => "NumpadDivide".to_string(),
+        rdev::Key::KpDelete => "NumpadDecimal".to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+/// Parses a key name case-insensitively, ignoring spaces and underscores. The
+/// names written by `key_to_string`, the plain rdev variant names written by
+/// earlier versions (e.g. "KeyA", "Num1", "Kp1") and a bare digit for the
+/// number row are accepted.
+pub fn string_to_rdev_key(s: &str) -> Option<rdev::Key> {
+    let mut s: String = s.chars().filter(|c| !c.is_whitespace() && *c != '_').collect();
+    if s.len() == 1 && s.as_bytes()[0].is_ascii_digit() {
+        s.insert_str(0, "Digit");
+    }
+    SUPPORTED_KEYS.iter().copied().find(|key| {
+        key_to_string(key).eq_ignore_ascii_case(&s) || format!("{:?}", key).eq_ignore_ascii_case(&s)
+    })
+}
+
+/// US name shown in the UI, e.g. "Left Ctrl" or "Numpad 5". Not meant to be
+/// parsed back; layout::display_name labels character keys as on the user's
+/// keyboard layout instead.
+pub fn display_name(key: &rdev::Key) -> String {
+    use rdev::Key::*;
+    let name = match key {
+        ControlLeft => "Left Ctrl",
+        ControlRight => "Right Ctrl",
+        ShiftLeft => "Left Shift",
+        ShiftRight => "Right Shift",
+        MetaLeft => "Left Meta",
+        MetaRight => "Right Meta",
+        AltGr => "Alt Gr",
+        CapsLock => "Caps Lock",
+        Return => "Enter",
+        Escape => "Esc",
+        PrintScreen => "Print Screen",
+        ScrollLock => "Scroll Lock",
+        NumLock => "Num Lock",
+        PageUp => "Page Up",
+        PageDown => "Page Down",
+        UpArrow => "Up",
+        DownArrow => "Down",
+        LeftArrow => "Left",
+        RightArrow => "Right",
+        Function => "Fn",
+        BackQuote => "`",
+        Num0 => "0",
+        Num1 => "1",
+        Num2 => "2",
+        Num3 => "3",
+        Num4 => "4",
+        Num5 => "5",
+        Num6 => "6",
+        Num7 => "7",
+        Num8 => "8",
+        Num9 => "9",
+        Minus => "-",
+        Equal => "=",
+        LeftBracket => "[",
+        RightBracket => "]",
+        SemiColon => ";",
+        Quote => "'",
+        BackSlash => "\\",
+        IntlBackslash => "\\ (ISO)",
+        Comma => ",",
+        Dot => ".",
+        Slash => "/",
+        KpReturn => "Numpad Enter",
+        KpMinus => "Numpad -",
+        KpPlus => "Numpad +",
+        KpMultiply => "Numpad *",
+        KpDivide => "Numpad /",
+        KpDelete => "Numpad .",
+        Kp0 => "Numpad 0",
+        Kp1 => "Numpad 1",
+        Kp2 => "Numpad 2",
+        Kp3 => "Numpad 3",
+        Kp4 => "Numpad 4",
+        Kp5 => "Numpad 5",
+        Kp6 => "Numpad 6",
+        Kp7 => "Numpad 7",
+        Kp8 => "Numpad 8",
+        Kp9 => "Numpad 9",
+        Unknown(code) => return format!("Unknown key (code {})", code),
+        _ => return key_to_string(key),
+    };
+    name.to_string()
+}
+
+/// PC scancode (set 1) of a key, with 0xE0 in the high byte for extended
+/// keys. Pause and Fn have no single make code.
+pub fn scancode(key: &rdev::Key) -> Option<u16> {
+    use rdev::Key::*;
+    let code = match key {
+        Escape => 0x01,
+        Num1 => 0x02,
+        Num2 => 0x03,
+        Num3 => 0x04,
+        Num4 => 0x05,
+        Num5 => 0x06,
+        Num6 => 0x07,
+        Num7 => 0x08,
+        Num8 => 0x09,
+        Num9 => 0x0A,
+        Num0 => 0x0B,
+        Minus => 0x0C,
+        Equal => 0x0D,
+        Backspace => 0x0E,
+        Tab => 0x0F,
+        KeyQ => 0x10,
+        KeyW => 0x11,
+        KeyE => 0x12,
+        KeyR => 0x13,
+        KeyT => 0x14,
+        KeyY => 0x15,
+        KeyU => 0x16,
+        KeyI => 0x17,
+        KeyO => 0x18,
+        KeyP => 0x19,
+        LeftBracket => 0x1A,
+        RightBracket => 0x1B,
+        Return => 0x1C,
+        ControlLeft => 0x1D,
+        KeyA => 0x1E,
+        KeyS => 0x1F,
+        KeyD => 0x20,
+        KeyF => 0x21,
+        KeyG => 0x22,
+        KeyH => 0x23,
+        KeyJ => 0x24,
+        KeyK => 0x25,
+        KeyL => 0x26,
+        SemiColon => 0x27,
+        Quote => 0x28,
+        BackQuote => 0x29,
+        ShiftLeft => 0x2A,
+        BackSlash => 0x2B,
+        KeyZ => 0x2C,
+        KeyX => 0x2D,
+        KeyC => 0x2E,
+        KeyV => 0x2F,
+        KeyB => 0x30,
+        KeyN => 0x31,
+        KeyM => 0x32,
+        Comma => 0x33,
+        Dot => 0x34,
+        Slash => 0x35,
+        ShiftRight => 0x36,
+        KpMultiply => 0x37,
+        Alt => 0x38,
+        Space => 0x39,
+        CapsLock => 0x3A,
+        F1 => 0x3B,
+        F2 => 0x3C,
+        F3 => 0x3D,
+        F4 => 0x3E,
+        F5 => 0x3F,
+        F6 => 0x40,
+        F7 => 0x41,
+        F8 => 0x42,
+        F9 => 0x43,
+        F10 => 0x44,
+        NumLock => 0x45,
+        ScrollLock => 0x46,
+        Kp7 => 0x47,
+        Kp8 => 0x48,
+        Kp9 => 0x49,
+        KpMinus => 0x4A,
+        Kp4 => 0x4B,
+        Kp5 => 0x4C,
+        Kp6 => 0x4D,
+        KpPlus => 0x4E,
+        Kp1 => 0x4F,
+        Kp2 => 0x50,
+        Kp3 => 0x51,
+        Kp0 => 0x52,
+        KpDelete => 0x53,
+        IntlBackslash => 0x56,
+        F11 => 0x57,
+        F12 => 0x58,
+        KpReturn => 0xE01C,
+        ControlRight => 0xE01D,
+        KpDivide => 0xE035,
+        PrintScreen => 0xE037,
+        AltGr => 0xE038,
+        Home => 0xE047,
+        UpArrow => 0xE048,
+        PageUp => 0xE049,
+        LeftArrow => 0xE04B,
+        RightArrow => 0xE04D,
+        End => 0xE04F,
+        DownArrow => 0xE050,
+        PageDown => 0xE051,
+        Insert => 0xE052,
+        Delete => 0xE053,
+        MetaLeft => 0xE05B,
+        MetaRight => 0xE05C,
+        Pause | Function | Unknown(_) => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rdev::Key::*;
+
+    // Fails to compile when rdev adds a key, as a reminder to extend SUPPORTED_KEYS.
+    #[allow(dead_code)]
+    fn every_variant_is_considered(key: rdev::Key) {
+        match key {
+            Alt | AltGr | Backspace | CapsLock | ControlLeft | ControlRight | Delete | DownArrow | End | Escape | F1 | F10 | F11 | F12 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | Home | LeftArrow | MetaLeft | MetaRight | PageDown | PageUp | Return | RightArrow | ShiftLeft | ShiftRight | Space | Tab | UpArrow | PrintScreen | ScrollLock | Pause | NumLock | BackQuote | Num1 | Num2 | Num3 | Num4 | Num5 | Num6 | Num7 | Num8 | Num9 | Num0 | Minus | Equal | KeyQ | KeyW | KeyE | KeyR | KeyT | KeyY | KeyU | KeyI | KeyO | KeyP | LeftBracket | RightBracket | KeyA | KeyS | KeyD | KeyF | KeyG | KeyH | KeyJ | KeyK | KeyL | SemiColon | Quote | BackSlash | IntlBackslash | KeyZ | KeyX | KeyC | KeyV | KeyB | KeyN | KeyM | Comma | Dot | Slash | Insert | KpReturn | KpMinus | KpPlus | KpMultiply | KpDivide | Kp0 | Kp1 | Kp2 | Kp3 | Kp4 | Kp5 | Kp6 | Kp7 | Kp8 | Kp9 | KpDelete | Function | Unknown(_) => {}
+        }
+    }
+
+    #[test]
+    fn supported_keys_round_trip() {
+        for key in SUPPORTED_KEYS {
+            let name = key_to_string(key);
+            assert_eq!(string_to_rdev_key(&name), Some(*key), "{}", name);
+            assert_eq!(string_to_rdev_key(&name.to_lowercase()), Some(*key), "{}", name);
+            assert_eq!(string_to_rdev_key(&name.to_uppercase()), Some(*key), "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_key_is_supported_once() {
+        // 105 variants besides Unknown.
+        assert_eq!(SUPPORTED_KEYS.len(), 105);
+        let mut names: Vec<String> = SUPPORTED_KEYS.iter().map(key_to_string).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), SUPPORTED_KEYS.len());
+
+        let mut labels: Vec<String> = SUPPORTED_KEYS.iter().map(display_name).collect();
+        labels.sort();
+        labels.dedup();
+        assert_eq!(labels.len(), SUPPORTED_KEYS.len());
+    }
+
+    #[test]
+    fn scancodes_are_unique() {
+        let mut codes: Vec<u16> = SUPPORTED_KEYS.iter().filter_map(scancode).collect();
+        assert_eq!(codes.len(), SUPPORTED_KEYS.len() - 2);
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), SUPPORTED_KEYS.len() - 2);
+        assert_eq!(scancode(&KeyA), Some(0x1E));
+        assert_eq!(scancode(&UpArrow), Some(0xE048));
+    }
+
+    #[test]
+    fn names_written_by_earlier_versions_still_parse() {
+        assert_eq!(string_to_rdev_key("Insert"), Some(Insert));
+        assert_eq!(string_to_rdev_key("f7"), Some(F7));
+        assert_eq!(string_to_rdev_key("q"), Some(KeyQ));
+        assert_eq!(string_to_rdev_key("Num5"), Some(Num5));
+        assert_eq!(string_to_rdev_key("ScrollLock"), Some(ScrollLock));
+        assert_eq!(string_to_rdev_key("LeftBracket"), Some(LeftBracket));
+        assert_eq!(string_to_rdev_key("Kp5"), Some(Kp5));
+        assert_eq!(string_to_rdev_key("KeyA"), Some(KeyA));
+    }
+
+    #[test]
+    fn numpad_keys_stay_apart_from_the_number_row() {
+        assert_eq!(key_to_string(&Num1), "Digit1");
+        assert_eq!(key_to_string(&Kp1), "Numpad1");
+        assert_eq!(string_to_rdev_key("1"), Some(Num1));
+        assert_eq!(string_to_rdev_key("Num1"), Some(Num1));
+        assert_eq!(string_to_rdev_key("Kp1"), Some(Kp1));
+        assert_eq!(string_to_rdev_key("Numpad 1"), Some(Kp1));
+        assert_eq!(string_to_rdev_key("KP_1"), Some(Kp1));
+        assert_eq!(string_to_rdev_key("Numpad Enter"), Some(KpReturn));
+        assert_eq!(string_to_rdev_key("numpad_decimal"), Some(KpDelete));
+        assert_eq!(string_to_rdev_key("12"), None);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert_eq!(string_to_rdev_key(&key_to_string(&Unknown(42))), None);
+        assert_eq!(string_to_rdev_key(""), None);
+        assert_eq!(string_to_rdev_key("F13"), None);
+    }
+}
@@ -0,0 +1,173 @@
+// Keyboard layout labels: the character a key types on the user's layout, so
+// a German user sees "Z" for the key rdev calls KeyY. Read once, from the X
+// server's keyboard mapping on Linux and with MapVirtualKey on Windows. Keys
+// that type no character, keysyms X only has legacy codes for (Cyrillic,
+// Greek...) and other systems keep the US names.
+
+use std::sync::OnceLock;
+
+use crate::keys::{self, scancode, SUPPORTED_KEYS};
+
+type Labels = Vec<(rdev::Key, String)>;
+
+static LABELS: OnceLock<Labels> = OnceLock::new();
+
+/// Name shown in the UI, e.g. "Left Ctrl" or "Numpad 5", with character keys
+/// labelled as on the user's keyboard layout. Not meant to be parsed back.
+pub fn display_name(key: &rdev::Key) -> String {
+    name_in(LABELS.get_or_init(read_labels), key)
+}
+
+/// What `key` is labelled in `labels`, or its US name if it isn't.
+fn name_in(labels: &[(rdev::Key, String)], key: &rdev::Key) -> String {
+    labels.iter().find(|(labelled, _)| labelled == key).map_or_else(|| keys::display_name(key), |(_, label)| label.clone())
+}
+
+/// The labels that name one key only. Keys typing the same character, like
+/// BackSlash and IntlBackslash on a US layout, or typing what another key is
+/// called, keep their US names so no two keys look alike.
+fn distinct(mut labels: Labels) -> Labels {
+    loop {
+        let names: Vec<String> = SUPPORTED_KEYS.iter().map(|key| name_in(&labels, key)).collect();
+        let before = labels.len();
+        labels.retain(|(_, label)| names.iter().filter(|name| *name == label).count() == 1);
+        if labels.len() == before {
+            return labels;
+        }
+    }
+}
+
+/// Keys whose character depends on the layout.
+fn types_character(key: &rdev::Key) -> bool {
+    use rdev::Key::*;
+    matches!(
+        key,
+        KeyA | KeyB | KeyC | KeyD | KeyE | KeyF | KeyG | KeyH | KeyI | KeyJ | KeyK | KeyL | KeyM | KeyN | KeyO | KeyP | KeyQ
+            | KeyR | KeyS | KeyT | KeyU | KeyV | KeyW | KeyX | KeyY | KeyZ
+            | Num0 | Num1 | Num2 | Num3 | Num4 | Num5 | Num6 | Num7 | Num8 | Num9
+            | BackQuote | Minus | Equal | LeftBracket | RightBracket | SemiColon | Quote | BackSlash | IntlBackslash | Comma | Dot | Slash
+    )
+}
+
+fn read_labels() -> Labels {
+    let keys: Vec<(rdev::Key, u16)> =
+        SUPPORTED_KEYS.iter().filter(|key| types_character(key)).filter_map(|key| Some((*key, scancode(key)?))).collect();
+    let codes: Vec<u16> = keys.iter().map(|&(_, code)| code).collect();
+    let Some(characters) = platform::characters(&codes) else { return Vec::new() };
+    distinct(keys.into_iter().zip(characters).filter_map(|((key, _), character)| Some((key, to_label(character?)?))).collect())
+}
+
+/// A typed character as a key label: letters in upper case, nothing for
+/// blanks and control characters.
+fn to_label(character: char) -> Option<String> {
+    if character.is_whitespace() || character.is_control() {
+        return None;
+    }
+    let mut upper = character.to_uppercase();
+    // "ß" would become "SS"; a label is one character.
+    Some(match (upper.next(), upper.next()) {
+        (Some(single), None) => single.to_string(),
+        _ => character.to_string(),
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    // X key codes are evdev codes plus 8; for the character keys the evdev
+    // codes are the set 1 scancodes.
+    const EVDEV_OFFSET: usize = 8;
+
+    /// The unshifted character of each key, by scancode.
+    pub fn characters(codes: &[u16]) -> Option<Vec<Option<char>>> {
+        let (conn, _) = x11rb::connect(None).ok()?;
+        let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+        let mapping = conn.get_keyboard_mapping(min, max - min + 1).ok()?.reply().ok()?;
+        let per_key = usize::from(mapping.keysyms_per_keycode);
+        let character = |code: u16| {
+            let index = (usize::from(code) + EVDEV_OFFSET).checked_sub(usize::from(min))? * per_key;
+            keysym_char(*mapping.keysyms.get(index)?)
+        };
+        Some(codes.iter().map(|&code| character(code)).collect())
+    }
+
+    /// Latin-1 keysyms are their own code points; Unicode ones are offset.
+    pub fn keysym_char(keysym: u32) -> Option<char> {
+        match keysym {
+            0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+            0x0100_0000..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    const MAPVK_VSC_TO_VK: u32 = 1;
+    const MAPVK_VK_TO_CHAR: u32 = 2;
+    // Set on the characters of dead keys.
+    const DEAD_KEY: u32 = 0x8000_0000;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn MapVirtualKeyW(code: u32, map_type: u32) -> u32;
+    }
+
+    /// The unshifted character of each key, by scancode.
+    pub fn characters(codes: &[u16]) -> Option<Vec<Option<char>>> {
+        let character = |code: u16| {
+            let vk = unsafe { MapVirtualKeyW(u32::from(code), MAPVK_VSC_TO_VK) };
+            let character = unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_CHAR) } & !DEAD_KEY;
+            char::from_u32(character).filter(|&c| vk != 0 && c != '\0')
+        };
+        Some(codes.iter().map(|&code| character(code)).collect())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub fn characters(_codes: &[u16]) -> Option<Vec<Option<char>>> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_characters_become_labels() {
+        assert_eq!(to_label('z').as_deref(), Some("Z"));
+        assert_eq!(to_label('ö').as_deref(), Some("Ö"));
+        assert_eq!(to_label('ß').as_deref(), Some("ß"));
+        assert_eq!(to_label('<').as_deref(), Some("<"));
+        assert_eq!(to_label(' '), None);
+        assert_eq!(SUPPORTED_KEYS.iter().filter(|key| types_character(key)).count(), 48);
+
+        use rdev::Key::*;
+        let labels = distinct(vec![
+            (BackSlash, "\\".to_string()),
+            (IntlBackslash, "\\".to_string()),
+            (KeyY, "Z".to_string()),
+            (KeyZ, "Y".to_string()),
+            // Looks like the unlabelled KeyA.
+            (KeyQ, "A".to_string()),
+        ]);
+        assert_eq!(labels, [(KeyY, "Z".to_string()), (KeyZ, "Y".to_string())]);
+        assert_eq!(name_in(&labels, &BackSlash), keys::display_name(&BackSlash));
+        let mut names: Vec<String> = SUPPORTED_KEYS.iter().map(|key| name_in(&labels, key)).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), SUPPORTED_KEYS.len());
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(platform::keysym_char(0x7a), Some('z'));
+            assert_eq!(platform::keysym_char(0xf6), Some('ö'));
+            assert_eq!(platform::keysym_char(0x0100_20ac), Some('€'));
+            // dead_circumflex
+            assert_eq!(platform::keysym_char(0xfe52), None);
+        }
+    }
+}
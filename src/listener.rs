@@ -0,0 +1,510 @@
+// Global input listener (built with the `listener` feature).
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+use rdev::{listen, Button, Event, EventType, Key};
+
+use crate::config::{Activation, CHORD_WINDOW, MAX_CPS, MIN_CPS};
+use crate::keys::{key_to_string, string_to_rdev_key};
+use crate::macros;
+use crate::pause;
+use crate::state::Shared;
+use crate::stats::Latency;
+
+// Activation gesture timings.
+pub const HOLD_TIME: Duration = Duration::from_millis(500);
+pub const DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+// First wait before restarting a failed listener; it doubles after every failure.
+const RETRY_MIN: Duration = Duration::from_secs(1);
+// A listener that ran this long before failing starts over from the first wait.
+const RETRY_RESET: Duration = Duration::from_secs(60);
+const RETRY_POLL: Duration = Duration::from_millis(100);
+
+/// Recognizes the activation gesture from hotkey presses and releases.
+#[derive(Default)]
+struct Gesture {
+    // Start of the current press; auto-repeat presses while down are ignored.
+    down_since: Option<Instant>,
+    // Whether the current hold already toggled.
+    hold_fired: bool,
+    // First tap of a possible double tap.
+    last_tap: Option<Instant>,
+}
+
+impl Gesture {
+    /// Returns true if this press toggles the clicker right away.
+    fn press(&mut self, activation: Activation, now: Instant) -> bool {
+        if self.down_since.is_some() {
+            return false;
+        }
+        self.down_since = Some(now);
+        self.hold_fired = false;
+        match activation {
+            Activation::Press => true,
+            Activation::Hold => false,
+            Activation::DoubleTap => match self.last_tap.take() {
+                Some(first) if now.duration_since(first) <= DOUBLE_TAP_WINDOW => true,
+                _ => {
+                    self.last_tap = Some(now);
+                    false
+                }
+            },
+        }
+    }
+
+    fn release(&mut self) {
+        self.down_since = None;
+    }
+
+    /// Returns true once the key has been held long enough to toggle.
+    fn poll(&mut self, activation: Activation, now: Instant) -> bool {
+        let held = self.down_since.is_some_and(|since| now.duration_since(since) >= HOLD_TIME);
+        if activation == Activation::Hold && held && !self.hold_fired {
+            self.hold_fired = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Recognizes the first key of a chord in front of the hotkey.
+#[derive(Default)]
+struct Chord {
+    // Last press of the first key, if no other key came since.
+    armed: Option<Instant>,
+}
+
+impl Chord {
+    /// Returns true if this press of `key` can count as the hotkey: always
+    /// without a `first` key, else only within `CHORD_WINDOW` of pressing it.
+    fn press(&mut self, key: Key, first: Option<Key>, hotkey: Key, now: Instant) -> bool {
+        let Some(first) = first else { return true };
+        if key == hotkey {
+            // Kept armed, so a double tap works as the second step too.
+            self.armed.is_some_and(|at| now.duration_since(at) <= CHORD_WINDOW)
+        } else {
+            self.armed = (key == first).then_some(now);
+            false
+        }
+    }
+}
+
+/// Follows the turbo button as the user holds it, apart from the presses
+/// the clicker sends.
+#[derive(Default)]
+struct Turbo {
+    held: bool,
+}
+
+impl Turbo {
+    /// Takes a real press (`down`) or release of `button`, and returns
+    /// whether clicking should run now if that changed. A turbo button the
+    /// clicker clicks itself is refused, since its own releases could be taken
+    /// for the user's.
+    fn button(&mut self, button: MouseButton, down: bool, turbo: Option<MouseButton>, clicked: &[MouseButton]) -> Option<bool> {
+        if turbo != Some(button) {
+            return None;
+        }
+        if clicked.contains(&button) {
+            self.held = false;
+            return None;
+        }
+        (self.held != down).then(|| {
+            self.held = down;
+            down
+        })
+    }
+}
+
+fn mouse_button(button: Button) -> Option<MouseButton> {
+    match button {
+        Button::Left => Some(MouseButton::Left),
+        Button::Middle => Some(MouseButton::Middle),
+        Button::Right => Some(MouseButton::Right),
+        _ => None,
+    }
+}
+
+/// Target CPS after one press of a CPS hotkey.
+fn nudged_cps(cps: f64, step: f64, up: bool) -> f64 {
+    let cps = if up { cps + step } else { cps - step };
+    cps.clamp(MIN_CPS, MAX_CPS)
+}
+
+fn toggle(clicking: &AtomicBool) {
+    let new_state = !clicking.load(Ordering::SeqCst);
+    clicking.store(new_state, Ordering::SeqCst);
+}
+
+/// Wait before the restart after `wait`, at most `longest`.
+fn next_retry(wait: Duration, longest: Duration) -> Duration {
+    (wait * 2).min(longest).max(RETRY_MIN)
+}
+
+/// Toggles clicking; a start is timed from `pressed` to its first click.
+fn toggle_timed(clicking: &AtomicBool, latency: &Mutex<Latency>, pressed: Instant) {
+    toggle(clicking);
+    let mut latency = latency.lock().unwrap();
+    match clicking.load(Ordering::SeqCst) {
+        true => latency.pressed(pressed),
+        false => latency.cancel(),
+    }
+}
+
+/// Global event listener for toggling, hotkey change, and CPS counting.
+/// Restarted with a growing wait whenever listening fails.
+pub fn spawn(shared: &Shared) {
+    let listener_error = shared.listener_error.clone();
+    let retry_listener = shared.retry_listener.clone();
+    let listener_retry = shared.listener_retry.clone();
+    let shutdown = shared.shutdown.clone();
+    let listener_log = shared.log.clone();
+    let clicking_for_listener = shared.clicking.clone();
+    let hotkey = shared.hotkey.clone();
+    let changing_hotkey = shared.changing_hotkey.clone();
+    let rejected_key = shared.rejected_key.clone();
+    let activation = shared.activation.clone();
+    let clicks = shared.clicks.clone();
+    let cursor = shared.cursor.clone();
+    let picked_points = shared.picked_points.clone();
+    let macro_playing = shared.macro_playing.clone();
+    let bench_running = shared.bench_running.clone();
+    let abort_key = shared.abort_key.clone();
+    let step_key = shared.step_key.clone();
+    let macro_hotkeys = shared.macro_hotkeys.clone();
+    let macro_handles = shared.macro_handles();
+    let recording = shared.recording.clone();
+    let own_window = shared.own_window.clone();
+    let chord_key = shared.chord_key.clone();
+    let turbo_button = shared.turbo_button.clone();
+    let selected_buttons = shared.selected_buttons.clone();
+    let activity = shared.activity.clone();
+    let receipts = shared.receipts.clone();
+    let latency = shared.latency.clone();
+    let (cps_up_key, cps_down_key) = (shared.cps_up_key.clone(), shared.cps_down_key.clone());
+    let cps_step = shared.cps_step.clone();
+    let cps_nudged = shared.cps_nudged.clone();
+    let target_cps = shared.target_cps.clone();
+    let log = shared.log.clone();
+    let typer = shared.typer.clone();
+    let typer_running = shared.typer_running.clone();
+    let pause_key = shared.pause_key.clone();
+    let pause_handles = shared.pause_handles();
+    thread::spawn(move || {
+        // Key just captured as the hotkey; its auto-repeat presses are ignored
+        // until it is released so the capture doesn't also toggle the clicker.
+        let mut captured: Option<Key> = None;
+        let gesture = Arc::new(Mutex::new(Gesture::default()));
+        let mut chord = Chord::default();
+        let mut turbo = Turbo::default();
+        let callback = move |event: Event| {
+            // Input the clicker sends itself, recognized as it comes back,
+            // doesn't show the user is there.
+            let clicking = clicking_for_listener.load(Ordering::SeqCst);
+            let playing = macro_playing.load(Ordering::SeqCst);
+            let sent = match event.event_type {
+                EventType::KeyPress(key) => activity.injected_key(key, true),
+                EventType::KeyRelease(key) => activity.injected_key(key, false),
+                EventType::ButtonPress(button) => mouse_button(button).is_some_and(|button| activity.injected_button(button, true)),
+                EventType::ButtonRelease(button) => mouse_button(button).is_some_and(|button| activity.injected_button(button, false)),
+                EventType::MouseMove { x, y } => activity.injected_move((x, y)),
+                EventType::Wheel { .. } => false,
+            };
+            let keyboard = matches!(event.event_type, EventType::KeyPress(_) | EventType::KeyRelease(_));
+            let injected = sent || (keyboard && typer_running.load(Ordering::SeqCst));
+            // Keys the clicker presses itself don't break a chord.
+            let chorded = match event.event_type {
+                EventType::KeyPress(key) if !injected => chord.press(key, *chord_key.lock().unwrap(), *hotkey.lock().unwrap(), Instant::now()),
+                _ => true,
+            };
+            if let (EventType::ButtonPress(_), true, true, false) = (event.event_type, sent, clicking, playing) {
+                receipts.seen();
+            }
+            if !injected {
+                activity.input(Instant::now());
+                if let EventType::KeyPress(key) = event.event_type {
+                    // Typing doesn't include the clicker's own keys.
+                    let own_key = [*hotkey.lock().unwrap(), *abort_key.lock().unwrap(), *step_key.lock().unwrap()].contains(&key)
+                        || [*cps_up_key.lock().unwrap(), *cps_down_key.lock().unwrap(), *chord_key.lock().unwrap(), *pause_key.lock().unwrap()].contains(&Some(key))
+                        || typer.lock().unwrap().as_ref().is_some_and(|t| t.hotkey == key)
+                        || macro_hotkeys.lock().unwrap().iter().any(|h| h.key == key)
+                        || changing_hotkey.load(Ordering::SeqCst);
+                    if !own_key {
+                        activity.typed(Instant::now());
+                    }
+                }
+            }
+            if let (EventType::ButtonPress(button) | EventType::ButtonRelease(button), false) = (event.event_type, sent) {
+                // Clicking runs while the user holds the turbo button down.
+                let down = matches!(event.event_type, EventType::ButtonPress(_));
+                let (turbo_button, clicked) = (turbo_button.lock().unwrap().map(MouseButton::from), selected_buttons.lock().unwrap());
+                if let Some(run) = mouse_button(button).and_then(|button| turbo.button(button, down, turbo_button, &clicked)) {
+                    clicking_for_listener.store(run, Ordering::SeqCst);
+                }
+            }
+            if let (EventType::ButtonPress(button), false) = (event.event_type, sent) {
+                if let Some(recording) = recording.lock().unwrap().as_mut() {
+                    let at = *cursor.lock().unwrap();
+                    // Clicks on our own window, like the Stop button, are left out.
+                    let on_own_window = at.zip(*own_window.lock().unwrap()).is_some_and(|((x, y), window)| window.contains(x, y));
+                    if let (Some(button), false) = (mouse_button(button), on_own_window) {
+                        recording.clicked(button, at, Instant::now());
+                    }
+                }
+            }
+            match event.event_type {
+                // The clicker's own presses don't trigger hotkeys, pick points
+                // or count in the CPS test.
+                EventType::KeyPress(_) | EventType::KeyRelease(_) | EventType::ButtonPress(_) if sent => {}
+                EventType::KeyPress(key) => {
+                    if captured == Some(key) {
+                        return;
+                    }
+                    if key == *abort_key.lock().unwrap()
+                        && (macro_playing.swap(false, Ordering::SeqCst)
+                            | typer_running.swap(false, Ordering::SeqCst)
+                            | bench_running.swap(false, Ordering::SeqCst))
+                    {
+                        return;
+                    }
+                    if key == *step_key.lock().unwrap() && macro_handles.stepper.request_next() {
+                        return;
+                    }
+                    if let Some(recording) = recording.lock().unwrap().as_mut() {
+                        // The abort key ends a recording; hotkeys work as usual and aren't recorded.
+                        let own_key = key == *hotkey.lock().unwrap()
+                            || Some(key) == *pause_key.lock().unwrap()
+                            || macro_hotkeys.lock().unwrap().iter().any(|h| h.key == key);
+                        if key == *abort_key.lock().unwrap() {
+                            recording.active = false;
+                            return;
+                        } else if !own_key {
+                            recording.key_pressed(key, Instant::now());
+                        }
+                    }
+                    if changing_hotkey.load(Ordering::SeqCst) {
+                        if string_to_rdev_key(&key_to_string(&key)).is_none() {
+                            *rejected_key.lock().unwrap() = Some(key);
+                            return;
+                        }
+                        if let Ok(mut hk) = hotkey.lock() {
+                            *hk = key;
+                        }
+                        captured = Some(key);
+                        *gesture.lock().unwrap() = Gesture::default();
+                        changing_hotkey.store(false, Ordering::SeqCst);
+                    } else {
+                        if Some(key) == *pause_key.lock().unwrap() {
+                            pause::toggle_all(&pause_handles);
+                            return;
+                        }
+                        let bound = {
+                            let hotkeys = macro_hotkeys.lock().unwrap();
+                            hotkeys.iter().find(|h| h.key == key).map(|h| h.name.clone())
+                        };
+                        if typer.lock().unwrap().as_ref().is_some_and(|t| t.hotkey == key) {
+                            toggle(&typer_running);
+                            return;
+                        }
+                        if let Some(name) = bound {
+                            // Pressing a macro hotkey during playback stops it instead.
+                            if macro_playing.swap(false, Ordering::SeqCst) {
+                                return;
+                            }
+                            match macros::load(&name) {
+                                Ok(mac) => macros::spawn_player(macro_handles.clone(), name, mac, false),
+                                Err(e) => macro_handles.log.push(e),
+                            }
+                            return;
+                        }
+                        let up = Some(key) == *cps_up_key.lock().unwrap();
+                        if (up || Some(key) == *cps_down_key.lock().unwrap()) && clicking_for_listener.load(Ordering::SeqCst) {
+                            let mut cps = target_cps.lock().unwrap();
+                            *cps = nudged_cps(*cps, *cps_step.lock().unwrap(), up);
+                            log.push(format!("Target CPS: {}", cps));
+                            cps_nudged.store(true, Ordering::SeqCst);
+                            return;
+                        }
+                        let current_hotkey = { *hotkey.lock().unwrap() };
+                        if key == current_hotkey && chorded {
+                            let activation = *activation.lock().unwrap();
+                            // When the key went down, which can be a little before the callback runs.
+                            let now = Instant::now();
+                            let pressed = now.checked_sub(event.time.elapsed().unwrap_or_default()).unwrap_or(now);
+                            if gesture.lock().unwrap().press(activation, now) {
+                                toggle_timed(&clicking_for_listener, &latency, pressed);
+                            } else if activation == Activation::Hold {
+                                let gesture = gesture.clone();
+                                let clicking = clicking_for_listener.clone();
+                                let latency = latency.clone();
+                                thread::spawn(move || {
+                                    thread::sleep(HOLD_TIME);
+                                    if gesture.lock().unwrap().poll(activation, Instant::now()) {
+                                        // Timed from the end of the hold, when the user meant clicking to start.
+                                        toggle_timed(&clicking, &latency, pressed + HOLD_TIME);
+                                    }
+                                });
+                            }
+                        }
+                    }
+                }
+                EventType::KeyRelease(key) if captured == Some(key) => {
+                    captured = None;
+                }
+                EventType::KeyRelease(key) if key == *hotkey.lock().unwrap() => {
+                    gesture.lock().unwrap().release();
+                }
+                EventType::MouseMove { x, y } => {
+                    *cursor.lock().unwrap() = Some((x, y));
+                    if let Some(recording) = recording.lock().unwrap().as_mut() {
+                        recording.cursor_moved((x, y), Instant::now());
+                    }
+                }
+                EventType::ButtonPress(Button::Left) => {
+                    if let Some(points) = picked_points.lock().unwrap().as_mut() {
+                        points.extend(*cursor.lock().unwrap());
+                    }
+                    let now = Instant::now();
+                    if let Ok(mut clicks) = clicks.lock() {
+                        clicks.push(now);
+                    }
+                }
+                _ => {}
+            }
+        };
+
+        // Kept across restarts, along with the state it holds.
+        let callback = Rc::new(RefCell::new(callback));
+        let mut wait = RETRY_MIN;
+        loop {
+            let started = Instant::now();
+            let listening = callback.clone();
+            let error = match listen(move |event| (*listening.borrow_mut())(event)) {
+                Err(error) => format!("{:?}", error),
+                Ok(()) => "listener stopped".to_string(),
+            };
+            if started.elapsed() >= RETRY_RESET {
+                wait = RETRY_MIN;
+            }
+            listener_log.push(format!("Global hotkeys unavailable ({}), retrying in {} s", error, wait.as_secs()));
+            *listener_error.lock().unwrap() = Some(error);
+            let deadline = Instant::now() + wait;
+            while Instant::now() < deadline && !retry_listener.swap(false, Ordering::SeqCst) {
+                if shutdown.load(Ordering::SeqCst) {
+                    return;
+                }
+                thread::sleep(RETRY_POLL);
+            }
+            wait = next_retry(wait, *listener_retry.lock().unwrap());
+            *listener_error.lock().unwrap() = None;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn press_toggles_immediately_and_ignores_repeats() {
+        let start = Instant::now();
+        let mut gesture = Gesture::default();
+        assert!(gesture.press(Activation::Press, start));
+        assert!(!gesture.press(Activation::Press, start + ms(30)));
+        gesture.release();
+        assert!(gesture.press(Activation::Press, start + ms(60)));
+    }
+
+    #[test]
+    fn hold_requires_the_full_hold_time() {
+        let start = Instant::now();
+        let mut gesture = Gesture::default();
+        assert!(!gesture.press(Activation::Hold, start));
+        assert!(!gesture.poll(Activation::Hold, start + ms(499)));
+        assert!(gesture.poll(Activation::Hold, start + HOLD_TIME));
+        // Only once per hold.
+        assert!(!gesture.poll(Activation::Hold, start + ms(800)));
+
+        // A short tap never toggles, even if polled later.
+        gesture.release();
+        assert!(!gesture.press(Activation::Hold, start + ms(1000)));
+        gesture.release();
+        assert!(!gesture.poll(Activation::Hold, start + ms(1600)));
+    }
+
+    #[test]
+    fn cps_hotkeys_step_within_range() {
+        assert_eq!(nudged_cps(10.0, 5.0, true), 15.0);
+        assert_eq!(nudged_cps(10.0, 5.0, false), 5.0);
+        assert_eq!(nudged_cps(3.0, 5.0, false), MIN_CPS);
+        assert_eq!(nudged_cps(9998.0, 5.0, true), MAX_CPS);
+    }
+
+    #[test]
+    fn retries_back_off_up_to_the_longest_wait() {
+        let longest = Duration::from_secs(10);
+        let waits: Vec<u64> = std::iter::successors(Some(RETRY_MIN), |&wait| Some(next_retry(wait, longest))).take(6).map(|w| w.as_secs()).collect();
+        assert_eq!(waits, [1, 2, 4, 8, 10, 10]);
+        assert_eq!(next_retry(Duration::from_secs(30), Duration::from_secs(5)), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn chords_need_the_first_key_just_before() {
+        let (g, c) = (Key::KeyG, Key::KeyC);
+        let start = Instant::now();
+        let mut chord = Chord::default();
+        assert!(chord.press(c, None, c, start));
+        assert!(!chord.press(c, Some(g), c, start));
+        assert!(!chord.press(g, Some(g), c, start));
+        assert!(chord.press(c, Some(g), c, start + ms(400)));
+        assert!(chord.press(c, Some(g), c, start + CHORD_WINDOW));
+        assert!(!chord.press(c, Some(g), c, start + ms(1001)));
+
+        // Another key in between breaks the chord.
+        assert!(!chord.press(g, Some(g), c, start + ms(2000)));
+        assert!(!chord.press(Key::KeyX, Some(g), c, start + ms(2100)));
+        assert!(!chord.press(c, Some(g), c, start + ms(2200)));
+    }
+
+    #[test]
+    fn turbo_follows_the_real_button_only() {
+        let (left, right) = (MouseButton::Left, MouseButton::Right);
+        let mut turbo = Turbo::default();
+        assert_eq!(turbo.button(right, true, Some(right), &[left]), Some(true));
+        // Its own clicks of the left button never reach here; repeats change nothing.
+        assert_eq!(turbo.button(right, true, Some(right), &[left]), None);
+        assert_eq!(turbo.button(left, false, Some(right), &[left]), None);
+        assert_eq!(turbo.button(right, false, Some(right), &[left]), Some(false));
+
+        // The button the clicker clicks can't be the turbo button.
+        assert_eq!(turbo.button(left, true, Some(left), &[left]), None);
+        assert_eq!(turbo.button(left, false, Some(left), &[left]), None);
+    }
+
+    #[test]
+    fn double_tap_within_window() {
+        let start = Instant::now();
+        let mut gesture = Gesture::default();
+        assert!(!gesture.press(Activation::DoubleTap, start));
+        gesture.release();
+        assert!(gesture.press(Activation::DoubleTap, start + ms(250)));
+        gesture.release();
+
+        // Too slow: the second tap starts a new double tap instead.
+        assert!(!gesture.press(Activation::DoubleTap, start + ms(1000)));
+        gesture.release();
+        assert!(!gesture.press(Activation::DoubleTap, start + ms(1400)));
+        gesture.release();
+        assert!(gesture.press(Activation::DoubleTap, start + ms(1500)));
+    }
+}
@@ -0,0 +1,49 @@
+// In-app activity log shown in the GUI.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Oldest lines are dropped past this point.
+const MAX_LINES: usize = 500;
+
+pub struct Log {
+    start: Instant,
+    lines: Mutex<VecDeque<String>>,
+    // Lines pushed since startup, including dropped ones.
+    total: AtomicU64,
+}
+
+impl Log {
+    pub fn new() -> Self {
+        Log { start: Instant::now(), lines: Mutex::new(VecDeque::new()), total: AtomicU64::new(0) }
+    }
+
+    pub fn push(&self, message: impl AsRef<str>) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == MAX_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(format!("[{:>9.3}s] {}", elapsed, message.as_ref()));
+        self.total.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Lines pushed after the first `seen`, plus the new total to pass next time.
+    #[cfg_attr(feature = "gui", allow(dead_code))]
+    pub fn lines_since(&self, seen: u64) -> (Vec<String>, u64) {
+        let lines = self.lines.lock().unwrap();
+        let total = self.total.load(Ordering::SeqCst);
+        let new = (total - seen).min(lines.len() as u64) as usize;
+        (lines.iter().skip(lines.len() - new).cloned().collect(), total)
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+}
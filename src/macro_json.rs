@@ -0,0 +1,249 @@
+// Shareable macro files: a versioned JSON document holding the steps and
+// metadata, for passing macros between people and machines.
+//
+//     {
+//       "format": "superspeed-autoclicker-macro",
+//       "version": 1,
+//       "name": "farm_route",
+//       "author": "someone",
+//       "description": "Walks the farm loop",
+//       "resolution": [1920, 1080],
+//       "loops": 3,
+//       "loop_delay_ms": 1000,
+//       "speed": 1.5,
+//       "tags": ["farm"],
+//       "steps": [
+//         {"type": "move", "x": 640, "y": 360},
+//         {"type": "click", "button": "left", "x": 100, "y": 200},
+//         {"type": "key", "key": "W"},
+//         {"type": "wait", "ms": 250},
+//         {"type": "if_pixel", "x": 100, "y": 200, "color": "#ff0000", "goto": 2},
+//         {"type": "wait_change", "x": 100, "y": 200, "timeout": 5000},
+//         {"type": "repeat", "from": 1, "times": 5}
+//       ]
+//     }
+//
+// `loops` is null to loop forever; clicks without "x"/"y" click where the
+// cursor is. Step numbers count from 1 as in the text format. Files written
+// by a newer version are refused rather than half understood.
+
+use std::time::Duration;
+
+use crate::geometry::Point;
+use crate::json::{self, Value};
+use crate::keys::{key_to_string, string_to_rdev_key};
+use crate::macros::{button_name, parse_button, Macro, Step, MAX_SPEED, MIN_SPEED};
+use crate::screen::{format_color, parse_color};
+
+pub const FORMAT: &str = "superspeed-autoclicker-macro";
+pub const VERSION: u64 = 1;
+// Default file extension of exported macros.
+pub const EXTENSION: &str = "json";
+
+fn step_value(step: &Step) -> Value {
+    let entry = |key: &str, value: Value| (key.to_string(), value);
+    let point = |(x, y): Point| [entry("x", x.into()), entry("y", y.into())];
+    let mut members = Vec::new();
+    match step {
+        Step::Move(at) => {
+            members.push(entry("type", "move".into()));
+            members.extend(point(*at));
+        }
+        Step::Click { button, at } => {
+            members.push(entry("type", "click".into()));
+            members.push(entry("button", button_name(*button).into()));
+            members.extend(at.map(point).into_iter().flatten());
+        }
+        Step::Key(key) => {
+            members.push(entry("type", "key".into()));
+            members.push(entry("key", key_to_string(key).as_str().into()));
+        }
+        Step::Wait(duration) => {
+            members.push(entry("type", "wait".into()));
+            members.push(entry("ms", (duration.as_millis() as u64).into()));
+        }
+        Step::IfPixel { at, color, goto } => {
+            members.push(entry("type", "if_pixel".into()));
+            members.extend(point(*at));
+            members.push(entry("color", format_color(*color).as_str().into()));
+            members.push(entry("goto", (*goto as u64 + 1).into()));
+        }
+        Step::WaitChange { at, timeout } => {
+            members.push(entry("type", "wait_change".into()));
+            members.extend(point(*at));
+            members.push(entry("timeout", (timeout.as_millis() as u64).into()));
+        }
+        Step::Repeat { start, times } => {
+            members.push(entry("type", "repeat".into()));
+            members.push(entry("from", (*start as u64 + 1).into()));
+            members.push(entry("times", u64::from(*times).into()));
+        }
+    }
+    Value::Object(members)
+}
+
+/// The shareable document for a macro saved as `name`. Play history stays local.
+pub fn to_json(name: &str, mac: &Macro) -> String {
+    let mut members = vec![
+        ("format".to_string(), FORMAT.into()),
+        ("version".to_string(), VERSION.into()),
+        ("name".to_string(), name.into()),
+        ("author".to_string(), mac.author.as_str().into()),
+        ("description".to_string(), mac.description.as_str().into()),
+    ];
+    let resolution = mac.resolution.map_or(Value::Null, |(w, h)| Value::Array(vec![u64::from(w).into(), u64::from(h).into()]));
+    members.push(("resolution".to_string(), resolution));
+    members.push(("loops".to_string(), mac.loops.map_or(Value::Null, |n| u64::from(n).into())));
+    members.push(("loop_delay_ms".to_string(), (mac.loop_delay.as_millis() as u64).into()));
+    members.push(("speed".to_string(), mac.speed.into()));
+    members.push(("tags".to_string(), Value::Array(mac.tags.iter().map(|t| t.as_str().into()).collect())));
+    members.push(("steps".to_string(), Value::Array(mac.steps.iter().map(step_value).collect())));
+    Value::Object(members).pretty()
+}
+
+fn number(step: &Value, key: &str) -> Result<f64, String> {
+    step.get(key).and_then(Value::as_f64).filter(|n| n.is_finite()).ok_or_else(|| format!("missing or invalid \"{}\"", key))
+}
+
+fn whole(value: &Value, key: &str) -> Result<u64, String> {
+    value.get(key).and_then(Value::as_u64).ok_or_else(|| format!("missing or invalid \"{}\"", key))
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Result<&'a str, String> {
+    value.get(key).and_then(Value::as_str).ok_or_else(|| format!("missing or invalid \"{}\"", key))
+}
+
+// Optional strings are flattened to one line, as the text format needs.
+fn optional_text(value: &Value, key: &str) -> Result<String, String> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(String::new()),
+        Some(Value::String(s)) => Ok(s.split_whitespace().collect::<Vec<_>>().join(" ")),
+        Some(_) => Err(format!("\"{}\" must be a string", key)),
+    }
+}
+
+// A 1-based step number that must be at most `max`.
+fn step_number(value: &Value, key: &str, max: usize) -> Result<usize, String> {
+    let number = whole(value, key)? as usize;
+    if (1..=max).contains(&number) {
+        Ok(number - 1)
+    } else {
+        Err(format!("\"{}\" must be a step number from 1 to {}", key, max))
+    }
+}
+
+fn parse_resolution(value: &Value) -> Option<(u32, u32)> {
+    let [width, height] = value.as_array()? else { return None };
+    let size = (u32::try_from(width.as_u64()?).ok()?, u32::try_from(height.as_u64()?).ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+fn parse_step(value: &Value, index: usize, len: usize) -> Result<Step, String> {
+    let point = || Ok::<_, String>((number(value, "x")?, number(value, "y")?));
+    Ok(match text(value, "type")? {
+        "move" => Step::Move(point()?),
+        "click" => {
+            let button = text(value, "button")?;
+            let button = parse_button(button).ok_or_else(|| format!("unknown button \"{}\"", button))?;
+            let at = if value.get("x").is_some() || value.get("y").is_some() { Some(point()?) } else { None };
+            Step::Click { button, at }
+        }
+        "key" => {
+            let name = text(value, "key")?;
+            Step::Key(string_to_rdev_key(name).ok_or_else(|| format!("unknown key \"{}\"", name))?)
+        }
+        "wait" => Step::Wait(Duration::from_millis(whole(value, "ms")?)),
+        "if_pixel" => {
+            let color = text(value, "color")?;
+            let color = parse_color(color).ok_or_else(|| format!("invalid color \"{}\"", color))?;
+            Step::IfPixel { at: point()?, color, goto: step_number(value, "goto", len)? }
+        }
+        "wait_change" => Step::WaitChange { at: point()?, timeout: Duration::from_millis(whole(value, "timeout")?) },
+        "repeat" => {
+            let times = u32::try_from(whole(value, "times")?).ok().filter(|&t| t >= 1).ok_or("\"times\" must be at least 1")?;
+            Step::Repeat { start: step_number(value, "from", index + 1)?, times }
+        }
+        other => return Err(format!("unknown step type \"{}\"", other)),
+    })
+}
+
+/// Reads a shareable document, returning the name it was exported under and
+/// the macro, with every step checked.
+pub fn from_json(source: &str) -> Result<(String, Macro), String> {
+    let doc = json::parse(source).map_err(|e| format!("not valid JSON: {}", e))?;
+    if doc.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err("not a macro file".to_string());
+    }
+    let version = whole(&doc, "version")?;
+    if version > VERSION {
+        return Err(format!("made by a newer version (format {}); this one reads up to {}", version, VERSION));
+    }
+    let name = optional_text(&doc, "name")?;
+    let mut mac = Macro { author: optional_text(&doc, "author")?, description: optional_text(&doc, "description")?, ..Macro::default() };
+    mac.resolution = match doc.get("resolution") {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(parse_resolution(value).ok_or("\"resolution\" must be [width, height]")?),
+    };
+    mac.loops = match doc.get("loops") {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(value.as_u64().and_then(|n| u32::try_from(n).ok()).filter(|&n| n >= 1).ok_or("\"loops\" must be at least 1")?),
+    };
+    if doc.get("loop_delay_ms").is_some() {
+        mac.loop_delay = Duration::from_millis(whole(&doc, "loop_delay_ms")?);
+    }
+    if let Some(speed) = doc.get("speed") {
+        let speed = speed.as_f64().filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed));
+        mac.speed = speed.ok_or_else(|| format!("\"speed\" must be from {} to {}", MIN_SPEED, MAX_SPEED))?;
+    }
+    if let Some(tags) = doc.get("tags") {
+        let tags = tags.as_array().ok_or("\"tags\" must be a list")?;
+        let tags: Option<Vec<&str>> = tags.iter().map(Value::as_str).collect();
+        mac.tags = crate::macros::parse_tags(&tags.ok_or("tags must be strings")?.join(","));
+    }
+    let steps = doc.get("steps").and_then(Value::as_array).ok_or("missing \"steps\" list")?;
+    for (index, step) in steps.iter().enumerate() {
+        mac.steps.push(parse_step(step, index, steps.len()).map_err(|e| format!("step {}: {}", index + 1, e))?);
+    }
+    Ok((name, mac))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_round_trip() {
+        let text = "loops = forever\nloop_delay = 500\ntags = farm, daily\nauthor = Sam\ndescription = Farm loop\n\
+                    resolution = 1920x1080\nspeed = 1.5\nmove 640,360\nclick left 100.5,200\nclick right\nkey W\nwait 250\n\
+                    if pixel 100,200 is #ff0000 goto 2\nwait until pixel 100,200 changes timeout 5000\nrepeat from 1 times 5\n";
+        let mac = Macro::parse(text).unwrap();
+        let json = to_json("farm route", &mac);
+        assert_eq!(from_json(&json), Ok(("farm route".to_string(), mac)));
+    }
+
+    #[test]
+    fn invalid_documents_are_explained() {
+        let doc = |version: u64, steps: &str| {
+            format!("{{\"format\": \"{}\", \"version\": {}, \"steps\": [{}]}}", FORMAT, version, steps)
+        };
+        assert!(from_json(&doc(1, "")).is_ok());
+        assert_eq!(from_json("{\"steps\": []}").unwrap_err(), "not a macro file");
+        assert!(from_json(&doc(2, "")).unwrap_err().contains("newer version"));
+        assert_eq!(from_json(&doc(1, r#"{"type": "move", "x": 1}"#)).unwrap_err(), "step 1: missing or invalid \"y\"");
+        assert_eq!(from_json(&doc(1, r#"{"type": "key", "key": "Nope"}"#)).unwrap_err(), "step 1: unknown key \"Nope\"");
+        let goto = r##"{"type": "if_pixel", "x": 1, "y": 1, "color": "#000000", "goto": 3}"##;
+        assert!(from_json(&doc(1, goto)).unwrap_err().starts_with("step 1: \"goto\" must be"));
+        assert!(from_json(&doc(1, r#"{"type": "repeat", "from": 2, "times": 2}"#)).is_err());
+    }
+
+    #[test]
+    fn coordinates_scale_to_another_screen() {
+        let mut mac = Macro::parse("resolution = 1920x1080\nmove 960,540\nclick left 1919,0\nclick left\nwait 5\n").unwrap();
+        assert!(!mac.clone().scale_to((1920, 1080)));
+        assert!(mac.scale_to((1280, 720)));
+        assert_eq!(mac.resolution, Some((1280, 720)));
+        assert_eq!(mac.steps_text(), "move 640,360\nclick left 1279,0\nclick left\nwait 5\n");
+        // Without a recorded resolution there is nothing to adapt from.
+        assert!(!Macro::default().scale_to((800, 600)));
+    }
+}
@@ -0,0 +1,1046 @@
+// Macros: lists of input steps saved as text files in the config directory,
+// one step per line, and played back on their own thread.
+//
+//     loops = 3
+//     loop_delay = 1000
+//     move 640,360
+//     click left 100,200
+//     key W
+//     wait 250
+//     if pixel 100,200 is #ff0000 goto 2
+//     wait until pixel 100,200 changes timeout 5000
+//     repeat from 1 times 5
+//
+// Step numbers count steps from 1, ignoring blank and `key = value` lines.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use enigo::MouseButton;
+
+use crate::activity::{Activity, Reporting};
+use crate::backend::{InputBackend, SelectedBackend};
+use crate::buttons::ClickButton;
+use crate::config::{config_dir, BackendKind, KeyInjection};
+use crate::engine::{Clock, SystemClock};
+use crate::geometry::{format_point, format_size, parse_point, parse_size, scale_point, simplify, Point};
+use crate::guard::{GuardHandles, Guarded};
+use crate::keys::{key_to_string, string_to_rdev_key};
+use crate::log::Log;
+use crate::screen::{format_color, parse_color, Color, Screen};
+use crate::telemetry::Telemetry;
+
+// Longest sleep between checks of the stop flag during a wait.
+const STOP_POLL: Duration = Duration::from_millis(10);
+// Time between two reads of a pixel being waited on.
+const PIXEL_POLL: Duration = Duration::from_millis(50);
+// Shortest time between two inputs, however fast the playback speed.
+pub const MIN_EVENT_GAP: Duration = Duration::from_millis(5);
+pub const MIN_SPEED: f64 = 0.25;
+pub const MAX_SPEED: f64 = 10.0;
+// Recorded cursor paths are simplified to within this many pixels, then
+// filled in with moves at most MOVE_SPACING apart and MOVE_GAP apart in time.
+const PATH_TOLERANCE: f64 = 2.0;
+const MOVE_SPACING: f64 = 20.0;
+const MOVE_GAP: Duration = Duration::from_millis(15);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Move(Point),
+    /// Clicks at the cursor, or moves to `at` first.
+    Click { button: MouseButton, at: Option<Point> },
+    Key(rdev::Key),
+    Wait(Duration),
+    /// Jumps to step `goto` (0-based) if the pixel at `at` has `color`.
+    IfPixel { at: Point, color: Color, goto: usize },
+    /// Waits until the pixel at `at` changes color, or `timeout` passed.
+    WaitChange { at: Point, timeout: Duration },
+    /// Goes back to step `start` (0-based) until the steps from there have
+    /// run `times` times in total.
+    Repeat { start: usize, times: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub steps: Vec<Step>,
+    // Times to play the steps; `None` to loop until stopped.
+    pub loops: Option<u32>,
+    // Pause between two loops.
+    pub loop_delay: Duration,
+    // Playback speed; waits and the loop delay are divided by it.
+    pub speed: f64,
+    // Library metadata: free-form tags and when the macro last played.
+    pub tags: Vec<String>,
+    pub last_used: Option<SystemTime>,
+    // Sharing metadata, empty when unknown. `resolution` is the screen size
+    // the coordinates were made for.
+    pub author: String,
+    pub description: String,
+    pub resolution: Option<(u32, u32)>,
+}
+
+impl Default for Macro {
+    fn default() -> Self {
+        Macro {
+            steps: Vec::new(),
+            loops: Some(1),
+            loop_delay: Duration::ZERO,
+            speed: 1.0,
+            tags: Vec::new(),
+            last_used: None,
+            author: String::new(),
+            description: String::new(),
+            resolution: None,
+        }
+    }
+}
+
+impl From<ClickButton> for MouseButton {
+    fn from(button: ClickButton) -> MouseButton {
+        match button {
+            ClickButton::Left => MouseButton::Left,
+            ClickButton::Middle => MouseButton::Middle,
+            ClickButton::Right => MouseButton::Right,
+        }
+    }
+}
+
+pub fn button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Middle => ClickButton::Middle,
+        MouseButton::Right => ClickButton::Right,
+        _ => ClickButton::Left,
+    }
+    .name()
+}
+
+pub fn parse_button(name: &str) -> Option<MouseButton> {
+    ClickButton::from_name(name).map(MouseButton::from)
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Move(point) => write!(f, "move {}", format_point(*point)),
+            Step::Click { button, at: None } => write!(f, "click {}", button_name(*button)),
+            Step::Click { button, at: Some(at) } => write!(f, "click {} {}", button_name(*button), format_point(*at)),
+            Step::Key(key) => write!(f, "key {}", key_to_string(key)),
+            Step::Wait(duration) => write!(f, "wait {}", duration.as_millis()),
+            Step::IfPixel { at, color, goto } => {
+                write!(f, "if pixel {} is {} goto {}", format_point(*at), format_color(*color), goto + 1)
+            }
+            Step::WaitChange { at, timeout } => {
+                write!(f, "wait until pixel {} changes timeout {}", format_point(*at), timeout.as_millis())
+            }
+            Step::Repeat { start, times } => write!(f, "repeat from {} times {}", start + 1, times),
+        }
+    }
+}
+
+/// Splits a comma-separated tag list, dropping empty and repeated tags.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Parses a 1-based step number into an index below `len`.
+fn parse_step_number(text: &str, len: usize) -> Option<usize> {
+    let number = text.parse::<usize>().ok()?;
+    (1..=len).contains(&number).then(|| number - 1)
+}
+
+impl Macro {
+    /// Parses the text format, failing with the first problem found.
+    pub fn parse(text: &str) -> Result<Macro, String> {
+        let mut mac = Macro::default();
+        let mut lines = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            match line.split_once('=') {
+                _ if line.is_empty() => {}
+                Some((key, value)) => mac
+                    .apply_setting(key.trim(), value.trim())
+                    .ok_or_else(|| format!("line {}: could not parse \"{}\"", index + 1, line))?,
+                None => lines.push((index + 1, line)),
+            }
+        }
+        let len = lines.len();
+        for (index, (line_number, line)) in lines.into_iter().enumerate() {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let step = match words.as_slice() {
+                ["move", point] => parse_point(point).map(Step::Move),
+                ["click", button] => parse_button(button).map(|button| Step::Click { button, at: None }),
+                ["click", button, point] => parse_button(button)
+                    .zip(parse_point(point))
+                    .map(|(button, at)| Step::Click { button, at: Some(at) }),
+                ["key", name] => string_to_rdev_key(name).map(Step::Key),
+                ["wait", ms] => ms.parse::<u64>().ok().map(|ms| Step::Wait(Duration::from_millis(ms))),
+                ["if", "pixel", point, "is", color, "goto", step] => match (parse_point(point), parse_color(color)) {
+                    (Some(at), Some(color)) => parse_step_number(step, len).map(|goto| Step::IfPixel { at, color, goto }),
+                    _ => None,
+                },
+                ["wait", "until", "pixel", point, "changes", "timeout", ms] => parse_point(point)
+                    .zip(ms.parse::<u64>().ok())
+                    .map(|(at, ms)| Step::WaitChange { at, timeout: Duration::from_millis(ms) }),
+                ["repeat", "from", step, "times", times] => parse_step_number(step, index + 1)
+                    .zip(times.parse::<u32>().ok().filter(|&t| t >= 1))
+                    .map(|(start, times)| Step::Repeat { start, times }),
+                _ => None,
+            };
+            mac.steps.push(step.ok_or_else(|| format!("line {}: could not parse \"{}\"", line_number, line))?);
+        }
+        Ok(mac)
+    }
+
+    fn apply_setting(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "loops" if value == "forever" => self.loops = None,
+            "loops" => self.loops = Some(value.parse::<u32>().ok().filter(|&n| n >= 1)?),
+            "loop_delay" => self.loop_delay = Duration::from_millis(value.parse().ok()?),
+            "speed" => self.speed = value.parse::<f64>().ok().filter(|speed| (MIN_SPEED..=MAX_SPEED).contains(speed))?,
+            "tags" => self.tags = parse_tags(value),
+            "last_used" => self.last_used = Some(UNIX_EPOCH + Duration::from_secs(value.parse().ok()?)),
+            "author" => self.author = value.to_string(),
+            "description" => self.description = value.to_string(),
+            "resolution" => self.resolution = Some(parse_size(value)?),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// The playback settings and metadata as `key = value` lines.
+    pub fn settings_text(&self) -> String {
+        let loops = self.loops.map_or("forever".to_string(), |n| n.to_string());
+        let mut text = format!("loops = {}\nloop_delay = {}\n", loops, self.loop_delay.as_millis());
+        if self.speed != 1.0 {
+            text += &format!("speed = {}\n", self.speed);
+        }
+        if !self.tags.is_empty() {
+            text += &format!("tags = {}\n", self.tags.join(", "));
+        }
+        if let Some(time) = self.last_used {
+            let secs = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+            text += &format!("last_used = {}\n", secs);
+        }
+        if !self.author.is_empty() {
+            text += &format!("author = {}\n", self.author);
+        }
+        if !self.description.is_empty() {
+            text += &format!("description = {}\n", self.description);
+        }
+        if let Some(size) = self.resolution {
+            text += &format!("resolution = {}\n", format_size(size));
+        }
+        text
+    }
+
+    /// Moves every coordinate from the recorded resolution to a screen of
+    /// `size`, keeping positions proportional. Returns whether anything changed.
+    pub fn scale_to(&mut self, size: (u32, u32)) -> bool {
+        let Some(from) = self.resolution.filter(|&from| from != size && from.0 > 0 && from.1 > 0) else {
+            return false;
+        };
+        for step in &mut self.steps {
+            match step {
+                Step::Move(at) | Step::Click { at: Some(at), .. } | Step::IfPixel { at, .. } | Step::WaitChange { at, .. } => {
+                    *at = scale_point(*at, from, size)
+                }
+                _ => {}
+            }
+        }
+        self.resolution = Some(size);
+        true
+    }
+
+    /// The steps alone, one per line.
+    pub fn steps_text(&self) -> String {
+        self.steps.iter().map(|step| format!("{}\n", step)).collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        self.settings_text() + &self.steps_text()
+    }
+}
+
+/// Source of screen pixels for conditional steps.
+pub trait PixelSource {
+    fn pixel(&self, point: Point) -> Option<Color>;
+}
+
+impl PixelSource for Option<Screen> {
+    fn pixel(&self, (x, y): Point) -> Option<Color> {
+        self.as_ref()?.pixel(x.round() as i32, y.round() as i32)
+    }
+}
+
+/// Everything a step needs to run.
+pub struct Output<'a> {
+    pub backend: &'a mut dyn InputBackend,
+    pub clock: &'a mut dyn Clock,
+    pub pixels: &'a dyn PixelSource,
+    pub injection: KeyInjection,
+    // Playback stops when this is cleared.
+    pub running: &'a AtomicBool,
+    // Playback holds where it is while this is set.
+    pub paused: &'a AtomicBool,
+    // Set to wait for a go-ahead before every step.
+    pub stepper: Option<&'a Stepper>,
+}
+
+/// Step-through playback: the player waits before each step until the
+/// next one is requested.
+#[derive(Debug, Default)]
+pub struct Stepper {
+    // Step about to run or running; `None` outside a debug session.
+    current: Mutex<Option<usize>>,
+    next: AtomicBool,
+}
+
+impl Stepper {
+    pub fn current(&self) -> Option<usize> {
+        *self.current.lock().unwrap()
+    }
+
+    /// Lets the waiting step run. Ignored outside a debug session.
+    pub fn request_next(&self) -> bool {
+        let debugging = self.current().is_some();
+        if debugging {
+            self.next.store(true, Ordering::SeqCst);
+        }
+        debugging
+    }
+
+    /// Shows `step` as the current one and waits for the go-ahead.
+    /// Returns false if playback was stopped instead.
+    fn wait_for_next(&self, step: usize, out: &mut Output) -> bool {
+        *self.current.lock().unwrap() = Some(step);
+        while out.running.load(Ordering::SeqCst) {
+            if self.next.swap(false, Ordering::SeqCst) {
+                return true;
+            }
+            out.clock.sleep(STOP_POLL);
+        }
+        false
+    }
+
+    fn end(&self) {
+        *self.current.lock().unwrap() = None;
+        self.next.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Position in a macro, plus how often each repeat has run so far.
+#[derive(Debug, Default)]
+pub struct Playhead {
+    pub step: usize,
+    repeats: HashMap<usize, u32>,
+    // Clock time of the last input sent.
+    last_input: Option<Duration>,
+}
+
+impl Playhead {
+    /// Runs the current step and moves on. Returns false once the macro is done.
+    pub fn advance(&mut self, mac: &Macro, out: &mut Output) -> bool {
+        let steps = &mac.steps;
+        let Some(step) = steps.get(self.step) else { return false };
+        let mut next = self.step + 1;
+        if matches!(step, Step::Move(_) | Step::Click { .. } | Step::Key(_)) {
+            if let Some(last) = self.last_input {
+                wait((last + MIN_EVENT_GAP).saturating_sub(out.clock.now()), out);
+            }
+            self.last_input = Some(out.clock.now());
+        }
+        match step {
+            Step::Move(point) => out.backend.move_to(*point),
+            Step::Click { button, at } => {
+                if let Some(at) = at {
+                    out.backend.move_to(*at);
+                }
+                out.backend.click(*button);
+            }
+            Step::Key(key) => out.backend.press(*key, out.injection),
+            Step::Wait(duration) => wait(duration.div_f64(mac.speed), out),
+            Step::IfPixel { at, color, goto } => {
+                if out.pixels.pixel(*at) == Some(*color) {
+                    next = *goto;
+                }
+            }
+            Step::WaitChange { at, timeout } => wait_for_change(*at, *timeout, out),
+            Step::Repeat { start, times } => {
+                let runs = self.repeats.entry(self.step).or_insert(1);
+                if *runs < *times {
+                    *runs += 1;
+                    next = *start;
+                } else {
+                    // Starts counting again if an outer loop comes back here.
+                    self.repeats.remove(&self.step);
+                }
+            }
+        }
+        self.step = next;
+        self.step < steps.len()
+    }
+}
+
+/// Sleeps while playback is paused, returning how long that was.
+fn hold(out: &mut Output) -> Duration {
+    let start = out.clock.now();
+    while out.paused.load(Ordering::SeqCst) && out.running.load(Ordering::SeqCst) {
+        out.clock.sleep(STOP_POLL);
+    }
+    out.clock.now() - start
+}
+
+/// Sleeps for `duration`, returning early if playback is stopped. Time spent
+/// paused doesn't count.
+fn wait(duration: Duration, out: &mut Output) {
+    let mut end = out.clock.now() + duration;
+    while out.running.load(Ordering::SeqCst) {
+        end += hold(out);
+        let now = out.clock.now();
+        if now >= end {
+            break;
+        }
+        out.clock.sleep((end - now).min(STOP_POLL));
+    }
+}
+
+/// Waits until the pixel at `at` no longer has the color it had at the start,
+/// or `timeout` passed. Not sped up with the playback speed: it waits on the
+/// screen, not on a recorded time.
+fn wait_for_change(at: Point, timeout: Duration, out: &mut Output) {
+    let end = out.clock.now() + timeout;
+    let before = out.pixels.pixel(at);
+    while out.running.load(Ordering::SeqCst) {
+        let now = out.clock.now();
+        if now >= end {
+            break;
+        }
+        wait((end - now).min(PIXEL_POLL), out);
+        // An unreadable pixel waits out the timeout.
+        if before.is_some() && out.pixels.pixel(at) != before {
+            break;
+        }
+    }
+}
+
+/// Plays every loop of a macro until it ends or `out.running` is cleared.
+pub fn play(mac: &Macro, out: &mut Output) {
+    if mac.steps.is_empty() {
+        return;
+    }
+    let mut played = 0;
+    while out.running.load(Ordering::SeqCst) && mac.loops.is_none_or(|loops| played < loops) {
+        if played > 0 {
+            wait(mac.loop_delay.div_f64(mac.speed), out);
+        }
+        let mut playhead = Playhead::default();
+        loop {
+            if let Some(stepper) = out.stepper {
+                if !stepper.wait_for_next(playhead.step, out) {
+                    break;
+                }
+            }
+            hold(out);
+            if !out.running.load(Ordering::SeqCst) || !playhead.advance(mac, out) {
+                break;
+            }
+        }
+        played += 1;
+    }
+}
+
+/// Input captured by the listener while recording a macro.
+pub struct Recording {
+    // Record clicks at their positions and skip cursor movement.
+    pub clicks_only: bool,
+    // Cleared by the abort key; the GUI then collects the steps.
+    pub active: bool,
+    steps: Vec<(Instant, Step)>,
+}
+
+impl Recording {
+    pub fn new(clicks_only: bool) -> Self {
+        Recording { clicks_only, active: true, steps: Vec::new() }
+    }
+
+    pub fn cursor_moved(&mut self, to: Point, now: Instant) {
+        if !self.clicks_only && self.active {
+            self.steps.push((now, Step::Move(to)));
+        }
+    }
+
+    pub fn clicked(&mut self, button: MouseButton, cursor: Option<Point>, now: Instant) {
+        if self.active {
+            self.steps.push((now, Step::Click { button, at: cursor.filter(|_| self.clicks_only) }));
+        }
+    }
+
+    pub fn key_pressed(&mut self, key: rdev::Key, now: Instant) {
+        if self.active {
+            self.steps.push((now, Step::Key(key)));
+        }
+    }
+
+    /// The recorded steps with the pauses between them as waits, and each
+    /// run of cursor moves smoothed into a compact path.
+    pub fn finish(self) -> Macro {
+        let mut steps = Vec::new();
+        let mut path = Vec::new();
+        for (at, step) in self.steps {
+            match step {
+                Step::Move(point) => path.push((at, point)),
+                step => {
+                    steps.extend(smooth_path(&path));
+                    path.clear();
+                    steps.push((at, step));
+                }
+            }
+        }
+        steps.extend(smooth_path(&path));
+
+        let mut mac = Macro::default();
+        let mut last = None;
+        for (at, step) in steps {
+            let gap = last.map_or(Duration::ZERO, |last| at.duration_since(last));
+            if gap.as_millis() > 0 {
+                mac.steps.push(Step::Wait(Duration::from_millis(gap.as_millis() as u64)));
+            }
+            mac.steps.push(step);
+            last = Some(at);
+        }
+        mac
+    }
+}
+
+/// The corners of a recorded cursor path, with evenly spaced moves along the
+/// long stretches between them so playback glides instead of jumping.
+fn smooth_path(path: &[(Instant, Point)]) -> Vec<(Instant, Step)> {
+    let points: Vec<Point> = path.iter().map(|&(_, point)| point).collect();
+    let mut steps = Vec::new();
+    let mut previous: Option<(Instant, Point)> = None;
+    for (at, to) in simplify(&points, PATH_TOLERANCE).into_iter().map(|i| path[i]) {
+        if let Some((from_at, from)) = previous {
+            let gap = at.duration_since(from_at);
+            let by_distance = ((to.0 - from.0).hypot(to.1 - from.1) / MOVE_SPACING).ceil() as u32;
+            let by_time = (gap.as_nanos() / MOVE_GAP.as_nanos()) as u32;
+            let pieces = by_distance.min(by_time).max(1);
+            for piece in 1..pieces {
+                let t = piece as f64 / pieces as f64;
+                let point = ((from.0 + (to.0 - from.0) * t).round(), (from.1 + (to.1 - from.1) * t).round());
+                steps.push((from_at + gap.mul_f64(t), Step::Move(point)));
+            }
+        }
+        steps.push((at, Step::Move(to)));
+        previous = Some((at, to));
+    }
+    steps
+}
+
+/// Shared state the macro player is allowed to touch.
+#[derive(Clone)]
+pub struct MacroHandles {
+    // Set while a macro plays; clearing it stops playback.
+    pub playing: Arc<AtomicBool>,
+    // Set by the pause-all key to hold playback where it is.
+    pub paused: Arc<AtomicBool>,
+    pub backend: Arc<Mutex<BackendKind>>,
+    pub key_injection: Arc<Mutex<KeyInjection>>,
+    pub stepper: Arc<Stepper>,
+    pub telemetry: Arc<Telemetry>,
+    pub activity: Arc<Activity>,
+    pub screen_size: Arc<Mutex<Option<(u32, u32)>>>,
+    pub scale_recordings: Arc<AtomicBool>,
+    pub guard: GuardHandles,
+    pub log: Arc<Log>,
+}
+
+/// Plays `mac` on its own thread unless another macro is already playing.
+/// With `debug` every step waits for `Stepper::request_next`.
+pub fn spawn_player(handles: MacroHandles, name: String, mut mac: Macro, debug: bool) {
+    if handles.playing.swap(true, Ordering::SeqCst) {
+        handles.log.push("A macro is already playing");
+        return;
+    }
+    handles.paused.store(false, Ordering::SeqCst);
+    handles.telemetry.record(if debug { "macro_debug" } else { "macro_play" });
+    thread::spawn(move || {
+        handles.log.push(format!("{} macro {}", if debug { "Debugging" } else { "Playing" }, name));
+        if let (Some(made), Some(here)) = (mac.resolution, *handles.screen_size.lock().unwrap()) {
+            if made != here && handles.scale_recordings.load(Ordering::SeqCst) {
+                mac.scale_to(here);
+                handles.log.push(format!("Scaled {} from {} to this screen", name, format_size(made)));
+            } else if made != here {
+                handles.log.push(format!("{} was made for {}; this screen is {}", name, format_size(made), format_size(here)));
+            }
+        }
+        let backend = SelectedBackend::new(handles.backend.clone(), handles.log.clone());
+        let backend = Reporting { inner: backend, activity: handles.activity.clone() };
+        let mut backend = Guarded::new(backend, handles.guard.clone(), handles.playing.clone());
+        let mut clock = SystemClock::new();
+        let screen = Screen::new();
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels: &screen,
+            injection: *handles.key_injection.lock().unwrap(),
+            running: &handles.playing,
+            paused: &handles.paused,
+            stepper: debug.then_some(&*handles.stepper),
+        };
+        if let Err(e) = mark_used(&name) {
+            eprintln!("Failed to update macro {}: {}", name, e);
+        }
+        play(&mac, &mut out);
+        handles.stepper.end();
+        let stopped = !handles.playing.swap(false, Ordering::SeqCst);
+        handles.log.push(format!("Macro {} {}", name, if stopped { "stopped" } else { "finished" }));
+    });
+}
+
+/// Directory holding the saved macros.
+pub fn macros_dir() -> PathBuf {
+    config_dir().join("macros")
+}
+
+/// Whether `name` can be used as a file name on every platform.
+pub fn valid_name(name: &str) -> bool {
+    let name = name.trim();
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+}
+
+/// Names of the saved macros, sorted.
+pub fn list() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(macros_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn load(name: &str) -> Result<Macro, String> {
+    let text = load_text(name).map_err(|e| format!("could not read macro {}: {}", name, e))?;
+    Macro::parse(&text).map_err(|e| format!("macro {}: {}", name, e))
+}
+
+/// Raw text of a saved macro.
+pub fn load_text(name: &str) -> std::io::Result<String> {
+    fs::read_to_string(path_of(name))
+}
+
+pub fn save(name: &str, mac: &Macro) -> std::io::Result<()> {
+    fs::create_dir_all(macros_dir())?;
+    fs::write(path_of(name), mac.to_text())
+}
+
+fn path_of(name: &str) -> PathBuf {
+    macros_dir().join(format!("{}.txt", name))
+}
+
+/// Replaces the tags of a saved macro.
+pub fn set_tags(name: &str, tags: Vec<String>) -> Result<(), String> {
+    let mut mac = load(name)?;
+    mac.tags = tags;
+    save(name, &mac).map_err(|e| e.to_string())
+}
+
+/// Records that a saved macro was just played. Unsaved macros are ignored.
+fn mark_used(name: &str) -> Result<(), String> {
+    if !path_of(name).exists() {
+        return Ok(());
+    }
+    let mut mac = load(name)?;
+    mac.last_used = Some(SystemTime::now());
+    save(name, &mac).map_err(|e| e.to_string())
+}
+
+/// Renames a saved macro, refusing to overwrite another one.
+pub fn rename(from: &str, to: &str) -> Result<(), String> {
+    if !valid_name(to) {
+        return Err(format!("\"{}\" is not a valid macro name", to));
+    }
+    if path_of(to).exists() {
+        return Err(format!("a macro named {} already exists", to));
+    }
+    fs::rename(path_of(from), path_of(to)).map_err(|e| e.to_string())
+}
+
+/// Copies a saved macro under a free name like "name copy 2", returning it.
+pub fn duplicate(name: &str) -> Result<String, String> {
+    let copy = (1..)
+        .map(|n| if n == 1 { format!("{} copy", name) } else { format!("{} copy {}", name, n) })
+        .find(|candidate| !path_of(candidate).exists())
+        .unwrap();
+    fs::copy(path_of(name), path_of(&copy)).map_err(|e| e.to_string())?;
+    Ok(copy)
+}
+
+pub fn delete(name: &str) -> std::io::Result<()> {
+    fs::remove_file(path_of(name))
+}
+
+/// "never", "just now", "5 min ago", "3 h ago" or "2 days ago".
+pub fn format_last_used(last_used: Option<SystemTime>, now: SystemTime) -> String {
+    let Some(time) = last_used else { return "never".to_string() };
+    let secs = now.duration_since(time).map_or(0, |ago| ago.as_secs());
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "move 640,360\nclick left 100,200\n\nkey W\nwait 250\nif pixel 100,200 is #ff0000 goto 2\nrepeat from 1 times 5\nclick right\n";
+
+    /// Records what the steps sent, as text.
+    #[derive(Default)]
+    struct Recorder {
+        actions: Vec<String>,
+    }
+
+    impl InputBackend for Recorder {
+        fn button_down(&mut self, button: MouseButton) {
+            self.actions.push(format!("down {}", button_name(button)));
+        }
+
+        fn button_up(&mut self, _button: MouseButton) {}
+
+        fn move_to(&mut self, point: Point) {
+            self.actions.push(format!("move {}", format_point(point)));
+        }
+
+        fn key_down(&mut self, key: rdev::Key, _injection: KeyInjection) {
+            self.actions.push(format!("key {}", key_to_string(&key)));
+        }
+
+        fn key_up(&mut self, _key: rdev::Key, _injection: KeyInjection) {}
+    }
+
+    struct TestClock(Duration);
+
+    impl Clock for TestClock {
+        fn now(&self) -> Duration {
+            self.0
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.0 += duration;
+        }
+    }
+
+    /// Screen with a single colored pixel at (1, 1), present for `frames` reads.
+    struct Blinking(std::cell::Cell<u32>);
+
+    impl PixelSource for Blinking {
+        fn pixel(&self, point: Point) -> Option<Color> {
+            let left = self.0.get();
+            self.0.set(left.saturating_sub(1));
+            Some(if point == (1.0, 1.0) && left > 0 { [255, 0, 0] } else { [0, 0, 0] })
+        }
+    }
+
+    fn run(text: &str, pixels: &dyn PixelSource) -> (Vec<String>, Duration) {
+        let mac = Macro::parse(text).unwrap();
+        let (mut backend, mut clock) = (Recorder::default(), TestClock(Duration::ZERO));
+        let running = AtomicBool::new(true);
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels,
+            injection: KeyInjection::VirtualKey,
+            running: &running,
+            paused: &AtomicBool::new(false),
+            stepper: None,
+        };
+        play(&mac, &mut out);
+        (backend.actions, clock.0)
+    }
+
+    #[test]
+    fn text_format_round_trips() {
+        let mac = Macro::parse(EXAMPLE).unwrap();
+        assert_eq!(mac.steps.len(), 7);
+        assert_eq!(mac.steps[4], Step::IfPixel { at: (100.0, 200.0), color: [255, 0, 0], goto: 1 });
+        assert_eq!(mac.steps[5], Step::Repeat { start: 0, times: 5 });
+        assert_eq!(mac.steps_text(), EXAMPLE.replace("\n\n", "\n"));
+        assert_eq!(Macro::parse(&mac.to_text()), Ok(mac));
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        assert_eq!(Macro::parse("wait 5\njump 3\n"), Err("line 2: could not parse \"jump 3\"".to_string()));
+        // Jumps must land on a step, repeats must go backwards.
+        assert!(Macro::parse("if pixel 1,1 is #000000 goto 3\nwait 1\n").is_err());
+        assert!(Macro::parse("repeat from 2 times 3\nwait 1\n").is_err());
+        assert!(Macro::parse("wait 1\nrepeat from 1 times 0\n").is_err());
+        assert!(Macro::parse("if pixel 1,1 is red goto 1\n").is_err());
+    }
+
+    #[test]
+    fn repeat_runs_the_block_the_given_number_of_times() {
+        let (actions, elapsed) = run("click left\nwait 100\nrepeat from 1 times 3\nkey W\n", &None);
+        assert_eq!(actions, ["down left", "down left", "down left", "key W"]);
+        assert_eq!(elapsed, Duration::from_millis(300));
+
+        // Nested: the inner block runs twice per outer iteration.
+        let (actions, _) = run("key A\nclick left\nrepeat from 2 times 2\nrepeat from 1 times 2\n", &None);
+        assert_eq!(actions, ["key A", "down left", "down left", "key A", "down left", "down left"]);
+    }
+
+    #[test]
+    fn tight_macros_are_held_to_the_cps_guard() {
+        let mac = Macro::parse("click left\nwait 0\nrepeat from 1 times 11\n").unwrap();
+        let guard = GuardHandles {
+            max_cps: Arc::new(Mutex::new(Some(200.0))),
+            power_cap: Arc::new(Mutex::new(None)),
+            blocklist: Arc::new(Mutex::new(Vec::new())),
+            focused_app: Arc::new(Mutex::new(None)),
+            pacer: Arc::new(crate::guard::Pacer::default()),
+            log: Arc::new(Log::new()),
+        };
+        let running = Arc::new(AtomicBool::new(true));
+        let mut backend = Guarded::new(Recorder::default(), guard, running.clone());
+        let mut clock = TestClock(Duration::ZERO);
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels: &None,
+            injection: KeyInjection::VirtualKey,
+            running: &running,
+            paused: &AtomicBool::new(false),
+            stepper: None,
+        };
+        let start = Instant::now();
+        play(&mac, &mut out);
+        // 11 presses at 200 CPS are 10 gaps of 5 ms, however short the waits.
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn pixel_condition_jumps_while_it_matches() {
+        // Waits while the pixel is red, then clicks once it changes.
+        let text = "if pixel 1,1 is #ff0000 goto 3\nclick left\nwait 50\nif pixel 1,1 is #ff0000 goto 3\nclick right\n";
+        let (actions, elapsed) = run(text, &Blinking(std::cell::Cell::new(3)));
+        assert_eq!(actions, ["down right"]);
+        assert_eq!(elapsed, Duration::from_millis(150));
+    }
+
+    #[test]
+    fn pixel_wait_holds_until_the_color_changes() {
+        let text = "wait until pixel 1,1 changes timeout 1000\nclick left\n";
+        let mac = Macro::parse(text).unwrap();
+        assert_eq!(mac.steps[0], Step::WaitChange { at: (1.0, 1.0), timeout: Duration::from_millis(1000) });
+        assert_eq!(mac.steps_text(), text);
+        // Red for the first read and two polls after it.
+        let (actions, elapsed) = run(text, &Blinking(std::cell::Cell::new(3)));
+        assert_eq!(actions, ["down left"]);
+        assert_eq!(elapsed, Duration::from_millis(150));
+
+        // A pixel that never changes, or can't be read, waits out the timeout.
+        let (actions, elapsed) = run("wait until pixel 2,2 changes timeout 200\nclick left\n", &Blinking(std::cell::Cell::new(0)));
+        assert_eq!((actions.len(), elapsed), (1, Duration::from_millis(200)));
+        let (_, elapsed) = run("wait until pixel 1,1 changes timeout 200\n", &None);
+        assert_eq!(elapsed, Duration::from_millis(200));
+    }
+
+    #[test]
+    fn loops_repeat_with_a_delay_between() {
+        let (actions, elapsed) = run("loops = 3\nloop_delay = 500\nclick left\nwait 100\n", &None);
+        assert_eq!(actions, ["down left", "down left", "down left"]);
+        assert_eq!(elapsed, Duration::from_millis(3 * 100 + 2 * 500));
+
+        let mac = Macro::parse("loops = forever\nwait 1\n").unwrap();
+        assert_eq!(mac.loops, None);
+        assert_eq!(Macro::parse(&mac.to_text()), Ok(mac));
+        assert!(Macro::parse("loops = 0\n").is_err());
+        assert!(Macro::parse("volume = 2\n").is_err());
+    }
+
+    #[test]
+    fn library_metadata_round_trips() {
+        let mac = Macro::parse("tags = farm, Daily, farm,, \nlast_used = 1700000000\nwait 1\n").unwrap();
+        assert_eq!(mac.tags, ["farm", "Daily"]);
+        assert_eq!(mac.last_used, Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+        assert_eq!(Macro::parse(&mac.to_text()), Ok(mac));
+        let shared = Macro::parse("author = Sam\ndescription = a = b\nresolution = 800x600\n").unwrap();
+        assert_eq!((shared.description.as_str(), shared.resolution), ("a = b", Some((800, 600))));
+        assert_eq!(Macro::parse(&shared.to_text()), Ok(shared));
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let ago = |secs| Some(now - Duration::from_secs(secs));
+        assert_eq!(format_last_used(None, now), "never");
+        assert_eq!(format_last_used(ago(30), now), "just now");
+        assert_eq!(format_last_used(ago(300), now), "5 min ago");
+        assert_eq!(format_last_used(ago(3 * 3600), now), "3 h ago");
+        assert_eq!(format_last_used(ago(2 * 86_400 + 5), now), "2 days ago");
+    }
+
+    #[test]
+    fn recording_keeps_clicks_and_pauses() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let record = |clicks_only| {
+            let mut recording = Recording::new(clicks_only);
+            for (ms, x) in [(0, 10.0), (5, 11.0), (10, 12.0), (40, 50.0)] {
+                recording.cursor_moved((x, 5.0), at(ms));
+            }
+            recording.clicked(MouseButton::Left, Some((50.0, 5.0)), at(100));
+            recording.key_pressed(rdev::Key::KeyW, at(350));
+            recording.active = false;
+            recording.clicked(MouseButton::Right, Some((0.0, 0.0)), at(400));
+            recording.finish().steps_text()
+        };
+        assert_eq!(record(true), "click left 50,5\nwait 250\nkey W\n");
+        // The straight run keeps its ends and is filled in every 20 ms.
+        assert_eq!(record(false), "move 10,5\nwait 20\nmove 30,5\nwait 20\nmove 50,5\nwait 60\nclick left\nwait 250\nkey W\n");
+    }
+
+    #[test]
+    fn recorded_paths_come_out_compact() {
+        // A slow, slightly shaky sweep across the screen: one move a millisecond.
+        let start = Instant::now();
+        let mut recording = Recording::new(false);
+        for ms in 0..1000u64 {
+            let wobble = if ms % 2 == 0 { 0.5 } else { -0.5 };
+            recording.cursor_moved((ms as f64, 100.0 + wobble), start + Duration::from_millis(ms));
+        }
+        let mac = recording.finish();
+        let moves: Vec<Point> = mac.steps.iter().filter_map(|step| if let Step::Move(at) = step { Some(*at) } else { None }).collect();
+        assert_eq!(moves.len(), 51);
+        assert!(moves.windows(2).all(|pair| (pair[1].0 - pair[0].0).abs() <= MOVE_SPACING));
+        assert_eq!(moves.last(), Some(&(999.0, 99.5)));
+    }
+
+    #[test]
+    fn speed_scales_waits_down_to_the_event_gap() {
+        let (_, elapsed) = run("speed = 2\nloops = 2\nloop_delay = 500\nclick left\nwait 100\n", &None);
+        assert_eq!(elapsed, Duration::from_millis(2 * 50 + 250));
+        // 20 ms at 10x would be 2 ms, less than the gap between two inputs.
+        let (actions, elapsed) = run("speed = 10\nclick left\nwait 20\nclick left\nclick left\n", &None);
+        assert_eq!(actions, ["down left", "down left", "down left"]);
+        assert_eq!(elapsed, MIN_EVENT_GAP * 2);
+        assert!(Macro::parse("speed = 20\n").is_err());
+        assert!(Macro::parse("speed = 0.1\n").is_err());
+    }
+
+    /// Clock that asks for the next step whenever the player waits, noting
+    /// which step was shown at the time.
+    struct Stepping<'a> {
+        stepper: &'a Stepper,
+        shown: Vec<Option<usize>>,
+        time: Duration,
+    }
+
+    impl Clock for Stepping<'_> {
+        fn now(&self) -> Duration {
+            self.time
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.time += duration;
+            self.shown.push(self.stepper.current());
+            self.stepper.request_next();
+        }
+    }
+
+    #[test]
+    fn debug_playback_waits_before_every_step() {
+        let mac = Macro::parse("click left\nkey W\nclick right\n").unwrap();
+        let stepper = Stepper::default();
+        assert!(!stepper.request_next(), "no session to step");
+        let mut backend = Recorder::default();
+        let mut clock = Stepping { stepper: &stepper, shown: Vec::new(), time: Duration::ZERO };
+        let running = AtomicBool::new(true);
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels: &None,
+            injection: KeyInjection::VirtualKey,
+            running: &running,
+            paused: &AtomicBool::new(false),
+            stepper: Some(&stepper),
+        };
+        play(&mac, &mut out);
+        assert_eq!(clock.shown, [Some(0), Some(1), Some(2)]);
+        assert_eq!(backend.actions, ["down left", "key W", "down right"]);
+    }
+
+    #[test]
+    fn stop_ends_playback() {
+        let mac = Macro::parse("wait 1000\nclick left\n").unwrap();
+        let (mut backend, mut clock) = (Recorder::default(), TestClock(Duration::ZERO));
+        let running = AtomicBool::new(false);
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels: &None,
+            injection: KeyInjection::VirtualKey,
+            running: &running,
+            paused: &AtomicBool::new(false),
+            stepper: None,
+        };
+        play(&mac, &mut out);
+        assert!(backend.actions.is_empty());
+    }
+
+    /// Clock that pauses playback over a stretch of time.
+    struct Pausing<'a> {
+        paused: &'a AtomicBool,
+        from: Duration,
+        to: Duration,
+        time: Duration,
+    }
+
+    impl Clock for Pausing<'_> {
+        fn now(&self) -> Duration {
+            self.time
+        }
+
+        fn sleep(&mut self, duration: Duration) {
+            self.time += duration;
+            self.paused.store((self.from..self.to).contains(&self.time), Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn pausing_holds_playback_without_eating_waits() {
+        let mac = Macro::parse("wait 100\nclick left\n").unwrap();
+        let (running, paused) = (AtomicBool::new(true), AtomicBool::new(false));
+        let mut backend = Recorder::default();
+        let mut clock = Pausing { paused: &paused, from: Duration::from_millis(50), to: Duration::from_millis(550), time: Duration::ZERO };
+        let mut out = Output {
+            backend: &mut backend,
+            clock: &mut clock,
+            pixels: &None,
+            injection: KeyInjection::VirtualKey,
+            running: &running,
+            paused: &paused,
+            stepper: None,
+        };
+        play(&mac, &mut out);
+        assert_eq!(backend.actions, ["down left"]);
+        // The 100 ms wait still ran in full around the pause.
+        assert_eq!(clock.time, Duration::from_millis(600));
+    }
+}
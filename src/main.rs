@@ -1,470 +1,319 @@
-#![windows_subsystem = "windows"]
+#![cfg_attr(feature = "gui", windows_subsystem = "windows")]
+// Reduced feature sets leave some GUI/listener-only state unused.
+#![cfg_attr(not(all(feature = "gui", feature = "audio", feature = "listener")), allow(dead_code))]
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
-};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+#[cfg(not(feature = "gui"))]
 use std::thread;
-use std::time::{Duration, Instant};
-use std::io::{BufReader, Cursor};
-use std::fs::File;
-use std::io::{Write, BufRead};
-
-use enigo::*;
-use rdev::{listen, Event, EventType, Button, Key as RdevKey};
-
-use eframe::{egui, Frame, App};
-
-use rodio;
-
-// Simple config structure
-#[derive(Debug)]
-struct Config {
-    hotkey: String,  // e.g. "Insert"
-    fast_mode: bool,
-    target_cps: f64,
-    left_click: bool,
-    middle_click: bool,
-    right_click: bool,
-    play_sound: bool,
-}
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+mod activity;
+mod affinity;
+mod awake;
+#[cfg(feature = "audio")]
+mod audio;
+mod backend;
+mod buttons;
+#[cfg(feature = "gui")]
+mod bench;
+mod command_files;
+mod config;
+mod engine;
+mod foreground;
+mod geometry;
+mod guard;
+mod history;
+mod indicator;
+mod hooks;
+mod hotkeys;
+#[cfg(all(windows, feature = "interception"))]
+mod interception;
+#[cfg(feature = "gui")]
+mod gui;
+mod ipc;
+mod json;
+mod keys;
+mod layout;
+#[cfg(feature = "listener")]
+mod listener;
+mod log;
+mod macro_json;
+mod macros;
+mod mqtt;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod overlay;
+#[cfg(feature = "gui")]
+mod palette;
+mod patterns;
+mod pause;
+mod power;
+mod receipts;
+mod screen;
+mod session;
+mod sounds;
+mod state;
+mod stats;
+mod sync;
+mod telemetry;
+mod timer;
+mod totals;
+mod twitch;
+mod typer;
+mod travel;
+#[cfg(target_os = "linux")]
+mod uinput;
+mod webhook;
+
+use config::ConfigFile;
+#[cfg(not(feature = "gui"))]
+use config::ConfigIssue;
+use state::Shared;
+use sync::SyncState;
+use telemetry::Telemetry;
+use totals::Totals;
+
+// How long exiting waits for each thread to clean up.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 
-impl Config {
-    fn load() -> Option<Config> {
-        let file = File::open("config.txt").ok()?;
-        let reader = BufReader::new(file);
-        // Set defaults
-        let mut hotkey = "Insert".to_string();
-        let mut fast_mode = true;
-        let mut target_cps = 10.0;
-        let mut left_click = true;
-        let mut middle_click = false;
-        let mut right_click = false;
-        let mut play_sound = false;
-
-        for line in reader.lines() {
-            if let Ok(line) = line {
-                let parts: Vec<&str> = line.splitn(2, '=').collect();
-                if parts.len() != 2 { continue; }
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-                match key {
-                    "hotkey" => hotkey = value.to_string(),
-                    "fast_mode" => fast_mode = value.parse::<bool>().unwrap_or(true),
-                    "target_cps" => target_cps = value.parse::<f64>().unwrap_or(10.0),
-                    "left_click" => left_click = value.parse::<bool>().unwrap_or(true),
-                    "middle_click" => middle_click = value.parse::<bool>().unwrap_or(false),
-                    "right_click" => right_click = value.parse::<bool>().unwrap_or(false),
-                    "play_sound" => play_sound = value.parse::<bool>().unwrap_or(false),
-                    _ => {},
-                }
-            }
-        }
+fn main() {
+    // Companion CLI subcommands talk to an already running instance.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = ipc::run_cli(&args) {
+        std::process::exit(code);
+    }
 
-        Some(Config {
-            hotkey,
-            fast_mode,
-            target_cps,
-            left_click,
-            middle_click,
-            right_click,
-            play_sound,
-        })
+    // Load configuration (or use defaults), converting an old config.txt first.
+    let (migration_notice, migration_issues) = config::migrate_legacy().unzip();
+    let (mut config_file, loaded_issues) = ConfigFile::load().unwrap_or_default();
+    let config_issues: Vec<_> = migration_issues.into_iter().flatten().chain(loaded_issues).collect();
+    // Profiles changed on other machines, before any of them is applied.
+    let sync_notice = config_file.sync_dir.clone().map(|dir| sync::merge(&mut config_file, &dir, &mut SyncState::load()));
+    let mut shared = Shared::from_config(config_file.active());
+    *shared.sync_dir.lock().unwrap() = config_file.sync_dir.clone();
+    if let Some(notice) = sync_notice {
+        shared.log.push(notice.unwrap_or_else(|e| e));
+    }
+    shared.telemetry = Arc::new(Telemetry::load(config_file.telemetry));
+    shared.profiles.lock().unwrap().clone_from(&config_file.profiles);
+    shared.bindings.lock().unwrap().clone_from(&config_file.bindings);
+    shared.title_rules.lock().unwrap().clone_from(&config_file.title_rules);
+    shared.blocklist.lock().unwrap().clone_from(&config_file.blocklist);
+    shared.macro_hotkeys.lock().unwrap().clone_from(&config_file.macro_hotkeys);
+    for warning in hotkeys::check_file(&config_file) {
+        shared.log.push(warning);
+    }
+    *shared.backend.lock().unwrap() = config_file.backend;
+    *shared.max_cps.lock().unwrap() = config_file.max_cps;
+    *shared.superspeed.lock().unwrap() = config_file.superspeed;
+    // A config made on a machine with more cores pins nowhere here.
+    match config_file.pin_core {
+        Some(core) if core >= affinity::cores() => shared.log.push(format!("This machine has no core {}; the click engine isn't pinned", core)),
+        core => *shared.pin_core.lock().unwrap() = core,
     }
+    *shared.typing_pause.lock().unwrap() = config_file.typing_pause;
 
-    fn save(&self) -> std::io::Result<()> {
-        let mut file = File::create("config.txt")?;
-        writeln!(file, "hotkey={}", self.hotkey)?;
-        writeln!(file, "fast_mode={}", self.fast_mode)?;
-        writeln!(file, "target_cps={}", self.target_cps)?;
-        writeln!(file, "left_click={}", self.left_click)?;
-        writeln!(file, "middle_click={}", self.middle_click)?;
-        writeln!(file, "right_click={}", self.right_click)?;
-        writeln!(file, "play_sound={}", self.play_sound)?;
-        Ok(())
+    // Theme of the click, start and stop sounds.
+    let theme = sounds::load(&config_file.sound_theme).unwrap_or_else(|e| {
+        shared.log.push(e);
+        sounds::load(config::DEFAULT_SOUND_THEME).unwrap_or_default()
+    });
+    *shared.sound_theme.lock().unwrap() = Arc::new(theme);
+
+    // Initialize rodio for sound playback.
+    #[cfg(feature = "audio")]
+    let audio = audio::Audio::init()
+        .map_err(|e| shared.log.push(format!("Click sound disabled: {}", e)))
+        .ok();
+
+    #[cfg(feature = "audio")]
+    if let Some(audio) = &audio {
+        audio.spawn_beeper(shared.beep_handles());
     }
-}
 
-// Utility functions for converting rdev::Key to/from String.
-fn key_to_string(key: &rdev::Key) -> String {
-    match key {
-        rdev::Key::Insert => "Insert".to_string(),
-        rdev::Key::F1 => "F1".to_string(),
-        rdev::Key::F2 => "F2".to_string(),
-        rdev::Key::F3 => "F3".to_string(),
-        rdev::Key::F4 => "F4".to_string(),
-        rdev::Key::F5 => "F5".to_string(),
-        rdev::Key::F6 => "F6".to_string(),
-        rdev::Key::F7 => "F7".to_string(),
-        rdev::Key::F8 => "F8".to_string(),
-        rdev::Key::F9 => "F9".to_string(),
-        rdev::Key::F10 => "F10".to_string(),
-        rdev::Key::F11 => "F11".to_string(),
-        rdev::Key::F12 => "F12".to_string(),
-        rdev::Key::KeyA => "A".to_string(),
-        rdev::Key::KeyB => "B".to_string(),
-        rdev::Key::KeyC => "C".to_string(),
-        rdev::Key::KeyD => "D".to_string(),
-        rdev::Key::KeyE => "E".to_string(),
-        rdev::Key::KeyF => "F".to_string(),
-        rdev::Key::KeyG => "G".to_string(),
-        rdev::Key::KeyH => "H".to_string(),
-        rdev::Key::KeyI => "I".to_string(),
-        rdev::Key::KeyJ => "J".to_string(),
-        rdev::Key::KeyK => "K".to_string(),
-        rdev::Key::KeyL => "L".to_string(),
-        rdev::Key::KeyM => "M".to_string(),
-        rdev::Key::KeyN => "N".to_string(),
-        rdev::Key::KeyO => "O".to_string(),
-        rdev::Key::KeyP => "P".to_string(),
-        rdev::Key::KeyQ => "Q".to_string(),
-        rdev::Key::KeyR => "R".to_string(),
-        rdev::Key::KeyS => "S".to_string(),
-        rdev::Key::KeyT => "T".to_string(),
-        rdev::Key::KeyU => "U".to_string(),
-        rdev::Key::KeyV => "V".to_string(),
-        rdev::Key::KeyW => "W".to_string(),
-        rdev::Key::KeyX => "X".to_string(),
-        rdev::Key::KeyY => "Y".to_string(),
-        rdev::Key::KeyZ => "Z".to_string(),
-        _ => format!("{:?}", key),
+    // Control channel for the CLI subcommands.
+    ipc::spawn_server(shared.control_handles());
+    shared.command_files.store(config_file.command_files, Ordering::SeqCst);
+    command_files::spawn(shared.command_file_handles());
+    if let Some(mqtt) = config_file.mqtt.clone() {
+        *shared.mqtt.lock().unwrap() = Some(mqtt.clone());
+        mqtt::spawn(shared.mqtt_handles(), mqtt);
+    }
+    *shared.hooks.lock().unwrap() = config_file.hooks.clone();
+    hooks::spawn_runner(shared.hooks_handles());
+    if let Some(webhook) = config_file.webhook.clone() {
+        *shared.webhook.lock().unwrap() = Some(webhook.clone());
+        webhook::spawn(shared.webhook_handles(), webhook);
     }
-}
 
-fn string_to_rdev_key(s: &str) -> Option<rdev::Key> {
-    let s_lower = s.to_lowercase();
-    match s_lower.as_str() {
-        "insert" => Some(rdev::Key::Insert),
-        "f1" => Some(rdev::Key::F1),
-        "f2" => Some(rdev::Key::F2),
-        "f3" => Some(rdev::Key::F3),
-        "f4" => Some(rdev::Key::F4),
-        "f5" => Some(rdev::Key::F5),
-        "f6" => Some(rdev::Key::F6),
-        "f7" => Some(rdev::Key::F7),
-        "f8" => Some(rdev::Key::F8),
-        "f9" => Some(rdev::Key::F9),
-        "f10" => Some(rdev::Key::F10),
-        "f11" => Some(rdev::Key::F11),
-        "f12" => Some(rdev::Key::F12),
-        _ if s.len() == 1 => {
-            let ch = s.chars().next()?.to_ascii_uppercase();
-            match ch {
-                'A' => Some(rdev::Key::KeyA),
-                'B' => Some(rdev::Key::KeyB),
-                'C' => Some(rdev::Key::KeyC),
-                'D' => Some(rdev::Key::KeyD),
-                'E' => Some(rdev::Key::KeyE),
-                'F' => Some(rdev::Key::KeyF),
-                'G' => Some(rdev::Key::KeyG),
-                'H' => Some(rdev::Key::KeyH),
-                'I' => Some(rdev::Key::KeyI),
-                'J' => Some(rdev::Key::KeyJ),
-                'K' => Some(rdev::Key::KeyK),
-                'L' => Some(rdev::Key::KeyL),
-                'M' => Some(rdev::Key::KeyM),
-                'N' => Some(rdev::Key::KeyN),
-                'O' => Some(rdev::Key::KeyO),
-                'P' => Some(rdev::Key::KeyP),
-                'Q' => Some(rdev::Key::KeyQ),
-                'R' => Some(rdev::Key::KeyR),
-                'S' => Some(rdev::Key::KeyS),
-                'T' => Some(rdev::Key::KeyT),
-                'U' => Some(rdev::Key::KeyU),
-                'V' => Some(rdev::Key::KeyV),
-                'W' => Some(rdev::Key::KeyW),
-                'X' => Some(rdev::Key::KeyX),
-                'Y' => Some(rdev::Key::KeyY),
-                'Z' => Some(rdev::Key::KeyZ),
-                _ => None,
-            }
-        },
-        _ => None,
+    // Browser-source status page for streaming software.
+    if let Some(port) = config_file.overlay_port {
+        *shared.overlay_port.lock().unwrap() = Some(port);
+        if let Err(e) = overlay::spawn_server(shared.overlay_handles(), port) {
+            shared.log.push(format!("Stream overlay unavailable on port {}: {}", port, e));
+        }
     }
-}
 
-/// Shared application state.
-struct AppState {
-    clicking: Arc<AtomicBool>,
-    hotkey: Arc<Mutex<rdev::Key>>,
-    changing_hotkey: Arc<AtomicBool>,
-    clicks: Arc<Mutex<Vec<Instant>>>,
-    fast_mode: Arc<AtomicBool>,
-    target_cps: Arc<Mutex<f64>>,
-    // Mouse button selections.
-    left_click: bool,
-    middle_click: bool,
-    right_click: bool,
-    selected_buttons: Arc<Mutex<Vec<MouseButton>>>,
-    play_sound: Arc<AtomicBool>,
-}
+    // Click bursts requested from a Twitch channel's chat.
+    if let Some(trigger) = config_file.chat_trigger.clone() {
+        *shared.chat_trigger.lock().unwrap() = Some(trigger.clone());
+        twitch::spawn(shared.twitch_handles(), trigger);
+    }
 
-impl AppState {
-    fn from_config(config: Config) -> Self {
-        let hotkey = string_to_rdev_key(&config.hotkey).unwrap_or(rdev::Key::Insert);
-        let mut buttons = Vec::new();
-        if config.left_click { buttons.push(MouseButton::Left); }
-        if config.middle_click { buttons.push(MouseButton::Middle); }
-        if config.right_click { buttons.push(MouseButton::Right); }
-        AppState {
-            clicking: Arc::new(AtomicBool::new(false)),
-            hotkey: Arc::new(Mutex::new(hotkey)),
-            changing_hotkey: Arc::new(AtomicBool::new(false)),
-            clicks: Arc::new(Mutex::new(Vec::new())),
-            fast_mode: Arc::new(AtomicBool::new(config.fast_mode)),
-            target_cps: Arc::new(Mutex::new(config.target_cps)),
-            left_click: config.left_click,
-            middle_click: config.middle_click,
-            right_click: config.right_click,
-            selected_buttons: Arc::new(Mutex::new(buttons)),
-            play_sound: Arc::new(AtomicBool::new(config.play_sound)),
-        }
+    // Starting or stopping clicking on text read from the screen.
+    if let Some(trigger) = config_file.text_trigger.clone() {
+        #[cfg(feature = "ocr")]
+        ocr::spawn(shared.ocr_handles(), trigger);
+        #[cfg(not(feature = "ocr"))]
+        shared.log.push(format!("The text trigger for \"{}\" needs the ocr feature, which is off in this build", trigger.text));
     }
 
-    fn to_config(&self) -> Config {
-        let hotkey = {
-            let hk = self.hotkey.lock().unwrap();
-            key_to_string(&hk)
-        };
-        let fast_mode = self.fast_mode.load(Ordering::SeqCst);
-        let target_cps = *self.target_cps.lock().unwrap();
-        let left_click = self.left_click;
-        let middle_click = self.middle_click;
-        let right_click = self.right_click;
-        let play_sound = self.play_sound.load(Ordering::SeqCst);
-        Config {
-            hotkey,
-            fast_mode,
-            target_cps,
-            left_click,
-            middle_click,
-            right_click,
-            play_sound,
-        }
+    // Autoclicker thread.
+    #[cfg(feature = "audio")]
+    let engine = match &audio {
+        Some(audio) => engine::spawn(shared.engine_handles(), audio.click_player(shared.click_sound_handles())),
+        None => engine::spawn(shared.engine_handles(), || {}),
+    };
+    #[cfg(not(feature = "audio"))]
+    let engine = engine::spawn(shared.engine_handles(), || {});
+
+    #[cfg(feature = "listener")]
+    {
+        *shared.listener_retry.lock().unwrap() = config_file.listener_retry;
+        listener::spawn(&shared);
+    }
+
+    *shared.screen_size.lock().unwrap() = screen::size();
+    shared.scale_recordings.store(config_file.scale_recordings, Ordering::SeqCst);
+
+    // Checking that clicks arrive; only the listener sees them come back.
+    shared.receipts.enabled.store(config_file.verify_clicks && cfg!(feature = "listener"), Ordering::SeqCst);
+    #[cfg(feature = "listener")]
+    receipts::spawn_checker(shared.receipt_handles());
+    #[cfg(not(feature = "listener"))]
+    if config_file.verify_clicks {
+        shared.log.push("Click verification needs the global listener; it is off in this build.");
     }
+
+    // Per-application profile bindings.
+    let watcher = foreground::spawn_watcher(shared.foreground_handles());
+    foreground::spawn_window_tracker(shared.window_handles());
+
+    // Clicks today, this week and ever.
+    *shared.totals.lock().unwrap() = Totals::load();
+    *shared.rollover.lock().unwrap() = config_file.rollover;
+    let counter = totals::spawn_counter(shared.totals_handles());
+
+    // Summaries of past runs.
+    *shared.history.lock().unwrap() = history::History::load();
+    *shared.profile.lock().unwrap() = config_file.active_profile.clone();
+    let recorder = history::spawn_recorder(shared.history_handles());
+
+    // Pausing while the workstation is locked.
+    *shared.lock_policy.lock().unwrap() = config_file.on_lock;
+    session::spawn_watcher(shared.lock_handles());
+
+    // Pausing or throttling on battery.
+    *shared.battery_policy.lock().unwrap() = config_file.on_battery;
+    *shared.battery_cps.lock().unwrap() = config_file.battery_cps;
+    power::spawn_watcher(shared.power_handles());
+
+    // Keeping the machine awake through long runs.
+    shared.keep_awake.store(config_file.keep_awake, Ordering::SeqCst);
+    *shared.pause_key.lock().unwrap() = config_file.pause_key;
+    awake::spawn_keeper(shared.awake_handles());
+
+    // Showing that clicking is active over whatever is on screen.
+    *shared.indicator.lock().unwrap() = config_file.indicator;
+    indicator::spawn(shared.indicator_handles());
+
+    // Typing a text over and over while its hotkey has it on.
+    *shared.typer.lock().unwrap() = config_file.typer.clone();
+    typer::spawn(shared.typer_handles());
+
+    // Stopping once the user walked away.
+    *shared.idle_stop.lock().unwrap() = config_file.idle_stop;
+    activity::spawn_idle_stop(shared.idle_handles());
+
+    #[cfg(feature = "gui")]
+    gui::run(gui::AppState::new(shared.clone(), config_file, config_issues, migration_notice));
+    #[cfg(not(feature = "gui"))]
+    run_headless(shared.clone(), config_issues, migration_notice);
+    shut_down(&shared, [Some(engine), watcher, Some(counter), Some(recorder)].into_iter().flatten().collect());
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        AppState::from_config(Config {
-            hotkey: "Insert".to_string(),
-            fast_mode: true,
-            target_cps: 10.0,
-            left_click: true,
-            middle_click: false,
-            right_click: false,
-            play_sound: false,
-        })
+/// Stops clicking and waits for the engine and watcher threads to release
+/// held keys and count the last clicks. The hotkey listener can't be
+/// interrupted and ends with the process.
+fn shut_down(shared: &Shared, threads: Vec<JoinHandle<()>>) {
+    shared.clicking.store(false, Ordering::SeqCst);
+    shared.macro_playing.store(false, Ordering::SeqCst);
+    shared.shutdown.store(true, Ordering::SeqCst);
+    for thread in threads {
+        engine::join_within(thread, SHUTDOWN_TIMEOUT);
+    }
+    if let Err(e) = shared.telemetry.save() {
+        eprintln!("Failed to save usage counts: {}", e);
+    }
+    if let Some(dir) = shared.sync_dir.lock().unwrap().as_ref() {
+        if let Err(e) = sync::save_stats(dir, &shared.app_clicks.lock().unwrap()) {
+            eprintln!("Failed to sync click counts: {}", e);
+        }
     }
 }
 
-impl App for AppState {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
-        // Remove old clicks (older than 1 sec) for CPS count.
-        let now = Instant::now();
-        {
-            let mut clicks = self.clicks.lock().unwrap();
-            clicks.retain(|&t| now.duration_since(t) <= Duration::from_secs(1));
+/// Daemon mode: no window, controlled through the CLI subcommands and the
+/// hotkey. Returns once the `quit` command asks the app to exit.
+#[cfg(not(feature = "gui"))]
+fn run_headless(shared: Shared, config_issues: Vec<ConfigIssue>, migration_notice: Option<Result<String, String>>) {
+    match migration_notice {
+        Some(Ok(msg)) => println!("{}", msg),
+        Some(Err(msg)) => eprintln!("{}", msg),
+        None => {}
+    }
+    for issue in &config_issues {
+        eprintln!("config: {}", issue);
+    }
+    println!("Running headless on control port {}.", ipc::IPC_PORT);
+
+    let mut seen = 0;
+    while !shared.quit.load(Ordering::SeqCst) {
+        let pending = shared.pending_profile.lock().unwrap().take();
+        if let Some((name, config)) = pending {
+            shared.apply_config(&config);
+            shared.log.push(format!("Switched to profile {}", name));
+            *shared.profile.lock().unwrap() = name;
+        }
+        // The log is the only output channel without a window.
+        let (lines, total) = shared.log.lines_since(seen);
+        for line in lines {
+            println!("{}", line);
         }
-        let cps = self.clicks.lock().unwrap().len() as f64;
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Superspeed Autoclicker");
-
-            // Autoclicker toggle.
-            ui.horizontal(|ui| {
-                let state_str = if self.clicking.load(Ordering::SeqCst) {
-                    "Running"
-                } else {
-                    "Stopped"
-                };
-                ui.label(format!("Autoclicker: {}", state_str));
-                if ui.button("Toggle Autoclicker").clicked() {
-                    let new_state = !self.clicking.load(Ordering::SeqCst);
-                    self.clicking.store(new_state, Ordering::SeqCst);
-                }
-            });
-            ui.separator();
-
-            // Hotkey section.
-            {
-                let current_hotkey = {
-                    let hk = self.hotkey.lock().unwrap();
-                    key_to_string(&hk)
-                };
-                ui.label(format!("Start/Stop Key: {}", current_hotkey));
-                if ui.button("Change start/stop key").clicked() {
-                    self.changing_hotkey.store(true, Ordering::SeqCst);
-                }
-                if self.changing_hotkey.load(Ordering::SeqCst) {
-                    ui.label("Press a key to set as new hotkey...");
-                }
-            }
-            ui.separator();
-
-            // Click rate mode.
-            let fast_mode = self.fast_mode.load(Ordering::SeqCst);
-            ui.horizontal(|ui| {
-                ui.label("Click Rate Mode:");
-                if ui.radio_value(&mut { fast_mode }, true, "Superspeed").clicked() {
-                    self.fast_mode.store(true, Ordering::SeqCst);
-                }
-                if ui.radio_value(&mut { fast_mode }, false, "Consistent Rate").clicked() {
-                    self.fast_mode.store(false, Ordering::SeqCst);
-                }
-            });
-            if !self.fast_mode.load(Ordering::SeqCst) {
-                let mut cps_target = *self.target_cps.lock().unwrap();
-                ui.add(egui::Slider::new(&mut cps_target, 1.0..=1000.0).text("Target CPS (1-1000)"));
-                *self.target_cps.lock().unwrap() = cps_target;
-            }
-            ui.separator();
-
-            // Mouse button selection.
-            ui.heading("Mouse Button Actions");
-            ui.checkbox(&mut self.left_click, "Left Click");
-            ui.checkbox(&mut self.middle_click, "Middle Click");
-            ui.checkbox(&mut self.right_click, "Right Click");
-            {
-                let mut buttons = self.selected_buttons.lock().unwrap();
-                buttons.clear();
-                if self.left_click { buttons.push(MouseButton::Left); }
-                if self.middle_click { buttons.push(MouseButton::Middle); }
-                if self.right_click { buttons.push(MouseButton::Right); }
-            }
-            ui.separator();
-
-            // Sound option.
-            let mut play_sound_val = self.play_sound.load(Ordering::SeqCst);
-            ui.checkbox(&mut play_sound_val, "Play sound on click");
-            self.play_sound.store(play_sound_val, Ordering::SeqCst);
-            ui.separator();
-
-            // CPS test.
-            egui::CollapsingHeader::new("CPS Test")
-                .default_open(true)
-                .show(ui, |ui| {
-                    ui.label(format!("Current CPS: {:.1}", cps));
-                    ui.label("Click anywhere using the left mouse button to test.");
-                });
-            ui.separator();
-
-            // Save Config button.
-            if ui.button("Save Config").clicked() {
-                if let Err(e) = self.to_config().save() {
-                    ui.label(format!("Failed to save config: {}", e));
-                } else {
-                    ui.label("Config saved.");
-                }
-            }
-        });
-
-        ctx.request_repaint();
+        seen = total;
+        thread::sleep(Duration::from_millis(100));
     }
 }
 
-fn main() {
-    // Initialize rodio for sound playback.
-    let (_stream, stream_handle) =
-        rodio::OutputStream::try_default().expect("Failed to get audio output stream");
-    let stream_handle = Arc::new(stream_handle);
-    let click_sound_data =
-        Arc::new(std::fs::read("click.wav").expect("Failed to read click.wav"));
-
-    // Load configuration (or use defaults).
-    let app_state = if let Some(config) = Config::load() {
-        AppState::from_config(config)
-    } else {
-        AppState::default()
-    };
+#[cfg(all(test, not(feature = "gui")))]
+mod tests {
+    use super::*;
 
-    // Autoclicker thread.
-    let clicking_for_clicker = app_state.clicking.clone();
-    let fast_mode = app_state.fast_mode.clone();
-    let target_cps = app_state.target_cps.clone();
-    let play_sound = app_state.play_sound.clone();
-    let selected_buttons = app_state.selected_buttons.clone();
-
-    thread::spawn(move || {
-        let mut enigo = Enigo::new();
-        loop {
-            if clicking_for_clicker.load(Ordering::SeqCst) {
-                let buttons = selected_buttons.lock().unwrap().clone();
-                if buttons.is_empty() {
-                    enigo.mouse_click(MouseButton::Left);
-                } else {
-                    for btn in &buttons {
-                        enigo.mouse_down(*btn);
-                        enigo.mouse_up(*btn);
-                    }
-                    if play_sound.load(Ordering::SeqCst) {
-                        let stream_handle = stream_handle.clone();
-                        let click_sound_data = click_sound_data.clone();
-                        thread::spawn(move || {
-                            let cursor = Cursor::new(click_sound_data.as_ref().clone());
-                            let decoder = rodio::Decoder::new(BufReader::new(cursor)).unwrap();
-                            let sink = rodio::Sink::try_new(&stream_handle).unwrap();
-                            sink.append(decoder);
-                            sink.sleep_until_end();
-                        });
-                    }
-                    if !fast_mode.load(Ordering::SeqCst) {
-                        let cps = *target_cps.lock().unwrap();
-                        let sleep_duration = Duration::from_secs_f64(1.0 / cps);
-                        thread::sleep(sleep_duration);
-                    } else {
-                        // In fast mode, yield briefly.
-                        std::hint::spin_loop();
-                    }
-                }
-            } else {
-                thread::sleep(Duration::from_millis(10));
-            }
+    #[test]
+    fn quitting_ends_the_headless_loop() {
+        let shared = Shared::from_config(&config::Config::default());
+        let headless = {
+            let shared = shared.clone();
+            thread::spawn(move || run_headless(shared, Vec::new(), None))
+        };
+        thread::sleep(Duration::from_millis(150));
+        assert!(!headless.is_finished());
+        ipc::handle_command("quit", &shared.control_handles()).unwrap();
+        let start = std::time::Instant::now();
+        while !headless.is_finished() && start.elapsed() < SHUTDOWN_TIMEOUT {
+            thread::sleep(Duration::from_millis(10));
         }
-    });
-
-    // Global event listener for toggling, hotkey change, and CPS counting.
-    {
-        let clicking_for_listener = app_state.clicking.clone();
-        let hotkey = app_state.hotkey.clone();
-        let changing_hotkey = app_state.changing_hotkey.clone();
-        let clicks = app_state.clicks.clone();
-        thread::spawn(move || {
-            let callback = move |event: Event| {
-                match event.event_type {
-                    EventType::KeyPress(key) => {
-                        if changing_hotkey.load(Ordering::SeqCst) {
-                            if let Ok(mut hk) = hotkey.lock() {
-                                *hk = key;
-                            }
-                            changing_hotkey.store(false, Ordering::SeqCst);
-                        } else {
-                            let current_hotkey = { *hotkey.lock().unwrap() };
-                            if key == current_hotkey {
-                                let new_state = !clicking_for_listener.load(Ordering::SeqCst);
-                                clicking_for_listener.store(new_state, Ordering::SeqCst);
-                            }
-                        }
-                    }
-                    EventType::ButtonPress(Button::Left) => {
-                        let now = Instant::now();
-                        if let Ok(mut clicks) = clicks.lock() {
-                            clicks.push(now);
-                        }
-                    }
-                    _ => {}
-                }
-            };
-
-            if let Err(error) = listen(callback) {
-                eprintln!("Error in global listener: {:?}", error);
-            }
-        });
+        assert!(headless.is_finished());
     }
-
-    // Configure eframe native options.
-    let mut native_options = eframe::NativeOptions::default();
-    native_options.initial_window_size = Some(egui::vec2(330.0, 370.0));
-    
-    eframe::run_native("Superspeed Autoclicker", native_options, Box::new(|_cc| Box::new(app_state)));
-}
\ No newline at end of file
+}
@@ -0,0 +1,286 @@
+// MQTT control, for home automation setups like Home Assistant. A minimal
+// MQTT 3.1.1 client (QoS 0 only) that takes control channel commands from
+// "<topic>/set" and publishes, retained:
+//
+//     <topic>/state         {"running": true, "mode": "consistent", "target_cps": 10}
+//     <topic>/availability  "online", or "offline" once the app is gone
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::MqttConfig;
+use crate::ipc::{self, ControlHandles};
+use crate::json::Value;
+use crate::log::Log;
+
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+// How often the state is compared with the last one published.
+const POLL: Duration = Duration::from_millis(250);
+const MIN_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Packet types, in the high nibble of the first byte.
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const SUBSCRIBE: u8 = 0x82;
+const PINGREQ: u8 = 0xc0;
+const DISCONNECT: u8 = 0xe0;
+
+/// Shared state the MQTT client is allowed to touch.
+pub struct MqttHandles {
+    // Broker to stay connected to; the client disconnects once this no longer matches it.
+    pub config: Arc<Mutex<Option<MqttConfig>>>,
+    pub control: ControlHandles,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+/// Appends `len` in MQTT's variable length encoding.
+fn put_length(out: &mut Vec<u8>, mut len: usize) {
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        out.push(if len > 0 { byte | 0x80 } else { byte });
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn put_str(out: &mut Vec<u8>, text: &str) {
+    out.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    put_length(&mut out, body.len());
+    out.extend_from_slice(body);
+    out
+}
+
+fn connect_packet(config: &MqttConfig, client_id: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT");
+    body.push(4);
+    // Clean session, with a retained "offline" will.
+    let mut flags = 0x02 | 0x04 | 0x20;
+    let username = !config.username.is_empty();
+    // A password is only allowed along with a username.
+    let password = username && !config.password.is_empty();
+    if username {
+        flags |= 0x80;
+    }
+    if password {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    put_str(&mut body, client_id);
+    put_str(&mut body, &format!("{}/availability", config.topic));
+    put_str(&mut body, "offline");
+    if username {
+        put_str(&mut body, &config.username);
+    }
+    if password {
+        put_str(&mut body, &config.password);
+    }
+    packet(CONNECT, &body)
+}
+
+fn subscribe_packet(topic: &str) -> Vec<u8> {
+    let mut body = 1u16.to_be_bytes().to_vec();
+    put_str(&mut body, topic);
+    body.push(0);
+    packet(SUBSCRIBE, &body)
+}
+
+fn publish_packet(topic: &str, payload: &str, retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_str(&mut body, topic);
+    body.extend_from_slice(payload.as_bytes());
+    packet(PUBLISH | u8::from(retain), &body)
+}
+
+/// Removes the first complete packet from `buffer`, as its first byte and body.
+fn take_packet(buffer: &mut Vec<u8>) -> Option<(u8, Vec<u8>)> {
+    let mut len = 0;
+    let mut header = 1;
+    loop {
+        let byte = *buffer.get(header)?;
+        len += usize::from(byte & 0x7f) << (7 * (header - 1));
+        header += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if header > 4 {
+            // Longer than the protocol allows; drop what was read.
+            buffer.clear();
+            return None;
+        }
+    }
+    if buffer.len() < header + len {
+        return None;
+    }
+    let kind = buffer[0];
+    let body = buffer[header..header + len].to_vec();
+    buffer.drain(..header + len);
+    Some((kind, body))
+}
+
+/// Topic and payload of a received PUBLISH body.
+fn parse_publish(kind: u8, body: &[u8]) -> Option<(String, String)> {
+    let len = usize::from(u16::from_be_bytes([*body.first()?, *body.get(1)?]));
+    let topic = std::str::from_utf8(body.get(2..2 + len)?).ok()?;
+    // QoS 1 and 2 messages carry a packet id before the payload.
+    let start = 2 + len + if kind & 0x06 != 0 { 2 } else { 0 };
+    let payload = std::str::from_utf8(body.get(start..)?).ok()?;
+    Some((topic.to_string(), payload.trim().to_string()))
+}
+
+fn state(control: &ControlHandles) -> String {
+    Value::Object(vec![
+        ("running".to_string(), Value::Bool(control.clicking.load(Ordering::SeqCst))),
+        ("mode".to_string(), if control.fast_mode.load(Ordering::SeqCst) { "superspeed" } else { "consistent" }.into()),
+        ("target_cps".to_string(), (*control.target_cps.lock().unwrap()).into()),
+    ])
+    .to_string()
+}
+
+/// Host and port of a "host" or "host:port" broker address.
+fn address(broker: &str) -> Result<(String, u16), String> {
+    match broker.rsplit_once(':') {
+        Some((host, port)) => Ok((host.to_string(), port.parse().map_err(|_| format!("invalid port \"{}\"", port))?)),
+        None => Ok((broker.to_string(), DEFAULT_PORT)),
+    }
+}
+
+fn send(stream: &mut TcpStream, bytes: Vec<u8>) -> Result<(), String> {
+    stream.write_all(&bytes).map_err(|e| e.to_string())
+}
+
+/// Stays connected to the broker of `config` on its own thread, reconnecting
+/// with a growing delay until it is changed or turned off.
+pub fn spawn(handles: MqttHandles, config: MqttConfig) {
+    thread::spawn(move || {
+        let mut backoff = MIN_BACKOFF;
+        while active(&handles, &config) {
+            let started = Instant::now();
+            match run(&handles, &config) {
+                Ok(()) => break,
+                Err(e) => handles.log.push(format!("MQTT disconnected: {}", e)),
+            }
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = MIN_BACKOFF;
+            }
+            let retry = Instant::now() + backoff;
+            while Instant::now() < retry && active(&handles, &config) {
+                thread::sleep(POLL);
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+fn active(handles: &MqttHandles, config: &MqttConfig) -> bool {
+    !handles.shutdown.load(Ordering::SeqCst) && handles.config.lock().unwrap().as_ref() == Some(config)
+}
+
+/// One connection; returns `Ok` once the client was turned off.
+fn run(handles: &MqttHandles, config: &MqttConfig) -> Result<(), String> {
+    let (host, port) = address(&config.broker)?;
+    let mut stream = TcpStream::connect((host.as_str(), port)).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(POLL)).map_err(|e| e.to_string())?;
+    let id = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.subsec_micros());
+    send(&mut stream, connect_packet(config, &format!("autoclicker-{}", id)))?;
+
+    let commands = format!("{}/set", config.topic);
+    let mut buffer = Vec::new();
+    let mut published: Option<String> = None;
+    let mut last_sent = Instant::now();
+    let mut connected = false;
+    while active(handles, config) {
+        let mut chunk = [0; 1024];
+        match stream.read(&mut chunk) {
+            Ok(0) => return Err("connection closed".to_string()),
+            Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        while let Some((kind, body)) = take_packet(&mut buffer) {
+            match kind & 0xf0 {
+                CONNACK => {
+                    if body.get(1) != Some(&0) {
+                        return Err(format!("broker refused the connection (code {})", body.get(1).copied().unwrap_or(0)));
+                    }
+                    connected = true;
+                    send(&mut stream, subscribe_packet(&commands))?;
+                    send(&mut stream, publish_packet(&format!("{}/availability", config.topic), "online", true))?;
+                    handles.log.push(format!("MQTT connected to {}, listening on {}", config.broker, commands));
+                }
+                PUBLISH => {
+                    if let Some((topic, line)) = parse_publish(kind, &body).filter(|(topic, _)| *topic == commands) {
+                        if let Err(e) = ipc::handle_command(&line, &handles.control) {
+                            handles.log.push(format!("MQTT command \"{}\" on {} rejected: {}", line, topic, e));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !connected {
+            continue;
+        }
+        let current = state(&handles.control);
+        if published.as_ref() != Some(&current) {
+            send(&mut stream, publish_packet(&format!("{}/state", config.topic), &current, true))?;
+            published = Some(current);
+            last_sent = Instant::now();
+        } else if last_sent.elapsed() >= KEEP_ALIVE / 2 {
+            send(&mut stream, packet(PINGREQ, &[]))?;
+            last_sent = Instant::now();
+        }
+    }
+    // A clean disconnect skips the will, so say goodbye first.
+    let _ = send(&mut stream, publish_packet(&format!("{}/availability", config.topic), "offline", true));
+    let _ = send(&mut stream, packet(DISCONNECT, &[]));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_round_trip() {
+        let mut buffer = publish_packet("home/clicker/set", "set-cps 50", false);
+        buffer.extend(packet(CONNACK, &[0, 0]));
+        let (kind, body) = take_packet(&mut buffer).unwrap();
+        assert_eq!(parse_publish(kind, &body), Some(("home/clicker/set".to_string(), "set-cps 50".to_string())));
+        assert_eq!(take_packet(&mut buffer), Some((CONNACK, vec![0, 0])));
+        assert!(buffer.is_empty());
+
+        // Lengths over 127 take more than one byte, and partial packets wait for the rest.
+        let long = publish_packet("t", &"x".repeat(200), true);
+        assert_eq!(&long[..3], &[PUBLISH | 1, 203 - 128 + 0x80, 1]);
+        let mut partial = long[..100].to_vec();
+        assert_eq!(take_packet(&mut partial), None);
+        partial.extend_from_slice(&long[100..]);
+        assert_eq!(take_packet(&mut partial).map(|(_, body)| body.len()), Some(203));
+    }
+
+    #[test]
+    fn brokers_default_to_port_1883() {
+        assert_eq!(address("homeassistant.local"), Ok(("homeassistant.local".to_string(), 1883)));
+        assert_eq!(address("10.0.0.5:8883"), Ok(("10.0.0.5".to_string(), 8883)));
+        assert!(address("broker:mqtt").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+// Text triggers: a screen region read every so often with the tesseract
+// command, starting or stopping clicking when the text shows up, like
+// stopping once "Inventory full" appears. Only built with the `ocr` feature;
+// tesseract itself has to be installed and on the PATH.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::config::{TextAction, TextTrigger};
+use crate::log::Log;
+use crate::screen::{Color, Screen};
+
+/// Shared state the text trigger is allowed to touch.
+pub struct OcrHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Lowercase words of `text`, one space apart.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Whether text read from the screen contains `wanted`, whatever the case and
+/// however it was broken into lines.
+fn shows(read: &str, wanted: &str) -> bool {
+    normalize(read).contains(&normalize(wanted))
+}
+
+/// Binary PPM image of the pixels, which tesseract reads without a PNG encoder.
+fn to_ppm(width: u16, height: u16, pixels: &[Color]) -> Vec<u8> {
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    image.extend(pixels.iter().flatten());
+    image
+}
+
+/// Whether the text was on screen at the last read, so a trigger fires once
+/// each time it appears rather than on every read.
+#[derive(Default)]
+struct TextWatch {
+    shown: bool,
+}
+
+impl TextWatch {
+    /// Returns true when the text has just appeared.
+    fn update(&mut self, shown: bool) -> bool {
+        let appeared = shown && !self.shown;
+        self.shown = shown;
+        appeared
+    }
+}
+
+/// Text tesseract reads in the image at `path`.
+fn read_text(path: &Path) -> Result<String, String> {
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => "tesseract isn't installed or not on the PATH".to_string(),
+            _ => format!("tesseract couldn't run: {}", e),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("tesseract failed: {}", stderr.lines().next().unwrap_or_default()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the trigger's region until shutdown, or until tesseract can't be run.
+pub fn spawn(handles: OcrHandles, trigger: TextTrigger) {
+    thread::spawn(move || {
+        let Some(screen) = Screen::new() else {
+            handles.log.push("Text trigger: the screen can't be read here");
+            return;
+        };
+        let region = trigger.region;
+        let (x, y) = (region.x.round() as i32, region.y.round() as i32);
+        let (width, height) = (region.width.round().clamp(1.0, 4096.0) as u16, region.height.round().clamp(1.0, 4096.0) as u16);
+        let path = std::env::temp_dir().join(format!("autoclicker-ocr-{}.ppm", std::process::id()));
+        let mut watch = TextWatch::default();
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(trigger.every);
+            let Some(pixels) = screen.capture(x, y, width, height) else { continue };
+            if let Err(e) = fs::write(&path, to_ppm(width, height, &pixels)) {
+                handles.log.push(format!("Text trigger: couldn't save the screen image: {}", e));
+                break;
+            }
+            let read = match read_text(&path) {
+                Ok(read) => read,
+                Err(e) => {
+                    handles.log.push(format!("Text trigger stopped: {}", e));
+                    break;
+                }
+            };
+            if !watch.update(shows(&read, &trigger.text)) {
+                continue;
+            }
+            let start = trigger.action == TextAction::Start;
+            if handles.clicking.swap(start, Ordering::SeqCst) != start {
+                let what = if start { "started" } else { "stopped" };
+                handles.log.push(format!("\"{}\" appeared on screen; clicking {}", trigger.text, what));
+            }
+        }
+        let _ = fs::remove_file(&path);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_matched_across_lines_and_case() {
+        assert!(shows("Backpack\nINVENTORY\n  FULL!\n", "Inventory full"));
+        assert!(!shows("Inventory: 12/40", "Inventory full"));
+        assert_eq!(to_ppm(2, 1, &[[255, 0, 0], [0, 0, 255]]), b"P6\n2 1\n255\n\xff\x00\x00\x00\x00\xff");
+
+        // Fires once per appearance.
+        let mut watch = TextWatch::default();
+        assert_eq!([true, true, false, true].map(|shown| watch.update(shown)), [true, false, false, true]);
+    }
+}
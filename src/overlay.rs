@@ -0,0 +1,162 @@
+// Stream overlay: a tiny HTTP server on the loopback interface serving a
+// transparent status page, meant to be added to OBS as a browser source.
+//
+//     GET /       the overlay page, which polls /state
+//     GET /state  {"running": true, "mode": "superspeed", "target_cps": 10, "cps": 812.5}
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::json::Value;
+use crate::log::Log;
+
+pub const DEFAULT_PORT: u16 = 47654;
+// How often the server checks whether it was turned off between requests.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+// Shortest window the live CPS is measured over.
+const RATE_WINDOW: Duration = Duration::from_millis(250);
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Autoclicker overlay</title>
+<style>
+  html, body { background: transparent; margin: 0; }
+  #status { font: bold 28px sans-serif; color: #fff; text-shadow: 0 0 4px #000, 0 0 8px #000; padding: 8px; }
+  .dot { display: inline-block; width: 0.6em; height: 0.6em; border-radius: 50%; margin-right: 0.3em; background: #888; }
+  .running .dot { background: #e33; }
+</style>
+</head>
+<body>
+<div id="status"><span class="dot"></span><span id="text">Connecting...</span></div>
+<script>
+async function update() {
+  try {
+    const state = await (await fetch("/state", { cache: "no-store" })).json();
+    document.getElementById("status").className = state.running ? "running" : "";
+    document.getElementById("text").textContent = state.running
+      ? Math.round(state.cps) + " CPS" + (state.mode === "superspeed" ? " (superspeed)" : "")
+      : "Stopped";
+  } catch (e) {
+    document.getElementById("text").textContent = "Offline";
+  }
+}
+update();
+setInterval(update, 250);
+</script>
+</body>
+</html>
+"#;
+
+/// Shared state the overlay is allowed to read.
+pub struct OverlayHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub fast_mode: Arc<AtomicBool>,
+    pub target_cps: Arc<Mutex<f64>>,
+    pub emitted: Arc<AtomicU64>,
+    // Port to serve on; the server stops once this no longer names its port.
+    pub port: Arc<Mutex<Option<u16>>>,
+    pub log: Arc<Log>,
+}
+
+/// Live click rate, measured from the emitted count between requests.
+struct Rate {
+    sample: (u64, Instant),
+    cps: f64,
+}
+
+impl Rate {
+    fn update(&mut self, emitted: u64) -> f64 {
+        let elapsed = self.sample.1.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.cps = emitted.saturating_sub(self.sample.0) as f64 / elapsed.as_secs_f64();
+            self.sample = (emitted, Instant::now());
+        }
+        self.cps
+    }
+}
+
+/// Starts serving the overlay on `port` of the loopback interface.
+pub fn spawn_server(handles: OverlayHandles, port: u16) -> Result<(), String> {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port))).map_err(|e| e.to_string())?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    handles.log.push(format!("Stream overlay at http://127.0.0.1:{}/", port));
+    thread::spawn(move || {
+        let mut rate = Rate { sample: (handles.emitted.load(Ordering::Relaxed), Instant::now()), cps: 0.0 };
+        while *handles.port.lock().unwrap() == Some(port) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = serve(stream, &handles, &mut rate) {
+                        eprintln!("Overlay request failed: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+                Err(e) => eprintln!("Overlay connection failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn serve(stream: TcpStream, handles: &OverlayHandles, rate: &mut Rate) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers don't matter, but are read so the client sees a clean close.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let state = || {
+        Value::Object(vec![
+            ("running".to_string(), Value::Bool(handles.clicking.load(Ordering::SeqCst))),
+            ("mode".to_string(), if handles.fast_mode.load(Ordering::SeqCst) { "superspeed" } else { "consistent" }.into()),
+            ("target_cps".to_string(), (*handles.target_cps.lock().unwrap()).into()),
+            ("cps".to_string(), rate.update(handles.emitted.load(Ordering::Relaxed)).into()),
+        ])
+    };
+    let (status, content_type, body) = respond(path, state);
+    write!(
+        &stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Status line, content type and body for a request of `path`.
+fn respond(path: &str, state: impl FnOnce() -> Value) -> (&'static str, &'static str, String) {
+    match path.split('?').next().unwrap_or_default() {
+        "/" => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+        "/state" => ("200 OK", "application/json", state().to_string()),
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_routed() {
+        let state = || Value::Object(vec![("running".to_string(), Value::Bool(true))]);
+        let (status, content_type, body) = respond("/?scene=1", state);
+        assert_eq!((status, content_type), ("200 OK", "text/html; charset=utf-8"));
+        assert!(body.contains("fetch(\"/state\""));
+        assert_eq!(respond("/state", state), ("200 OK", "application/json", "{\"running\": true}".to_string()));
+        assert_eq!(respond("/favicon.ico", state).0, "404 Not Found");
+    }
+}
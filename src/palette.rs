@@ -0,0 +1,104 @@
+// Ctrl+K command palette: every action of the window, found by typing a few
+// letters of its name.
+
+/// Collapsible sections of the window, in the order they are shown.
+pub const SECTIONS: &[&str] = &[
+    "CPS Hotkeys",
+    "Key Auto-Press",
+    "Text Typer",
+    "Click Area",
+    "Click Pattern",
+    "CPS Test",
+    "Statistics",
+    "Usage Metrics",
+    "App Bindings",
+    "Macros",
+    "Macro Library",
+    "Diagnostics",
+    "Log",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    ToggleClicking,
+    Superspeed,
+    ConsistentRate,
+    MouseJiggler,
+    SaveConfig,
+    SwitchProfile(String),
+    ImportProfile,
+    CopyProfileCode,
+    PlayMacro(String),
+    StopMacro,
+    OpenSection(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub label: String,
+    pub action: Action,
+}
+
+impl Command {
+    pub fn new(label: impl Into<String>, action: Action) -> Command {
+        Command { label: label.into(), action }
+    }
+}
+
+/// Palette being shown: the text typed and the highlighted result.
+#[derive(Default)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+/// How well `query` matches `label`, if every character of it appears in
+/// order, ignoring case. Runs of characters and word starts score higher.
+pub fn score(query: &str, label: &str) -> Option<i32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut at = 0;
+    let mut last: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = at + label[at..].iter().position(|&l| l == c)?;
+        score += 1;
+        if last == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last = Some(found);
+        at = found + 1;
+    }
+    Some(score)
+}
+
+/// The commands matching `query`, best first; all of them for an empty query.
+pub fn search(commands: Vec<Command>, query: &str) -> Vec<Command> {
+    let mut scored: Vec<(i32, Command)> =
+        commands.into_iter().filter_map(|command| Some((score(query, &command.label)?, command))).collect();
+    // Stable, so equally good matches keep their order.
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, command)| command).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_starts_rank_first() {
+        assert_eq!(score("xyz", "Toggle clicking"), None);
+        assert!(score("tc", "Toggle clicking") > score("tc", "Statistics"));
+        let commands = vec![
+            Command::new("Open Statistics", Action::OpenSection("Statistics")),
+            Command::new("Switch to profile pvp", Action::SwitchProfile("pvp".to_string())),
+            Command::new("Play macro farm", Action::PlayMacro("farm".to_string())),
+        ];
+        let found = search(commands.clone(), "prof");
+        assert_eq!(found[0].action, Action::SwitchProfile("pvp".to_string()));
+        assert_eq!(search(commands.clone(), ""), commands);
+        assert_eq!(search(commands, "stats").len(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+// Generators for click sequences: a grid over a region, points on a circle,
+// or an evenly spaced line.
+
+use std::f64::consts::TAU;
+
+use crate::geometry::{Point, Rect};
+
+/// Shape of a generated sequence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// Centers of `columns` x `rows` equal cells, row by row.
+    Grid { columns: usize, rows: usize },
+    /// `points` steps around the largest circle inside the region, clockwise from the top.
+    Circle { points: usize },
+    /// `points` steps across the middle of the region, left to right.
+    Line { points: usize },
+}
+
+impl Pattern {
+    pub fn generate(&self, region: Rect) -> Vec<Point> {
+        let center = (region.x + region.width / 2.0, region.y + region.height / 2.0);
+        match *self {
+            Pattern::Grid { columns, rows } => {
+                let cell = (region.width / columns as f64, region.height / rows as f64);
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                    .map(|(column, row)| {
+                        (region.x + cell.0 * (column as f64 + 0.5), region.y + cell.1 * (row as f64 + 0.5))
+                    })
+                    .collect()
+            }
+            Pattern::Circle { points } => {
+                let radius = region.width.min(region.height) / 2.0;
+                (0..points)
+                    .map(|n| {
+                        let angle = TAU * n as f64 / points as f64;
+                        (center.0 + radius * angle.sin(), center.1 - radius * angle.cos())
+                    })
+                    .collect()
+            }
+            Pattern::Line { points } => {
+                let step = region.width / points as f64;
+                (0..points).map(|n| (region.x + step * (n as f64 + 0.5), center.1)).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: Rect = Rect { x: 100.0, y: 200.0, width: 300.0, height: 200.0 };
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn grid_covers_cell_centers_row_by_row() {
+        let points = Pattern::Grid { columns: 3, rows: 2 }.generate(REGION);
+        assert_eq!(
+            points,
+            vec![(150.0, 250.0), (250.0, 250.0), (350.0, 250.0), (150.0, 350.0), (250.0, 350.0), (350.0, 350.0)]
+        );
+        assert!(points.iter().all(|&(x, y)| REGION.contains(x, y)));
+    }
+
+    #[test]
+    fn circle_starts_at_the_top_and_goes_clockwise() {
+        let points = Pattern::Circle { points: 4 }.generate(REGION);
+        assert_eq!(points.len(), 4);
+        assert_close(points[0], (250.0, 200.0));
+        assert_close(points[1], (350.0, 300.0));
+        assert_close(points[2], (250.0, 400.0));
+        assert_close(points[3], (150.0, 300.0));
+    }
+
+    #[test]
+    fn line_sweeps_the_middle() {
+        let points = Pattern::Line { points: 3 }.generate(REGION);
+        assert_eq!(points, vec![(150.0, 300.0), (250.0, 300.0), (350.0, 300.0)]);
+        assert!(Pattern::Line { points: 0 }.generate(REGION).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+// Pausing everything: one hotkey stops clicking, holds the playing macro where
+// it is and stops the typer, remembering which of them were running. The next
+// press resumes just those. Each pause pushes what it stopped, so pausing
+// something started by hand in between doesn't lose the earlier pause.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::log::Log;
+
+// Pauses remembered at most; the oldest is forgotten past this.
+const MAX_SAVED: usize = 8;
+
+/// What was running when everything was paused.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RunState {
+    pub clicking: bool,
+    pub macro_playing: bool,
+    pub typing: bool,
+}
+
+impl RunState {
+    pub fn any(self) -> bool {
+        self.clicking || self.macro_playing || self.typing
+    }
+}
+
+/// Run states saved by each pause, latest last.
+#[derive(Debug, Default)]
+pub struct RunStack {
+    saved: Mutex<Vec<RunState>>,
+}
+
+impl RunStack {
+    /// What should run after the pause key: nothing if something runs now,
+    /// which is saved, and otherwise what the last pause stopped.
+    pub fn toggle(&self, current: RunState) -> RunState {
+        let mut saved = self.saved.lock().unwrap();
+        if current.any() {
+            if saved.len() == MAX_SAVED {
+                saved.remove(0);
+            }
+            saved.push(current);
+            RunState::default()
+        } else {
+            saved.pop().unwrap_or_default()
+        }
+    }
+}
+
+/// Shared state the pause key is allowed to touch.
+pub struct PauseHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub macro_playing: Arc<AtomicBool>,
+    pub macro_paused: Arc<AtomicBool>,
+    pub typer_running: Arc<AtomicBool>,
+    pub stack: Arc<RunStack>,
+    pub log: Arc<Log>,
+}
+
+/// Pauses whatever runs, or resumes what the last pause stopped.
+pub fn toggle_all(handles: &PauseHandles) {
+    let current = RunState {
+        clicking: handles.clicking.load(Ordering::SeqCst),
+        macro_playing: handles.macro_playing.load(Ordering::SeqCst) && !handles.macro_paused.load(Ordering::SeqCst),
+        typing: handles.typer_running.load(Ordering::SeqCst),
+    };
+    let next = handles.stack.toggle(current);
+    handles.clicking.store(next.clicking, Ordering::SeqCst);
+    // A macro that ended while paused stays ended.
+    handles.macro_paused.store(!next.macro_playing, Ordering::SeqCst);
+    handles.typer_running.store(next.typing, Ordering::SeqCst);
+    if current.any() {
+        handles.log.push("Paused everything");
+    } else if next.any() {
+        handles.log.push("Resumed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resume_brings_back_what_was_paused() {
+        let stack = RunStack::default();
+        let both = RunState { clicking: true, macro_playing: true, typing: false };
+        assert_eq!(stack.toggle(both), RunState::default());
+        assert_eq!(stack.toggle(RunState::default()), both);
+        // Nothing saved, nothing to resume.
+        assert_eq!(stack.toggle(RunState::default()), RunState::default());
+
+        // Something started by hand while paused is paused and resumed on its own.
+        let typing = RunState { typing: true, ..RunState::default() };
+        stack.toggle(both);
+        stack.toggle(typing);
+        assert_eq!(stack.toggle(RunState::default()), typing);
+        assert_eq!(stack.toggle(RunState::default()), both);
+    }
+}
@@ -0,0 +1,182 @@
+// Battery awareness for laptops: pauses or throttles clicking while running
+// on battery or with battery saver on. Read on Windows from
+// GetSystemPowerStatus, on Linux from /sys/class/power_supply (discharging)
+// and the ACPI platform profile ("low-power" while power saver is on).
+// Elsewhere the machine always counts as plugged in.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::BatteryPolicy;
+use crate::log::Log;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shared state the power watcher is allowed to touch.
+pub struct PowerHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub policy: Arc<Mutex<BatteryPolicy>>,
+    pub battery_cps: Arc<Mutex<f64>>,
+    pub power_cap: Arc<Mutex<Option<f64>>>,
+    pub low_power: Arc<AtomicBool>,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    pub saver: bool,
+}
+
+/// Whether clicking was paused for running on battery.
+#[derive(Default)]
+struct PowerWatch {
+    paused: bool,
+}
+
+impl PowerWatch {
+    /// Clicking state to switch to for `policy`, if any. While on battery
+    /// clicking is kept off, and it resumes once plugged in if it was paused.
+    fn update(&mut self, policy: BatteryPolicy, low: bool, clicking: bool) -> Option<bool> {
+        if low && policy == BatteryPolicy::Pause {
+            if clicking {
+                self.paused = true;
+                return Some(false);
+            }
+            return None;
+        }
+        // Plugged in again, or the policy no longer pauses.
+        std::mem::take(&mut self.paused).then_some(true)
+    }
+}
+
+/// Polls the power status and applies the battery policy until shutdown.
+pub fn spawn_watcher(handles: PowerHandles) {
+    thread::spawn(move || {
+        let mut watch = PowerWatch::default();
+        let mut was_low = false;
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            let policy = *handles.policy.lock().unwrap();
+            let status = status().unwrap_or_default();
+            let low = status.on_battery || status.saver;
+            handles.low_power.store(low, Ordering::SeqCst);
+            if low != was_low && policy != BatteryPolicy::Ignore {
+                let reason = if status.saver { "Battery saver on" } else { "Running on battery" };
+                handles.log.push(if low { reason } else { "Plugged in" });
+            }
+            was_low = low;
+            let cap = (low && policy == BatteryPolicy::Throttle).then(|| *handles.battery_cps.lock().unwrap());
+            *handles.power_cap.lock().unwrap() = cap;
+            match watch.update(policy, low, handles.clicking.load(Ordering::SeqCst)) {
+                Some(false) => {
+                    handles.clicking.store(false, Ordering::SeqCst);
+                    handles.log.push("Clicking paused on battery");
+                }
+                Some(true) => {
+                    handles.clicking.store(true, Ordering::SeqCst);
+                    handles.log.push("Clicking resumed");
+                }
+                None => {}
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+use platform::status;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::path::Path;
+
+    use super::PowerStatus;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).map(|text| text.trim().to_string()).unwrap_or_default()
+    }
+
+    pub fn status() -> Option<PowerStatus> {
+        let supplies = fs::read_dir("/sys/class/power_supply").ok()?;
+        // Peripheral batteries (mice, headsets) have scope "Device".
+        let on_battery = supplies.flatten().map(|entry| entry.path()).any(|supply| {
+            read(&supply.join("type")) == "Battery"
+                && read(&supply.join("scope")) != "Device"
+                && read(&supply.join("status")) == "Discharging"
+        });
+        let saver = read(Path::new("/sys/firmware/acpi/platform_profile")) == "low-power";
+        Some(PowerStatus { on_battery, saver })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::PowerStatus;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        _battery_flag: u8,
+        _battery_life_percent: u8,
+        system_status_flag: u8,
+        _battery_life_time: u32,
+        _battery_full_life_time: u32,
+    }
+
+    const AC_OFFLINE: u8 = 0;
+    const BATTERY_SAVER_ON: u8 = 1;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    pub fn status() -> Option<PowerStatus> {
+        let mut status = SystemPowerStatus::default();
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+            return None;
+        }
+        Some(PowerStatus {
+            on_battery: status.ac_line_status == AC_OFFLINE,
+            saver: status.system_status_flag == BATTERY_SAVER_ON,
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::PowerStatus;
+
+    pub fn status() -> Option<PowerStatus> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_pauses_until_plugged_in() {
+        let mut watch = PowerWatch::default();
+        assert_eq!(watch.update(BatteryPolicy::Pause, false, true), None);
+        assert_eq!(watch.update(BatteryPolicy::Pause, true, true), Some(false));
+        assert_eq!(watch.update(BatteryPolicy::Pause, true, false), None);
+        // Started again on battery: stopped again.
+        assert_eq!(watch.update(BatteryPolicy::Pause, true, true), Some(false));
+        assert_eq!(watch.update(BatteryPolicy::Pause, false, false), Some(true));
+        assert_eq!(watch.update(BatteryPolicy::Pause, false, true), None);
+
+        // Throttling and ignoring never stop clicking.
+        assert_eq!(watch.update(BatteryPolicy::Throttle, true, true), None);
+        assert_eq!(watch.update(BatteryPolicy::Ignore, true, true), None);
+
+        // Switching away from pausing while on battery resumes.
+        assert_eq!(watch.update(BatteryPolicy::Pause, true, true), Some(false));
+        assert_eq!(watch.update(BatteryPolicy::Throttle, true, false), Some(true));
+    }
+}
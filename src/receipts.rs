@@ -0,0 +1,147 @@
+// Click receipt verification: button presses the engine sends, against the
+// ones the global listener sees come back from the system. When clicking asks
+// for more than the system delivers, the difference shows up as dropped clicks.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::log::Log;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// Polls the dropped share is measured over; long enough for the listener to catch up.
+const WINDOW: usize = 5;
+// Fewer presses than this in a window say too little to warn about.
+const MIN_SENT: u64 = 20;
+// Share of dropped presses worth a warning.
+pub const DROP_WARNING: f64 = 0.05;
+
+#[derive(Default)]
+pub struct Receipts {
+    pub enabled: AtomicBool,
+    sent: AtomicU64,
+    seen: AtomicU64,
+}
+
+impl Receipts {
+    /// Counts `n` button presses sent to the system, while verification is on.
+    pub fn sent(&self, n: u64) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.sent.fetch_add(n, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts a button press the listener saw while clicking.
+    pub fn seen(&self) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.seen.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Presses sent and seen while verification was on.
+    pub fn counts(&self) -> (u64, u64) {
+        (self.sent.load(Ordering::Relaxed), self.seen.load(Ordering::Relaxed))
+    }
+}
+
+/// Presses dropped over the last few polls, and the rate that did arrive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drops {
+    pub share: f64,
+    pub delivered_cps: f64,
+}
+
+/// Sent and seen totals of the last `WINDOW` polls.
+#[derive(Default)]
+struct DropMeter {
+    samples: VecDeque<(u64, u64)>,
+}
+
+impl DropMeter {
+    /// Adds the totals of one poll, `POLL_INTERVAL` after the last one. Returns
+    /// the drops over the window once enough presses went out.
+    fn update(&mut self, sent: u64, seen: u64) -> Option<Drops> {
+        self.samples.push_back((sent, seen));
+        if self.samples.len() > WINDOW + 1 {
+            self.samples.pop_front();
+        }
+        let (first_sent, first_seen) = *self.samples.front()?;
+        let sent = sent - first_sent;
+        if self.samples.len() <= WINDOW || sent < MIN_SENT {
+            return None;
+        }
+        // The user's own clicks can make up for a few dropped ones, never more.
+        let seen = (seen - first_seen).min(sent);
+        Some(Drops {
+            share: (sent - seen) as f64 / sent as f64,
+            delivered_cps: seen as f64 / (WINDOW as f64 * POLL_INTERVAL.as_secs_f64()),
+        })
+    }
+}
+
+/// Shared state the receipt checker is allowed to touch.
+pub struct ReceiptHandles {
+    pub receipts: Arc<Receipts>,
+    pub clicking: Arc<AtomicBool>,
+    // Set while the share of dropped presses is over `DROP_WARNING`.
+    pub dropped: Arc<Mutex<Option<Drops>>>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Measures dropped presses while clicking with verification on, until shutdown.
+pub fn spawn_checker(handles: ReceiptHandles) {
+    thread::spawn(move || {
+        let mut meter = DropMeter::default();
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+            let active = handles.receipts.enabled.load(Ordering::SeqCst) && handles.clicking.load(Ordering::SeqCst);
+            if !active {
+                meter = DropMeter::default();
+                *handles.dropped.lock().unwrap() = None;
+                continue;
+            }
+            let (sent, seen) = handles.receipts.counts();
+            let drops = meter.update(sent, seen).filter(|drops| drops.share >= DROP_WARNING);
+            let mut dropped = handles.dropped.lock().unwrap();
+            if let (Some(drops), None) = (drops, *dropped) {
+                handles.log.push(format!(
+                    "{:.0}% of clicks dropped, the system delivers about {:.0} CPS",
+                    drops.share * 100.0,
+                    drops.delivered_cps
+                ));
+            }
+            *dropped = drops;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_are_measured_over_the_window() {
+        let mut meter = DropMeter::default();
+        // 100 presses a second go out, 80 arrive.
+        for poll in 0..WINDOW as u64 {
+            assert_eq!(meter.update(poll * 100, poll * 80), None);
+        }
+        let drops = meter.update(500, 400).unwrap();
+        assert_eq!(drops, Drops { share: 0.2, delivered_cps: 80.0 });
+
+        // Everything arrives from now on; the old drops leave the window.
+        for poll in 6..=10 {
+            meter.update(poll * 100, 400 + (poll - 5) * 100);
+        }
+        assert_eq!(meter.update(1100, 1000).unwrap().share, 0.0);
+
+        // Too few presses to tell.
+        let mut meter = DropMeter::default();
+        for poll in 0..=WINDOW as u64 {
+            assert_eq!(meter.update(poll, 0), None);
+        }
+    }
+}
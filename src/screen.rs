@@ -0,0 +1,233 @@
+// Reading screen pixels and size, for macro steps and text triggers that react to what is on screen.
+//
+// Supported on Windows and on Linux under X11. Elsewhere no pixel is ever
+// known, so pixel conditions never match.
+
+/// RGB color.
+pub type Color = [u8; 3];
+
+/// Parses "#rrggbb".
+pub fn parse_color(text: &str) -> Option<Color> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn format_color([r, g, b]: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+pub use platform::Screen;
+
+/// Size of the screen in pixels, if it can be read here.
+pub fn size() -> Option<(u32, u32)> {
+    Screen::new().map(|screen| screen.size()).filter(|&(width, height)| width > 0 && height > 0)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    use super::Color;
+
+    pub struct Screen {
+        conn: RustConnection,
+        root: Window,
+        size: (u32, u32),
+        msb_first: bool,
+    }
+
+    impl Screen {
+        pub fn new() -> Option<Self> {
+            let (conn, screen) = x11rb::connect(None).ok()?;
+            let root_screen = &conn.setup().roots[screen];
+            let root = root_screen.root;
+            let size = (u32::from(root_screen.width_in_pixels), u32::from(root_screen.height_in_pixels));
+            let msb_first = conn.setup().image_byte_order == ImageOrder::MSB_FIRST;
+            Some(Screen { conn, root, size, msb_first })
+        }
+
+        /// Size of the whole desktop in pixels.
+        pub fn size(&self) -> (u32, u32) {
+            self.size
+        }
+
+        pub fn pixel(&self, x: i32, y: i32) -> Option<Color> {
+            let (x, y) = (i16::try_from(x).ok()?, i16::try_from(y).ok()?);
+            let reply = self.conn.get_image(ImageFormat::Z_PIXMAP, self.root, x, y, 1, 1, !0).ok()?.reply().ok()?;
+            // 24-bit visuals store pixels as 32-bit BGRX words.
+            let data = reply.data.get(..4)?;
+            Some(if self.msb_first { [data[1], data[2], data[3]] } else { [data[2], data[1], data[0]] })
+        }
+
+        /// Pixels of the `width` by `height` rect at (x, y), row by row.
+        #[cfg(feature = "ocr")]
+        pub fn capture(&self, x: i32, y: i32, width: u16, height: u16) -> Option<Vec<Color>> {
+            let (x, y) = (i16::try_from(x).ok()?, i16::try_from(y).ok()?);
+            let reply = self.conn.get_image(ImageFormat::Z_PIXMAP, self.root, x, y, width, height, !0).ok()?.reply().ok()?;
+            let pixels: Vec<Color> = reply
+                .data
+                .chunks_exact(4)
+                .map(|data| if self.msb_first { [data[1], data[2], data[3]] } else { [data[2], data[1], data[0]] })
+                .collect();
+            (pixels.len() == usize::from(width) * usize::from(height)).then_some(pixels)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    use super::Color;
+
+    type Handle = *mut c_void;
+
+    const CLR_INVALID: u32 = 0xFFFF_FFFF;
+    const SM_CXSCREEN: i32 = 0;
+    const SM_CYSCREEN: i32 = 1;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetDC(window: Handle) -> Handle;
+        fn ReleaseDC(window: Handle, dc: Handle) -> i32;
+        fn GetSystemMetrics(index: i32) -> i32;
+    }
+
+    #[link(name = "gdi32")]
+    extern "system" {
+        fn GetPixel(dc: Handle, x: i32, y: i32) -> u32;
+    }
+
+    #[cfg(feature = "ocr")]
+    mod gdi {
+        use super::Handle;
+
+        pub const SRCCOPY: u32 = 0x00CC_0020;
+        pub const DIB_RGB_COLORS: u32 = 0;
+
+        #[repr(C)]
+        pub struct BitmapInfo {
+            pub size: u32,
+            pub width: i32,
+            pub height: i32,
+            pub planes: u16,
+            pub bit_count: u16,
+            pub compression: u32,
+            pub size_image: u32,
+            pub x_pels_per_meter: i32,
+            pub y_pels_per_meter: i32,
+            pub clr_used: u32,
+            pub clr_important: u32,
+            pub colors: [u32; 1],
+        }
+
+        #[link(name = "gdi32")]
+        extern "system" {
+            pub fn CreateCompatibleDC(dc: Handle) -> Handle;
+            pub fn CreateCompatibleBitmap(dc: Handle, width: i32, height: i32) -> Handle;
+            pub fn SelectObject(dc: Handle, object: Handle) -> Handle;
+            pub fn BitBlt(dest: Handle, x: i32, y: i32, width: i32, height: i32, src: Handle, src_x: i32, src_y: i32, rop: u32) -> i32;
+            pub fn GetDIBits(dc: Handle, bitmap: Handle, start: u32, lines: u32, bits: *mut u8, info: *mut BitmapInfo, usage: u32) -> i32;
+            pub fn DeleteObject(object: Handle) -> i32;
+            pub fn DeleteDC(dc: Handle) -> i32;
+        }
+    }
+
+    pub struct Screen;
+
+    impl Screen {
+        pub fn new() -> Option<Self> {
+            Some(Screen)
+        }
+
+        /// Size of the primary monitor in pixels.
+        pub fn size(&self) -> (u32, u32) {
+            unsafe { (GetSystemMetrics(SM_CXSCREEN).max(0) as u32, GetSystemMetrics(SM_CYSCREEN).max(0) as u32) }
+        }
+
+        pub fn pixel(&self, x: i32, y: i32) -> Option<Color> {
+            unsafe {
+                let dc = GetDC(std::ptr::null_mut());
+                if dc.is_null() {
+                    return None;
+                }
+                let color = GetPixel(dc, x, y);
+                ReleaseDC(std::ptr::null_mut(), dc);
+                // COLORREF is 0x00BBGGRR.
+                (color != CLR_INVALID).then(|| [color as u8, (color >> 8) as u8, (color >> 16) as u8])
+            }
+        }
+
+        /// Pixels of the `width` by `height` rect at (x, y), row by row.
+        #[cfg(feature = "ocr")]
+        pub fn capture(&self, x: i32, y: i32, width: u16, height: u16) -> Option<Vec<Color>> {
+            use gdi::*;
+            let (w, h) = (i32::from(width), i32::from(height));
+            // Top-down 32-bit BGRX rows.
+            let mut info = BitmapInfo {
+                size: (std::mem::size_of::<BitmapInfo>() - std::mem::size_of::<[u32; 1]>()) as u32,
+                width: w,
+                height: -h,
+                planes: 1,
+                bit_count: 32,
+                compression: 0,
+                size_image: 0,
+                x_pels_per_meter: 0,
+                y_pels_per_meter: 0,
+                clr_used: 0,
+                clr_important: 0,
+                colors: [0],
+            };
+            let mut bits = vec![0u8; usize::from(width) * usize::from(height) * 4];
+            let copied = unsafe {
+                let screen = GetDC(std::ptr::null_mut());
+                if screen.is_null() {
+                    return None;
+                }
+                let memory = CreateCompatibleDC(screen);
+                let bitmap = CreateCompatibleBitmap(screen, w, h);
+                let old = SelectObject(memory, bitmap);
+                let copied = BitBlt(memory, 0, 0, w, h, screen, x, y, SRCCOPY) != 0
+                    && GetDIBits(memory, bitmap, 0, u32::from(height), bits.as_mut_ptr(), &mut info, DIB_RGB_COLORS) == h;
+                SelectObject(memory, old);
+                DeleteObject(bitmap);
+                DeleteDC(memory);
+                ReleaseDC(std::ptr::null_mut(), screen);
+                copied
+            };
+            copied.then(|| bits.chunks_exact(4).map(|data| [data[2], data[1], data[0]]).collect())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use super::Color;
+
+    pub struct Screen;
+
+    impl Screen {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn size(&self) -> (u32, u32) {
+            (0, 0)
+        }
+
+        pub fn pixel(&self, _x: i32, _y: i32) -> Option<Color> {
+            None
+        }
+
+        #[cfg(feature = "ocr")]
+        pub fn capture(&self, _x: i32, _y: i32, _width: u16, _height: u16) -> Option<Vec<Color>> {
+            None
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// Pausing while the workstation is locked, so the clicker doesn't hammer the
+// lock screen. The lock state is polled like the focused application: on
+// Windows by whether the input desktop can be switched to (it can't while
+// the lock screen owns it) or the screensaver runs, on Linux from logind's
+// LockedHint through loginctl. Elsewhere the session never counts as locked.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::LockPolicy;
+use crate::log::Log;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shared state the lock watcher is allowed to touch.
+pub struct LockHandles {
+    pub clicking: Arc<AtomicBool>,
+    pub macro_playing: Arc<AtomicBool>,
+    pub policy: Arc<Mutex<LockPolicy>>,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+/// Lock state seen so far and whether clicking was paused by it.
+#[derive(Default)]
+struct LockWatch {
+    locked: bool,
+    resume: bool,
+}
+
+impl LockWatch {
+    /// Clicking state to switch to after reading the lock state, if any.
+    /// Clicking started while locked is stopped again.
+    fn update(&mut self, policy: LockPolicy, locked: bool, clicking: bool) -> Option<bool> {
+        if locked {
+            let first = !self.locked;
+            self.locked = true;
+            if policy == LockPolicy::Ignore || !clicking {
+                return None;
+            }
+            if first {
+                self.resume = policy == LockPolicy::Resume;
+            }
+            return Some(false);
+        }
+        let unlocked = std::mem::take(&mut self.locked);
+        (unlocked && std::mem::take(&mut self.resume)).then_some(true)
+    }
+}
+
+/// Polls the lock state and applies the lock policy until shutdown.
+pub fn spawn_watcher(handles: LockHandles) {
+    let Some(detector) = Detector::new() else {
+        handles.log.push("Workstation lock can't be detected here; clicking won't pause on lock.");
+        return;
+    };
+    thread::spawn(move || {
+        let mut watch = LockWatch::default();
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            thread::sleep(POLL_INTERVAL);
+            let policy = *handles.policy.lock().unwrap();
+            let locked = detector.locked();
+            if locked && policy != LockPolicy::Ignore && handles.macro_playing.swap(false, Ordering::SeqCst) {
+                handles.log.push("Workstation locked, macro stopped");
+            }
+            match watch.update(policy, locked, handles.clicking.load(Ordering::SeqCst)) {
+                Some(false) => {
+                    handles.clicking.store(false, Ordering::SeqCst);
+                    handles.log.push("Workstation locked, clicking paused");
+                }
+                Some(true) => {
+                    handles.clicking.store(true, Ordering::SeqCst);
+                    handles.log.push("Workstation unlocked, clicking resumed");
+                }
+                None => {}
+            }
+        }
+    });
+}
+
+use platform::Detector;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    pub struct Detector {
+        session: String,
+    }
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            // "auto" is the caller's session on systemd 243 and newer.
+            let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+            let detector = Detector { session };
+            detector.locked_hint()?;
+            Some(detector)
+        }
+
+        fn locked_hint(&self) -> Option<bool> {
+            let output = Command::new("loginctl")
+                .args(["show-session", &self.session, "--property=LockedHint", "--value"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())?;
+            match String::from_utf8_lossy(&output.stdout).trim() {
+                "yes" => Some(true),
+                "no" => Some(false),
+                _ => None,
+            }
+        }
+
+        pub fn locked(&self) -> bool {
+            self.locked_hint().unwrap_or(false)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+
+    type Handle = *mut c_void;
+
+    const DESKTOP_SWITCHDESKTOP: u32 = 0x0100;
+    const SPI_GETSCREENSAVERRUNNING: u32 = 0x0072;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn OpenInputDesktop(flags: u32, inherit: i32, access: u32) -> Handle;
+        fn SwitchDesktop(desktop: Handle) -> i32;
+        fn CloseDesktop(desktop: Handle) -> i32;
+        fn SystemParametersInfoW(action: u32, param: u32, value: *mut c_void, win_ini: u32) -> i32;
+    }
+
+    pub struct Detector;
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            Some(Detector)
+        }
+
+        pub fn locked(&self) -> bool {
+            unsafe {
+                let mut screensaver: i32 = 0;
+                SystemParametersInfoW(SPI_GETSCREENSAVERRUNNING, 0, &mut screensaver as *mut i32 as *mut c_void, 0);
+                if screensaver != 0 {
+                    return true;
+                }
+                let desktop = OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP);
+                if desktop.is_null() {
+                    return true;
+                }
+                let switchable = SwitchDesktop(desktop) != 0;
+                CloseDesktop(desktop);
+                !switchable
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub struct Detector;
+
+    impl Detector {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn locked(&self) -> bool {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locking_pauses_per_policy() {
+        let mut watch = LockWatch::default();
+        assert_eq!(watch.update(LockPolicy::Resume, false, true), None);
+        assert_eq!(watch.update(LockPolicy::Resume, true, true), Some(false));
+        // Started again from a remote command while still locked.
+        assert_eq!(watch.update(LockPolicy::Resume, true, true), Some(false));
+        assert_eq!(watch.update(LockPolicy::Resume, true, false), None);
+        assert_eq!(watch.update(LockPolicy::Resume, false, false), Some(true));
+        assert_eq!(watch.update(LockPolicy::Resume, false, true), None);
+
+        assert_eq!(watch.update(LockPolicy::Pause, true, true), Some(false));
+        assert_eq!(watch.update(LockPolicy::Pause, false, false), None);
+
+        // Nothing to resume when clicking was already off.
+        assert_eq!(watch.update(LockPolicy::Resume, true, false), None);
+        assert_eq!(watch.update(LockPolicy::Resume, false, false), None);
+
+        assert_eq!(watch.update(LockPolicy::Ignore, true, true), None);
+        assert_eq!(watch.update(LockPolicy::Ignore, false, true), None);
+    }
+}
@@ -0,0 +1,179 @@
+// Sound themes: the click sound and the start and stop sounds, as WAV data.
+// "classic" is click.wav next to the program with plain beeps; mechanical,
+// soft and arcade are generated here; any other name is a folder in
+//
+//     <config_dir>/sounds/<name>/{click,start,stop}.wav
+//
+// where missing files fall back to no click sound and the plain beeps.
+
+use std::f32::consts::TAU;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::config::{config_dir, DEFAULT_SOUND_THEME as DEFAULT_THEME};
+use crate::travel::Jitter;
+
+pub const BUILTIN_THEMES: [&str; 4] = [DEFAULT_THEME, "mechanical", "soft", "arcade"];
+const SAMPLE_RATE: u32 = 44_100;
+const SAMPLE_NAMES: [&str; 3] = ["click.wav", "start.wav", "stop.wav"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub click: Option<Vec<u8>>,
+    pub start: Option<Vec<u8>>,
+    pub stop: Option<Vec<u8>>,
+}
+
+pub fn themes_dir() -> PathBuf {
+    config_dir().join("sounds")
+}
+
+/// Built-in themes, then the theme folders, by name.
+pub fn names() -> Vec<String> {
+    let mut folders: Vec<String> = fs::read_dir(themes_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| !BUILTIN_THEMES.contains(&name.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    folders.sort();
+    BUILTIN_THEMES.iter().map(|name| name.to_string()).chain(folders).collect()
+}
+
+/// The theme called `name`: a built-in one or a theme folder.
+pub fn load(name: &str) -> Result<Theme, String> {
+    match name {
+        DEFAULT_THEME => Ok(Theme { name: name.to_string(), click: fs::read("click.wav").ok(), start: None, stop: None }),
+        "mechanical" | "soft" | "arcade" => Ok(generated(name)),
+        _ => load_folder(name, &themes_dir().join(name)),
+    }
+}
+
+fn load_folder(name: &str, dir: &Path) -> Result<Theme, String> {
+    let [click, start, stop] = SAMPLE_NAMES.map(|file| fs::read(dir.join(file)).ok());
+    if click.is_none() && start.is_none() && stop.is_none() {
+        return Err(format!("No sound theme {}: {} has none of {}", name, dir.display(), SAMPLE_NAMES.join(", ")));
+    }
+    Ok(Theme { name: name.to_string(), click, start, stop })
+}
+
+#[derive(Clone, Copy)]
+enum Wave {
+    Sine,
+    Square,
+    Noise,
+}
+
+/// One part of a generated sound: a wave gliding from one frequency to
+/// another and fading out with an exponential decay.
+struct Part {
+    wave: Wave,
+    from_hz: f32,
+    to_hz: f32,
+    length: Duration,
+    // Time for the loudness to fall to about a third.
+    decay: Duration,
+    volume: f32,
+}
+
+const fn part(wave: Wave, from_hz: f32, to_hz: f32, length_ms: u64, decay_ms: u64, volume: f32) -> Part {
+    Part { wave, from_hz, to_hz, length: Duration::from_millis(length_ms), decay: Duration::from_millis(decay_ms), volume }
+}
+
+fn generated(name: &str) -> Theme {
+    let (click, start, stop): (&[Part], &[Part], &[Part]) = match name {
+        "mechanical" => (
+            &[part(Wave::Noise, 0.0, 0.0, 25, 3, 0.5), part(Wave::Sine, 1800.0, 1500.0, 25, 5, 0.3)],
+            &[part(Wave::Noise, 0.0, 0.0, 40, 4, 0.4), part(Wave::Sine, 900.0, 1400.0, 60, 25, 0.3)],
+            &[part(Wave::Noise, 0.0, 0.0, 40, 4, 0.4), part(Wave::Sine, 1400.0, 900.0, 60, 25, 0.3)],
+        ),
+        "soft" => (
+            &[part(Wave::Sine, 620.0, 560.0, 45, 12, 0.3)],
+            &[part(Wave::Sine, 523.0, 659.0, 140, 60, 0.25)],
+            &[part(Wave::Sine, 659.0, 523.0, 140, 60, 0.25)],
+        ),
+        _ => (
+            &[part(Wave::Square, 1000.0, 1000.0, 25, 40, 0.15)],
+            &[part(Wave::Square, 600.0, 1200.0, 120, 200, 0.15)],
+            &[part(Wave::Square, 1200.0, 600.0, 120, 200, 0.15)],
+        ),
+    };
+    Theme { name: name.to_string(), click: Some(render(click)), start: Some(render(start)), stop: Some(render(stop)) }
+}
+
+/// Mixes `parts` into a mono 16-bit WAV file.
+fn render(parts: &[Part]) -> Vec<u8> {
+    let length = parts.iter().map(|part| part.length).max().unwrap_or_default();
+    let mut samples = vec![0.0f32; (length.as_secs_f32() * SAMPLE_RATE as f32) as usize];
+    // Fixed seed: the noise sounds the same every time.
+    let mut noise = Jitter::new(0x5eed);
+    for part in parts {
+        let count = (part.length.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+        let mut phase = 0.0f32;
+        for (i, sample) in samples.iter_mut().take(count).enumerate() {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let hz = part.from_hz + (part.to_hz - part.from_hz) * i as f32 / count as f32;
+            phase = (phase + hz / SAMPLE_RATE as f32).fract();
+            let value = match part.wave {
+                Wave::Sine => (phase * TAU).sin(),
+                Wave::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+                Wave::Noise => noise.range(-1.0, 1.0) as f32,
+            };
+            *sample += value * part.volume * (-t / part.decay.as_secs_f32()).exp();
+        }
+    }
+    wav(&samples)
+}
+
+fn wav(samples: &[f32]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    // PCM, one channel, 16 bits per sample.
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        out.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_come_built_in_or_from_folders() {
+        for name in ["mechanical", "soft", "arcade"] {
+            let theme = load(name).unwrap();
+            let click = theme.click.unwrap();
+            assert_eq!(&click[..4], b"RIFF");
+            assert_eq!(click.len(), 44 + u32::from_le_bytes(click[40..44].try_into().unwrap()) as usize);
+            assert!(theme.start.is_some() && theme.stop.is_some());
+        }
+
+        let dir = std::env::temp_dir().join(format!("autoclicker-sounds-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        assert!(load_folder("mine", &dir).is_err());
+        fs::write(dir.join("start.wav"), b"RIFF").unwrap();
+        let theme = load_folder("mine", &dir).unwrap();
+        assert_eq!((theme.click, theme.start, theme.stop), (None, Some(b"RIFF".to_vec()), None));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,732 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+
+use enigo::MouseButton;
+
+use crate::activity::{Activity, IdleHandles};
+use crate::awake::AwakeHandles;
+#[cfg(feature = "gui")]
+use crate::bench::BenchHandles;
+use crate::buttons::ClickButton;
+use crate::affinity::JitterReadout;
+#[cfg(feature = "audio")]
+use crate::audio::{BeepHandles, ClickSoundHandles};
+use crate::command_files::CommandFileHandles;
+use crate::config::{Activation, AppBinding, BackendKind, BatteryPolicy, ChatTrigger, Config, Hooks, IndicatorPlace, KeyInjection, LockPolicy, SuperspeedMode, MacroHotkey, MqttConfig, Rollover, SequenceWait, TextTyper, TitleRule, WebhookConfig, DEFAULT_PROFILE};
+use crate::engine::{EngineHandles, Watchdog};
+use crate::foreground::{ForegroundHandles, WindowHandles};
+use crate::geometry::{Point, Rect};
+use crate::guard::{GuardHandles, Pacer};
+use crate::indicator::IndicatorHandles;
+use crate::ipc::ControlHandles;
+use crate::keys::{key_to_string, string_to_rdev_key};
+use crate::log::Log;
+use crate::macros::{MacroHandles, Recording, Stepper};
+use crate::mqtt::MqttHandles;
+#[cfg(feature = "ocr")]
+use crate::ocr::OcrHandles;
+use crate::webhook::WebhookHandles;
+use crate::overlay::OverlayHandles;
+use crate::pause::{PauseHandles, RunStack};
+use crate::power::PowerHandles;
+use crate::receipts::{Drops, ReceiptHandles, Receipts};
+use crate::history::{History, HistoryHandles, Run};
+use crate::hooks::HooksHandles;
+use crate::session::LockHandles;
+use crate::sounds::Theme;
+use crate::stats::{AppClicks, Heatmap, Intervals, Latency};
+use crate::telemetry::Telemetry;
+use crate::totals::{Totals, TotalsHandles};
+use crate::twitch::TwitchHandles;
+use crate::typer::TyperHandles;
+
+/// State shared between the GUI, the engine, the listener and the control channel.
+#[derive(Clone)]
+pub struct Shared {
+    pub clicking: Arc<AtomicBool>,
+    pub hotkey: Arc<Mutex<rdev::Key>>,
+    pub changing_hotkey: Arc<AtomicBool>,
+    pub activation: Arc<Mutex<Activation>>,
+    // Last key pressed during capture that can't be saved as a hotkey.
+    pub rejected_key: Arc<Mutex<Option<rdev::Key>>>,
+    // Why the global listener failed while it waits to be restarted, and a
+    // request to restart it right away.
+    pub listener_error: Arc<Mutex<Option<String>>>,
+    pub retry_listener: Arc<AtomicBool>,
+    pub listener_retry: Arc<Mutex<Duration>>,
+    pub clicks: Arc<Mutex<Vec<Instant>>>,
+    pub fast_mode: Arc<AtomicBool>,
+    pub target_cps: Arc<Mutex<f64>>,
+    pub selected_buttons: Arc<Mutex<Vec<MouseButton>>>,
+    pub play_sound: Arc<AtomicBool>,
+    pub sound_rate: Arc<Mutex<u32>>,
+    pub vary_pitch: Arc<AtomicBool>,
+    // Shared by every profile, like the config file's other top-level settings.
+    pub sound_theme: Arc<Mutex<Arc<Theme>>>,
+    pub toggle_beep: Arc<AtomicBool>,
+    pub start_delay: Arc<Mutex<Duration>>,
+    pub countdown_beeps: Arc<AtomicBool>,
+    // Profile queued by the control channel, applied by the GUI (or the headless loop).
+    pub pending_profile: Arc<Mutex<Option<(String, Config)>>>,
+    // Dry-run mode: run the engine without emitting real input.
+    pub dry_run: Arc<AtomicBool>,
+    // Clicks emitted by the engine since startup.
+    pub emitted: Arc<AtomicU64>,
+    pub log: Arc<Log>,
+    // Per-application profile bindings and the last focused application.
+    pub bindings: Arc<Mutex<Vec<AppBinding>>>,
+    pub title_rules: Arc<Mutex<Vec<TitleRule>>>,
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    pub blocklist: Arc<Mutex<Vec<String>>>,
+    pub app_clicks: Arc<Mutex<AppClicks>>,
+    // Clicks across runs, and when their day and week start over.
+    pub totals: Arc<Mutex<Totals>>,
+    pub rollover: Arc<Mutex<Rollover>>,
+    // Name of the profile in use, for the run history.
+    pub profile: Arc<Mutex<String>>,
+    // Every profile as the running instance has it, saved or not.
+    pub profiles: Arc<Mutex<BTreeMap<String, Config>>>,
+    pub history: Arc<Mutex<History>>,
+    pub last_run: Arc<Mutex<Option<Run>>>,
+    pub heatmap: Arc<Mutex<Heatmap>>,
+    pub intervals: Arc<Mutex<Intervals>>,
+    pub watchdog: Arc<Watchdog>,
+    // Set when the app exits, for the threads that clean up before it ends.
+    pub shutdown: Arc<AtomicBool>,
+    // Set by the `quit` command to have the app exit.
+    pub quit: Arc<AtomicBool>,
+    // Port the stream overlay serves on, if it is on.
+    pub overlay_port: Arc<Mutex<Option<u16>>>,
+    // Twitch chat command that triggers click bursts, if it is on.
+    pub chat_trigger: Arc<Mutex<Option<ChatTrigger>>>,
+    // Text typed over and over while `typer_running` is set by its hotkey.
+    pub typer: Arc<Mutex<Option<TextTyper>>>,
+    pub typer_running: Arc<AtomicBool>,
+    pub command_files: Arc<AtomicBool>,
+    // Folder profiles and click counts are synced through, if set.
+    pub sync_dir: Arc<Mutex<Option<PathBuf>>>,
+    // MQTT broker to take commands from, if it is on.
+    pub mqtt: Arc<Mutex<Option<MqttConfig>>>,
+    // URL clicking events are posted to, if it is on.
+    pub webhook: Arc<Mutex<Option<WebhookConfig>>>,
+    // Commands run when clicking starts and stops.
+    pub hooks: Arc<Mutex<Hooks>>,
+    pub lock_policy: Arc<Mutex<LockPolicy>>,
+    pub battery_policy: Arc<Mutex<BatteryPolicy>>,
+    pub battery_cps: Arc<Mutex<f64>>,
+    // Set by the power watcher: the engine's battery cap and whether the policy applies now.
+    pub power_cap: Arc<Mutex<Option<f64>>>,
+    pub low_power: Arc<AtomicBool>,
+    pub keep_awake: Arc<AtomicBool>,
+    pub indicator: Arc<Mutex<IndicatorPlace>>,
+    pub activity: Arc<Activity>,
+    pub idle_stop: Arc<Mutex<Option<Duration>>>,
+    pub typing_pause: Arc<Mutex<Option<Duration>>>,
+    // Opt-in usage counts, replaced by the saved ones at startup.
+    pub telemetry: Arc<Telemetry>,
+    // Clicks sent against clicks the listener saw, and the drops measured from them.
+    pub receipts: Arc<Receipts>,
+    pub dropped: Arc<Mutex<Option<Drops>>>,
+    pub latency: Arc<Mutex<Latency>>,
+    pub pin_core: Arc<Mutex<Option<usize>>>,
+    pub jitter: Arc<Mutex<JitterReadout>>,
+    // Cursor position seen by the listener and the GUI window's screen rect.
+    pub cursor: Arc<Mutex<Option<Point>>>,
+    pub own_window: Arc<Mutex<Option<Rect>>>,
+    pub test_pad: Arc<Mutex<Option<Rect>>>,
+    pub region: Arc<Mutex<Option<Rect>>>,
+    pub exclusions: Arc<Mutex<Vec<Rect>>>,
+    pub sequence: Arc<Mutex<Vec<Point>>>,
+    pub offsets: Arc<Mutex<Vec<Point>>>,
+    pub sequence_resolution: Arc<Mutex<Option<(u32, u32)>>>,
+    pub sequence_wait: Arc<Mutex<Option<SequenceWait>>>,
+    pub relative_to: Arc<Mutex<Option<String>>>,
+    // Size of the primary display, looked up at startup.
+    pub screen_size: Arc<Mutex<Option<(u32, u32)>>>,
+    pub scale_recordings: Arc<AtomicBool>,
+    // Set by the window tracker.
+    pub window_origin: Arc<Mutex<Option<Point>>>,
+    pub workers: Arc<AtomicUsize>,
+    pub backend: Arc<Mutex<BackendKind>>,
+    pub press_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub key_injection: Arc<Mutex<KeyInjection>>,
+    pub hold_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub chord_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub turbo_button: Arc<Mutex<Option<ClickButton>>>,
+    pub human_travel: Arc<AtomicBool>,
+    pub return_cursor: Arc<AtomicBool>,
+    pub jiggle: Arc<AtomicBool>,
+    pub jiggle_interval: Arc<Mutex<Duration>>,
+    pub jiggle_back: Arc<AtomicBool>,
+    pub cps_up_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub cps_down_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub cps_step: Arc<Mutex<f64>>,
+    pub cps_beep: Arc<AtomicBool>,
+    // Set by the listener when a CPS hotkey changed the rate, for the beeper.
+    pub cps_nudged: Arc<AtomicBool>,
+    pub travel_speed: Arc<Mutex<f64>>,
+    pub max_cps: Arc<Mutex<Option<f64>>>,
+    // Where every sender's next press is due under the CPS guard and power cap,
+    // and the blocklisted app input was last held back from.
+    pub pacer: Arc<Pacer>,
+    pub superspeed: Arc<Mutex<SuperspeedMode>>,
+    // Set while a macro plays; the abort key clears it.
+    pub macro_playing: Arc<AtomicBool>,
+    // Set while the backend benchmark runs; the abort key clears it.
+    pub bench_running: Arc<AtomicBool>,
+    // Set by the pause-all key, which saves what it paused on the run stack.
+    pub macro_paused: Arc<AtomicBool>,
+    pub pause_key: Arc<Mutex<Option<rdev::Key>>>,
+    pub run_stack: Arc<RunStack>,
+    pub abort_key: Arc<Mutex<rdev::Key>>,
+    pub step_key: Arc<Mutex<rdev::Key>>,
+    // Step-through playback of a macro being debugged.
+    pub macro_stepper: Arc<Stepper>,
+    // Global hotkeys that play saved macros.
+    pub macro_hotkeys: Arc<Mutex<Vec<MacroHotkey>>>,
+    // Input being recorded for a macro; `None` when not recording.
+    pub recording: Arc<Mutex<Option<Recording>>>,
+    // Screen points clicked while picking a rectangle; `None` when not picking.
+    pub picked_points: Arc<Mutex<Option<Vec<Point>>>>,
+}
+
+fn buttons_from_config(config: &Config) -> Vec<MouseButton> {
+    let mut buttons = Vec::new();
+    if config.left_click { buttons.push(MouseButton::Left); }
+    if config.middle_click { buttons.push(MouseButton::Middle); }
+    if config.right_click { buttons.push(MouseButton::Right); }
+    buttons
+}
+
+impl Shared {
+    pub fn from_config(config: &Config) -> Self {
+        let hotkey = string_to_rdev_key(&config.hotkey).unwrap_or(rdev::Key::Insert);
+        Shared {
+            clicking: Arc::new(AtomicBool::new(false)),
+            hotkey: Arc::new(Mutex::new(hotkey)),
+            changing_hotkey: Arc::new(AtomicBool::new(false)),
+            listener_error: Arc::new(Mutex::new(None)),
+            retry_listener: Arc::new(AtomicBool::new(false)),
+            listener_retry: Arc::new(Mutex::new(Duration::from_secs(60))),
+            activation: Arc::new(Mutex::new(config.activation)),
+            rejected_key: Arc::new(Mutex::new(None)),
+            clicks: Arc::new(Mutex::new(Vec::new())),
+            fast_mode: Arc::new(AtomicBool::new(config.fast_mode)),
+            target_cps: Arc::new(Mutex::new(config.target_cps)),
+            selected_buttons: Arc::new(Mutex::new(buttons_from_config(config))),
+            play_sound: Arc::new(AtomicBool::new(config.play_sound)),
+            sound_rate: Arc::new(Mutex::new(config.sound_rate)),
+            vary_pitch: Arc::new(AtomicBool::new(config.vary_pitch)),
+            sound_theme: Arc::new(Mutex::new(Arc::new(Theme::default()))),
+            toggle_beep: Arc::new(AtomicBool::new(config.toggle_beep)),
+            start_delay: Arc::new(Mutex::new(config.start_delay)),
+            countdown_beeps: Arc::new(AtomicBool::new(config.countdown_beeps)),
+            pending_profile: Arc::new(Mutex::new(None)),
+            dry_run: Arc::new(AtomicBool::new(false)),
+            emitted: Arc::new(AtomicU64::new(0)),
+            log: Arc::new(Log::new()),
+            bindings: Arc::new(Mutex::new(Vec::new())),
+            title_rules: Arc::new(Mutex::new(Vec::new())),
+            focused_app: Arc::new(Mutex::new(None)),
+            blocklist: Arc::new(Mutex::new(Vec::new())),
+            app_clicks: Arc::new(Mutex::new(AppClicks::default())),
+            totals: Arc::new(Mutex::new(Totals::default())),
+            rollover: Arc::new(Mutex::new(Rollover::default())),
+            profile: Arc::new(Mutex::new(DEFAULT_PROFILE.to_string())),
+            profiles: Arc::new(Mutex::new(BTreeMap::new())),
+            history: Arc::new(Mutex::new(History::default())),
+            last_run: Arc::new(Mutex::new(None)),
+            heatmap: Arc::new(Mutex::new(Heatmap::default())),
+            intervals: Arc::new(Mutex::new(Intervals::default())),
+            watchdog: Arc::new(Watchdog::default()),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            quit: Arc::new(AtomicBool::new(false)),
+            telemetry: Arc::new(Telemetry::default()),
+            receipts: Arc::new(Receipts::default()),
+            dropped: Arc::new(Mutex::new(None)),
+            latency: Arc::new(Mutex::new(Latency::default())),
+            pin_core: Arc::new(Mutex::new(None)),
+            jitter: Arc::new(Mutex::new(JitterReadout::default())),
+            overlay_port: Arc::new(Mutex::new(None)),
+            chat_trigger: Arc::new(Mutex::new(None)),
+            typer: Arc::new(Mutex::new(None)),
+            typer_running: Arc::new(AtomicBool::new(false)),
+            command_files: Arc::new(AtomicBool::new(false)),
+            sync_dir: Arc::new(Mutex::new(None)),
+            mqtt: Arc::new(Mutex::new(None)),
+            webhook: Arc::new(Mutex::new(None)),
+            hooks: Arc::new(Mutex::new(Hooks::default())),
+            lock_policy: Arc::new(Mutex::new(LockPolicy::Pause)),
+            battery_policy: Arc::new(Mutex::new(BatteryPolicy::Ignore)),
+            battery_cps: Arc::new(Mutex::new(10.0)),
+            power_cap: Arc::new(Mutex::new(None)),
+            low_power: Arc::new(AtomicBool::new(false)),
+            keep_awake: Arc::new(AtomicBool::new(true)),
+            indicator: Arc::new(Mutex::new(IndicatorPlace::Off)),
+            activity: Arc::new(Activity::default()),
+            idle_stop: Arc::new(Mutex::new(None)),
+            typing_pause: Arc::new(Mutex::new(None)),
+            cursor: Arc::new(Mutex::new(None)),
+            own_window: Arc::new(Mutex::new(None)),
+            test_pad: Arc::new(Mutex::new(None)),
+            region: Arc::new(Mutex::new(config.region)),
+            exclusions: Arc::new(Mutex::new(config.exclusions.clone())),
+            sequence: Arc::new(Mutex::new(config.sequence.clone())),
+            offsets: Arc::new(Mutex::new(config.offsets.clone())),
+            sequence_resolution: Arc::new(Mutex::new(config.sequence_resolution)),
+            sequence_wait: Arc::new(Mutex::new(config.sequence_wait)),
+            relative_to: Arc::new(Mutex::new(config.relative_to.clone())),
+            screen_size: Arc::new(Mutex::new(None)),
+            scale_recordings: Arc::new(AtomicBool::new(false)),
+            window_origin: Arc::new(Mutex::new(None)),
+            workers: Arc::new(AtomicUsize::new(config.workers)),
+            backend: Arc::new(Mutex::new(BackendKind::Enigo)),
+            press_key: Arc::new(Mutex::new(config.press_key)),
+            key_injection: Arc::new(Mutex::new(config.key_injection)),
+            hold_key: Arc::new(Mutex::new(config.hold_key)),
+            chord_key: Arc::new(Mutex::new(config.chord_key)),
+            turbo_button: Arc::new(Mutex::new(config.turbo_button)),
+            human_travel: Arc::new(AtomicBool::new(config.human_travel)),
+            return_cursor: Arc::new(AtomicBool::new(config.return_cursor)),
+            jiggle: Arc::new(AtomicBool::new(config.jiggle)),
+            jiggle_interval: Arc::new(Mutex::new(config.jiggle_interval)),
+            jiggle_back: Arc::new(AtomicBool::new(config.jiggle_back)),
+            cps_up_key: Arc::new(Mutex::new(config.cps_up_key)),
+            cps_down_key: Arc::new(Mutex::new(config.cps_down_key)),
+            cps_step: Arc::new(Mutex::new(config.cps_step)),
+            cps_beep: Arc::new(AtomicBool::new(config.cps_beep)),
+            cps_nudged: Arc::new(AtomicBool::new(false)),
+            travel_speed: Arc::new(Mutex::new(config.travel_speed)),
+            max_cps: Arc::new(Mutex::new(None)),
+            pacer: Arc::new(Pacer::default()),
+            superspeed: Arc::new(Mutex::new(SuperspeedMode::Balanced)),
+            macro_playing: Arc::new(AtomicBool::new(false)),
+            bench_running: Arc::new(AtomicBool::new(false)),
+            macro_paused: Arc::new(AtomicBool::new(false)),
+            pause_key: Arc::new(Mutex::new(None)),
+            run_stack: Arc::new(RunStack::default()),
+            abort_key: Arc::new(Mutex::new(config.abort_key)),
+            step_key: Arc::new(Mutex::new(config.step_key)),
+            macro_stepper: Arc::new(Stepper::default()),
+            macro_hotkeys: Arc::new(Mutex::new(Vec::new())),
+            recording: Arc::new(Mutex::new(None)),
+            picked_points: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Applies a config to the running state in place, keeping the shared handles.
+    pub fn apply_config(&self, config: &Config) {
+        if let Some(key) = string_to_rdev_key(&config.hotkey) {
+            *self.hotkey.lock().unwrap() = key;
+        }
+        self.fast_mode.store(config.fast_mode, Ordering::SeqCst);
+        *self.target_cps.lock().unwrap() = config.target_cps;
+        *self.selected_buttons.lock().unwrap() = buttons_from_config(config);
+        self.play_sound.store(config.play_sound, Ordering::SeqCst);
+        *self.sound_rate.lock().unwrap() = config.sound_rate;
+        self.vary_pitch.store(config.vary_pitch, Ordering::SeqCst);
+        self.toggle_beep.store(config.toggle_beep, Ordering::SeqCst);
+        *self.start_delay.lock().unwrap() = config.start_delay;
+        self.countdown_beeps.store(config.countdown_beeps, Ordering::SeqCst);
+        *self.activation.lock().unwrap() = config.activation;
+        *self.region.lock().unwrap() = config.region;
+        self.exclusions.lock().unwrap().clone_from(&config.exclusions);
+        self.sequence.lock().unwrap().clone_from(&config.sequence);
+        self.offsets.lock().unwrap().clone_from(&config.offsets);
+        *self.sequence_resolution.lock().unwrap() = config.sequence_resolution;
+        *self.sequence_wait.lock().unwrap() = config.sequence_wait;
+        self.relative_to.lock().unwrap().clone_from(&config.relative_to);
+        self.workers.store(config.workers, Ordering::SeqCst);
+        *self.press_key.lock().unwrap() = config.press_key;
+        *self.key_injection.lock().unwrap() = config.key_injection;
+        *self.hold_key.lock().unwrap() = config.hold_key;
+        *self.chord_key.lock().unwrap() = config.chord_key;
+        *self.turbo_button.lock().unwrap() = config.turbo_button;
+        self.human_travel.store(config.human_travel, Ordering::SeqCst);
+        self.return_cursor.store(config.return_cursor, Ordering::SeqCst);
+        self.jiggle.store(config.jiggle, Ordering::SeqCst);
+        *self.jiggle_interval.lock().unwrap() = config.jiggle_interval;
+        self.jiggle_back.store(config.jiggle_back, Ordering::SeqCst);
+        *self.cps_up_key.lock().unwrap() = config.cps_up_key;
+        *self.cps_down_key.lock().unwrap() = config.cps_down_key;
+        *self.cps_step.lock().unwrap() = config.cps_step;
+        self.cps_beep.store(config.cps_beep, Ordering::SeqCst);
+        *self.travel_speed.lock().unwrap() = config.travel_speed;
+        *self.abort_key.lock().unwrap() = config.abort_key;
+        *self.step_key.lock().unwrap() = config.step_key;
+    }
+
+    pub fn to_config(&self) -> Config {
+        let hotkey = {
+            let hk = self.hotkey.lock().unwrap();
+            key_to_string(&hk)
+        };
+        let buttons = self.selected_buttons.lock().unwrap().clone();
+        Config {
+            hotkey,
+            fast_mode: self.fast_mode.load(Ordering::SeqCst),
+            target_cps: *self.target_cps.lock().unwrap(),
+            left_click: buttons.contains(&MouseButton::Left),
+            middle_click: buttons.contains(&MouseButton::Middle),
+            right_click: buttons.contains(&MouseButton::Right),
+            play_sound: self.play_sound.load(Ordering::SeqCst),
+            sound_rate: *self.sound_rate.lock().unwrap(),
+            vary_pitch: self.vary_pitch.load(Ordering::SeqCst),
+            toggle_beep: self.toggle_beep.load(Ordering::SeqCst),
+            start_delay: *self.start_delay.lock().unwrap(),
+            countdown_beeps: self.countdown_beeps.load(Ordering::SeqCst),
+            activation: *self.activation.lock().unwrap(),
+            region: *self.region.lock().unwrap(),
+            exclusions: self.exclusions.lock().unwrap().clone(),
+            sequence: self.sequence.lock().unwrap().clone(),
+            offsets: self.offsets.lock().unwrap().clone(),
+            sequence_resolution: *self.sequence_resolution.lock().unwrap(),
+            sequence_wait: *self.sequence_wait.lock().unwrap(),
+            relative_to: self.relative_to.lock().unwrap().as_deref().map(str::trim).filter(|app| !app.is_empty()).map(str::to_string),
+            workers: self.workers.load(Ordering::SeqCst),
+            press_key: *self.press_key.lock().unwrap(),
+            key_injection: *self.key_injection.lock().unwrap(),
+            hold_key: *self.hold_key.lock().unwrap(),
+            chord_key: *self.chord_key.lock().unwrap(),
+            turbo_button: *self.turbo_button.lock().unwrap(),
+            human_travel: self.human_travel.load(Ordering::SeqCst),
+            return_cursor: self.return_cursor.load(Ordering::SeqCst),
+            jiggle: self.jiggle.load(Ordering::SeqCst),
+            jiggle_interval: *self.jiggle_interval.lock().unwrap(),
+            jiggle_back: self.jiggle_back.load(Ordering::SeqCst),
+            cps_up_key: *self.cps_up_key.lock().unwrap(),
+            cps_down_key: *self.cps_down_key.lock().unwrap(),
+            cps_step: *self.cps_step.lock().unwrap(),
+            cps_beep: self.cps_beep.load(Ordering::SeqCst),
+            travel_speed: *self.travel_speed.lock().unwrap(),
+            abort_key: *self.abort_key.lock().unwrap(),
+            step_key: *self.step_key.lock().unwrap(),
+        }
+    }
+
+    pub fn engine_handles(&self) -> EngineHandles {
+        EngineHandles {
+            clicking: self.clicking.clone(),
+            fast_mode: self.fast_mode.clone(),
+            target_cps: self.target_cps.clone(),
+            selected_buttons: self.selected_buttons.clone(),
+            dry_run: self.dry_run.clone(),
+            emitted: self.emitted.clone(),
+            log: self.log.clone(),
+            cursor: self.cursor.clone(),
+            own_window: self.own_window.clone(),
+            test_pad: self.test_pad.clone(),
+            region: self.region.clone(),
+            exclusions: self.exclusions.clone(),
+            blocklist: self.blocklist.clone(),
+            focused_app: self.focused_app.clone(),
+            sequence: self.sequence.clone(),
+            offsets: self.offsets.clone(),
+            sequence_resolution: self.sequence_resolution.clone(),
+            sequence_wait: self.sequence_wait.clone(),
+            relative_to: self.relative_to.clone(),
+            window_origin: self.window_origin.clone(),
+            screen_size: self.screen_size.clone(),
+            scale_recordings: self.scale_recordings.clone(),
+            workers: self.workers.clone(),
+            backend: self.backend.clone(),
+            press_key: self.press_key.clone(),
+            key_injection: self.key_injection.clone(),
+            hold_key: self.hold_key.clone(),
+            human_travel: self.human_travel.clone(),
+            return_cursor: self.return_cursor.clone(),
+            jiggle: self.jiggle.clone(),
+            jiggle_interval: self.jiggle_interval.clone(),
+            jiggle_back: self.jiggle_back.clone(),
+            travel_speed: self.travel_speed.clone(),
+            max_cps: self.max_cps.clone(),
+            superspeed: self.superspeed.clone(),
+            power_cap: self.power_cap.clone(),
+            heatmap: self.heatmap.clone(),
+            intervals: self.intervals.clone(),
+            watchdog: self.watchdog.clone(),
+            shutdown: self.shutdown.clone(),
+            telemetry: self.telemetry.clone(),
+            start_delay: self.start_delay.clone(),
+            activity: self.activity.clone(),
+            typing_pause: self.typing_pause.clone(),
+            receipts: self.receipts.clone(),
+            latency: self.latency.clone(),
+            pin_core: self.pin_core.clone(),
+            jitter: self.jitter.clone(),
+            guard: self.guard_handles(),
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn click_sound_handles(&self) -> ClickSoundHandles {
+        ClickSoundHandles {
+            play_sound: self.play_sound.clone(),
+            sound_rate: self.sound_rate.clone(),
+            vary_pitch: self.vary_pitch.clone(),
+            theme: self.sound_theme.clone(),
+        }
+    }
+
+    #[cfg(feature = "audio")]
+    pub fn beep_handles(&self) -> BeepHandles {
+        BeepHandles {
+            clicking: self.clicking.clone(),
+            toggle_beep: self.toggle_beep.clone(),
+            start_delay: self.start_delay.clone(),
+            countdown_beeps: self.countdown_beeps.clone(),
+            target_cps: self.target_cps.clone(),
+            cps_beep: self.cps_beep.clone(),
+            cps_nudged: self.cps_nudged.clone(),
+            theme: self.sound_theme.clone(),
+        }
+    }
+
+    pub fn overlay_handles(&self) -> OverlayHandles {
+        OverlayHandles {
+            clicking: self.clicking.clone(),
+            fast_mode: self.fast_mode.clone(),
+            target_cps: self.target_cps.clone(),
+            emitted: self.emitted.clone(),
+            port: self.overlay_port.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn twitch_handles(&self) -> TwitchHandles {
+        TwitchHandles {
+            trigger: self.chat_trigger.clone(),
+            selected_buttons: self.selected_buttons.clone(),
+            macros: self.macro_handles(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn bench_handles(&self) -> BenchHandles {
+        BenchHandles {
+            running: self.bench_running.clone(),
+            test_pad: self.test_pad.clone(),
+            cursor: self.cursor.clone(),
+            activity: self.activity.clone(),
+        }
+    }
+
+    #[cfg(feature = "ocr")]
+    pub fn ocr_handles(&self) -> OcrHandles {
+        OcrHandles { clicking: self.clicking.clone(), log: self.log.clone(), shutdown: self.shutdown.clone() }
+    }
+
+    pub fn command_file_handles(&self) -> CommandFileHandles {
+        CommandFileHandles {
+            control: self.control_handles(),
+            enabled: self.command_files.clone(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn hooks_handles(&self) -> HooksHandles {
+        HooksHandles {
+            hooks: self.hooks.clone(),
+            clicking: self.clicking.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn webhook_handles(&self) -> WebhookHandles {
+        WebhookHandles {
+            config: self.webhook.clone(),
+            clicking: self.clicking.clone(),
+            emitted: self.emitted.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn mqtt_handles(&self) -> MqttHandles {
+        MqttHandles {
+            config: self.mqtt.clone(),
+            control: self.control_handles(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn typer_handles(&self) -> TyperHandles {
+        TyperHandles {
+            typer: self.typer.clone(),
+            running: self.typer_running.clone(),
+            dry_run: self.dry_run.clone(),
+            guard: self.guard_handles(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn lock_handles(&self) -> LockHandles {
+        LockHandles {
+            clicking: self.clicking.clone(),
+            macro_playing: self.macro_playing.clone(),
+            policy: self.lock_policy.clone(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn awake_handles(&self) -> AwakeHandles {
+        AwakeHandles {
+            keep_awake: self.keep_awake.clone(),
+            clicking: self.clicking.clone(),
+            macro_playing: self.macro_playing.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn indicator_handles(&self) -> IndicatorHandles {
+        IndicatorHandles {
+            place: self.indicator.clone(),
+            clicking: self.clicking.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn power_handles(&self) -> PowerHandles {
+        PowerHandles {
+            clicking: self.clicking.clone(),
+            policy: self.battery_policy.clone(),
+            battery_cps: self.battery_cps.clone(),
+            power_cap: self.power_cap.clone(),
+            low_power: self.low_power.clone(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn idle_handles(&self) -> IdleHandles {
+        IdleHandles {
+            clicking: self.clicking.clone(),
+            macro_playing: self.macro_playing.clone(),
+            activity: self.activity.clone(),
+            idle_stop: self.idle_stop.clone(),
+            shutdown: self.shutdown.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn receipt_handles(&self) -> ReceiptHandles {
+        ReceiptHandles {
+            receipts: self.receipts.clone(),
+            clicking: self.clicking.clone(),
+            dropped: self.dropped.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn control_handles(&self) -> ControlHandles {
+        ControlHandles {
+            clicking: self.clicking.clone(),
+            fast_mode: self.fast_mode.clone(),
+            target_cps: self.target_cps.clone(),
+            pending_profile: self.pending_profile.clone(),
+            profile: self.profile.clone(),
+            profiles: self.profiles.clone(),
+            quit: self.quit.clone(),
+        }
+    }
+
+    pub fn macro_handles(&self) -> MacroHandles {
+        MacroHandles {
+            playing: self.macro_playing.clone(),
+            paused: self.macro_paused.clone(),
+            backend: self.backend.clone(),
+            key_injection: self.key_injection.clone(),
+            stepper: self.macro_stepper.clone(),
+            telemetry: self.telemetry.clone(),
+            activity: self.activity.clone(),
+            screen_size: self.screen_size.clone(),
+            scale_recordings: self.scale_recordings.clone(),
+            guard: self.guard_handles(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn guard_handles(&self) -> GuardHandles {
+        GuardHandles {
+            max_cps: self.max_cps.clone(),
+            power_cap: self.power_cap.clone(),
+            blocklist: self.blocklist.clone(),
+            focused_app: self.focused_app.clone(),
+            pacer: self.pacer.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn pause_handles(&self) -> PauseHandles {
+        PauseHandles {
+            clicking: self.clicking.clone(),
+            macro_playing: self.macro_playing.clone(),
+            macro_paused: self.macro_paused.clone(),
+            typer_running: self.typer_running.clone(),
+            stack: self.run_stack.clone(),
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn window_handles(&self) -> WindowHandles {
+        WindowHandles {
+            relative_to: self.relative_to.clone(),
+            window_origin: self.window_origin.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn history_handles(&self) -> HistoryHandles {
+        HistoryHandles {
+            clicking: self.clicking.clone(),
+            emitted: self.emitted.clone(),
+            receipts: self.receipts.clone(),
+            profile: self.profile.clone(),
+            history: self.history.clone(),
+            last_run: self.last_run.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn totals_handles(&self) -> TotalsHandles {
+        TotalsHandles {
+            emitted: self.emitted.clone(),
+            focused_app: self.focused_app.clone(),
+            totals: self.totals.clone(),
+            rollover: self.rollover.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+
+    pub fn foreground_handles(&self) -> ForegroundHandles {
+        ForegroundHandles {
+            bindings: self.bindings.clone(),
+            title_rules: self.title_rules.clone(),
+            focused_app: self.focused_app.clone(),
+            pending_profile: self.pending_profile.clone(),
+            clicking: self.clicking.clone(),
+            emitted: self.emitted.clone(),
+            app_clicks: self.app_clicks.clone(),
+            log: self.log.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+// Click statistics shown in the Statistics section.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::BackendKind;
+use crate::geometry::Point;
+
+/// Side of a heatmap cell in screen pixels.
+pub const HEAT_CELL: f64 = 8.0;
+// Side of a cell in rendered images.
+const IMAGE_CELL: usize = 4;
+const IMAGE_BACKGROUND: [u8; 3] = [24, 24, 24];
+// Intervals this long or longer share the last histogram bucket.
+const MAX_INTERVAL_MS: u64 = 2000;
+// Hotkey latencies kept, over every backend.
+const MAX_LATENCIES: usize = 100;
+
+/// Emitted clicks per application that had focus while they were sent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AppClicks(HashMap<String, u64>);
+
+impl AppClicks {
+    pub fn add(&mut self, app: &str, clicks: u64) {
+        if clicks > 0 {
+            *self.0.entry(app.to_string()).or_insert(0) += clicks;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
+
+    /// Applications with their counts, most clicked first.
+    pub fn ranked(&self) -> Vec<(String, u64)> {
+        let mut apps: Vec<(String, u64)> = self.0.iter().map(|(app, &n)| (app.clone(), n)).collect();
+        apps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        apps
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Injected clicks counted per `HEAT_CELL` square of the screen.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Heatmap(HashMap<(i32, i32), u64>);
+
+impl Heatmap {
+    pub fn add(&mut self, (x, y): Point, clicks: u64) {
+        let cell = ((x / HEAT_CELL).floor() as i32, (y / HEAT_CELL).floor() as i32);
+        *self.0.entry(cell).or_insert(0) += clicks;
+    }
+
+    /// Moves every count of `other` into this map.
+    pub fn absorb(&mut self, other: &mut Heatmap) {
+        for (cell, clicks) in other.0.drain() {
+            *self.0.entry(cell).or_insert(0) += clicks;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Top left corner of every cell with its heat from 0 to 1, on a log
+    /// scale so a few hot spots don't hide the rest.
+    pub fn cells(&self) -> Vec<(Point, f32)> {
+        let max = self.0.values().copied().max().unwrap_or(1) as f32;
+        self.0
+            .iter()
+            .map(|(&(cx, cy), &n)| {
+                let heat = if max > 1.0 { (n as f32).ln_1p() / max.ln_1p() } else { 1.0 };
+                ((cx as f64 * HEAT_CELL, cy as f64 * HEAT_CELL), heat)
+            })
+            .collect()
+    }
+
+    /// RGB image of the area that was clicked, as (width, height, pixels).
+    pub fn render(&self) -> Option<(usize, usize, Vec<u8>)> {
+        let min_x = self.0.keys().map(|c| c.0).min()?;
+        let min_y = self.0.keys().map(|c| c.1).min()?;
+        let max_x = self.0.keys().map(|c| c.0).max()?;
+        let max_y = self.0.keys().map(|c| c.1).max()?;
+        let width = (max_x - min_x + 1) as usize * IMAGE_CELL;
+        let height = (max_y - min_y + 1) as usize * IMAGE_CELL;
+        let mut pixels = IMAGE_BACKGROUND.repeat(width * height);
+        for ((x, y), heat) in self.cells() {
+            let color = heat_color(heat);
+            let left = ((x / HEAT_CELL) as i32 - min_x) as usize * IMAGE_CELL;
+            let top = ((y / HEAT_CELL) as i32 - min_y) as usize * IMAGE_CELL;
+            for row in top..top + IMAGE_CELL {
+                for column in left..left + IMAGE_CELL {
+                    let i = (row * width + column) * 3;
+                    pixels[i..i + 3].copy_from_slice(&color);
+                }
+            }
+        }
+        Some((width, height, pixels))
+    }
+}
+
+/// Time between the main engine's click rounds, counted per whole millisecond.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Intervals {
+    counts: BTreeMap<u64, u64>,
+    // Exact sums in milliseconds, for the mean and spread.
+    sum: f64,
+    sum_of_squares: f64,
+}
+
+impl Intervals {
+    pub fn add(&mut self, interval: Duration) {
+        let ms = interval.as_secs_f64() * 1000.0;
+        *self.counts.entry((ms as u64).min(MAX_INTERVAL_MS)).or_insert(0) += 1;
+        self.sum += ms;
+        self.sum_of_squares += ms * ms;
+    }
+
+    /// Moves every interval of `other` into this one.
+    pub fn absorb(&mut self, other: &mut Intervals) {
+        for (ms, n) in std::mem::take(&mut other.counts) {
+            *self.counts.entry(ms).or_insert(0) += n;
+        }
+        self.sum += std::mem::take(&mut other.sum);
+        self.sum_of_squares += std::mem::take(&mut other.sum_of_squares);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Intervals::default();
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Mean and standard deviation in milliseconds.
+    pub fn mean_and_deviation(&self) -> Option<(f64, f64)> {
+        let n = self.count() as f64;
+        let mean = self.sum / n;
+        (n > 0.0).then(|| (mean, (self.sum_of_squares / n - mean * mean).max(0.0).sqrt()))
+    }
+
+    /// At most `max_bars` equally wide buckets from the shortest interval
+    /// seen to the longest, as (first ms, last ms, rounds).
+    pub fn bars(&self, max_bars: u64) -> Vec<(u64, u64, u64)> {
+        let (Some((&min, _)), Some((&max, _))) = (self.counts.first_key_value(), self.counts.last_key_value()) else {
+            return Vec::new();
+        };
+        let width = (max - min + 1).div_ceil(max_bars.max(1));
+        (min..=max)
+            .step_by(width as usize)
+            .map(|start| (start, start + width - 1, self.counts.range(start..start + width).map(|(_, n)| n).sum()))
+            .collect()
+    }
+}
+
+/// Time from a hotkey press starting clicking to the first click sent, per backend.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Latency {
+    // Press waiting for its first click.
+    pressed: Option<Instant>,
+    samples: VecDeque<(BackendKind, Duration)>,
+}
+
+impl Latency {
+    pub fn pressed(&mut self, at: Instant) {
+        self.pressed = Some(at);
+    }
+
+    /// Forgets the waiting press: clicking stopped, paused or was delayed on purpose.
+    pub fn cancel(&mut self) {
+        self.pressed = None;
+    }
+
+    /// Times the waiting press, if any, against a click `backend` sent at `at`.
+    pub fn clicked(&mut self, backend: BackendKind, at: Instant) {
+        let Some(pressed) = self.pressed.take() else { return };
+        if self.samples.len() == MAX_LATENCIES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((backend, at.saturating_duration_since(pressed)));
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Median and last latency and the number of presses timed, for each backend with any.
+    pub fn summary(&self) -> Vec<(BackendKind, Duration, Duration, usize)> {
+        BackendKind::ALL
+            .into_iter()
+            .filter_map(|kind| {
+                let mut times: Vec<Duration> = self.samples.iter().filter(|(k, _)| *k == kind).map(|(_, t)| *t).collect();
+                let last = *times.last()?;
+                times.sort();
+                Some((kind, times[times.len() / 2], last, times.len()))
+            })
+            .collect()
+    }
+}
+
+/// Blue for the coldest cells through green and yellow to red.
+pub fn heat_color(heat: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 4] = [[40.0, 60.0, 220.0], [40.0, 200.0, 80.0], [240.0, 220.0, 40.0], [230.0, 40.0, 30.0]];
+    let t = heat.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let mix = |c: usize| (STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f).round() as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+/// Short count like "950", "3.2k", "80k" or "1.5M".
+pub fn format_count(n: u64) -> String {
+    let short = |value: f64, suffix: &str| {
+        if value < 10.0 {
+            format!("{:.1}{}", value, suffix).replace(".0", "")
+        } else {
+            format!("{:.0}{}", value, suffix)
+        }
+    };
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => short(n as f64 / 1e3, "k"),
+        _ => short(n as f64 / 1e6, "M"),
+    }
+}
+
+/// Short duration like "12.5 s", "3 min 05 s" or "1 h 02 min".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{:.1} s", duration.as_secs_f64()),
+        60..=3599 => format!("{} min {:02} s", secs / 60, secs % 60),
+        _ => format!("{} h {:02} min", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apps_are_ranked_by_clicks() {
+        let mut clicks = AppClicks::default();
+        clicks.add("chrome", 3_000);
+        clicks.add("minecraft", 50_000);
+        clicks.add("idle", 0);
+        clicks.add("minecraft", 30_000);
+        assert_eq!(clicks.ranked(), [("minecraft".to_string(), 80_000), ("chrome".to_string(), 3_000)]);
+        assert_eq!(clicks.total(), 83_000);
+    }
+
+    #[test]
+    fn heatmap_bins_clicks_into_cells() {
+        let mut local = Heatmap::default();
+        local.add((3.0, 3.0), 1);
+        local.add((7.9, 0.0), 99);
+        local.add((17.0, -1.0), 1);
+        let mut map = Heatmap::default();
+        map.absorb(&mut local);
+        assert!(local.is_empty());
+        let mut cells = map.cells();
+        cells.sort_by(|a, b| a.0 .0.total_cmp(&b.0 .0));
+        assert_eq!(cells, [((0.0, 0.0), 1.0), ((16.0, -8.0), 2f32.ln() / 101f32.ln())]);
+
+        // Three cells wide, two high; the hot cell is red.
+        let (width, height, pixels) = map.render().unwrap();
+        assert_eq!((width, height), (3 * IMAGE_CELL, 2 * IMAGE_CELL));
+        let at = |x: usize, y: usize| &pixels[(y * width + x) * 3..][..3];
+        assert_eq!(at(0, IMAGE_CELL), heat_color(1.0));
+        assert_eq!(at(0, 0), IMAGE_BACKGROUND);
+        assert_eq!(heat_color(1.0), [230, 40, 30]);
+        assert!(Heatmap::default().render().is_none());
+    }
+
+    #[test]
+    fn intervals_are_bucketed_per_millisecond() {
+        let mut local = Intervals::default();
+        for ms in [10.2, 10.8, 11.5, 14.0, 5000.0] {
+            local.add(Duration::from_secs_f64(ms / 1000.0));
+        }
+        let mut intervals = Intervals::default();
+        intervals.absorb(&mut local);
+        assert!(local.is_empty());
+        assert_eq!(intervals.count(), 5);
+        assert_eq!(intervals.bars(2000)[..5], [(10, 10, 2), (11, 11, 1), (12, 12, 0), (13, 13, 0), (14, 14, 1)]);
+        // The long pause lands in the last bucket.
+        assert_eq!(intervals.bars(2), [(10, 1005, 4), (1006, 2001, 1)]);
+
+        let mut steady = Intervals::default();
+        steady.add(Duration::from_millis(4));
+        steady.add(Duration::from_millis(6));
+        assert_eq!(steady.mean_and_deviation(), Some((5.0, 1.0)));
+        assert_eq!(Intervals::default().mean_and_deviation(), None);
+    }
+
+    #[test]
+    fn latency_is_timed_from_the_press_per_backend() {
+        let start = Instant::now();
+        let ms = |n| Duration::from_millis(n);
+        let mut latency = Latency::default();
+        // Clicks without a waiting press aren't timed.
+        latency.clicked(BackendKind::Enigo, start);
+        for (kind, n) in [(BackendKind::Enigo, 30), (BackendKind::Uinput, 4), (BackendKind::Enigo, 10), (BackendKind::Enigo, 20)] {
+            latency.pressed(start);
+            latency.clicked(kind, start + ms(n));
+            latency.clicked(kind, start + ms(n + 100));
+        }
+        latency.pressed(start);
+        latency.cancel();
+        latency.clicked(BackendKind::Uinput, start + ms(1));
+        assert_eq!(latency.summary(), [(BackendKind::Enigo, ms(20), ms(20), 3), (BackendKind::Uinput, ms(4), ms(4), 1)]);
+    }
+
+    #[test]
+    fn counts_are_shortened() {
+        assert_eq!(format_count(950), "950");
+        assert_eq!(format_count(3_000), "3k");
+        assert_eq!(format_count(3_250), "3.2k");
+        assert_eq!(format_count(80_400), "80k");
+        assert_eq!(format_count(1_500_000), "1.5M");
+        assert_eq!(format_duration(Duration::from_millis(12_540)), "12.5 s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3 min 05 s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1 h 02 min");
+    }
+}
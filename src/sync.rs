@@ -0,0 +1,274 @@
+// Settings sync through a user-chosen folder, typically inside Dropbox or
+// OneDrive. Every profile is kept there as its own shared profile file, so
+// machines editing different profiles don't overwrite each other, and each
+// machine adds its click counts to a stats file of its own:
+//
+//     <sync_dir>/profiles/<name>.profile.toml
+//     <sync_dir>/stats/<machine>.toml
+//
+// Profiles are merged on startup: a synced file that changed since this
+// machine last read or wrote it wins over the local copy. After that, only
+// profiles changed on this machine are written back. When each file was last
+// seen is kept in sync_state.toml next to config.toml.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use toml_edit::{value, Document, Item, Table};
+
+use crate::config::{self, config_dir, Config, ConfigFile, PROFILE_EXTENSION};
+use crate::stats::AppClicks;
+
+/// A synced profile file: its profile and when it was last written.
+struct Remote {
+    name: String,
+    config: Config,
+    modified: SystemTime,
+}
+
+/// Modification time of each synced profile file when this machine last read or wrote it.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncState(BTreeMap<String, SystemTime>);
+
+impl SyncState {
+    fn path() -> PathBuf {
+        config_dir().join("sync_state.toml")
+    }
+
+    pub fn load() -> SyncState {
+        let Some(doc) = fs::read_to_string(SyncState::path()).ok().and_then(|text| text.parse::<Document>().ok()) else {
+            return SyncState::default();
+        };
+        let seen = doc
+            .iter()
+            .filter_map(|(name, item)| Some((name.to_string(), UNIX_EPOCH + Duration::from_millis(u64::try_from(item.as_integer()?).ok()?))))
+            .collect();
+        SyncState(seen)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut doc = Document::new();
+        for (name, modified) in &self.0 {
+            doc[name.as_str()] = value(modified.duration_since(UNIX_EPOCH).map_or(0, |t| t.as_millis() as i64));
+        }
+        fs::create_dir_all(config_dir())?;
+        fs::write(SyncState::path(), doc.to_string())
+    }
+}
+
+fn profiles_dir(dir: &Path) -> PathBuf {
+    dir.join("profiles")
+}
+
+fn stats_dir(dir: &Path) -> PathBuf {
+    dir.join("stats")
+}
+
+/// File name for `name`, with characters some file systems reject hex-escaped.
+fn file_name(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ') {
+            out.push(c);
+        } else {
+            for byte in c.to_string().bytes() {
+                out.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    format!("{}.{}", out, PROFILE_EXTENSION)
+}
+
+fn read_profiles(dir: &Path) -> Vec<Remote> {
+    let Ok(entries) = fs::read_dir(profiles_dir(dir)) else { return Vec::new() };
+    let mut remotes = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path.to_string_lossy().ends_with(PROFILE_EXTENSION) {
+            continue;
+        }
+        let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else { continue };
+        // Files half written by the sync client are picked up next time.
+        match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| config::import_profile(&text)) {
+            Ok(shared) => remotes.push(Remote { name: shared.name, config: shared.config, modified }),
+            Err(e) => eprintln!("Skipped synced profile {}: {}", path.display(), e),
+        }
+    }
+    remotes
+}
+
+/// Takes the synced profiles that are new here or changed since `state` last
+/// saw them. One deleted here stays deleted unless it changed elsewhere since.
+/// Returns the names of the profiles taken.
+fn merge_remotes(profiles: &mut BTreeMap<String, Config>, remotes: Vec<Remote>, state: &mut SyncState) -> Vec<String> {
+    let mut taken = Vec::new();
+    for remote in remotes {
+        let changed = state.0.get(&remote.name).is_none_or(|seen| remote.modified > *seen);
+        state.0.insert(remote.name.clone(), remote.modified);
+        match profiles.get(&remote.name) {
+            Some(local) if *local == remote.config => {}
+            _ if !changed => {}
+            _ => {
+                taken.push(remote.name.clone());
+                profiles.insert(remote.name, remote.config);
+            }
+        }
+    }
+    taken
+}
+
+/// Merges the profiles of the sync folder into `file` and writes the ones
+/// changed only here back. Returns what happened, for the log.
+pub fn merge(file: &mut ConfigFile, dir: &Path, state: &mut SyncState) -> Result<String, String> {
+    let taken = merge_remotes(&mut file.profiles, read_profiles(dir), state);
+    for (name, config) in file.profiles.iter().filter(|(name, _)| !taken.contains(name)) {
+        write_profile(dir, name, config, state).map_err(|e| format!("Sync folder not writable: {}", e))?;
+    }
+    state.save().map_err(|e| format!("Failed to save the sync state: {}", e))?;
+    Ok(match taken.len() {
+        0 => format!("Profiles in sync with {}", dir.display()),
+        n => format!("Synced {} profile(s) from {}: {}", n, dir.display(), taken.join(", ")),
+    })
+}
+
+fn write_profile(dir: &Path, name: &str, config: &Config, state: &mut SyncState) -> io::Result<()> {
+    let path = profiles_dir(dir).join(file_name(name));
+    let text = config::export_profile(name, config);
+    // Rewriting unchanged files would make them look changed to other machines.
+    if fs::read_to_string(&path).is_ok_and(|old| old == text) {
+        return Ok(());
+    }
+    fs::create_dir_all(profiles_dir(dir))?;
+    fs::write(&path, text)?;
+    state.0.insert(name.to_string(), fs::metadata(&path)?.modified()?);
+    Ok(())
+}
+
+/// Writes the profiles that differ from `last_synced`, then remembers them.
+pub fn write_changed(
+    dir: &Path,
+    profiles: &BTreeMap<String, Config>,
+    last_synced: &mut BTreeMap<String, Config>,
+    state: &mut SyncState,
+) -> io::Result<()> {
+    for (name, config) in profiles {
+        if last_synced.get(name) != Some(config) {
+            write_profile(dir, name, config, state)?;
+        }
+    }
+    last_synced.clone_from(profiles);
+    state.save()
+}
+
+/// Name of this machine's stats file.
+fn machine() -> String {
+    let name = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "this-machine".to_string());
+    name.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+fn parse_stats(text: &str) -> AppClicks {
+    let mut clicks = AppClicks::default();
+    let Ok(doc) = text.parse::<Document>() else { return clicks };
+    if let Some(apps) = doc.get("apps").and_then(Item::as_table) {
+        for (app, item) in apps.iter() {
+            if let Some(n) = item.as_integer().filter(|n| *n > 0) {
+                clicks.add(app, n as u64);
+            }
+        }
+    }
+    clicks
+}
+
+fn stats_text(clicks: &AppClicks) -> String {
+    let mut apps = Table::new();
+    for (app, n) in clicks.ranked() {
+        apps[app.as_str()] = value(n as i64);
+    }
+    let mut doc = Document::new();
+    doc["apps"] = Item::Table(apps);
+    doc.to_string()
+}
+
+/// Adds this session's clicks to this machine's stats file.
+pub fn save_stats(dir: &Path, session: &AppClicks) -> io::Result<()> {
+    if session.total() == 0 {
+        return Ok(());
+    }
+    let path = stats_dir(dir).join(format!("{}.toml", machine()));
+    let mut clicks = fs::read_to_string(&path).map(|text| parse_stats(&text)).unwrap_or_default();
+    for (app, n) in session.ranked() {
+        clicks.add(&app, n);
+    }
+    fs::create_dir_all(stats_dir(dir))?;
+    fs::write(path, stats_text(&clicks))
+}
+
+/// Clicks per application of every machine synced through `dir`, up to their last exit.
+pub fn load_stats(dir: &Path) -> AppClicks {
+    let mut total = AppClicks::default();
+    let Ok(entries) = fs::read_dir(stats_dir(dir)) else { return total };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if let Ok(text) = fs::read_to_string(&path) {
+            for (app, n) in parse_stats(&text).ranked() {
+                total.add(&app, n);
+            }
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_changed_elsewhere_win() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let fast = Config { target_cps: 50.0, ..Config::default() };
+        let mut profiles = BTreeMap::from([("edited".to_string(), Config::default()), ("kept".to_string(), Config::default())]);
+        let mut state = SyncState(BTreeMap::from([("edited".to_string(), at(1000)), ("kept".to_string(), at(1000))]));
+        let remotes = vec![
+            Remote { name: "edited".to_string(), config: fast.clone(), modified: at(2000) },
+            // Unchanged since this machine wrote it, so the local edit stays.
+            Remote { name: "kept".to_string(), config: fast.clone(), modified: at(1000) },
+            Remote { name: "new".to_string(), config: fast.clone(), modified: at(1) },
+        ];
+        assert_eq!(merge_remotes(&mut profiles, remotes, &mut state), ["edited", "new"]);
+        assert_eq!(profiles["edited"], fast);
+        assert_eq!(profiles["kept"], Config::default());
+        assert_eq!(profiles["new"], fast);
+        assert_eq!(state.0["new"], at(1));
+    }
+
+    #[test]
+    fn profiles_deleted_here_stay_deleted() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        let mut profiles = BTreeMap::new();
+        let mut state = SyncState(BTreeMap::from([("gone".to_string(), at(1000)), ("revived".to_string(), at(1000))]));
+        let remotes = vec![
+            Remote { name: "gone".to_string(), config: Config::default(), modified: at(1000) },
+            // Edited on another machine after this one deleted it.
+            Remote { name: "revived".to_string(), config: Config::default(), modified: at(2000) },
+        ];
+        assert_eq!(merge_remotes(&mut profiles, remotes, &mut state), ["revived"]);
+        assert!(!profiles.contains_key("gone"));
+    }
+
+    #[test]
+    fn stats_and_names_survive_files() {
+        let mut clicks = AppClicks::default();
+        clicks.add("javaw.exe", 120);
+        clicks.add("Game \"2\"", 3);
+        assert_eq!(parse_stats(&stats_text(&clicks)).ranked(), clicks.ranked());
+        assert_eq!(file_name("pvp 2"), "pvp 2.profile.toml");
+        assert_eq!(file_name("a/b:é"), "a%2Fb%3Aé.profile.toml");
+    }
+}
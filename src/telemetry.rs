@@ -0,0 +1,145 @@
+// Opt-in usage metrics: how often each feature is used, counted locally in
+// usage.json. Nothing is counted unless enabled, and nothing leaves the
+// machine until the user presses "Send". The summary holds the app version,
+// the OS family and the counts; no identifiers, paths or application names.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::{config_dir, parse_endpoint};
+use crate::json::{self, Value};
+
+const FILE: &str = "usage.json";
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct Telemetry {
+    pub enabled: AtomicBool,
+    counts: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Telemetry {
+    fn path() -> PathBuf {
+        config_dir().join(FILE)
+    }
+
+    /// Counts saved by an earlier session, if any.
+    pub fn load(enabled: bool) -> Telemetry {
+        let counts = fs::read_to_string(Telemetry::path())
+            .ok()
+            .and_then(|text| json::parse(&text).ok())
+            .and_then(|doc| match doc.get("features") {
+                Some(Value::Object(members)) => {
+                    Some(members.iter().filter_map(|(name, n)| Some((name.clone(), n.as_u64()?))).collect())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        Telemetry { enabled: AtomicBool::new(enabled), counts: Mutex::new(counts) }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Telemetry::path();
+        let counts = self.counts.lock().unwrap();
+        if counts.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        drop(counts);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.summary())
+    }
+
+    /// Counts one use of `feature` while telemetry is enabled.
+    pub fn record(&self, feature: &str) {
+        if self.enabled.load(Ordering::SeqCst) {
+            *self.counts.lock().unwrap().entry(feature.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn clear(&self) {
+        self.counts.lock().unwrap().clear();
+    }
+
+    /// Exactly the document `submit` sends.
+    pub fn summary(&self) -> String {
+        let features = self.counts.lock().unwrap().iter().map(|(name, &n)| (name.clone(), n.into())).collect();
+        Value::Object(vec![
+            ("app_version".to_string(), env!("CARGO_PKG_VERSION").into()),
+            ("os".to_string(), std::env::consts::OS.into()),
+            ("features".to_string(), Value::Object(features)),
+        ])
+        .pretty()
+    }
+
+    /// Posts the summary to `endpoint` (a plain http:// URL) and clears the
+    /// counts once it was accepted.
+    pub fn submit(&self, endpoint: &str) -> Result<(), String> {
+        if !self.enabled.load(Ordering::SeqCst) {
+            return Err("usage metrics are turned off".to_string());
+        }
+        post_json(endpoint, &self.summary())?;
+        self.clear();
+        let _ = self.save();
+        Ok(())
+    }
+}
+
+/// Posts the JSON document `body` to `endpoint`, a plain http:// URL. Any
+/// 2xx answer counts as accepted.
+pub fn post_json(endpoint: &str, body: &str) -> Result<(), String> {
+    let (host, port, path) = parse_endpoint(endpoint)?;
+    let addr = (host.as_str(), port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("can't resolve {}", host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, SEND_TIMEOUT).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(SEND_TIMEOUT)).map_err(|e| e.to_string())?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path,
+        host,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    let _ = stream.take(1024).read_to_string(&mut response);
+    let status = response.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(format!("server answered \"{}\"", response.lines().next().unwrap_or_default()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_enabled_usage_is_counted() {
+        let telemetry = Telemetry::default();
+        telemetry.record("macro_play");
+        assert!(telemetry.counts.lock().unwrap().is_empty());
+        telemetry.enabled.store(true, Ordering::SeqCst);
+        telemetry.record("macro_play");
+        telemetry.record("macro_play");
+        telemetry.record("superspeed");
+        let summary = json::parse(&telemetry.summary()).unwrap();
+        let features = summary.get("features").unwrap();
+        assert_eq!(features.get("macro_play").and_then(Value::as_u64), Some(2));
+        assert_eq!(features.get("superspeed").and_then(Value::as_u64), Some(1));
+        assert_eq!(summary.get("os").and_then(Value::as_str), Some(std::env::consts::OS));
+    }
+}
@@ -0,0 +1,144 @@
+// Precise waits for slow click rates. A plain sleep wakes up only as exactly
+// as the system's default timer allows: in 15.6 ms steps on Windows, and late
+// by the thread's timer slack on Linux, which some desktops raise for
+// background processes. Below PRECISE_CPS the engine waits out the last
+// stretch before each round on an OS high resolution timer instead: a high
+// resolution waitable timer on Windows (10 1803 and later), and an absolute
+// CLOCK_MONOTONIC deadline with the timer slack cut to a nanosecond on Linux.
+// Elsewhere, or if the timer can't be made, it is a plain sleep.
+
+use std::time::Duration;
+
+/// Rates below this wait on the precise timer; faster ones keep the sleep
+/// loop, whose rounds come too close together for it to matter.
+pub const PRECISE_CPS: f64 = 20.0;
+
+/// Whether rounds `interval` apart wait on the precise timer.
+pub fn is_precise(interval: Duration) -> bool {
+    interval.as_secs_f64() * PRECISE_CPS >= 1.0
+}
+
+pub use platform::Timer;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+    use std::ptr;
+    use std::time::Duration;
+
+    const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+    /// Timer slack of the calling thread, cut to the least there is.
+    pub struct Timer;
+
+    impl Timer {
+        /// Sets up precise waits for the calling thread.
+        pub fn new() -> Result<Timer, String> {
+            if unsafe { libc::prctl(libc::PR_SET_TIMERSLACK, 1 as libc::c_ulong) } != 0 {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            Ok(Timer)
+        }
+
+        pub fn wait(&mut self, duration: Duration) {
+            let mut deadline = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+            unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut deadline) };
+            let nanos = deadline.tv_nsec as u64 + u64::from(duration.subsec_nanos());
+            deadline.tv_sec += (duration.as_secs() + nanos / NANOS_PER_SEC) as libc::time_t;
+            deadline.tv_nsec = (nanos % NANOS_PER_SEC) as libc::c_long;
+            // A signal cuts the wait short; the deadline stays the same.
+            while unsafe { libc::clock_nanosleep(libc::CLOCK_MONOTONIC, libc::TIMER_ABSTIME, &deadline, ptr::null_mut()) } == libc::EINTR {}
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr;
+    use std::thread;
+    use std::time::Duration;
+
+    type Handle = *mut c_void;
+
+    const CREATE_WAITABLE_TIMER_HIGH_RESOLUTION: u32 = 0x0000_0002;
+    const TIMER_ALL_ACCESS: u32 = 0x001f_0003;
+    const INFINITE: u32 = 0xffff_ffff;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateWaitableTimerExW(attributes: *mut c_void, name: *const u16, flags: u32, access: u32) -> Handle;
+        fn SetWaitableTimer(timer: Handle, due: *const i64, period: i32, routine: *mut c_void, arg: *mut c_void, resume: i32) -> i32;
+        fn WaitForSingleObject(handle: Handle, milliseconds: u32) -> u32;
+        fn CloseHandle(handle: Handle) -> i32;
+    }
+
+    /// A high resolution waitable timer.
+    pub struct Timer(Handle);
+
+    // A waitable timer may be set and waited on from any thread.
+    unsafe impl Send for Timer {}
+
+    impl Timer {
+        pub fn new() -> Result<Timer, String> {
+            let timer = unsafe { CreateWaitableTimerExW(ptr::null_mut(), ptr::null(), CREATE_WAITABLE_TIMER_HIGH_RESOLUTION, TIMER_ALL_ACCESS) };
+            if timer.is_null() {
+                return Err(io::Error::last_os_error().to_string());
+            }
+            Ok(Timer(timer))
+        }
+
+        pub fn wait(&mut self, duration: Duration) {
+            // Negative due times are relative, in 100 ns units.
+            let due = -((duration.as_nanos() / 100) as i64);
+            if unsafe { SetWaitableTimer(self.0, &due, 0, ptr::null_mut(), ptr::null_mut(), 0) } == 0 {
+                thread::sleep(duration);
+                return;
+            }
+            unsafe { WaitForSingleObject(self.0, INFINITE) };
+        }
+    }
+
+    impl Drop for Timer {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    use std::time::Duration;
+
+    pub struct Timer;
+
+    impl Timer {
+        pub fn new() -> Result<Timer, String> {
+            Err("not supported on this system".to_string())
+        }
+
+        pub fn wait(&mut self, duration: Duration) {
+            std::thread::sleep(duration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precise_waits_end_on_time() {
+        assert!(is_precise(Duration::from_millis(50)));
+        assert!(!is_precise(Duration::from_millis(49)));
+
+        #[cfg(target_os = "linux")]
+        {
+            let mut timer = Timer::new().unwrap();
+            let start = std::time::Instant::now();
+            timer.wait(Duration::from_millis(5));
+            assert!(start.elapsed() >= Duration::from_millis(5));
+        }
+    }
+}
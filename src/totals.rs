@@ -0,0 +1,261 @@
+// Click totals kept across runs: every click sent ever, per application, plus
+// the clicks of the current day and week. Days and weeks start over at the
+// configured local hour and weekday. Kept in totals.toml next to config.toml
+// and exported to JSON or CSV from the Statistics section.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use toml_edit::{value, Document, Item, Table};
+
+use crate::config::{config_dir, Rollover};
+use crate::foreground::UNKNOWN_APP;
+use crate::json::Value;
+use crate::log::Log;
+use crate::stats::AppClicks;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+const DAY: i64 = 24 * 60 * 60;
+// Days from Monday to Thursday, 1970-01-01.
+const EPOCH_WEEKDAY: i64 = 3;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Totals {
+    pub lifetime: AppClicks,
+    pub today: u64,
+    pub this_week: u64,
+    // Day and week the two counts above belong to.
+    day: i64,
+    week: i64,
+}
+
+/// Day and week numbers of the local time `unix_secs + utc_offset`, counted
+/// from the boundaries of `rollover`.
+pub fn period(rollover: Rollover, unix_secs: i64, utc_offset: i64) -> (i64, i64) {
+    let day = (unix_secs + utc_offset - i64::from(rollover.day_start) * 3600).div_euclid(DAY);
+    let week = (day + EPOCH_WEEKDAY - rollover.week_start as i64).div_euclid(7);
+    (day, week)
+}
+
+fn period_now(rollover: Rollover) -> (i64, i64) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs() as i64);
+    period(rollover, now, utc_offset())
+}
+
+impl Totals {
+    fn path() -> PathBuf {
+        config_dir().join("totals.toml")
+    }
+
+    /// Adds `clicks` sent to `app`, first starting over the counts of a day
+    /// or week that ended before `period`.
+    pub fn add(&mut self, app: &str, clicks: u64, (day, week): (i64, i64)) {
+        if day != self.day {
+            self.day = day;
+            self.today = 0;
+        }
+        if week != self.week {
+            self.week = week;
+            self.this_week = 0;
+        }
+        self.lifetime.add(app, clicks);
+        self.today += clicks;
+        self.this_week += clicks;
+    }
+
+    pub fn parse(text: &str) -> Totals {
+        let mut totals = Totals::default();
+        let Ok(doc) = text.parse::<Document>() else { return totals };
+        let count = |key: &str| doc.get(key).and_then(Item::as_integer).filter(|n| *n >= 0);
+        totals.day = count("day").unwrap_or(0);
+        totals.week = count("week").unwrap_or(0);
+        totals.today = count("today").unwrap_or(0) as u64;
+        totals.this_week = count("this_week").unwrap_or(0) as u64;
+        if let Some(apps) = doc.get("apps").and_then(Item::as_table) {
+            for (app, item) in apps.iter() {
+                if let Some(n) = item.as_integer().filter(|n| *n > 0) {
+                    totals.lifetime.add(app, n as u64);
+                }
+            }
+        }
+        totals
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut doc = Document::new();
+        doc["day"] = value(self.day);
+        doc["week"] = value(self.week);
+        doc["today"] = value(self.today as i64);
+        doc["this_week"] = value(self.this_week as i64);
+        let mut apps = Table::new();
+        for (app, n) in self.lifetime.ranked() {
+            apps[app.as_str()] = value(n as i64);
+        }
+        doc["apps"] = Item::Table(apps);
+        doc.to_string()
+    }
+
+    pub fn load() -> Totals {
+        fs::read_to_string(Totals::path()).map(|text| Totals::parse(&text)).unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(config_dir())?;
+        fs::write(Totals::path(), self.to_toml())
+    }
+
+    pub fn to_json(&self) -> String {
+        let apps = self.lifetime.ranked().into_iter().map(|(app, n)| (app, Value::Number(n as f64))).collect();
+        Value::Object(vec![
+            ("today".to_string(), Value::Number(self.today as f64)),
+            ("this_week".to_string(), Value::Number(self.this_week as f64)),
+            ("lifetime".to_string(), Value::Number(self.lifetime.total() as f64)),
+            ("apps".to_string(), Value::Object(apps)),
+        ])
+        .pretty()
+    }
+
+    /// One row per period and per application, for spreadsheets.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("period,app,clicks\n");
+        csv.push_str(&format!("today,,{}\nthis week,,{}\nlifetime,,{}\n", self.today, self.this_week, self.lifetime.total()));
+        for (app, n) in self.lifetime.ranked() {
+            csv.push_str(&format!("lifetime,{},{}\n", csv_field(&app), n));
+        }
+        csv
+    }
+}
+
+/// `text` quoted if it holds a comma, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Shared state the totals counter is allowed to touch.
+pub struct TotalsHandles {
+    pub emitted: Arc<AtomicU64>,
+    pub focused_app: Arc<Mutex<Option<String>>>,
+    pub totals: Arc<Mutex<Totals>>,
+    pub rollover: Arc<Mutex<Rollover>>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+/// Adds the clicks sent to the totals every second and saves them every
+/// minute. Returns after a last count and save once `shutdown` is set.
+pub fn spawn_counter(handles: TotalsHandles) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut counted = handles.emitted.load(Ordering::SeqCst);
+        let mut saved = Instant::now();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let done = handles.shutdown.load(Ordering::SeqCst);
+            let emitted = handles.emitted.load(Ordering::SeqCst);
+            let app = handles.focused_app.lock().unwrap().clone();
+            let period = period_now(*handles.rollover.lock().unwrap());
+            let mut totals = handles.totals.lock().unwrap();
+            totals.add(app.as_deref().unwrap_or(UNKNOWN_APP), emitted - counted, period);
+            counted = emitted;
+            if done || saved.elapsed() >= SAVE_INTERVAL {
+                if let Err(e) = totals.save() {
+                    handles.log.push(format!("Failed to save click totals: {}", e));
+                }
+                saved = Instant::now();
+            }
+            if done {
+                return;
+            }
+        }
+    })
+}
+
+pub use platform::utc_offset;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    /// Seconds local time is ahead of UTC right now.
+    pub fn utc_offset() -> i64 {
+        let now = unsafe { libc::time(std::ptr::null_mut()) };
+        let mut local: libc::tm = unsafe { std::mem::zeroed() };
+        if unsafe { libc::localtime_r(&now, &mut local) }.is_null() {
+            return 0;
+        }
+        local.tm_gmtoff
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    #[repr(C)]
+    #[derive(Default)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemTimeAsFileTime(time: *mut FileTime);
+        fn FileTimeToLocalFileTime(time: *const FileTime, local: *mut FileTime) -> i32;
+    }
+
+    /// Seconds local time is ahead of UTC right now.
+    pub fn utc_offset() -> i64 {
+        let (mut utc, mut local) = (FileTime::default(), FileTime::default());
+        unsafe { GetSystemTimeAsFileTime(&mut utc) };
+        if unsafe { FileTimeToLocalFileTime(&utc, &mut local) } == 0 {
+            return 0;
+        }
+        // File times count 100 ns ticks.
+        let ticks = |t: &FileTime| (i64::from(t.high) << 32) | i64::from(t.low);
+        (ticks(&local) - ticks(&utc)) / 10_000_000
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod platform {
+    pub fn utc_offset() -> i64 {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Weekday;
+
+    #[test]
+    fn days_and_weeks_start_at_the_rollover() {
+        let midnight = Rollover::default();
+        // Monday 1970-01-05, 00:00 UTC.
+        let monday = 4 * DAY;
+        assert_eq!(period(midnight, monday, 0), (4, 1));
+        assert_eq!(period(midnight, monday - 1, 0), (3, 0));
+        // 23:30 UTC is already Monday at UTC+1.
+        assert_eq!(period(midnight, monday - 1800, 3600), (4, 1));
+
+        let late = Rollover { day_start: 4, week_start: Weekday::Sunday };
+        assert_eq!(period(late, monday + 3 * 3600, 0), (3, 0));
+        assert_eq!(period(late, monday + 4 * 3600, 0), (4, 0));
+        assert_eq!(period(late, monday + 6 * DAY + 4 * 3600, 0), (10, 1));
+
+        let mut totals = Totals::default();
+        totals.add("game", 10, (4, 1));
+        totals.add("game", 5, (5, 1));
+        assert_eq!((totals.today, totals.this_week, totals.lifetime.total()), (5, 15, 15));
+        totals.add("editor, v2", 1, (11, 2));
+        assert_eq!((totals.today, totals.this_week, totals.lifetime.total()), (1, 1, 16));
+        assert_eq!(Totals::parse(&totals.to_toml()), totals);
+        assert!(totals.to_csv().ends_with("lifetime,game,15\nlifetime,\"editor, v2\",1\n"));
+    }
+}
@@ -0,0 +1,118 @@
+// Human-like cursor travel: a curved path to the target that overshoots
+// slightly and settles back, instead of teleporting.
+
+use std::time::Duration;
+
+use crate::geometry::Point;
+
+/// Time between cursor updates along a path.
+pub const STEP: Duration = Duration::from_millis(8);
+// Shortest travel, so close targets still get a visible movement.
+const MIN_DURATION: Duration = Duration::from_millis(40);
+// Share of the travel time spent settling back from the overshoot.
+const SETTLE_SHARE: f64 = 0.15;
+
+/// Small xorshift generator; the path only needs to look irregular.
+pub struct Jitter(u64);
+
+impl Jitter {
+    pub fn new(seed: u64) -> Self {
+        Jitter(seed.max(1))
+    }
+
+    /// Uniform in `low..high`.
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        low + (self.0 >> 11) as f64 / (1u64 << 53) as f64 * (high - low)
+    }
+
+    fn sign(&mut self) -> f64 {
+        if self.range(0.0, 1.0) < 0.5 { -1.0 } else { 1.0 }
+    }
+}
+
+fn lerp(a: Point, b: Point, t: f64) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+fn bezier(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> Point {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    (
+        a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+        a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+    )
+}
+
+// Slow start and end, fastest in the middle.
+fn ease(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Cursor positions from `from` to `to` at `speed` pixels per second, one
+/// per `STEP`. The last point is exactly `to`; empty if already there.
+pub fn path(from: Point, to: Point, speed: f64, jitter: &mut Jitter) -> Vec<Point> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let distance = dx.hypot(dy);
+    if distance < 1.0 {
+        return Vec::new();
+    }
+    let duration = Duration::from_secs_f64(distance / speed.max(1.0)).max(MIN_DURATION);
+    let steps = (duration.as_nanos().div_ceil(STEP.as_nanos()) as usize).max(2);
+    let settle_steps = ((steps as f64 * SETTLE_SHARE).round() as usize).max(1);
+    let curve_steps = steps - settle_steps;
+
+    // Unit vectors along and across the direction of travel.
+    let along = (dx / distance, dy / distance);
+    let across = (-along.1, along.0);
+    let offset = |point: Point, a: f64, c: f64| (point.0 + along.0 * a + across.0 * c, point.1 + along.1 * a + across.1 * c);
+
+    let overshoot = offset(to, distance * jitter.range(0.02, 0.06), distance * jitter.range(-0.02, 0.02));
+    let bend = jitter.sign() * distance;
+    let control1 = offset(lerp(from, to, 1.0 / 3.0), 0.0, bend * jitter.range(0.05, 0.2));
+    let control2 = offset(lerp(from, to, 2.0 / 3.0), 0.0, bend * jitter.range(0.0, 0.15));
+
+    let mut points: Vec<Point> = (1..=curve_steps)
+        .map(|n| bezier(from, control1, control2, overshoot, ease(n as f64 / curve_steps as f64)))
+        .collect();
+    points.extend((1..=settle_steps).map(|n| lerp(overshoot, to, ease(n as f64 / settle_steps as f64))));
+    *points.last_mut().unwrap() = to;
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_ends_exactly_on_target() {
+        let mut jitter = Jitter::new(7);
+        for target in [(500.0, 300.0), (-20.0, 10.0), (3.0, 0.0)] {
+            let points = path((0.0, 0.0), target, 2000.0, &mut jitter);
+            assert_eq!(points.last(), Some(&target));
+        }
+        assert!(path((5.0, 5.0), (5.2, 5.0), 2000.0, &mut jitter).is_empty());
+    }
+
+    #[test]
+    fn path_takes_distance_over_speed() {
+        let mut jitter = Jitter::new(7);
+        // 1000 px at 2000 px/s: 500 ms, one point per 8 ms step.
+        let points = path((0.0, 0.0), (1000.0, 0.0), 2000.0, &mut jitter);
+        assert_eq!(points.len(), 63);
+        // Short moves still take a few steps.
+        assert_eq!(path((0.0, 0.0), (10.0, 0.0), 2000.0, &mut jitter).len(), 5);
+    }
+
+    #[test]
+    fn path_curves_and_overshoots() {
+        let mut jitter = Jitter::new(42);
+        let points = path((0.0, 0.0), (1000.0, 0.0), 2000.0, &mut jitter);
+        assert!(points.iter().any(|p| p.0 > 1000.0), "no overshoot");
+        assert!(points.iter().any(|p| p.1.abs() > 10.0), "straight line");
+        // Never wanders far from the straight path.
+        assert!(points.iter().all(|p| p.1.abs() < 250.0 && p.0 > -50.0 && p.0 < 1100.0));
+    }
+}
@@ -0,0 +1,179 @@
+// Twitch chat trigger: joins a channel's chat anonymously over IRC and plays
+// a short click burst when a viewer sends the configured command, e.g.
+// "!click 10". Each viewer has a cooldown, bursts are capped at
+// `max_clicks` (and `MAX_CHAT_CLICKS` whatever the config says) and click no
+// faster than `MAX_CHAT_CPS`, and a command sent while another burst or macro
+// is playing is ignored. Bursts play as macros, so the CPS guard and the
+// blocklist hold for them too.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use enigo::MouseButton;
+
+use crate::config::{ChatTrigger, MAX_CHAT_CLICKS, MAX_CHAT_CPS, MIN_CPS};
+use crate::log::Log;
+use crate::macros::{self, MacroHandles, Step};
+
+const SERVER: &str = "irc.chat.twitch.tv:6667";
+// How often a quiet connection checks whether it was turned off.
+const READ_POLL: Duration = Duration::from_secs(1);
+const MIN_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Shared state the chat trigger is allowed to touch.
+pub struct TwitchHandles {
+    // Trigger to run; the connection closes once this no longer matches it.
+    pub trigger: Arc<Mutex<Option<ChatTrigger>>>,
+    pub selected_buttons: Arc<Mutex<Vec<MouseButton>>>,
+    pub macros: MacroHandles,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+/// Per-viewer cooldowns for one trigger.
+pub struct Triggers {
+    trigger: ChatTrigger,
+    last: HashMap<String, Instant>,
+}
+
+impl Triggers {
+    pub fn new(trigger: ChatTrigger) -> Triggers {
+        Triggers { trigger, last: HashMap::new() }
+    }
+
+    /// Clicks `user` asked for with `text`, or `None` if it isn't the
+    /// command or they are still cooling down. A missing count means one click.
+    pub fn request(&mut self, user: &str, text: &str, now: Instant) -> Option<u32> {
+        let mut words = text.split_whitespace();
+        if !words.next()?.eq_ignore_ascii_case(&self.trigger.command) {
+            return None;
+        }
+        let clicks = match words.next() {
+            Some(count) => count.parse::<u32>().ok()?,
+            None => 1,
+        };
+        if clicks == 0 {
+            return None;
+        }
+        if self.last.get(user).is_some_and(|&last| now.duration_since(last) < self.trigger.cooldown) {
+            return None;
+        }
+        self.last.insert(user.to_string(), now);
+        Some(clicks.min(self.trigger.max_clicks).min(MAX_CHAT_CLICKS))
+    }
+}
+
+/// Sender and text of a chat message line, e.g.
+/// ":viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :!click 5".
+fn parse_privmsg(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let (prefix, rest) = rest.split_once(' ')?;
+    let rest = rest.strip_prefix("PRIVMSG ")?;
+    let (_, text) = rest.split_once(" :")?;
+    let user = prefix.split('!').next().filter(|user| !user.is_empty())?;
+    Some((user, text.trim_end()))
+}
+
+/// Joins the chat of `trigger.channel` on its own thread, reconnecting with
+/// a growing delay until the trigger is changed or turned off.
+pub fn spawn(handles: TwitchHandles, trigger: ChatTrigger) {
+    thread::spawn(move || {
+        let mut triggers = Triggers::new(trigger.clone());
+        let mut backoff = MIN_BACKOFF;
+        while active(&handles, &trigger) {
+            let started = Instant::now();
+            match run(&handles, &trigger, &mut triggers) {
+                Ok(()) => break,
+                Err(e) => handles.log.push(format!("Twitch chat disconnected: {}", e)),
+            }
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = MIN_BACKOFF;
+            }
+            let retry = Instant::now() + backoff;
+            while Instant::now() < retry && active(&handles, &trigger) {
+                thread::sleep(READ_POLL);
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+fn active(handles: &TwitchHandles, trigger: &ChatTrigger) -> bool {
+    !handles.shutdown.load(Ordering::SeqCst) && handles.trigger.lock().unwrap().as_ref() == Some(trigger)
+}
+
+/// One connection; returns `Ok` once the trigger was turned off.
+fn run(handles: &TwitchHandles, trigger: &ChatTrigger, triggers: &mut Triggers) -> Result<(), String> {
+    let stream = TcpStream::connect(SERVER).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(READ_POLL)).map_err(|e| e.to_string())?;
+    // Anonymous read-only login: any "justinfan" nick without a password.
+    let nick = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.subsec_micros()) % 100_000;
+    write!(&stream, "NICK justinfan{}\r\nJOIN #{}\r\n", nick, trigger.channel).map_err(|e| e.to_string())?;
+    handles.log.push(format!("Listening for {} in #{}", trigger.command, trigger.channel));
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    while active(handles, trigger) {
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err("connection closed".to_string()),
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+        if let Some(server) = line.strip_prefix("PING ") {
+            write!(&stream, "PONG {}\r\n", server.trim_end()).map_err(|e| e.to_string())?;
+        } else if let Some((user, text)) = parse_privmsg(&line) {
+            // Commands sent during a burst are dropped without costing the viewer their cooldown.
+            if !handles.macros.playing.load(Ordering::SeqCst) {
+                if let Some(clicks) = triggers.request(user, text, Instant::now()) {
+                    play_burst(handles, trigger, user, clicks);
+                }
+            }
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+fn play_burst(handles: &TwitchHandles, trigger: &ChatTrigger, user: &str, clicks: u32) {
+    let button = handles.selected_buttons.lock().unwrap().first().copied().unwrap_or(MouseButton::Left);
+    let burst = macros::Macro {
+        steps: vec![Step::Click { button, at: None }, Step::Wait(Duration::from_secs_f64(1.0 / trigger.cps.clamp(MIN_CPS, MAX_CHAT_CPS)))],
+        loops: Some(clicks),
+        ..Default::default()
+    };
+    macros::spawn_player(handles.macros.clone(), format!("{} x{} from {}", trigger.command, clicks, user), burst, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_lines_are_parsed() {
+        let line = ":viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #streamer :!click 5\r\n";
+        assert_eq!(parse_privmsg(line), Some(("viewer", "!click 5")));
+        assert_eq!(parse_privmsg(":tmi.twitch.tv 001 justinfan1 :Welcome, GLHF!"), None);
+        assert_eq!(parse_privmsg("PING :tmi.twitch.tv"), None);
+    }
+
+    #[test]
+    fn bursts_are_capped_and_cooled_down() {
+        let mut triggers = Triggers::new(ChatTrigger { max_clicks: 50, ..ChatTrigger::new("streamer") });
+        let now = Instant::now();
+        assert_eq!(triggers.request("a", "!CLICK 10", now), Some(10));
+        assert_eq!(triggers.request("a", "!click 10", now + Duration::from_secs(29)), None);
+        assert_eq!(triggers.request("b", "!click 9000", now), Some(50));
+        assert_eq!(triggers.request("c", "!click", now), Some(1));
+        assert_eq!(triggers.request("d", "!click lots", now), None);
+        assert_eq!(triggers.request("d", "!clicker 5", now), None);
+        assert_eq!(triggers.request("a", "!click 10", now + Duration::from_secs(30)), Some(10));
+    }
+}
@@ -0,0 +1,106 @@
+// Text typer: types a configured text over and over, one character at a time
+// through enigo's text entry, while its hotkey has it on. Each character is a
+// press to the guard, which holds it to the CPS guard and keeps it out of
+// blocklisted apps.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use enigo::{Enigo, KeyboardControllable};
+
+use crate::config::TextTyper;
+use crate::guard::GuardHandles;
+use crate::log::Log;
+
+const POLL: Duration = Duration::from_millis(10);
+
+/// Shared state the typer thread is allowed to touch.
+pub struct TyperHandles {
+    pub typer: Arc<Mutex<Option<TextTyper>>>,
+    // Toggled by the typer's hotkey; cleared by the abort key.
+    pub running: Arc<AtomicBool>,
+    pub dry_run: Arc<AtomicBool>,
+    pub guard: GuardHandles,
+    pub shutdown: Arc<AtomicBool>,
+    pub log: Arc<Log>,
+}
+
+/// Sends `text` to `send` a character at a time, `delay` apart. Returns
+/// false if `running` was cleared before the end.
+fn type_once(text: &str, delay: Duration, running: &AtomicBool, mut send: impl FnMut(&str)) -> bool {
+    let mut buffer = [0; 4];
+    for (index, c) in text.chars().enumerate() {
+        if index > 0 {
+            sleep_while(delay, running);
+        }
+        if !running.load(Ordering::SeqCst) {
+            return false;
+        }
+        send(c.encode_utf8(&mut buffer));
+    }
+    true
+}
+
+/// Sleeps for `duration`, or until `running` is cleared.
+fn sleep_while(duration: Duration, running: &AtomicBool) {
+    let deadline = Instant::now() + duration;
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        thread::sleep((deadline - now).min(POLL));
+    }
+}
+
+/// Types whenever the typer is running, until shutdown.
+pub fn spawn(handles: TyperHandles) {
+    thread::spawn(move || {
+        // Created on first use, so a typer that never runs needs no display.
+        let mut enigo: Option<Enigo> = None;
+        while !handles.shutdown.load(Ordering::SeqCst) {
+            if !handles.running.load(Ordering::SeqCst) {
+                thread::sleep(POLL);
+                continue;
+            }
+            let Some(typer) = handles.typer.lock().unwrap().clone() else {
+                handles.running.store(false, Ordering::SeqCst);
+                continue;
+            };
+            if handles.dry_run.load(Ordering::SeqCst) {
+                handles.log.push(format!("dry run: type {:?}", typer.text));
+                type_once(&typer.text, typer.char_delay, &handles.running, |_| {});
+            } else {
+                type_once(&typer.text, typer.char_delay, &handles.running, |c| {
+                    if handles.guard.admit(&handles.running) {
+                        enigo.get_or_insert_with(Enigo::new).key_sequence(c);
+                    }
+                });
+            }
+            sleep_while(typer.repeat_delay, &handles.running);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_stops_between_characters() {
+        let running = AtomicBool::new(true);
+        let mut typed = String::new();
+        assert!(type_once("hé\n", Duration::ZERO, &running, |c| typed.push_str(c)));
+        assert_eq!(typed, "hé\n");
+
+        typed.clear();
+        let finished = type_once("abc", Duration::ZERO, &running, |c| {
+            typed.push_str(c);
+            running.store(false, Ordering::SeqCst);
+        });
+        assert!(!finished);
+        assert_eq!(typed, "a");
+    }
+}
@@ -0,0 +1,240 @@
+// Injection through a virtual /dev/uinput mouse and keyboard (Linux).
+//
+// The kernel delivers these events like a real device's, so they reach both
+// X11 and Wayland sessions. Only buttons are sent through uinput: without
+// compositor support a virtual device can't position the pointer absolutely,
+// so moves are passed to enigo (X11 only).
+
+use std::ffi::CString;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use enigo::MouseButton;
+use rdev::Key;
+
+use crate::backend::{EnigoBackend, InputBackend};
+use crate::config::KeyInjection;
+use crate::geometry::Point;
+use crate::keys::{scancode, SUPPORTED_KEYS};
+
+const DEVICE: &str = "/dev/uinput";
+
+// From linux/uinput.h and linux/input-event-codes.h.
+const UI_SET_EVBIT: u64 = 0x4004_5564;
+const UI_SET_KEYBIT: u64 = 0x4004_5565;
+const UI_SET_RELBIT: u64 = 0x4004_5566;
+const UI_DEV_CREATE: u64 = 0x5501;
+const UI_DEV_DESTROY: u64 = 0x5502;
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0;
+const REL_X: i32 = 0x00;
+const REL_Y: i32 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const KEY_PAUSE: u16 = 119;
+const BUS_USB: u16 = 0x03;
+const ABS_CNT: usize = 64;
+
+#[repr(C)]
+struct InputId {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+}
+
+#[repr(C)]
+struct UserDevice {
+    name: [u8; 80],
+    id: InputId,
+    ff_effects_max: u32,
+    absmax: [i32; ABS_CNT],
+    absmin: [i32; ABS_CNT],
+    absfuzz: [i32; ABS_CNT],
+    absflat: [i32; ABS_CNT],
+}
+
+#[repr(C)]
+struct InputEvent {
+    time: libc::timeval,
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+/// Linux input event code of a key. Codes match the set 1 scancodes except
+/// for the extended keys.
+fn evdev_code(key: &Key) -> Option<u16> {
+    if *key == Key::Pause {
+        return Some(KEY_PAUSE);
+    }
+    let code = scancode(key)?;
+    if code & 0xFF00 == 0 {
+        return Some(code);
+    }
+    let extended = match code & 0xFF {
+        0x1C => 96,  // KEY_KPENTER
+        0x1D => 97,  // KEY_RIGHTCTRL
+        0x35 => 98,  // KEY_KPSLASH
+        0x37 => 99,  // KEY_SYSRQ
+        0x38 => 100, // KEY_RIGHTALT
+        0x47 => 102, // KEY_HOME
+        0x48 => 103, // KEY_UP
+        0x49 => 104, // KEY_PAGEUP
+        0x4B => 105, // KEY_LEFT
+        0x4D => 106, // KEY_RIGHT
+        0x4F => 107, // KEY_END
+        0x50 => 108, // KEY_DOWN
+        0x51 => 109, // KEY_PAGEDOWN
+        0x52 => 110, // KEY_INSERT
+        0x53 => 111, // KEY_DELETE
+        0x5B => 125, // KEY_LEFTMETA
+        0x5C => 126, // KEY_RIGHTMETA
+        _ => return None,
+    };
+    Some(extended)
+}
+
+/// Checks that /dev/uinput can be opened, with setup guidance if not.
+pub fn check_access() -> Result<(), String> {
+    let path = CString::new(DEVICE).unwrap();
+    if unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0 {
+        return Ok(());
+    }
+    Err(match io::Error::last_os_error().raw_os_error() {
+        Some(libc::ENOENT) => "/dev/uinput is missing; load the module with `sudo modprobe uinput`".to_string(),
+        Some(libc::EACCES) | Some(libc::EPERM) => "no permission for /dev/uinput; add a udev rule \
+             KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\" and add your user to the input group"
+            .to_string(),
+        _ => format!("/dev/uinput unavailable: {}", io::Error::last_os_error()),
+    })
+}
+
+pub struct UinputBackend {
+    fd: libc::c_int,
+    // Pointer moves can't go through uinput, see above.
+    mover: EnigoBackend,
+}
+
+impl UinputBackend {
+    pub fn new() -> Result<Self, String> {
+        check_access()?;
+        let path = CString::new(DEVICE).unwrap();
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_NONBLOCK) };
+        if fd < 0 {
+            return Err(format!("could not open {}: {}", DEVICE, io::Error::last_os_error()));
+        }
+        let backend = UinputBackend { fd, mover: EnigoBackend::new() };
+        backend.setup().map_err(|e| format!("could not create the virtual mouse: {}", e))?;
+        // Give udev and the compositor a moment to pick up the new device.
+        thread::sleep(Duration::from_millis(200));
+        Ok(backend)
+    }
+
+    fn setup(&self) -> io::Result<()> {
+        self.ioctl(UI_SET_EVBIT, EV_KEY as libc::c_int)?;
+        for button in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
+            self.ioctl(UI_SET_KEYBIT, button as libc::c_int)?;
+        }
+        for code in SUPPORTED_KEYS.iter().filter_map(evdev_code) {
+            self.ioctl(UI_SET_KEYBIT, code as libc::c_int)?;
+        }
+        // Relative axes make libinput classify the device as a mouse.
+        self.ioctl(UI_SET_EVBIT, EV_REL as libc::c_int)?;
+        self.ioctl(UI_SET_RELBIT, REL_X)?;
+        self.ioctl(UI_SET_RELBIT, REL_Y)?;
+
+        let mut device = UserDevice {
+            name: [0; 80],
+            id: InputId { bustype: BUS_USB, vendor: 0x1234, product: 0x5678, version: 1 },
+            ff_effects_max: 0,
+            absmax: [0; ABS_CNT],
+            absmin: [0; ABS_CNT],
+            absfuzz: [0; ABS_CNT],
+            absflat: [0; ABS_CNT],
+        };
+        let name = b"Superspeed Autoclicker";
+        device.name[..name.len()].copy_from_slice(name);
+        self.write(&device)?;
+        self.ioctl(UI_DEV_CREATE, 0)
+    }
+
+    fn ioctl(&self, request: u64, arg: libc::c_int) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.fd, request as _, arg) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn write<T>(&self, value: &T) -> io::Result<()> {
+        let size = std::mem::size_of::<T>();
+        let written = unsafe { libc::write(self.fd, value as *const T as *const libc::c_void, size) };
+        if written != size as isize {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn emit(&self, kind: u16, code: u16, value: i32) {
+        let event = InputEvent { time: libc::timeval { tv_sec: 0, tv_usec: 0 }, kind, code, value };
+        if let Err(e) = self.write(&event) {
+            eprintln!("uinput write failed: {}", e);
+        }
+    }
+
+    fn button(&self, button: MouseButton, down: bool) {
+        let code = match button {
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+            _ => BTN_LEFT,
+        };
+        self.emit(EV_KEY, code, down as i32);
+        self.emit(EV_SYN, SYN_REPORT, 0);
+    }
+
+    fn key(&self, key: Key, down: bool) {
+        match evdev_code(&key) {
+            Some(code) => {
+                self.emit(EV_KEY, code, down as i32);
+                self.emit(EV_SYN, SYN_REPORT, 0);
+            }
+            None => eprintln!("{:?} has no uinput key code", key),
+        }
+    }
+}
+
+impl Drop for UinputBackend {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.fd, UI_DEV_DESTROY as _, 0);
+            libc::close(self.fd);
+        }
+    }
+}
+
+impl InputBackend for UinputBackend {
+    fn button_down(&mut self, button: MouseButton) {
+        self.button(button, true);
+    }
+
+    fn button_up(&mut self, button: MouseButton) {
+        self.button(button, false);
+    }
+
+    fn move_to(&mut self, point: Point) {
+        self.mover.move_to(point);
+    }
+
+    // Kernel key events are scancode-level either way.
+    fn key_down(&mut self, key: Key, _injection: KeyInjection) {
+        self.key(key, true);
+    }
+
+    fn key_up(&mut self, key: Key, _injection: KeyInjection) {
+        self.key(key, false);
+    }
+}
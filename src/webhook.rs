@@ -0,0 +1,150 @@
+// Click counting webhook, for dashboards and automation flows: a JSON document
+// posted to a plain http:// URL when clicking starts, when it stops and every
+// `every` clicks in between:
+//
+//     {"event": "start", "clicks": 0, "total_clicks": 51200, "time": 1760400000}
+//
+// "clicks" counts the clicks of the current run, "total_clicks" those since launch.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::WebhookConfig;
+use crate::json::Value;
+use crate::log::Log;
+use crate::telemetry::post_json;
+
+const POLL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Event {
+    Start,
+    Clicks(u64),
+    Stop(u64),
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Start => "start",
+            Event::Clicks(_) => "clicks",
+            Event::Stop(_) => "stop",
+        }
+    }
+
+    fn clicks(self) -> u64 {
+        match self {
+            Event::Start => 0,
+            Event::Clicks(n) | Event::Stop(n) => n,
+        }
+    }
+}
+
+/// Turns polls of the clicking flag and click count into events.
+#[derive(Default)]
+struct Tracker {
+    clicking: bool,
+    // Click count when the run started, and of the run at the last progress post.
+    started_at: u64,
+    reported: u64,
+}
+
+impl Tracker {
+    fn update(&mut self, clicking: bool, emitted: u64, every: u64) -> Option<Event> {
+        let clicks = emitted - self.started_at;
+        match (self.clicking, clicking) {
+            (false, true) => {
+                *self = Tracker { clicking, started_at: emitted, reported: 0 };
+                Some(Event::Start)
+            }
+            (true, false) => {
+                self.clicking = false;
+                Some(Event::Stop(clicks))
+            }
+            // Several steps passed in one poll still make one post.
+            (true, true) if every > 0 && clicks / every > self.reported / every => {
+                self.reported = clicks;
+                Some(Event::Clicks(clicks))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Shared state the webhook poster is allowed to touch.
+pub struct WebhookHandles {
+    // URL to post to; the poster stops once this no longer matches it.
+    pub config: Arc<Mutex<Option<WebhookConfig>>>,
+    pub clicking: Arc<AtomicBool>,
+    pub emitted: Arc<AtomicU64>,
+    pub log: Arc<Log>,
+    pub shutdown: Arc<AtomicBool>,
+}
+
+fn payload(event: Event, total_clicks: u64) -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |t| t.as_secs());
+    Value::Object(vec![
+        ("event".to_string(), event.name().into()),
+        ("clicks".to_string(), event.clicks().into()),
+        ("total_clicks".to_string(), total_clicks.into()),
+        ("time".to_string(), time.into()),
+    ])
+    .to_string()
+}
+
+/// Posts the events of `config` until it is replaced or the app shuts down.
+pub fn spawn(handles: WebhookHandles, config: WebhookConfig) {
+    thread::spawn(move || {
+        let mut tracker = Tracker::default();
+        let mut failing = false;
+        while !handles.shutdown.load(Ordering::SeqCst) && handles.config.lock().unwrap().as_ref() == Some(&config) {
+            let emitted = handles.emitted.load(Ordering::SeqCst);
+            if let Some(event) = tracker.update(handles.clicking.load(Ordering::SeqCst), emitted, config.every) {
+                // Logged once per outage rather than for every lost post.
+                match post_json(&config.url, &payload(event, emitted)) {
+                    Ok(()) if failing => {
+                        failing = false;
+                        handles.log.push(format!("Webhook {} reachable again", config.url));
+                    }
+                    Err(e) if !failing => {
+                        failing = true;
+                        handles.log.push(format!("Webhook post to {} failed: {}", config.url, e));
+                    }
+                    _ => {}
+                }
+            }
+            thread::sleep(POLL);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn runs_post_start_progress_and_stop() {
+        let mut tracker = Tracker::default();
+        assert_eq!(tracker.update(false, 40, 100), None);
+        assert_eq!(tracker.update(true, 40, 100), Some(Event::Start));
+        assert_eq!(tracker.update(true, 139, 100), None);
+        assert_eq!(tracker.update(true, 140, 100), Some(Event::Clicks(100)));
+        assert_eq!(tracker.update(true, 400, 100), Some(Event::Clicks(360)));
+        assert_eq!(tracker.update(true, 420, 100), None);
+        assert_eq!(tracker.update(false, 445, 100), Some(Event::Stop(405)));
+        assert_eq!(tracker.update(false, 445, 100), None);
+
+        // Progress posts off; a new run counts from zero again.
+        assert_eq!(tracker.update(true, 445, 0), Some(Event::Start));
+        assert_eq!(tracker.update(true, 10_000, 0), None);
+        assert_eq!(tracker.update(false, 10_005, 0), Some(Event::Stop(9560)));
+
+        let doc = json::parse(&payload(Event::Stop(7), 50)).unwrap();
+        assert_eq!(doc.get("event").and_then(Value::as_str), Some("stop"));
+        assert_eq!(doc.get("clicks").and_then(Value::as_u64), Some(7));
+        assert_eq!(doc.get("total_clicks").and_then(Value::as_u64), Some(50));
+    }
+}